@@ -0,0 +1,244 @@
+//! Derive macros for the `argoflows` crate.
+//!
+//! The macros are re-exported by `argoflows` when its `derive` feature is
+//! enabled, and should be used from there.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, ExprLit, Fields, Lit, LitStr, Meta};
+
+/// Derives `argoflows::types::workflow::WorkflowParams` for a struct with
+/// named fields.
+///
+/// Each field maps to a parameter named after the field. The `#[param(...)]`
+/// attribute accepts:
+///
+/// - `rename = "name"`: name of the parameter.
+/// - `default = "value"`: default value of the parameter.
+/// - `description = "text"`: description of the parameter. Defaults to the
+///   doc comment of the field.
+/// - `enum = ["a", "b"]`: values allowed for the parameter.
+/// - `json`: encodes the field as a JSON document.
+#[proc_macro_derive(WorkflowParams, attributes(param))]
+pub fn derive_workflow_params(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// `Field` holds the parameter options of a single struct field.
+struct Field {
+    ident: syn::Ident,
+    ty: syn::Type,
+    name: String,
+    default: Option<String>,
+    description: Option<String>,
+    allowed: Vec<String>,
+    json: bool,
+}
+
+impl Field {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let ident = field.ident.clone().expect("named field");
+        let mut parsed = Field {
+            name: ident.to_string().trim_start_matches("r#").to_string(),
+            ident,
+            ty: field.ty.clone(),
+            default: None,
+            description: doc_comment(&field.attrs),
+            allowed: Vec::new(),
+            json: false,
+        };
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("param")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    parsed.name = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("default") {
+                    parsed.default = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("description") {
+                    parsed.description = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("enum") {
+                    let list = meta.value()?.parse::<syn::ExprArray>()?;
+                    for elem in list.elems {
+                        match elem {
+                            Expr::Lit(ExprLit {
+                                lit: Lit::Str(s), ..
+                            }) => parsed.allowed.push(s.value()),
+                            other => {
+                                return Err(syn::Error::new_spanned(
+                                    other,
+                                    "expected a string literal",
+                                ))
+                            }
+                        }
+                    }
+                } else if meta.path.is_ident("json") {
+                    parsed.json = true;
+                } else {
+                    return Err(meta.error("unsupported param attribute"));
+                }
+                Ok(())
+            })?;
+        }
+
+        Ok(parsed)
+    }
+
+    /// Returns the expression building the declaration of the parameter.
+    fn declaration(&self) -> TokenStream2 {
+        let name = &self.name;
+        let default = option_string(self.default.as_deref());
+        let description = option_string(self.description.as_deref());
+        let allowed = &self.allowed;
+        let allowed = if allowed.is_empty() {
+            quote!(::std::option::Option::None)
+        } else {
+            quote!(::std::option::Option::Some(
+                ::std::vec![#(::std::string::String::from(#allowed)),*]
+            ))
+        };
+
+        quote! {{
+            let mut param = ::argoflows::types::workflow::Parameter::new(#name);
+            param.default = #default;
+            param.description = #description;
+            param.r#enum = #allowed;
+            param
+        }}
+    }
+
+    /// Returns the expression converting the field of `self` into a value.
+    fn to_value(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        if self.json {
+            quote!(::argoflows::types::workflow::json_to_param(&self.#ident))
+        } else {
+            quote!(::argoflows::types::workflow::ParamValue::to_param(&self.#ident))
+        }
+    }
+
+    /// Returns the field initializer parsing the value from `params`.
+    fn initializer(&self) -> TokenStream2 {
+        let ident = &self.ident;
+        let ty = &self.ty;
+        let name = &self.name;
+        let default = match &self.default {
+            Some(d) => quote!(::std::option::Option::Some(#d)),
+            None => quote!(::std::option::Option::None),
+        };
+        let allowed = &self.allowed;
+        let (missing, parse) = if self.json {
+            (
+                quote!(::std::option::Option::None),
+                quote!(::argoflows::types::workflow::json_from_param::<#ty>),
+            )
+        } else {
+            (
+                quote!(<#ty as ::argoflows::types::workflow::ParamValue>::missing()),
+                quote!(<#ty as ::argoflows::types::workflow::ParamValue>::from_param),
+            )
+        };
+
+        quote! {
+            #ident: ::argoflows::types::workflow::parse_param(
+                params,
+                #name,
+                #default,
+                &[#(#allowed),*],
+                #missing,
+                #parse,
+            )?
+        }
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "WorkflowParams can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "WorkflowParams can only be derived for structs",
+            ))
+        }
+    };
+    let fields = fields
+        .iter()
+        .map(Field::parse)
+        .collect::<syn::Result<Vec<Field>>>()?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let declarations = fields.iter().map(Field::declaration);
+    let values = fields.iter().map(Field::to_value);
+    let initializers = fields.iter().map(Field::initializer);
+
+    Ok(quote! {
+        impl #impl_generics ::argoflows::types::workflow::WorkflowParams for #ident #ty_generics #where_clause {
+            fn parameters() -> ::std::vec::Vec<::argoflows::types::workflow::Parameter> {
+                ::std::vec![#(#declarations),*]
+            }
+
+            fn to_parameters(&self) -> ::std::vec::Vec<::argoflows::types::workflow::Parameter> {
+                let values: ::std::vec::Vec<::std::option::Option<::std::string::String>> =
+                    ::std::vec![#(#values),*];
+                let mut params = Self::parameters();
+                for (param, value) in params.iter_mut().zip(values) {
+                    param.value = value;
+                }
+                params.retain(|p| p.value.is_some() || p.default.is_some());
+                params
+            }
+
+            fn from_parameters(
+                params: &[::argoflows::types::workflow::Parameter],
+            ) -> ::std::result::Result<Self, ::argoflows::error::ParamsError> {
+                ::std::result::Result::Ok(Self {
+                    #(#initializers),*
+                })
+            }
+        }
+    })
+}
+
+fn option_string(value: Option<&str>) -> TokenStream2 {
+    match value {
+        Some(v) => quote!(::std::option::Option::Some(::std::string::String::from(#v))),
+        None => quote!(::std::option::Option::None),
+    }
+}
+
+/// Returns the doc comment of a field, with its lines joined by spaces.
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(s), ..
+                }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .filter(|l| !l.is_empty())
+        .collect();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join(" "))
+    }
+}
@@ -1,6 +1,7 @@
 use argoflows::api::workflow;
 use argoflows::config::Config;
-use argoflows::types::workflow::CreateRequest;
+use argoflows::types::template::Template;
+use argoflows::types::workflow::{CreateRequest, Workflow};
 
 fn main() {
     let token = std::env::var("ARGO_TOKEN").expect("the ARGO_TOKEN env variable must be set");
@@ -11,33 +12,18 @@ fn main() {
         .build();
     let cfg = cfg.expect("failed to create client config");
 
-    let wf = r#"{
-        "apiVersion": "argoproj.io/v1alpha1",
-        "kind": "Workflow",
-        "metadata": {
-            "name": "wf-sample",
-            "namespace": "argoflows"
-        },
-        "spec": {
-            "entrypoint": "hello-world",
-            "templates": [
-                {
-                    "name": "hello-world",
-                    "container": {
-                        "image": "busybox",
-                        "command": [
-                            "echo"
-                        ],
-                        "args": [
-                            "hello world"
-                        ]
-                    }
-                }
-            ]
-        }
-    }"#;
+    let wf = Workflow::builder()
+        .name("wf-sample")
+        .namespace("argoflows")
+        .entrypoint("hello-world")
+        .template(
+            Template::container("hello-world", "busybox")
+                .command(&["echo"])
+                .args(&["hello world"]),
+        )
+        .build()
+        .expect("failed to build workflow");
 
-    let wf = serde_json::from_str(&wf).expect("failed to parse workflow template");
     let req = CreateRequest {
         namespace: Some(String::from("argoflows")),
         workflow: Some(Box::new(wf)),
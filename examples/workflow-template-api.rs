@@ -51,7 +51,7 @@ fn main() {
         }
     }"#;
 
-    let tmpl = serde_json::from_str(&tmpl).expect("failed to parse workflow template");
+    let tmpl = serde_json::from_str(tmpl).expect("failed to parse workflow template");
     let req = CreateRequest {
         namespace: Some(String::from("argoflows")),
         template: Some(Box::new(tmpl)),
@@ -0,0 +1,360 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::workflow::{Workflow, WorkflowList};
+
+/// Number of seconds in an hour, the unit prices are given for.
+const HOUR: f64 = 3600.0;
+
+/// `GroupBy` is the key usage is rolled up by in a `CostReport`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// The name of the workflow.
+    Workflow,
+    /// The namespace of the workflow.
+    Namespace,
+    /// The template run by the pods, as named in their nodes. For a
+    /// `template_ref`, the name of the template in the referenced
+    /// `WorkflowTemplate`.
+    Template,
+    /// The value of a label of the workflow, e.g.
+    /// `workflows.argoproj.io/workflow-template`.
+    Label(String),
+    /// The value of an annotation of the workflow, e.g. a `team`
+    /// annotation.
+    Annotation(String),
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupBy::Workflow => write!(f, "workflow"),
+            GroupBy::Namespace => write!(f, "namespace"),
+            GroupBy::Template => write!(f, "template"),
+            GroupBy::Label(key) => write!(f, "label:{}", key),
+            GroupBy::Annotation(key) => write!(f, "annotation:{}", key),
+        }
+    }
+}
+
+/// A `PriceTable` gives the price of the resources reported by
+/// `resources_duration`, per hour of use of one unit of the resource, in
+/// the units Argo counts them in: `cpu` in cores, `memory` in 100Mi and
+/// other resources, such as `nvidia.com/gpu`, in units of the resource.
+///
+/// It can be built in code, or deserialized from a map of resource names
+/// to prices, e.g. `{"cpu": 0.04, "memory": 0.0005}`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PriceTable {
+    prices: BTreeMap<String, f64>,
+}
+
+impl PriceTable {
+    /// Constructs a new `PriceTable` where every resource is free.
+    pub fn new() -> Self {
+        PriceTable::default()
+    }
+
+    /// Sets the price of one unit of `resource` for one hour.
+    pub fn price(mut self, resource: &str, per_hour: f64) -> Self {
+        self.prices.insert(String::from(resource), per_hour);
+        self
+    }
+
+    /// Returns the cost of `resources`, in seconds of use of each resource.
+    /// Resources without a price are free.
+    pub fn cost(&self, resources: &BTreeMap<String, i64>) -> f64 {
+        resources
+            .iter()
+            .filter_map(|(name, seconds)| Some(self.prices.get(name)? * *seconds as f64 / HOUR))
+            .sum()
+    }
+}
+
+/// `CostRow` is the usage and cost of the workflows sharing a key of a
+/// `CostReport`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostRow {
+    /// The key, or `None` for the workflows without the label or annotation
+    /// grouped by.
+    #[serde(rename = "key", skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+
+    /// Number of workflows counted in the row.
+    #[serde(rename = "workflows")]
+    pub workflows: usize,
+
+    /// Seconds of use of each resource, as in `resources_duration`.
+    #[serde(rename = "resourcesDuration")]
+    pub resources_duration: BTreeMap<String, i64>,
+
+    #[serde(rename = "cost")]
+    pub cost: f64,
+}
+
+/// `CostReport` rolls up the `resources_duration` of workflows by a
+/// `GroupBy` key, and prices it with a `PriceTable`.
+///
+/// Workflows can come from a `WorkflowList` or from the workflow archive;
+/// those which did not report resource usage yet are counted with none.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CostReport {
+    #[serde(rename = "groupBy")]
+    pub group_by: GroupBy,
+
+    /// The rows, most expensive first.
+    #[serde(rename = "rows")]
+    pub rows: Vec<CostRow>,
+}
+
+impl CostReport {
+    /// Constructs a new `CostReport` of `workflows`, grouped by `group_by`
+    /// and priced with `prices`.
+    ///
+    /// Usage is taken from the `resources_duration` of the workflows, or
+    /// of their pods when the workflow does not report it. When grouping by
+    /// template, it is taken from the pods, and a workflow is counted in
+    /// the row of every template it ran; a workflow without pods is counted
+    /// in the row without a key.
+    pub fn new<'a>(
+        group_by: GroupBy,
+        prices: &PriceTable,
+        workflows: impl IntoIterator<Item = &'a Workflow>,
+    ) -> Self {
+        let mut rows: BTreeMap<Option<String>, CostRow> = BTreeMap::new();
+        for workflow in workflows {
+            for (key, resources) in usage(&group_by, workflow) {
+                let row = rows.entry(key.clone()).or_insert_with(|| CostRow {
+                    key,
+                    ..Default::default()
+                });
+                row.workflows += 1;
+                for (name, seconds) in resources {
+                    *row.resources_duration.entry(name).or_default() += seconds;
+                }
+            }
+        }
+
+        let mut rows: Vec<CostRow> = rows.into_values().collect();
+        for row in rows.iter_mut() {
+            row.cost = prices.cost(&row.resources_duration);
+        }
+        rows.sort_by(|a, b| b.cost.total_cmp(&a.cost).then_with(|| a.key.cmp(&b.key)));
+        CostReport { group_by, rows }
+    }
+
+    /// Constructs a new `CostReport` of the workflows of `list`. See
+    /// `CostReport::new()`.
+    pub fn from_list(group_by: GroupBy, prices: &PriceTable, list: &WorkflowList) -> Self {
+        CostReport::new(group_by, prices, &list.items)
+    }
+
+    /// Returns the sum of the rows. Its `workflows` counts workflows once
+    /// per row they are in.
+    pub fn total(&self) -> CostRow {
+        let mut total = CostRow::default();
+        for row in &self.rows {
+            total.workflows += row.workflows;
+            total.cost += row.cost;
+            for (name, seconds) in &row.resources_duration {
+                *total.resources_duration.entry(name.clone()).or_default() += seconds;
+            }
+        }
+        total
+    }
+
+    /// Writes the report as CSV to `writer`: a header row, then one row per
+    /// key with the number of workflows, the seconds of use of each
+    /// resource found in the report, and the cost, and a last `total` row
+    /// with the sums of `CostReport::total()`.
+    pub fn write_csv<W: io::Write>(&self, mut writer: W) -> io::Result<()> {
+        let resources: BTreeSet<&str> = self
+            .rows
+            .iter()
+            .flat_map(|r| r.resources_duration.keys().map(String::as_str))
+            .collect();
+
+        let mut header = vec![self.group_by.to_string(), String::from("workflows")];
+        header.extend(resources.iter().map(|r| format!("{} seconds", r)));
+        header.push(String::from("cost"));
+        write_record(&mut writer, &header)?;
+
+        let total = CostRow {
+            key: Some(String::from("total")),
+            ..self.total()
+        };
+        for row in self.rows.iter().chain([&total]) {
+            let mut record = vec![
+                row.key.clone().unwrap_or_default(),
+                row.workflows.to_string(),
+            ];
+            record.extend(resources.iter().map(|r| {
+                let seconds = row.resources_duration.get(*r).copied().unwrap_or_default();
+                seconds.to_string()
+            }));
+            record.push(format!("{:.4}", row.cost));
+            write_record(&mut writer, &record)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the report as CSV. See `CostReport::write_csv()`.
+    pub fn to_csv(&self) -> String {
+        let mut csv = Vec::new();
+        self.write_csv(&mut csv).expect("writing to a Vec succeeds");
+        String::from_utf8(csv).expect("CSV is UTF-8")
+    }
+}
+
+/// Seconds of use of resources, by resource name.
+type Usage = Vec<(String, i64)>;
+
+/// Returns the usage of `workflow` under each of its keys.
+fn usage(group_by: &GroupBy, workflow: &Workflow) -> Vec<(Option<String>, Usage)> {
+    let metadata = &workflow.metadata;
+    let status = workflow.status.as_deref();
+    let pods = status
+        .and_then(|s| s.nodes.as_ref())
+        .into_iter()
+        .flat_map(|n| n.values())
+        .filter(|n| n.r#type == "Pod");
+
+    let key = match group_by {
+        GroupBy::Workflow => metadata.name.clone(),
+        GroupBy::Namespace => metadata.namespace.clone(),
+        GroupBy::Label(key) => metadata.labels.as_ref().and_then(|l| l.get(key).cloned()),
+        GroupBy::Annotation(key) => metadata
+            .annotations
+            .as_ref()
+            .and_then(|a| a.get(key).cloned()),
+        GroupBy::Template if pods.clone().next().is_none() => None,
+        GroupBy::Template => {
+            let mut templates: BTreeMap<Option<String>, Usage> = BTreeMap::new();
+            for pod in pods {
+                let referenced = pod.template_ref.as_deref().and_then(|r| r.template.clone());
+                let resources = templates
+                    .entry(referenced.or_else(|| pod.template_name.clone()))
+                    .or_default();
+                for (name, seconds) in pod.resources_duration.iter().flatten() {
+                    resources.push((name.clone(), *seconds));
+                }
+            }
+            return templates.into_iter().collect();
+        }
+    };
+
+    let resources: Usage = match status.and_then(|s| s.resources_duration.as_ref()) {
+        Some(resources) => resources.iter().map(|(n, s)| (n.clone(), *s)).collect(),
+        None => pods
+            .flat_map(|p| p.resources_duration.iter().flatten())
+            .map(|(n, s)| (n.clone(), *s))
+            .collect(),
+    };
+    vec![(key, resources)]
+}
+
+/// Writes a CSV record, quoting the fields which need it.
+fn write_record<W: io::Write>(writer: &mut W, fields: &[String]) -> io::Result<()> {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    writeln!(writer, "{}", fields.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn workflow(name: &str, status: Value) -> Workflow {
+        serde_json::from_value(json!({
+            "metadata": {"name": name, "namespace": "argo"},
+            "spec": {},
+            "status": status,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn price_table_from_map() {
+        let prices: PriceTable = serde_json::from_str(r#"{"cpu": 0.36, "memory": 0.01}"#).unwrap();
+        assert_eq!(
+            prices,
+            PriceTable::new().price("cpu", 0.36).price("memory", 0.01)
+        );
+        let resources = BTreeMap::from([
+            (String::from("cpu"), 600),
+            (String::from("memory"), 3600),
+            (String::from("nvidia.com/gpu"), 60),
+        ]);
+        assert!((prices.cost(&resources) - 0.07).abs() < 1e-9);
+        assert_eq!(
+            serde_json::to_value(&prices).unwrap(),
+            json!({"cpu": 0.36, "memory": 0.01})
+        );
+    }
+
+    #[test]
+    fn group_by_template() {
+        let workflows = [
+            workflow(
+                "pods",
+                json!({
+                    "resourcesDuration": {"cpu": 30},
+                    "nodes": {
+                        "a": {"id": "a", "name": "pods.a", "type": "Pod",
+                              "templateName": "build", "resourcesDuration": {"cpu": 10}},
+                        "b": {"id": "b", "name": "pods.b", "type": "Pod",
+                              "templateName": "test", "resourcesDuration": {"cpu": 20}}
+                    }
+                }),
+            ),
+            workflow("archived", json!({"resourcesDuration": {"cpu": 40}})),
+        ];
+        let prices = PriceTable::new().price("cpu", 3600.0);
+        let report = CostReport::new(GroupBy::Template, &prices, &workflows);
+        let rows: Vec<(Option<&str>, usize, f64)> = report
+            .rows
+            .iter()
+            .map(|r| (r.key.as_deref(), r.workflows, r.cost))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (None, 1, 40.0),
+                (Some("test"), 1, 20.0),
+                (Some("build"), 1, 10.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn csv() {
+        let workflows = [
+            workflow("a", json!({"resourcesDuration": {"cpu": 10, "memory": 5}})),
+            workflow("b,c", json!({"resourcesDuration": {"cpu": 20}})),
+        ];
+        let prices = PriceTable::new().price("cpu", 3600.0);
+        let report = CostReport::new(GroupBy::Workflow, &prices, &workflows);
+        assert_eq!(
+            report.to_csv(),
+            "workflow,workflows,cpu seconds,memory seconds,cost\n\
+             \"b,c\",1,20,0,20.0000\n\
+             a,1,10,5,10.0000\n\
+             total,2,30,5,30.0000\n"
+        );
+    }
+}
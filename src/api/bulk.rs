@@ -0,0 +1,466 @@
+use std::fmt;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::config::Config;
+use crate::error::{ApplyBulkError, BulkError};
+use crate::types::{
+    workflow::{
+        ResumeRequest, RetryRequest, StopRequest, SuspendRequest, TerminateRequest, Workflow,
+    },
+    Fields, ListOptions,
+};
+
+use super::workflow::{
+    delete_workflow, iter_workflows, resume_workflow, retry_workflow, stop_workflow,
+    suspend_workflow, terminate_workflow,
+};
+
+/// Default number of workflows an action is applied to at the same time.
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// `BulkAction` is the action `apply_bulk()` applies to each workflow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BulkAction {
+    Delete,
+    /// Stops the workflow, running its exit handlers.
+    Stop,
+    /// Terminates the workflow, without running its exit handlers.
+    Terminate,
+    Suspend,
+    Resume,
+    /// Retries the failed nodes of a failed workflow.
+    Retry,
+}
+
+impl BulkAction {
+    /// Returns why the action does not apply to `workflow`, if it does not.
+    fn skip_reason(&self, workflow: &Workflow) -> Option<&'static str> {
+        let status = workflow.status.as_deref();
+        let phase = status.and_then(|s| s.phase.as_deref());
+        let completed = matches!(phase, Some("Succeeded" | "Failed" | "Error"));
+        match self {
+            BulkAction::Delete => None,
+            BulkAction::Stop | BulkAction::Terminate | BulkAction::Resume if completed => {
+                Some("already completed")
+            }
+            BulkAction::Suspend if completed => Some("already completed"),
+            BulkAction::Suspend if workflow.spec.suspend == Some(true) => Some("already suspended"),
+            BulkAction::Retry if !matches!(phase, Some("Failed" | "Error")) => Some("not failed"),
+            _ => None,
+        }
+    }
+
+    /// Returns the action in the past tense, e.g. `stopped`.
+    fn past_tense(&self) -> &'static str {
+        match self {
+            BulkAction::Delete => "deleted",
+            BulkAction::Stop => "stopped",
+            BulkAction::Terminate => "terminated",
+            BulkAction::Suspend => "suspended",
+            BulkAction::Resume => "resumed",
+            BulkAction::Retry => "retried",
+        }
+    }
+}
+
+impl fmt::Display for BulkAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self {
+            BulkAction::Delete => "delete",
+            BulkAction::Stop => "stop",
+            BulkAction::Terminate => "terminate",
+            BulkAction::Suspend => "suspend",
+            BulkAction::Resume => "resume",
+            BulkAction::Retry => "retry",
+        };
+        f.write_str(action)
+    }
+}
+
+/// `BulkOptions` controls how `apply_bulk()` applies an action.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BulkOptions {
+    all: bool,
+    concurrency: usize,
+    dry_run: bool,
+    message: Option<String>,
+}
+
+impl Default for BulkOptions {
+    fn default() -> Self {
+        BulkOptions::new()
+    }
+}
+
+impl BulkOptions {
+    /// Constructs new `BulkOptions`, applying the action to 4 workflows at
+    /// the same time.
+    pub fn new() -> Self {
+        BulkOptions {
+            all: false,
+            concurrency: DEFAULT_CONCURRENCY,
+            dry_run: false,
+            message: None,
+        }
+    }
+
+    /// Allows the action to be applied without a label or field selector,
+    /// to every workflow of the namespace, or of all namespaces.
+    pub fn all(mut self, all: bool) -> Self {
+        self.all = all;
+        self
+    }
+
+    /// Sets the number of workflows the action is applied to at the same
+    /// time, at least 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Only reports the workflows the action would be applied to, without
+    /// applying it.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Sets the message of the nodes of stopped workflows.
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = Some(String::from(message));
+        self
+    }
+}
+
+/// `BulkOutcome` is the outcome of an action for one workflow.
+#[derive(Debug)]
+pub enum BulkOutcome {
+    Applied,
+    /// The action would have been applied, without dry run.
+    DryRun,
+    /// The action does not apply to the workflow, e.g. stopping a
+    /// completed workflow.
+    Skipped(String),
+    Failed(BulkError),
+}
+
+/// `BulkResult` is the outcome of an action for a workflow.
+#[derive(Debug)]
+pub struct BulkResult {
+    pub namespace: String,
+    pub name: String,
+    /// `WorkflowStatus::phase` when the workflow was listed.
+    pub phase: Option<String>,
+    pub outcome: BulkOutcome,
+}
+
+impl BulkResult {
+    /// Returns whether the action failed for the workflow.
+    pub fn is_failed(&self) -> bool {
+        matches!(self.outcome, BulkOutcome::Failed(_))
+    }
+}
+
+/// `BulkReport` is the outcome of `apply_bulk()` for each workflow selected,
+/// in the order they were listed.
+#[derive(Debug)]
+pub struct BulkReport {
+    pub action: BulkAction,
+    pub dry_run: bool,
+    pub results: Vec<BulkResult>,
+}
+
+impl BulkReport {
+    /// Returns the results for which the action was applied, or would have
+    /// been applied for a dry run.
+    pub fn applied(&self) -> impl Iterator<Item = &BulkResult> {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.outcome, BulkOutcome::Applied | BulkOutcome::DryRun))
+    }
+
+    /// Returns the results of the workflows the action did not apply to.
+    pub fn skipped(&self) -> impl Iterator<Item = &BulkResult> {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.outcome, BulkOutcome::Skipped(_)))
+    }
+
+    /// Returns the results for which the action failed.
+    pub fn failed(&self) -> impl Iterator<Item = &BulkResult> {
+        self.results.iter().filter(|r| r.is_failed())
+    }
+
+    /// Returns whether the action failed for none of the workflows.
+    pub fn is_success(&self) -> bool {
+        self.failed().next().is_none()
+    }
+}
+
+impl fmt::Display for BulkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for result in &self.results {
+            write!(f, "{}/{}: ", result.namespace, result.name)?;
+            match &result.outcome {
+                BulkOutcome::Applied => writeln!(f, "{}", self.action.past_tense())?,
+                BulkOutcome::DryRun => writeln!(f, "would {}", self.action)?,
+                BulkOutcome::Skipped(reason) => writeln!(f, "skipped, {}", reason)?,
+                BulkOutcome::Failed(e) => writeln!(f, "{}", e)?,
+            }
+        }
+        write!(
+            f,
+            "{}{}: {} applied, {} skipped, {} failed",
+            self.action,
+            if self.dry_run { " (dry run)" } else { "" },
+            self.applied().count(),
+            self.skipped().count(),
+            self.failed().count()
+        )
+    }
+}
+
+/// Applies `action` to the workflows of `namespace` selected by the label
+/// and field selectors of `list_options`, all namespaces when `namespace`
+/// is empty.
+///
+/// Unless `opts.all` is set, an error is returned when `list_options` has
+/// neither a label nor a field selector, so that a missing selector does
+/// not select every workflow.
+///
+/// The workflows are listed page by page first, an error being returned
+/// when they cannot be; the action is then applied to `opts.concurrency`
+/// of them at the same time. Its outcome is reported for each workflow,
+/// a failure not stopping the others.
+pub fn apply_bulk(
+    config: &Config,
+    namespace: &str,
+    list_options: Option<ListOptions>,
+    action: BulkAction,
+    opts: BulkOptions,
+) -> Result<BulkReport, ApplyBulkError> {
+    let selected = list_options.as_ref().is_some_and(|o| {
+        o.label_selector.as_ref().is_some_and(|s| !s.is_empty())
+            || o.field_selector.as_ref().is_some_and(|s| !s.is_empty())
+    });
+    if !selected && !opts.all {
+        return Err(ApplyBulkError::NoSelector);
+    }
+
+    let fields = Fields::excluding()
+        .nodes()
+        .field("status.compressedNodes")
+        .field("status.storedTemplates")
+        .field("status.storedWorkflowTemplateSpec");
+    let workflows: Vec<Workflow> =
+        iter_workflows(config, namespace, list_options, Some(&fields), None)
+            .collect::<Result<_, _>>()
+            .map_err(ApplyBulkError::List)?;
+
+    let next = AtomicUsize::new(0);
+    let mut outcomes: Vec<(usize, BulkOutcome)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..opts.concurrency.min(workflows.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut outcomes = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(workflow) = workflows.get(i) else {
+                            return outcomes;
+                        };
+                        outcomes.push((i, apply(config, workflow, action, &opts)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    });
+    outcomes.sort_by_key(|(i, _)| *i);
+
+    let results = workflows
+        .into_iter()
+        .zip(outcomes)
+        .map(|(workflow, (_, outcome))| BulkResult {
+            namespace: workflow.metadata.namespace.unwrap_or_default(),
+            name: workflow.metadata.name.unwrap_or_default(),
+            phase: workflow.status.and_then(|s| s.phase),
+            outcome,
+        })
+        .collect();
+    Ok(BulkReport {
+        action,
+        dry_run: opts.dry_run,
+        results,
+    })
+}
+
+/// Applies `action` to `workflow`, unless it does not apply or for a dry
+/// run.
+fn apply(
+    config: &Config,
+    workflow: &Workflow,
+    action: BulkAction,
+    opts: &BulkOptions,
+) -> BulkOutcome {
+    if let Some(reason) = action.skip_reason(workflow) {
+        return BulkOutcome::Skipped(String::from(reason));
+    }
+    if opts.dry_run {
+        return BulkOutcome::DryRun;
+    }
+
+    let namespace = workflow.metadata.namespace.clone().unwrap_or_default();
+    let name = workflow.metadata.name.clone().unwrap_or_default();
+    let (ns, n) = (namespace.as_str(), name.as_str());
+    let result = match action {
+        BulkAction::Delete => delete_workflow(config, ns, n, None, false)
+            .map(drop)
+            .map_err(BulkError::Delete),
+        BulkAction::Stop => {
+            let body = StopRequest {
+                message: opts.message.clone(),
+                name: Some(name.clone()),
+                namespace: Some(namespace.clone()),
+                ..Default::default()
+            };
+            stop_workflow(config, ns, n, body)
+                .map(drop)
+                .map_err(BulkError::Stop)
+        }
+        BulkAction::Terminate => {
+            let body = TerminateRequest {
+                name: Some(name.clone()),
+                namespace: Some(namespace.clone()),
+            };
+            terminate_workflow(config, ns, n, body)
+                .map(drop)
+                .map_err(BulkError::Terminate)
+        }
+        BulkAction::Suspend => {
+            let body = SuspendRequest {
+                name: Some(name.clone()),
+                namespace: Some(namespace.clone()),
+            };
+            suspend_workflow(config, ns, n, body)
+                .map(drop)
+                .map_err(BulkError::Suspend)
+        }
+        BulkAction::Resume => {
+            let body = ResumeRequest {
+                name: Some(name.clone()),
+                namespace: Some(namespace.clone()),
+                ..Default::default()
+            };
+            resume_workflow(config, ns, n, body)
+                .map(drop)
+                .map_err(BulkError::Resume)
+        }
+        BulkAction::Retry => {
+            let body = RetryRequest {
+                name: Some(name.clone()),
+                namespace: Some(namespace.clone()),
+                ..Default::default()
+            };
+            retry_workflow(config, ns, n, body)
+                .map(drop)
+                .map_err(BulkError::Retry)
+        }
+    };
+    match result {
+        Ok(()) => BulkOutcome::Applied,
+        Err(e) => BulkOutcome::Failed(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::types::LabelSelector;
+
+    fn workflow(phase: &str, suspend: bool) -> Workflow {
+        serde_json::from_value(json!({
+            "metadata": {"name": "wf", "namespace": "argo"},
+            "spec": {"suspend": suspend},
+            "status": {"phase": phase},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn skip_reason() {
+        let running = workflow("Running", false);
+        let failed = workflow("Failed", false);
+        let suspended = workflow("Running", true);
+        assert_eq!(BulkAction::Delete.skip_reason(&failed), None);
+        assert_eq!(BulkAction::Stop.skip_reason(&running), None);
+        assert_eq!(
+            BulkAction::Terminate.skip_reason(&failed),
+            Some("already completed")
+        );
+        assert_eq!(
+            BulkAction::Resume.skip_reason(&workflow("Succeeded", false)),
+            Some("already completed")
+        );
+        assert_eq!(
+            BulkAction::Suspend.skip_reason(&suspended),
+            Some("already suspended")
+        );
+        assert_eq!(BulkAction::Retry.skip_reason(&failed), None);
+        assert_eq!(BulkAction::Retry.skip_reason(&running), Some("not failed"));
+    }
+
+    #[test]
+    fn display() {
+        let result = |name: &str, outcome| BulkResult {
+            namespace: String::from("argo"),
+            name: String::from(name),
+            phase: None,
+            outcome,
+        };
+        let report = BulkReport {
+            action: BulkAction::Stop,
+            dry_run: false,
+            results: vec![
+                result("a", BulkOutcome::Applied),
+                result("b", BulkOutcome::Skipped(String::from("already completed"))),
+            ],
+        };
+        assert_eq!(
+            report.to_string(),
+            "argo/a: stopped\n\
+             argo/b: skipped, already completed\n\
+             stop: 1 applied, 1 skipped, 0 failed"
+        );
+        assert!(report.is_success());
+
+        let report = BulkReport {
+            action: BulkAction::Delete,
+            dry_run: true,
+            results: vec![result("a", BulkOutcome::DryRun)],
+        };
+        assert_eq!(
+            report.to_string(),
+            "argo/a: would delete\ndelete (dry run): 1 applied, 0 skipped, 0 failed"
+        );
+    }
+
+    #[test]
+    fn selector_required() {
+        let config = Config::new();
+        let apply =
+            |options| apply_bulk(&config, "", options, BulkAction::Delete, BulkOptions::new());
+        assert!(matches!(apply(None), Err(ApplyBulkError::NoSelector)));
+        let empty = ListOptions::new().label_selector(LabelSelector::builder().build().unwrap());
+        assert!(matches!(
+            apply(Some(empty)),
+            Err(ApplyBulkError::NoSelector)
+        ));
+    }
+}
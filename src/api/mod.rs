@@ -1,5 +1,16 @@
+use crate::error::Error;
+
+pub mod bulk;
+
 pub mod info;
 
+mod pagination;
+pub use self::pagination::{ListIter, DEFAULT_PAGE_SIZE};
+
+pub mod reflector;
+
+pub mod wait;
+
 pub mod workflow;
 
 pub mod workflow_template;
@@ -7,3 +18,16 @@ pub mod workflow_template;
 pub fn urlencode<T: AsRef<str>>(s: T) -> String {
     ::url::form_urlencoded::byte_serialize(s.as_ref().as_bytes()).collect()
 }
+
+/// Returns whether an error reports that the resource version a watch
+/// started from, or the continue token of a list, expired.
+pub(crate) fn is_expired<T>(error: &Error<T>) -> bool {
+    match error {
+        Error::Response(response) => {
+            response.status == reqwest::StatusCode::GONE
+                || response.content.contains("too old resource version")
+                || response.content.contains("continue parameter is too old")
+        }
+        _ => false,
+    }
+}
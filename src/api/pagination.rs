@@ -0,0 +1,126 @@
+use std::collections::HashSet;
+use std::vec;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1 as metav1;
+
+use crate::error::Error;
+use crate::types::ListOptions;
+
+use super::is_expired;
+
+/// Number of items per page when `ListOptions::limit` is not set.
+pub const DEFAULT_PAGE_SIZE: i64 = 100;
+
+/// Number of times a listing restarts after its continue token expired.
+const MAX_RESTARTS: usize = 3;
+
+type Fetch<'a, T, E> = Box<dyn FnMut(ListOptions) -> Result<Page<T>, Error<E>> + 'a>;
+
+/// A page of a listing: its items, and the token of the next page.
+pub(crate) struct Page<T> {
+    pub items: Vec<T>,
+    pub r#continue: Option<String>,
+    pub resource_version: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, metadata: &metav1::ListMeta) -> Self {
+        Page {
+            items,
+            r#continue: metadata.continue_.clone().filter(|c| !c.is_empty()),
+            resource_version: metadata.resource_version.clone(),
+        }
+    }
+}
+
+/// `ListIter` iterates over the items of a list API, reading pages of
+/// `ListOptions::limit` items as they are needed.
+///
+/// When the continue token of the next page expired (`410 Gone`), the
+/// listing restarts from the first page, skipping the items already
+/// returned. It yields the error when that keeps happening, or when a page
+/// cannot be read, and then ends.
+pub struct ListIter<'a, T, E> {
+    fetch: Fetch<'a, T, E>,
+    key: fn(&T) -> Option<String>,
+    options: ListOptions,
+    page: vec::IntoIter<T>,
+    next: Option<Option<String>>,
+    seen: HashSet<String>,
+    restarts: usize,
+    resource_version: Option<String>,
+}
+
+impl<'a, T, E> ListIter<'a, T, E> {
+    /// Constructs a new `ListIter` reading pages with `fetch`, and naming
+    /// items with `key` to skip them after a restart.
+    pub(crate) fn new<F>(options: ListOptions, key: fn(&T) -> Option<String>, fetch: F) -> Self
+    where
+        F: FnMut(ListOptions) -> Result<Page<T>, Error<E>> + 'a,
+    {
+        let mut options = options;
+        if options.limit.is_none() {
+            options.limit = Some(DEFAULT_PAGE_SIZE);
+        }
+        options.r#continue = None;
+        ListIter {
+            fetch: Box::new(fetch),
+            key,
+            options,
+            page: Vec::new().into_iter(),
+            next: Some(None),
+            seen: HashSet::new(),
+            restarts: 0,
+            resource_version: None,
+        }
+    }
+
+    /// Returns the resource version of the list, as of the last page read.
+    /// Changes made after it can be watched from it.
+    pub fn resource_version(&self) -> Option<&str> {
+        self.resource_version.as_deref()
+    }
+}
+
+impl<T, E> Iterator for ListIter<'_, T, E> {
+    type Item = Result<T, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for item in self.page.by_ref() {
+                if let Some(key) = (self.key)(&item) {
+                    if !self.seen.insert(key) && self.restarts > 0 {
+                        continue;
+                    }
+                }
+                return Some(Ok(item));
+            }
+
+            let token = self.next.take()?;
+            let mut options = self.options.clone();
+            options.r#continue = token.clone();
+            match (self.fetch)(options) {
+                Ok(page) => {
+                    self.page = page.items.into_iter();
+                    self.next = page.r#continue.map(Some);
+                    self.resource_version = page.resource_version;
+                }
+                Err(e) if token.is_some() && is_expired(&e) => {
+                    if self.restarts == MAX_RESTARTS {
+                        return Some(Err(e));
+                    }
+                    self.restarts += 1;
+                    self.next = Some(None);
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+/// Returns the `<namespace>/<name>` of an object, as the key of a
+/// `ListIter`.
+pub(crate) fn object_key(metadata: &metav1::ObjectMeta) -> Option<String> {
+    let namespace = metadata.namespace.as_deref().unwrap_or_default();
+    Some(format!("{}/{}", namespace, metadata.name.as_deref()?))
+}
@@ -0,0 +1,495 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::error::{Error, ReflectorError};
+use crate::types::{workflow::Workflow, FieldSelector, LabelSelector, ListOptions};
+
+use super::is_expired;
+use super::pagination::object_key;
+use super::workflow::{iter_workflows, watch_workflows};
+
+/// Default time after which a watch is closed and opened again.
+const DEFAULT_WATCH_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Watches closed sooner are opened again after a delay.
+const MIN_WATCH_DURATION: Duration = Duration::from_secs(1);
+
+/// First and maximum delays before opening again a watch closed early.
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+type ChangeHandler = Box<dyn FnMut(&Change) + Send>;
+
+/// `Change` to a workflow of a `Store`, given to the handlers of a
+/// `Reflector`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added(Arc<Workflow>),
+    Updated {
+        old: Arc<Workflow>,
+        new: Arc<Workflow>,
+    },
+    Deleted(Arc<Workflow>),
+}
+
+impl Change {
+    /// Returns the workflow as of the change: the new workflow of an
+    /// update, and the last known state of a deleted workflow.
+    pub fn workflow(&self) -> &Arc<Workflow> {
+        match self {
+            Change::Added(workflow) => workflow,
+            Change::Updated { new, .. } => new,
+            Change::Deleted(workflow) => workflow,
+        }
+    }
+}
+
+/// `Store` is the local cache of workflows kept current by a `Reflector`.
+/// It is a handle: clones share the same cache, and can be read from other
+/// threads while the reflector runs.
+#[derive(Clone, Debug, Default)]
+pub struct Store {
+    inner: Arc<RwLock<Index>>,
+}
+
+/// The workflows of a `Store`, by `<namespace>/<name>`, with the indexes
+/// over them.
+#[derive(Debug, Default)]
+struct Index {
+    workflows: HashMap<String, Arc<Workflow>>,
+    names: HashMap<String, BTreeSet<String>>,
+    labels: HashMap<(String, String), BTreeSet<String>>,
+    phases: HashMap<String, BTreeSet<String>>,
+    resource_version: Option<String>,
+    synced: bool,
+}
+
+impl Store {
+    /// Returns the workflow `name` of `namespace`.
+    pub fn get(&self, namespace: &str, name: &str) -> Option<Arc<Workflow>> {
+        let index = self.inner.read().expect("store lock is not poisoned");
+        index
+            .workflows
+            .get(&format!("{}/{}", namespace, name))
+            .cloned()
+    }
+
+    /// Returns all the workflows, ordered by namespace and name.
+    pub fn list(&self) -> Vec<Arc<Workflow>> {
+        let index = self.inner.read().expect("store lock is not poisoned");
+        let mut keys: Vec<&String> = index.workflows.keys().collect();
+        keys.sort();
+        keys.into_iter()
+            .map(|key| index.workflows[key].clone())
+            .collect()
+    }
+
+    /// Returns the workflows named `name`, in any namespace.
+    pub fn by_name(&self, name: &str) -> Vec<Arc<Workflow>> {
+        let index = self.inner.read().expect("store lock is not poisoned");
+        index.lookup(index.names.get(name))
+    }
+
+    /// Returns the workflows with the label `key` set to `value`.
+    pub fn by_label(&self, key: &str, value: &str) -> Vec<Arc<Workflow>> {
+        let index = self.inner.read().expect("store lock is not poisoned");
+        let label = (String::from(key), String::from(value));
+        index.lookup(index.labels.get(&label))
+    }
+
+    /// Returns the workflows in `phase`, e.g. `Running`. Workflows not yet
+    /// picked up by the controller have no phase, and are not returned.
+    pub fn by_phase(&self, phase: &str) -> Vec<Arc<Workflow>> {
+        let index = self.inner.read().expect("store lock is not poisoned");
+        index.lookup(index.phases.get(phase))
+    }
+
+    /// Returns the number of workflows.
+    pub fn len(&self) -> usize {
+        let index = self.inner.read().expect("store lock is not poisoned");
+        index.workflows.len()
+    }
+
+    /// Returns whether the store holds no workflow.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the resource version the store is current with.
+    pub fn resource_version(&self) -> Option<String> {
+        let index = self.inner.read().expect("store lock is not poisoned");
+        index.resource_version.clone()
+    }
+
+    /// Returns whether the workflows were listed, so that the store holds
+    /// all of them.
+    pub fn has_synced(&self) -> bool {
+        let index = self.inner.read().expect("store lock is not poisoned");
+        index.synced
+    }
+}
+
+impl Index {
+    fn lookup(&self, keys: Option<&BTreeSet<String>>) -> Vec<Arc<Workflow>> {
+        let keys = keys.into_iter().flatten();
+        keys.filter_map(|key| self.workflows.get(key).cloned())
+            .collect()
+    }
+
+    /// Adds or replaces `workflow`, returning the workflow it replaced.
+    fn insert(&mut self, key: String, workflow: Arc<Workflow>) -> Option<Arc<Workflow>> {
+        let old = self.remove(&key);
+        let metadata = &workflow.metadata;
+        if let Some(name) = &metadata.name {
+            let keys = self.names.entry(name.clone()).or_default();
+            keys.insert(key.clone());
+        }
+        for (label, value) in metadata.labels.iter().flatten() {
+            let keys = self.labels.entry((label.clone(), value.clone()));
+            keys.or_default().insert(key.clone());
+        }
+        if let Some(phase) = workflow.status.as_deref().and_then(|s| s.phase.as_ref()) {
+            let keys = self.phases.entry(phase.clone()).or_default();
+            keys.insert(key.clone());
+        }
+        self.workflows.insert(key, workflow);
+        old
+    }
+
+    /// Removes the workflow `key`, returning it.
+    fn remove(&mut self, key: &str) -> Option<Arc<Workflow>> {
+        let workflow = self.workflows.remove(key)?;
+        let metadata = &workflow.metadata;
+        if let Some(name) = &metadata.name {
+            unindex(&mut self.names, name, key);
+        }
+        for (label, value) in metadata.labels.iter().flatten() {
+            unindex(&mut self.labels, &(label.clone(), value.clone()), key);
+        }
+        if let Some(phase) = workflow.status.as_deref().and_then(|s| s.phase.as_ref()) {
+            unindex(&mut self.phases, phase, key);
+        }
+        Some(workflow)
+    }
+}
+
+fn unindex<K>(index: &mut HashMap<K, BTreeSet<String>>, value: &K, key: &str)
+where
+    K: std::hash::Hash + Eq,
+{
+    if let Some(keys) = index.get_mut(value) {
+        keys.remove(key);
+        if keys.is_empty() {
+            index.remove(value);
+        }
+    }
+}
+
+/// A `Reflector` keeps a `Store` of the workflows of a namespace current:
+/// it lists them page by page, then follows their changes through a watch,
+/// resuming it from the last resource version seen, and listing them again
+/// when that version expired.
+///
+/// `Reflector::run()` blocks, and is meant to be run on its own thread while
+/// other threads read the `Store`:
+///
+/// ```no_run
+/// use argoflows::api::reflector::Reflector;
+/// use argoflows::config::Config;
+///
+/// let mut reflector = Reflector::new(Config::new(), "argo")
+///     .on_change(|change| println!("{:?}", change.workflow().metadata.name));
+/// let store = reflector.store();
+/// std::thread::spawn(move || reflector.run());
+/// let running = store.by_phase("Running");
+/// ```
+pub struct Reflector {
+    config: Config,
+    namespace: String,
+    label_selector: Option<LabelSelector>,
+    field_selector: Option<FieldSelector>,
+    page_size: Option<i64>,
+    watch_timeout: Duration,
+    store: Store,
+    handlers: Vec<ChangeHandler>,
+}
+
+impl Reflector {
+    /// Constructs a new `Reflector` for the workflows of `namespace`, or of
+    /// all namespaces when it is empty.
+    pub fn new(config: Config, namespace: &str) -> Self {
+        Reflector {
+            config,
+            namespace: String::from(namespace),
+            label_selector: None,
+            field_selector: None,
+            page_size: None,
+            watch_timeout: DEFAULT_WATCH_TIMEOUT,
+            store: Store::default(),
+            handlers: Vec::new(),
+        }
+    }
+
+    /// Only keeps the workflows matching the label selector `selector`.
+    pub fn label_selector(mut self, selector: LabelSelector) -> Self {
+        self.label_selector = Some(selector);
+        self
+    }
+
+    /// Only keeps the workflows matching the field selector `selector`.
+    pub fn field_selector(mut self, selector: FieldSelector) -> Self {
+        self.field_selector = Some(selector);
+        self
+    }
+
+    /// Sets the number of workflows read per page when listing them.
+    /// Defaults to `DEFAULT_PAGE_SIZE`.
+    pub fn page_size(mut self, size: i64) -> Self {
+        self.page_size = Some(size);
+        self
+    }
+
+    /// Sets the time after which a watch is closed and opened again.
+    /// Defaults to 5 minutes.
+    pub fn watch_timeout(mut self, timeout: Duration) -> Self {
+        self.watch_timeout = timeout;
+        self
+    }
+
+    /// Registers a handler called with every change made to the store,
+    /// after it is made.
+    pub fn on_change<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&Change) + Send + 'static,
+    {
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    /// Returns a handle to the store.
+    pub fn store(&self) -> Store {
+        self.store.clone()
+    }
+
+    /// Lists the workflows and replaces the content of the store with them,
+    /// reporting the differences as changes.
+    pub fn relist(&mut self) -> Result<(), ReflectorError> {
+        let mut options = self.list_options();
+        options.limit = self.page_size;
+        let mut workflows =
+            iter_workflows(&self.config, &self.namespace, Some(options), None, None);
+        let mut listed = HashMap::new();
+        for workflow in workflows.by_ref() {
+            let workflow = workflow.map_err(ReflectorError::List)?;
+            if let Some(key) = object_key(&workflow.metadata) {
+                listed.insert(key, Arc::new(workflow));
+            }
+        }
+        let resource_version = workflows.resource_version().map(String::from);
+        drop(workflows);
+
+        let mut changes = Vec::new();
+        {
+            let mut index = self
+                .store
+                .inner
+                .write()
+                .expect("store lock is not poisoned");
+            let mut gone: Vec<String> = index
+                .workflows
+                .keys()
+                .filter(|key| !listed.contains_key(*key))
+                .cloned()
+                .collect();
+            gone.sort();
+            for key in gone {
+                changes.extend(index.remove(&key).map(Change::Deleted));
+            }
+            let mut listed: Vec<(String, Arc<Workflow>)> = listed.into_iter().collect();
+            listed.sort_by(|a, b| a.0.cmp(&b.0));
+            for (key, new) in listed {
+                let unchanged = index.workflows.get(&key).is_some_and(|old| {
+                    old.metadata.resource_version == new.metadata.resource_version
+                });
+                if !unchanged {
+                    changes.push(match index.insert(key, new.clone()) {
+                        Some(old) => Change::Updated { old, new },
+                        None => Change::Added(new),
+                    });
+                }
+            }
+            index.resource_version = resource_version;
+            index.synced = true;
+        }
+        self.notify(&changes);
+        Ok(())
+    }
+
+    /// Watches the workflows from the resource version of the store,
+    /// applying their changes to it until the watch is closed. Lists them
+    /// first when the store was not synced yet, and again when the version
+    /// expired.
+    pub fn watch(&mut self) -> Result<(), ReflectorError> {
+        let resource_version = self.store.resource_version();
+        if resource_version.is_none() || !self.store.has_synced() {
+            return self.relist();
+        }
+
+        let mut options = self.list_options();
+        options.resource_version = resource_version;
+        options.allow_watch_bookmarks = Some(true);
+        options.timeout_seconds = Some(self.watch_timeout.as_secs().max(1) as i64);
+        let events = match watch_workflows(&self.config, &self.namespace, Some(options), None) {
+            Ok(events) => events,
+            Err(e) if is_expired(&e) => return self.relist(),
+            Err(e) => return Err(ReflectorError::Watch(e)),
+        };
+        for event in events {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) if is_expired(&e) => return self.relist(),
+                // The stream was cut, e.g. by a proxy: it is opened again.
+                Err(Error::Io(_)) => return Ok(()),
+                Err(e) => return Err(ReflectorError::Watch(e)),
+            };
+            let workflow = Arc::new(*event.object);
+            let change = {
+                let mut index = self
+                    .store
+                    .inner
+                    .write()
+                    .expect("store lock is not poisoned");
+                if let Some(version) = &workflow.metadata.resource_version {
+                    index.resource_version = Some(version.clone());
+                }
+                let key = object_key(&workflow.metadata);
+                match (event.r#type.as_str(), key) {
+                    // A bookmark only moves the resource version forward.
+                    ("BOOKMARK", _) => None,
+                    ("ADDED" | "MODIFIED", Some(key)) => {
+                        Some(match index.insert(key, workflow.clone()) {
+                            Some(old) => Change::Updated { old, new: workflow },
+                            None => Change::Added(workflow),
+                        })
+                    }
+                    ("DELETED", Some(key)) => {
+                        Some(Change::Deleted(index.remove(&key).unwrap_or(workflow)))
+                    }
+                    _ => None,
+                }
+            };
+            self.notify(change.as_slice());
+        }
+        Ok(())
+    }
+
+    /// Keeps the store current, listing the workflows and then watching
+    /// them, until an error stops it, e.g. the server being unreachable.
+    /// Calling it again resumes from where it stopped.
+    ///
+    /// A watch closed right after it was opened, e.g. by a proxy, is opened
+    /// again after a delay, doubled each time it happens again in a row.
+    pub fn run(&mut self) -> Result<(), ReflectorError> {
+        let mut delay = Duration::ZERO;
+        loop {
+            let (started, synced) = (Instant::now(), self.store.has_synced());
+            self.watch()?;
+            // The first watch lists the workflows, without opening a watch.
+            if !synced || started.elapsed() >= MIN_WATCH_DURATION {
+                delay = Duration::ZERO;
+                continue;
+            }
+            delay = (delay * 2).clamp(INITIAL_RECONNECT_DELAY, MAX_RECONNECT_DELAY);
+            thread::sleep(delay);
+        }
+    }
+
+    fn list_options(&self) -> ListOptions {
+        ListOptions {
+            label_selector: self.label_selector.clone(),
+            field_selector: self.field_selector.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn notify(&mut self, changes: &[Change]) {
+        for change in changes {
+            for handler in self.handlers.iter_mut() {
+                handler(change);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn workflow(name: &str, labels: serde_json::Value, phase: Option<&str>) -> Arc<Workflow> {
+        let workflow = serde_json::from_value(json!({
+            "metadata": {"name": name, "namespace": "argo", "labels": labels},
+            "spec": {},
+            "status": {"phase": phase},
+        }))
+        .unwrap();
+        Arc::new(workflow)
+    }
+
+    fn keys(set: Option<&BTreeSet<String>>) -> Vec<&str> {
+        set.into_iter().flatten().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn phase_change() {
+        let mut index = Index::default();
+        let running = workflow("a", json!({"team": "data"}), Some("Running"));
+        let key = String::from("argo/a");
+        assert_eq!(index.insert(key.clone(), running.clone()), None);
+        index.insert(
+            String::from("argo/b"),
+            workflow("b", json!({}), Some("Running")),
+        );
+        assert_eq!(keys(index.phases.get("Running")), ["argo/a", "argo/b"]);
+
+        let succeeded = workflow("a", json!({"team": "data"}), Some("Succeeded"));
+        assert_eq!(index.insert(key.clone(), succeeded), Some(running));
+        assert_eq!(keys(index.phases.get("Running")), ["argo/b"]);
+        assert_eq!(keys(index.phases.get("Succeeded")), ["argo/a"]);
+        assert_eq!(keys(index.names.get("a")), ["argo/a"]);
+        let label = (String::from("team"), String::from("data"));
+        assert_eq!(keys(index.labels.get(&label)), ["argo/a"]);
+
+        index.insert(key, workflow("a", json!({}), None));
+        assert!(!index.phases.contains_key("Succeeded"));
+        assert!(index.labels.is_empty());
+    }
+
+    #[test]
+    fn remove() {
+        let mut index = Index::default();
+        let labels = json!({"team": "data", "env": "prod"});
+        index.insert(
+            String::from("argo/a"),
+            workflow("a", labels.clone(), Some("Failed")),
+        );
+        index.insert(String::from("other/a"), workflow("a", labels, None));
+
+        let removed = index.remove("argo/a").unwrap();
+        assert_eq!(removed.metadata.name.as_deref(), Some("a"));
+        assert_eq!(keys(index.names.get("a")), ["other/a"]);
+        let label = (String::from("env"), String::from("prod"));
+        assert_eq!(keys(index.labels.get(&label)), ["other/a"]);
+        assert!(index.phases.is_empty());
+
+        assert!(index.remove("other/a").is_some());
+        assert!(index.remove("other/a").is_none());
+        assert!(index.workflows.is_empty());
+        assert!(index.names.is_empty());
+        assert!(index.labels.is_empty());
+    }
+}
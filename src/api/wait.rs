@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::error::{Error, WaitError};
+use crate::types::{
+    template::Outputs,
+    workflow::{CreateRequest, NodeStatus, Workflow},
+    FieldSelector, ListOptions, SelectableField,
+};
+
+use super::is_expired;
+use super::workflow::{create_workflow, get_workflow, watch_workflows};
+
+/// Default time between two reads of a polled workflow.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+type ProgressCallback<'a> = Box<dyn FnMut(&Progress<'_>) + 'a>;
+
+/// `WaitOptions` controls how `wait_for_completion()` waits for a workflow.
+pub struct WaitOptions<'a> {
+    timeout: Option<Duration>,
+    poll_interval: Duration,
+    watch: bool,
+    on_progress: Option<ProgressCallback<'a>>,
+}
+
+impl Default for WaitOptions<'_> {
+    fn default() -> Self {
+        WaitOptions::new()
+    }
+}
+
+impl<'a> WaitOptions<'a> {
+    /// Constructs new `WaitOptions`, waiting without timeout, watching the
+    /// workflow and polling it every 5 seconds when it cannot be watched.
+    pub fn new() -> Self {
+        WaitOptions {
+            timeout: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            watch: true,
+            on_progress: None,
+        }
+    }
+
+    /// Sets how long to wait for the workflow to complete. As a watch is
+    /// only closed by the server or the client, waiting can last a few
+    /// seconds longer.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the time between two reads of the workflow when polling it.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Controls the use of a watch to follow the workflow. When disabled,
+    /// or when the server does not support it, the workflow is polled.
+    /// Defaults to `true`.
+    pub fn watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+
+    /// Registers a callback called with the `Progress` of the workflow when
+    /// it is first read, and then whenever its phase, progress or the phase
+    /// of one of its nodes changes.
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&Progress<'_>) + 'a,
+    {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+}
+
+/// `Progress` of a workflow, given to the callback of `WaitOptions`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Progress<'a> {
+    /// The workflow as read.
+    pub workflow: &'a Workflow,
+    /// `WorkflowStatus::phase`.
+    pub phase: Option<&'a str>,
+    /// `WorkflowStatus::progress`, e.g. `3/5`.
+    pub progress: Option<&'a str>,
+    /// The nodes which appeared or changed phase since the last call.
+    pub changes: Vec<NodeChange<'a>>,
+}
+
+/// `NodeChange` is a node which appeared or changed phase.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeChange<'a> {
+    pub node: &'a NodeStatus,
+    /// Phase of the node when last read, or `None` when it is new.
+    pub previous: Option<String>,
+}
+
+/// `Completion` is a workflow completed by `submit_and_wait()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Completion {
+    pub workflow: Workflow,
+    /// `WorkflowStatus::outputs`: the outputs of the entrypoint.
+    pub outputs: Outputs,
+}
+
+impl Completion {
+    /// Returns the value of the output parameter `name`.
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        let parameters = self.outputs.parameters.iter().flatten();
+        parameters
+            .filter(|p| p.name == name)
+            .find_map(|p| p.value.as_deref())
+    }
+
+    /// Returns the result (stdout) of the entrypoint, for a script template.
+    pub fn result(&self) -> Option<&str> {
+        self.outputs.result.as_deref()
+    }
+}
+
+/// Waits for the workflow `name` of `namespace` to complete, returning it
+/// once it has `Succeeded`.
+///
+/// The workflow is followed with a watch, and polled when the server does
+/// not support watches. A watch which is cut or whose resource version
+/// expired is opened again. Errors are returned when the workflow `Failed`
+/// or ended in `Error`, when it is deleted, when it is not completed in
+/// time, and when it cannot be read or watched.
+pub fn wait_for_completion(
+    config: &Config,
+    namespace: &str,
+    name: &str,
+    mut opts: WaitOptions<'_>,
+) -> Result<Workflow, WaitError> {
+    let deadline = opts.timeout.map(|timeout| Instant::now() + timeout);
+    let mut tracker = Tracker::default();
+    let mut watch = opts.watch;
+    'wait: loop {
+        let workflow = match get_workflow(config, namespace, name, None, None) {
+            Ok(workflow) => workflow,
+            Err(Error::Response(e)) if e.status == reqwest::StatusCode::NOT_FOUND => {
+                return Err(WaitError::Deleted);
+            }
+            Err(e) => return Err(WaitError::Get(e)),
+        };
+        let resource_version = workflow.metadata.resource_version.clone();
+        if let Some(result) = tracker.observe(workflow, &mut opts) {
+            return result;
+        }
+
+        let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        if remaining.is_some_and(|r| r.is_zero()) {
+            return Err(tracker.timeout());
+        }
+
+        if !watch {
+            let interval = opts.poll_interval;
+            thread::sleep(remaining.map_or(interval, |r| r.min(interval)));
+            continue;
+        }
+        let started = Instant::now();
+        let options = ListOptions {
+            field_selector: Some(FieldSelector::new().equals(SelectableField::Name, name)),
+            resource_version,
+            timeout_seconds: remaining.map(|r| r.as_secs().max(1) as i64),
+            ..Default::default()
+        };
+        let events = match watch_workflows(config, namespace, Some(options), None) {
+            Ok(events) => events,
+            Err(e) if is_unsupported(&e) => {
+                watch = false;
+                continue;
+            }
+            // The workflow is read again, and watched from its new version.
+            Err(e) if is_expired(&e) => continue,
+            Err(e) => return Err(WaitError::Watch(e)),
+        };
+        let mut changed = false;
+        for event in events {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) if is_unsupported(&e) => {
+                    watch = false;
+                    break;
+                }
+                // An expired version or a cut stream is followed by a new
+                // read of the workflow.
+                Err(e) if is_expired(&e) => continue 'wait,
+                Err(Error::Io(_)) => break,
+                Err(e) => return Err(WaitError::Watch(e)),
+            };
+            match event.r#type.as_str() {
+                "BOOKMARK" => continue,
+                "DELETED" => return Err(WaitError::Deleted),
+                _ => {}
+            }
+            changed = true;
+            if let Some(result) = tracker.observe(*event.object, &mut opts) {
+                return result;
+            }
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                break;
+            }
+        }
+        // A stream closed right away, e.g. by a proxy, is opened again no
+        // sooner than the workflow would have been polled.
+        let elapsed = started.elapsed();
+        if watch && !changed && elapsed < opts.poll_interval {
+            let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+            let wait = opts.poll_interval - elapsed;
+            thread::sleep(remaining.map_or(wait, |r| r.min(wait)));
+        }
+    }
+}
+
+/// Creates a workflow and waits for it to complete. See
+/// `wait_for_completion()`.
+pub fn submit_and_wait(
+    config: &Config,
+    namespace: &str,
+    body: CreateRequest,
+    opts: WaitOptions<'_>,
+) -> Result<Completion, WaitError> {
+    let created = create_workflow(config, namespace, body).map_err(WaitError::Create)?;
+    let name = created.metadata.name.unwrap_or_default();
+    let namespace = created.metadata.namespace.as_deref().unwrap_or(namespace);
+    let workflow = wait_for_completion(config, namespace, &name, opts)?;
+    let status = workflow.status.as_deref();
+    let outputs = status.and_then(|s| s.outputs.as_deref()).cloned();
+    Ok(Completion {
+        outputs: outputs.unwrap_or_default(),
+        workflow,
+    })
+}
+
+/// Follows the changes of a workflow.
+#[derive(Default)]
+struct Tracker {
+    last: Option<Workflow>,
+    phases: HashMap<String, String>,
+}
+
+impl Tracker {
+    /// Records `workflow`, calling the progress callback when it changed,
+    /// and returns the result of the wait when it is completed.
+    fn observe(
+        &mut self,
+        workflow: Workflow,
+        opts: &mut WaitOptions<'_>,
+    ) -> Option<Result<Workflow, WaitError>> {
+        let status = workflow.status.as_deref();
+        let phase = status.and_then(|s| s.phase.as_deref());
+        let progress = status.and_then(|s| s.progress.as_deref());
+        let last = self.last.as_ref().and_then(|w| w.status.as_deref());
+
+        let mut changes = Vec::new();
+        let mut nodes: Vec<&NodeStatus> = status
+            .and_then(|s| s.nodes.as_ref())
+            .into_iter()
+            .flat_map(|n| n.values())
+            .collect();
+        nodes.sort_by(|a, b| (&a.started_at, &a.name).cmp(&(&b.started_at, &b.name)));
+        for node in nodes {
+            let current = node.phase.clone().unwrap_or_default();
+            let previous = self.phases.insert(node.id.clone(), current.clone());
+            if previous.as_ref() != Some(&current) {
+                changes.push(NodeChange { node, previous });
+            }
+        }
+
+        let changed = self.last.is_none()
+            || !changes.is_empty()
+            || last.and_then(|s| s.phase.as_deref()) != phase
+            || last.and_then(|s| s.progress.as_deref()) != progress;
+        if let (true, Some(callback)) = (changed, opts.on_progress.as_mut()) {
+            callback(&Progress {
+                workflow: &workflow,
+                phase,
+                progress,
+                changes,
+            });
+        }
+
+        match phase {
+            Some("Succeeded") => Some(Ok(workflow)),
+            Some("Failed" | "Error") => Some(Err(WaitError::Failed(Box::new(workflow)))),
+            _ => {
+                self.last = Some(workflow);
+                None
+            }
+        }
+    }
+
+    /// Returns the timeout error, holding the workflow as last seen.
+    fn timeout(&mut self) -> WaitError {
+        WaitError::Timeout(Box::new(self.last.take().unwrap_or_default()))
+    }
+}
+
+/// Returns whether a watch failed because the server does not support it.
+fn is_unsupported<T>(error: &Error<T>) -> bool {
+    match error {
+        Error::Response(response) => matches!(
+            response.status,
+            reqwest::StatusCode::NOT_FOUND
+                | reqwest::StatusCode::METHOD_NOT_ALLOWED
+                | reqwest::StatusCode::NOT_IMPLEMENTED
+        ),
+        _ => false,
+    }
+}
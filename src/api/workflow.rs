@@ -1,16 +1,50 @@
+use std::io::{BufRead, BufReader, Lines};
+use std::time::Duration;
+
 use k8s_openapi::apimachinery::pkg::apis::meta::v1 as metav1;
+use reqwest::blocking::{RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
 
 use crate::config::Config;
 use crate::error::{
-    workflow::{CreateWorkflowError, DeleteWorkflowError, GetWorkflowError, ListWorkflowsError},
-    Error,
+    workflow::{
+        CreateWorkflowError, DeleteWorkflowError, GetWorkflowError, ListWorkflowsError,
+        ResumeWorkflowError, RetryWorkflowError, StopWorkflowError, SuspendWorkflowError,
+        TerminateWorkflowError, WatchWorkflowsError,
+    },
+    Error, GatewayRuntimeError, NodesError,
 };
 
 use crate::types::{
-    workflow::{CreateRequest, Workflow, WorkflowList},
-    ListOptions, ResponseContent,
+    workflow::{
+        CreateRequest, ResumeRequest, RetryRequest, StopRequest, SuspendRequest, TerminateRequest,
+        WatchEvent, Workflow, WorkflowList,
+    },
+    Fields, ListOptions, ResponseContent,
 };
 
+use super::pagination::{object_key, ListIter, Page};
+
+/// Fields of a list always kept by a projection, which paging needs: the
+/// names of the items tell apart the ones already returned after a restart.
+const LIST_FIELDS: [&str; 4] = [
+    "metadata.continue",
+    "metadata.resourceVersion",
+    "items.metadata.name",
+    "items.metadata.namespace",
+];
+
+/// Fields of a watch event always kept by a projection.
+const WATCH_FIELDS: [&str; 3] = [
+    "result.type",
+    "result.object.metadata.name",
+    "result.object.metadata.namespace",
+];
+
+/// Time given to the server to end a watch after its `timeout_seconds`.
+const WATCH_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
 pub fn create_workflow(
     config: &Config,
     namespace: &str,
@@ -136,12 +170,15 @@ pub fn delete_workflow(
     }
 }
 
+/// Reads the workflow `name`. Its compressed nodes are decompressed, and the
+/// server rehydrates offloaded ones; `WorkflowStatus::nodes_complete()` is
+/// `false` when either failed.
 pub fn get_workflow(
     config: &Config,
     namespace: &str,
     name: &str,
     resource_version: Option<&str>,
-    fields: Option<&str>,
+    fields: Option<&Fields>,
 ) -> Result<Workflow, Error<GetWorkflowError>> {
     let uri = format!(
         "{}/api/v1/workflows/{namespace}/{name}",
@@ -155,8 +192,8 @@ pub fn get_workflow(
     if let Some(version) = resource_version {
         req_builder = req_builder.query(&[("getOptions.resourceVersion", &version.to_string())]);
     }
-    if let Some(local_var_str) = fields {
-        req_builder = req_builder.query(&[("fields", &local_var_str.to_string())]);
+    if let Some(val) = fields.filter(|f| !f.is_empty()) {
+        req_builder = req_builder.query(&[("fields", &val.to_string())]);
     }
     if let Some(bearer_token) = &config.bearer_token {
         req_builder = req_builder.bearer_auth(bearer_token);
@@ -168,7 +205,9 @@ pub fn get_workflow(
     let content = res.text()?;
 
     if !status.is_client_error() && !status.is_server_error() {
-        serde_json::from_str(&content).map_err(Error::from)
+        let mut workflow: Workflow = serde_json::from_str(&content)?;
+        decompress(&mut workflow);
+        Ok(workflow)
     } else {
         let entity: Option<GetWorkflowError> = serde_json::from_str(&content).ok();
         let error = ResponseContent {
@@ -180,11 +219,51 @@ pub fn get_workflow(
     }
 }
 
+/// Completes the nodes of `workflow`, as read from any API: decompresses
+/// its `compressed_nodes` and, when they were offloaded, reads them from the
+/// server, which rehydrates them from the database of the controller.
+///
+/// An error is returned when the offloaded nodes cannot be read, e.g. when
+/// the server has no access to the database.
+pub fn hydrate_workflow(config: &Config, workflow: &mut Workflow) -> Result<(), NodesError> {
+    let status = match workflow.status.as_deref_mut() {
+        Some(status) => status,
+        None => return Ok(()),
+    };
+    status.decompress_nodes()?;
+    if !status.is_offloaded() {
+        return Ok(());
+    }
+
+    let namespace = workflow.metadata.namespace.as_deref().unwrap_or_default();
+    let name = workflow.metadata.name.as_deref().unwrap_or_default();
+    let fields = Fields::new()
+        .nodes()
+        .field("status.compressedNodes")
+        .field("status.offloadNodeStatusVersion");
+    let hydrated = get_workflow(config, namespace, name, None, Some(&fields))
+        .map_err(NodesError::Get)?
+        .status
+        .unwrap_or_default();
+    let mut hydrated = *hydrated;
+    hydrated.decompress_nodes()?;
+    if hydrated.is_offloaded() {
+        let version = hydrated.offload_node_status_version.unwrap_or_default();
+        return Err(NodesError::Offloaded(version));
+    }
+    status.nodes = hydrated.nodes;
+    status.offload_node_status_version = hydrated.offload_node_status_version;
+    Ok(())
+}
+
+/// Lists the workflows of `namespace`. Their compressed nodes are
+/// decompressed, but offloaded ones are not read: use `hydrate_workflow()`
+/// on the workflows for which `WorkflowStatus::nodes_complete()` is `false`.
 pub fn list_workflows(
     config: &Config,
     namespace: &str,
     list_options: Option<ListOptions>,
-    fields: Option<&str>,
+    fields: Option<&Fields>,
     name_filter: Option<&str>,
 ) -> Result<WorkflowList, Error<ListWorkflowsError>> {
     let uri = format!(
@@ -195,7 +274,361 @@ pub fn list_workflows(
 
     let mut req_builder = config.client.request(reqwest::Method::GET, uri.as_str());
 
+    req_builder = list_query(req_builder, list_options.unwrap_or_default());
+    if let Some(val) = fields.filter(|f| !f.is_empty()) {
+        let fields = val.render("items.", &LIST_FIELDS);
+        req_builder = req_builder.query(&[("fields", &fields)]);
+    }
+    if let Some(val) = name_filter {
+        req_builder = req_builder.query(&[("nameFilter", &val.to_string())]);
+    }
+
+    if let Some(bearer_token) = &config.bearer_token {
+        req_builder = req_builder.bearer_auth(bearer_token);
+    }
+
+    let req = req_builder.build()?;
+    let res = config.client.execute(req)?;
+    let status = res.status();
+    let content = res.text()?;
+
+    if !status.is_client_error() && !status.is_server_error() {
+        let mut list: WorkflowList = serde_json::from_str(&content)?;
+        list.items.iter_mut().for_each(decompress);
+        Ok(list)
+    } else {
+        let entity: Option<ListWorkflowsError> = serde_json::from_str(&content).ok();
+        let error = ResponseContent {
+            status,
+            content,
+            entity,
+        };
+        Err(Error::Response(error))
+    }
+}
+
+/// Lists the workflows of `namespace` lazily, reading pages of
+/// `list_options.limit` workflows (`DEFAULT_PAGE_SIZE` when unset) as the
+/// iterator is advanced. The `continue` token of `list_options` is ignored.
+///
+/// `fields` projects the workflows as for `list_workflows`.
+pub fn iter_workflows<'a>(
+    config: &'a Config,
+    namespace: &'a str,
+    list_options: Option<ListOptions>,
+    fields: Option<&Fields>,
+    name_filter: Option<&'a str>,
+) -> ListIter<'a, Workflow, ListWorkflowsError> {
+    let fields = fields.cloned();
+    ListIter::new(
+        list_options.unwrap_or_default(),
+        |w: &Workflow| object_key(&w.metadata),
+        move |options| {
+            let list = list_workflows(
+                config,
+                namespace,
+                Some(options),
+                fields.as_ref(),
+                name_filter,
+            )?;
+            Ok(Page::new(list.items, &list.metadata))
+        },
+    )
+}
+
+/// Lists all the workflows of `namespace`, reading them page by page. See
+/// `iter_workflows()`.
+pub fn list_all_workflows(
+    config: &Config,
+    namespace: &str,
+    list_options: Option<ListOptions>,
+    fields: Option<&Fields>,
+    name_filter: Option<&str>,
+) -> Result<Vec<Workflow>, Error<ListWorkflowsError>> {
+    iter_workflows(config, namespace, list_options, fields, name_filter).collect()
+}
+
+/// Resumes the suspended workflow `name`, or the suspended nodes selected
+/// by `body.node_field_selector`.
+pub fn resume_workflow(
+    config: &Config,
+    namespace: &str,
+    name: &str,
+    body: ResumeRequest,
+) -> Result<Workflow, Error<ResumeWorkflowError>> {
+    put_workflow(config, namespace, name, "resume", &body)
+}
+
+/// Retries the failed or errored workflow `name`, rerunning its failed
+/// nodes.
+pub fn retry_workflow(
+    config: &Config,
+    namespace: &str,
+    name: &str,
+    body: RetryRequest,
+) -> Result<Workflow, Error<RetryWorkflowError>> {
+    put_workflow(config, namespace, name, "retry", &body)
+}
+
+/// Stops the workflow `name`, running its exit handlers.
+pub fn stop_workflow(
+    config: &Config,
+    namespace: &str,
+    name: &str,
+    body: StopRequest,
+) -> Result<Workflow, Error<StopWorkflowError>> {
+    put_workflow(config, namespace, name, "stop", &body)
+}
+
+/// Suspends the workflow `name`: no new node is started until it is
+/// resumed.
+pub fn suspend_workflow(
+    config: &Config,
+    namespace: &str,
+    name: &str,
+    body: SuspendRequest,
+) -> Result<Workflow, Error<SuspendWorkflowError>> {
+    put_workflow(config, namespace, name, "suspend", &body)
+}
+
+/// Terminates the workflow `name`, without running its exit handlers.
+pub fn terminate_workflow(
+    config: &Config,
+    namespace: &str,
+    name: &str,
+    body: TerminateRequest,
+) -> Result<Workflow, Error<TerminateWorkflowError>> {
+    put_workflow(config, namespace, name, "terminate", &body)
+}
+
+/// Watches the workflows of `namespace`, returning the stream of their
+/// changes as they happen. Changes are read as the stream is iterated;
+/// it ends after the `timeout_seconds` of `list_options`, or when the
+/// server or the timeout of the client closes it. As for `list_workflows`,
+/// the nodes of a workflow may not be complete; see `hydrate_workflow()`.
+pub fn watch_workflows(
+    config: &Config,
+    namespace: &str,
+    list_options: Option<ListOptions>,
+    fields: Option<&Fields>,
+) -> Result<WorkflowWatch, Error<WatchWorkflowsError>> {
+    let uri = format!(
+        "{}/api/v1/workflow-events/{namespace}",
+        config.host,
+        namespace = super::urlencode(namespace)
+    );
+
+    let mut req_builder = config.client.request(reqwest::Method::GET, uri.as_str());
+
     let list_options = list_options.unwrap_or_default();
+    let timeout = list_options
+        .timeout_seconds
+        .and_then(|s| u64::try_from(s).ok());
+    if let Some(seconds) = timeout.filter(|s| *s > 0) {
+        req_builder = req_builder.timeout(Duration::from_secs(seconds) + WATCH_GRACE_PERIOD);
+    }
+    req_builder = list_query(req_builder, list_options);
+    if let Some(val) = fields.filter(|f| !f.is_empty()) {
+        let fields = val.render("result.object.", &WATCH_FIELDS);
+        req_builder = req_builder.query(&[("fields", &fields)]);
+    }
+
+    if let Some(bearer_token) = &config.bearer_token {
+        req_builder = req_builder.bearer_auth(bearer_token);
+    }
+
+    let req = req_builder.build()?;
+    let res = config.client.execute(req)?;
+    let status = res.status();
+
+    if !status.is_client_error() && !status.is_server_error() {
+        Ok(WorkflowWatch {
+            lines: BufReader::new(res).lines(),
+            done: false,
+        })
+    } else {
+        let content = res.text()?;
+        let entity: Option<WatchWorkflowsError> = serde_json::from_str(&content).ok();
+        let error = ResponseContent {
+            status,
+            content,
+            entity,
+        };
+        Err(Error::Response(error))
+    }
+}
+
+/// `WorkflowWatch` is the stream of changes returned by `watch_workflows`.
+/// It yields an error and ends when the stream cannot be read, or when the
+/// server reports an error, e.g. that the `resource_version` watched from
+/// is too old.
+pub struct WorkflowWatch {
+    lines: Lines<BufReader<Response>>,
+    done: bool,
+}
+
+/// A line of a watch stream.
+#[derive(Deserialize)]
+struct StreamResult {
+    result: Option<StreamEvent>,
+    error: Option<GatewayRuntimeError>,
+}
+
+/// An event of a watch stream, before its object is read.
+#[derive(Deserialize)]
+struct StreamEvent {
+    r#type: String,
+    #[serde(default)]
+    object: serde_json::Value,
+}
+
+impl StreamEvent {
+    /// Reads the workflow of the event. Bookmarks only hold the metadata of
+    /// a workflow, with the resource version they mark.
+    fn into_event(self) -> Result<WatchEvent, serde_json::Error> {
+        let object = if self.r#type == "BOOKMARK" {
+            let metadata = self.object.get("metadata").cloned();
+            Workflow {
+                metadata: serde_json::from_value(metadata.unwrap_or_else(|| json!({})))?,
+                ..Default::default()
+            }
+        } else {
+            serde_json::from_value(self.object)?
+        };
+        Ok(WatchEvent {
+            r#type: self.r#type,
+            object: Box::new(object),
+        })
+    }
+}
+
+impl Iterator for WorkflowWatch {
+    type Item = Result<WatchEvent, Error<WatchWorkflowsError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(Error::Io(e)));
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let result: StreamResult = match serde_json::from_str(&line) {
+                Ok(result) => result,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(Error::from(e)));
+                }
+            };
+            if let Some(event) = result.result {
+                let mut event = match event.into_event() {
+                    Ok(event) => event,
+                    Err(e) => {
+                        self.done = true;
+                        return Some(Err(Error::from(e)));
+                    }
+                };
+                decompress(&mut event.object);
+                return Some(Ok(event));
+            }
+            if let Some(error) = result.error {
+                self.done = true;
+                let status = error
+                    .code
+                    .and_then(grpc_status)
+                    .unwrap_or(reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+                return Some(Err(Error::Response(ResponseContent {
+                    status,
+                    content: line,
+                    entity: Some(WatchWorkflowsError::DefaultResponse(error)),
+                })));
+            }
+        }
+        None
+    }
+}
+
+/// Applies the action `action` to the workflow `name`, as the `PUT`
+/// endpoints of the workflow service do, returning the updated workflow.
+fn put_workflow<B, E>(
+    config: &Config,
+    namespace: &str,
+    name: &str,
+    action: &str,
+    body: &B,
+) -> Result<Workflow, Error<E>>
+where
+    B: Serialize,
+    E: DeserializeOwned,
+{
+    let uri = format!(
+        "{}/api/v1/workflows/{namespace}/{name}/{action}",
+        config.host,
+        namespace = super::urlencode(namespace),
+        name = super::urlencode(name),
+        action = action
+    );
+
+    let mut req_builder = config.client.request(reqwest::Method::PUT, uri.as_str());
+    req_builder = req_builder.json(body);
+
+    if let Some(bearer_token) = &config.bearer_token {
+        req_builder = req_builder.bearer_auth(bearer_token);
+    }
+
+    let req = req_builder.build()?;
+    let res = config.client.execute(req)?;
+    let status = res.status();
+    let content = res.text()?;
+
+    if !status.is_client_error() && !status.is_server_error() {
+        let mut workflow: Workflow = serde_json::from_str(&content)?;
+        decompress(&mut workflow);
+        Ok(workflow)
+    } else {
+        let entity: Option<E> = serde_json::from_str(&content).ok();
+        let error = ResponseContent {
+            status,
+            content,
+            entity,
+        };
+        Err(Error::Response(error))
+    }
+}
+
+/// Decompresses the nodes of a workflow read from the server, keeping them
+/// compressed when they cannot be decoded: `nodes_complete()` then reports
+/// it, and `hydrate_workflow()` returns the error.
+fn decompress(workflow: &mut Workflow) {
+    if let Some(status) = workflow.status.as_deref_mut() {
+        let _ = status.decompress_nodes();
+    }
+}
+
+/// Returns the HTTP status matching a gRPC status code, as the gateway of
+/// the server maps them.
+fn grpc_status(code: i32) -> Option<reqwest::StatusCode> {
+    let status = match code {
+        3 | 9 | 11 => reqwest::StatusCode::BAD_REQUEST,
+        5 => reqwest::StatusCode::NOT_FOUND,
+        6 | 10 => reqwest::StatusCode::CONFLICT,
+        7 => reqwest::StatusCode::FORBIDDEN,
+        16 => reqwest::StatusCode::UNAUTHORIZED,
+        4 => reqwest::StatusCode::GATEWAY_TIMEOUT,
+        8 => reqwest::StatusCode::TOO_MANY_REQUESTS,
+        12 => reqwest::StatusCode::NOT_IMPLEMENTED,
+        14 => reqwest::StatusCode::SERVICE_UNAVAILABLE,
+        _ => return None,
+    };
+    Some(status)
+}
+
+/// Adds the query parameters of `list_options` to a request.
+fn list_query(mut req_builder: RequestBuilder, list_options: ListOptions) -> RequestBuilder {
     if let Some(val) = list_options.label_selector {
         req_builder = req_builder.query(&[("listOptions.labelSelector", &val.to_string())]);
     }
@@ -226,31 +659,5 @@ pub fn list_workflows(
     if let Some(val) = list_options.send_initial_events {
         req_builder = req_builder.query(&[("listOptions.sendInitialEvents", &val.to_string())]);
     }
-    if let Some(val) = fields {
-        req_builder = req_builder.query(&[("fields", &val.to_string())]);
-    }
-    if let Some(val) = name_filter {
-        req_builder = req_builder.query(&[("nameFilter", &val.to_string())]);
-    }
-
-    if let Some(bearer_token) = &config.bearer_token {
-        req_builder = req_builder.bearer_auth(bearer_token);
-    }
-
-    let req = req_builder.build()?;
-    let res = config.client.execute(req)?;
-    let status = res.status();
-    let content = res.text()?;
-
-    if !status.is_client_error() && !status.is_server_error() {
-        serde_json::from_str(&content).map_err(Error::from)
-    } else {
-        let entity: Option<ListWorkflowsError> = serde_json::from_str(&content).ok();
-        let error = ResponseContent {
-            status,
-            content,
-            entity,
-        };
-        Err(Error::Response(error))
-    }
+    req_builder
 }
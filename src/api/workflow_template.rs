@@ -15,6 +15,8 @@ use crate::types::{
     ListOptions, ResponseContent,
 };
 
+use super::pagination::{object_key, ListIter, Page};
+
 pub fn create_workflow_template(
     config: &Config,
     namespace: &str,
@@ -130,7 +132,7 @@ pub fn delete_workflow_template(
         let error = ResponseContent {
             status,
             content,
-            entity: entity,
+            entity,
         };
         Err(Error::Response(error))
     }
@@ -234,10 +236,10 @@ pub fn list_workflow_templates(
 
     let list_options = list_options.unwrap_or_default();
     if let Some(val) = list_options.label_selector {
-        req_builder = req_builder.query(&[("listOptions.labelSelector", val)]);
+        req_builder = req_builder.query(&[("listOptions.labelSelector", val.to_string())]);
     }
     if let Some(val) = list_options.field_selector {
-        req_builder = req_builder.query(&[("listOptions.fieldSelector", val)]);
+        req_builder = req_builder.query(&[("listOptions.fieldSelector", val.to_string())]);
     }
     if let Some(val) = list_options.watch {
         req_builder = req_builder.query(&[("listOptions.watch", val)]);
@@ -322,3 +324,33 @@ pub fn update_workflow_template(
         Err(Error::Response(error))
     }
 }
+
+/// Lists the workflow templates of `namespace` lazily, reading pages of
+/// `list_options.limit` templates (`DEFAULT_PAGE_SIZE` when unset) as the
+/// iterator is advanced. The `continue` token of `list_options` is ignored.
+pub fn iter_workflow_templates<'a>(
+    config: &'a Config,
+    namespace: &'a str,
+    name_pattern: Option<&'a str>,
+    list_options: Option<ListOptions>,
+) -> ListIter<'a, WorkflowTemplate, ListWorkflowTemplatesError> {
+    ListIter::new(
+        list_options.unwrap_or_default(),
+        |t: &WorkflowTemplate| object_key(&t.metadata),
+        move |options| {
+            let list = list_workflow_templates(config, namespace, name_pattern, Some(options))?;
+            Ok(Page::new(list.items, &list.metadata))
+        },
+    )
+}
+
+/// Lists all the workflow templates of `namespace`, reading them page by
+/// page. See `iter_workflow_templates()`.
+pub fn list_all_workflow_templates(
+    config: &Config,
+    namespace: &str,
+    name_pattern: Option<&str>,
+    list_options: Option<ListOptions>,
+) -> Result<Vec<WorkflowTemplate>, Error<ListWorkflowTemplatesError>> {
+    iter_workflow_templates(config, namespace, name_pattern, list_options).collect()
+}
@@ -0,0 +1,75 @@
+use std::error;
+use std::fmt;
+
+/// Error returned by the `build()` method of the workflow and template builders.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    /// A field required by the resource was not set.
+    MissingField {
+        kind: &'static str,
+        field: &'static str,
+    },
+    /// Two entries of the same kind share a name.
+    DuplicateName { kind: &'static str, name: String },
+    /// A reference points to a template that is not declared in the spec.
+    UnknownTemplate(String),
+    /// A DAG task depends on a task that is not declared in the DAG.
+    UnknownTask { task: String, dependency: String },
+    /// The dependencies between DAG tasks form a cycle. The path starts and
+    /// ends with the same task.
+    Cycle(Vec<String>),
+    /// A DAG task sets both `dependencies` and `depends`.
+    MixedDependencies(String),
+    /// A reference points to an input or output the template does not declare.
+    Undeclared {
+        template: String,
+        kind: &'static str,
+        name: String,
+    },
+    /// A builder option is not applicable to the kind of resource being built.
+    Unsupported {
+        kind: &'static str,
+        option: &'static str,
+    },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingField { kind, field } => {
+                write!(f, "{} is missing required field '{}'", kind, field)
+            }
+            BuildError::DuplicateName { kind, name } => {
+                write!(f, "duplicate {} name '{}'", kind, name)
+            }
+            BuildError::UnknownTemplate(name) => write!(f, "unknown template '{}'", name),
+            BuildError::UnknownTask { task, dependency } => {
+                write!(
+                    f,
+                    "task '{}' depends on unknown task '{}'",
+                    task, dependency
+                )
+            }
+            BuildError::Cycle(path) => write!(f, "dependency cycle: {}", path.join(" -> ")),
+            BuildError::MixedDependencies(task) => write!(
+                f,
+                "task '{}' can not use both 'dependencies' and 'depends'",
+                task
+            ),
+            BuildError::Undeclared {
+                template,
+                kind,
+                name,
+            } => write!(
+                f,
+                "template '{}' does not declare {} '{}'",
+                template, kind, name
+            ),
+            BuildError::Unsupported { kind, option } => {
+                write!(f, "'{}' is not supported by {} templates", option, kind)
+            }
+        }
+    }
+}
+
+impl error::Error for BuildError {}
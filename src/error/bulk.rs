@@ -0,0 +1,77 @@
+use std::error;
+use std::fmt;
+
+use super::workflow::{
+    DeleteWorkflowError, ListWorkflowsError, ResumeWorkflowError, RetryWorkflowError,
+    StopWorkflowError, SuspendWorkflowError, TerminateWorkflowError,
+};
+use super::Error;
+
+/// Error returned by `apply_bulk()` when the action is applied to none of
+/// the workflows.
+#[derive(Debug)]
+pub enum ApplyBulkError {
+    /// Neither a label nor a field selector was given, which would select
+    /// every workflow, and `BulkOptions::all()` was not set.
+    NoSelector,
+    /// The workflows could not be listed.
+    List(Error<ListWorkflowsError>),
+}
+
+impl fmt::Display for ApplyBulkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyBulkError::NoSelector => write!(
+                f,
+                "no label or field selector given, refusing to select every workflow"
+            ),
+            ApplyBulkError::List(e) => write!(f, "failed to list workflows: {}", e),
+        }
+    }
+}
+
+impl error::Error for ApplyBulkError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ApplyBulkError::NoSelector => None,
+            ApplyBulkError::List(e) => Some(e),
+        }
+    }
+}
+
+/// Error of an action applied to one of the workflows of `apply_bulk()`.
+#[derive(Debug)]
+pub enum BulkError {
+    Delete(Error<DeleteWorkflowError>),
+    Stop(Error<StopWorkflowError>),
+    Terminate(Error<TerminateWorkflowError>),
+    Suspend(Error<SuspendWorkflowError>),
+    Resume(Error<ResumeWorkflowError>),
+    Retry(Error<RetryWorkflowError>),
+}
+
+impl fmt::Display for BulkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BulkError::Delete(e) => write!(f, "failed to delete workflow: {}", e),
+            BulkError::Stop(e) => write!(f, "failed to stop workflow: {}", e),
+            BulkError::Terminate(e) => write!(f, "failed to terminate workflow: {}", e),
+            BulkError::Suspend(e) => write!(f, "failed to suspend workflow: {}", e),
+            BulkError::Resume(e) => write!(f, "failed to resume workflow: {}", e),
+            BulkError::Retry(e) => write!(f, "failed to retry workflow: {}", e),
+        }
+    }
+}
+
+impl error::Error for BulkError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            BulkError::Delete(e) => Some(e),
+            BulkError::Stop(e) => Some(e),
+            BulkError::Terminate(e) => Some(e),
+            BulkError::Suspend(e) => Some(e),
+            BulkError::Resume(e) => Some(e),
+            BulkError::Retry(e) => Some(e),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::error;
+use std::fmt;
+
+/// Error returned when parsing or evaluating an `Expression`. `position` is
+/// the byte offset in the expression of the part at fault.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionError {
+    /// The expression is not valid.
+    Syntax { position: usize, message: String },
+    /// A variable is not set in the context.
+    UnknownVariable { position: usize, name: String },
+    /// A function is not one of the builtin or Sprig functions.
+    UnknownFunction { position: usize, name: String },
+    /// The evaluation failed, e.g. an operator was given values of the wrong
+    /// types.
+    Evaluation { position: usize, message: String },
+}
+
+impl ExpressionError {
+    /// Returns the byte offset in the expression of the part at fault.
+    pub fn position(&self) -> usize {
+        match self {
+            ExpressionError::Syntax { position, .. }
+            | ExpressionError::UnknownVariable { position, .. }
+            | ExpressionError::UnknownFunction { position, .. }
+            | ExpressionError::Evaluation { position, .. } => *position,
+        }
+    }
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpressionError::Syntax { position, message } => {
+                write!(f, "syntax error at offset {}: {}", position, message)
+            }
+            ExpressionError::UnknownVariable { position, name } => {
+                write!(f, "unknown variable '{}' at offset {}", name, position)
+            }
+            ExpressionError::UnknownFunction { position, name } => {
+                write!(f, "unknown function '{}' at offset {}", name, position)
+            }
+            ExpressionError::Evaluation { position, message } => {
+                write!(f, "evaluation error at offset {}: {}", position, message)
+            }
+        }
+    }
+}
+
+impl error::Error for ExpressionError {}
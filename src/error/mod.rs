@@ -1,6 +1,45 @@
+mod builder;
+pub use self::builder::BuildError;
+
+mod bulk;
+pub use self::bulk::{ApplyBulkError, BulkError};
+
 mod error;
 pub use self::error::*;
 
+mod expression;
+pub use self::expression::ExpressionError;
+
+mod nodes;
+pub use self::nodes::NodesError;
+
+mod params;
+pub use self::params::ParamsError;
+
+mod pod;
+pub use self::pod::PodPreviewError;
+
+mod reflector;
+pub use self::reflector::ReflectorError;
+
+mod render;
+pub use self::render::RenderError;
+
+mod resolve;
+pub use self::resolve::ResolveError;
+
+mod selector;
+pub use self::selector::SelectorError;
+
+mod simulation;
+pub use self::simulation::SimulationError;
+
+mod validation;
+pub use self::validation::{ValidationError, ValidationErrorKind};
+
+mod wait;
+pub use self::wait::WaitError;
+
 pub mod info;
 
 pub mod workflow;
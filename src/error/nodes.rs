@@ -0,0 +1,50 @@
+use std::error;
+use std::fmt;
+use std::io;
+
+use super::workflow::GetWorkflowError;
+use super::Error;
+
+/// Error returned when the nodes of a workflow status cannot be read.
+#[derive(Debug)]
+pub enum NodesError {
+    /// `compressed_nodes` is not valid base64.
+    Base64(base64::DecodeError),
+    /// `compressed_nodes` is not valid gzip.
+    Gzip(io::Error),
+    /// The decompressed nodes are not a valid node map.
+    Serde(serde_json::Error),
+    /// The workflow could not be read to rehydrate its offloaded nodes.
+    Get(Error<GetWorkflowError>),
+    /// The nodes are offloaded, and the server did not return them. Holds
+    /// the `offload_node_status_version`.
+    Offloaded(String),
+}
+
+impl fmt::Display for NodesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodesError::Base64(e) => write!(f, "invalid base64 in compressed nodes: {}", e),
+            NodesError::Gzip(e) => write!(f, "invalid gzip in compressed nodes: {}", e),
+            NodesError::Serde(e) => write!(f, "invalid compressed nodes: {}", e),
+            NodesError::Get(e) => write!(f, "failed to get workflow: {}", e),
+            NodesError::Offloaded(version) => write!(
+                f,
+                "nodes are offloaded (version '{}') and were not returned by the server",
+                version
+            ),
+        }
+    }
+}
+
+impl error::Error for NodesError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            NodesError::Base64(e) => Some(e),
+            NodesError::Gzip(e) => Some(e),
+            NodesError::Serde(e) => Some(e),
+            NodesError::Get(e) => Some(e),
+            NodesError::Offloaded(_) => None,
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::error;
+use std::fmt;
+
+/// Error returned when converting workflow parameters into a type
+/// implementing `WorkflowParams`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamsError {
+    /// A required parameter has neither a value nor a default.
+    Missing(String),
+    /// The value of a parameter can not be parsed into the field type.
+    Invalid {
+        name: String,
+        value: String,
+        message: String,
+    },
+    /// The value of a parameter is not one of its allowed `enum` values.
+    NotAllowed { name: String, value: String },
+}
+
+impl fmt::Display for ParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamsError::Missing(name) => write!(f, "missing value for parameter '{}'", name),
+            ParamsError::Invalid {
+                name,
+                value,
+                message,
+            } => write!(
+                f,
+                "invalid value '{}' for parameter '{}': {}",
+                value, name, message
+            ),
+            ParamsError::NotAllowed { name, value } => {
+                write!(
+                    f,
+                    "value '{}' is not allowed for parameter '{}'",
+                    value, name
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for ParamsError {}
@@ -0,0 +1,22 @@
+use std::error;
+use std::fmt;
+
+/// Error returned by `WorkflowSpec::pod_spec_preview()`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PodPreviewError {
+    /// A pod spec patch is not valid YAML or JSON, or does not produce a
+    /// valid pod spec.
+    InvalidPatch { patch: String, message: String },
+}
+
+impl fmt::Display for PodPreviewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PodPreviewError::InvalidPatch { message, .. } => {
+                write!(f, "invalid pod spec patch: {}", message)
+            }
+        }
+    }
+}
+
+impl error::Error for PodPreviewError {}
@@ -0,0 +1,32 @@
+use std::error;
+use std::fmt;
+
+use super::workflow::{ListWorkflowsError, WatchWorkflowsError};
+use super::Error;
+
+/// Error returned by `Reflector`.
+#[derive(Debug)]
+pub enum ReflectorError {
+    /// The workflows could not be listed.
+    List(Error<ListWorkflowsError>),
+    /// The workflows could not be watched.
+    Watch(Error<WatchWorkflowsError>),
+}
+
+impl fmt::Display for ReflectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReflectorError::List(e) => write!(f, "failed to list workflows: {}", e),
+            ReflectorError::Watch(e) => write!(f, "failed to watch workflows: {}", e),
+        }
+    }
+}
+
+impl error::Error for ReflectorError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ReflectorError::List(e) => Some(e),
+            ReflectorError::Watch(e) => Some(e),
+        }
+    }
+}
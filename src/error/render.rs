@@ -0,0 +1,29 @@
+use std::error;
+use std::fmt;
+
+/// Error returned by `Renderer::render()`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderError {
+    /// The spec sets no `entrypoint`.
+    MissingEntrypoint,
+    /// A reference points to a template that is not declared in the spec.
+    UnknownTemplate(String),
+    /// An input parameter of a template is given no value and has no default.
+    MissingParameter { template: String, name: String },
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::MissingEntrypoint => write!(f, "missing entrypoint"),
+            RenderError::UnknownTemplate(name) => write!(f, "unknown template '{}'", name),
+            RenderError::MissingParameter { template, name } => write!(
+                f,
+                "no value for input parameter '{}' of template '{}'",
+                name, template
+            ),
+        }
+    }
+}
+
+impl error::Error for RenderError {}
@@ -0,0 +1,80 @@
+use std::error;
+use std::fmt;
+
+/// Error returned by `TemplateResolver`.
+#[derive(Debug)]
+pub enum ResolveError {
+    /// A workflow template file could not be read.
+    Io(std::io::Error),
+    /// A workflow template file could not be parsed.
+    Yaml(serde_yaml::Error),
+    /// A workflow spec could not be joined with its workflow template.
+    Serde(serde_json::Error),
+    /// A reference points to a workflow template missing from the catalogue.
+    UnknownWorkflowTemplate { name: String, cluster_scope: bool },
+    /// A reference points to a template missing from its workflow template.
+    UnknownTemplate {
+        workflow_template: String,
+        template: String,
+    },
+    /// Referenced templates call each other, and can not be inlined. The
+    /// path starts and ends with the same `<workflow template>/<template>`.
+    Recursion(Vec<String>),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Io(e) => write!(f, "error in IO: {}", e),
+            ResolveError::Yaml(e) => write!(f, "error in YAML: {}", e),
+            ResolveError::Serde(e) => write!(f, "error in serde: {}", e),
+            ResolveError::UnknownWorkflowTemplate {
+                name,
+                cluster_scope: true,
+            } => write!(f, "unknown cluster workflow template '{}'", name),
+            ResolveError::UnknownWorkflowTemplate { name, .. } => {
+                write!(f, "unknown workflow template '{}'", name)
+            }
+            ResolveError::UnknownTemplate {
+                workflow_template,
+                template,
+            } => write!(
+                f,
+                "unknown template '{}' in workflow template '{}'",
+                template, workflow_template
+            ),
+            ResolveError::Recursion(path) => {
+                write!(f, "recursive template reference: {}", path.join(" -> "))
+            }
+        }
+    }
+}
+
+impl error::Error for ResolveError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ResolveError::Io(e) => Some(e),
+            ResolveError::Yaml(e) => Some(e),
+            ResolveError::Serde(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ResolveError {
+    fn from(e: std::io::Error) -> Self {
+        ResolveError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ResolveError {
+    fn from(e: serde_json::Error) -> Self {
+        ResolveError::Serde(e)
+    }
+}
+
+impl From<serde_yaml::Error> for ResolveError {
+    fn from(e: serde_yaml::Error) -> Self {
+        ResolveError::Yaml(e)
+    }
+}
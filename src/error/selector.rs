@@ -0,0 +1,37 @@
+use std::error;
+use std::fmt;
+
+/// Error returned by `LabelSelectorBuilder::build()` and when parsing a
+/// `LabelSelector`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectorError {
+    /// A label key is not a valid Kubernetes label key, i.e. an optional DNS
+    /// subdomain prefix and `/`, and a name of at most 63 alphanumeric
+    /// characters, `-`, `_` or `.`.
+    InvalidKey(String),
+    /// A label value is not a valid Kubernetes label value.
+    InvalidValue { key: String, value: String },
+    /// An `in` or `notin` requirement lists no value.
+    NoValues(String),
+    /// A requirement of a parsed selector is not of the form `key`, `!key`,
+    /// `key=value`, `key==value`, `key!=value`, `key in (values)` or
+    /// `key notin (values)`.
+    Syntax(String),
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectorError::InvalidKey(key) => write!(f, "invalid label key '{}'", key),
+            SelectorError::InvalidValue { key, value } => {
+                write!(f, "invalid value '{}' for label '{}'", value, key)
+            }
+            SelectorError::NoValues(key) => write!(f, "no values for label '{}'", key),
+            SelectorError::Syntax(requirement) => {
+                write!(f, "invalid label requirement '{}'", requirement)
+            }
+        }
+    }
+}
+
+impl error::Error for SelectorError {}
@@ -0,0 +1,88 @@
+use std::error;
+use std::fmt;
+
+use super::ExpressionError;
+
+/// Error returned by `Simulator::run()`. `node` is the name of the node
+/// being simulated.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationError {
+    /// The spec sets no `entrypoint`.
+    MissingEntrypoint,
+    /// A reference points to a template that is not declared in the spec.
+    UnknownTemplate(String),
+    /// An input parameter of a template is given no value and has no default.
+    MissingParameter { template: String, name: String },
+    /// A `when`, `depends`, hook or retry expression could not be evaluated.
+    Expression {
+        node: String,
+        expression: String,
+        error: ExpressionError,
+    },
+    /// A tag needed to decide the control flow has no value,
+    /// e.g. `{{steps.gen.outputs.result}}` when `gen` gives no result.
+    UnresolvedTag { node: String, tag: String },
+    /// A field has a value that cannot be used, e.g. a `with_param` which is
+    /// not a JSON list.
+    InvalidValue {
+        node: String,
+        field: &'static str,
+        value: String,
+    },
+    /// A stub returned a phase other than `Succeeded`, `Failed` or `Error`.
+    InvalidOutcome { node: String, phase: String },
+    /// A DAG task depends on a task which is not declared.
+    UnknownDependency { node: String, task: String },
+    /// The tasks of a DAG depend on each other.
+    DependencyCycle(String),
+    /// Templates call each other deeper than the simulator allows.
+    RecursionLimit(String),
+    /// A node without a retry `limit` kept failing.
+    RetryLimit(String),
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SimulationError::MissingEntrypoint => write!(f, "missing entrypoint"),
+            SimulationError::UnknownTemplate(name) => write!(f, "unknown template '{}'", name),
+            SimulationError::MissingParameter { template, name } => write!(
+                f,
+                "no value for input parameter '{}' of template '{}'",
+                name, template
+            ),
+            SimulationError::Expression {
+                node,
+                expression,
+                error,
+            } => write!(
+                f,
+                "{}: invalid expression '{}': {}",
+                node, expression, error
+            ),
+            SimulationError::UnresolvedTag { node, tag } => {
+                write!(f, "{}: no value for '{{{{{}}}}}'", node, tag)
+            }
+            SimulationError::InvalidValue { node, field, value } => {
+                write!(f, "{}: invalid {} '{}'", node, field, value)
+            }
+            SimulationError::InvalidOutcome { node, phase } => {
+                write!(f, "{}: stub returned invalid phase '{}'", node, phase)
+            }
+            SimulationError::UnknownDependency { node, task } => {
+                write!(f, "{}: unknown task '{}'", node, task)
+            }
+            SimulationError::DependencyCycle(node) => {
+                write!(f, "{}: tasks depend on each other", node)
+            }
+            SimulationError::RecursionLimit(node) => {
+                write!(f, "{}: templates call each other too deeply", node)
+            }
+            SimulationError::RetryLimit(node) => {
+                write!(f, "{}: retried too many times without a limit", node)
+            }
+        }
+    }
+}
+
+impl error::Error for SimulationError {}
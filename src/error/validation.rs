@@ -0,0 +1,119 @@
+use std::error;
+use std::fmt;
+
+/// A problem found by `WorkflowSpec::validate()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// JSON pointer to the offending field (e.g. `/templates/2/dag/tasks/0/name`).
+    pub pointer: String,
+    /// The problem found at `pointer`.
+    pub kind: ValidationErrorKind,
+}
+
+impl ValidationError {
+    pub(crate) fn new(pointer: String, kind: ValidationErrorKind) -> Self {
+        ValidationError { pointer, kind }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.pointer, self.kind)
+    }
+}
+
+impl error::Error for ValidationError {}
+
+/// The kind of problem reported by a `ValidationError`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationErrorKind {
+    /// A field required by the resource is not set.
+    MissingField(&'static str),
+    /// A reference points to a template that is not declared in the spec.
+    UnknownTemplate(String),
+    /// A template does not set exactly one template type. Holds the types
+    /// that are set.
+    TemplateType(Vec<&'static str>),
+    /// Two entries of the same kind share a name.
+    DuplicateName { kind: &'static str, name: String },
+    /// A DAG task depends on a task that is not declared in the DAG.
+    UnknownTask { task: String, dependency: String },
+    /// The dependencies between DAG tasks form a cycle. The path starts and
+    /// ends with the same task.
+    Cycle(Vec<String>),
+    /// A template tag does not resolve to a declared input, output, step,
+    /// task or parameter. Holds the tag without its braces.
+    UnresolvedReference(String),
+    /// A field is set to a value outside of the ones it accepts.
+    InvalidValue {
+        value: String,
+        allowed: &'static [&'static str],
+    },
+    /// A field is set but is not used by the action of a resource template.
+    UnusedField { field: &'static str, action: String },
+    /// The manifest of a resource template is not a valid Kubernetes object
+    /// or patch.
+    InvalidManifest(String),
+    /// A success or failure condition of a resource template is not a valid
+    /// selector. `position` is the byte offset of the problem in the
+    /// condition.
+    InvalidCondition { position: usize, message: String },
+    /// An expr-language expression does not parse. `position` is the byte
+    /// offset of the problem in the expression.
+    InvalidExpression { position: usize, message: String },
+}
+
+impl fmt::Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationErrorKind::MissingField(field) => write!(f, "missing field '{}'", field),
+            ValidationErrorKind::UnknownTemplate(name) => write!(f, "unknown template '{}'", name),
+            ValidationErrorKind::TemplateType(types) if types.is_empty() => {
+                write!(f, "template sets no template type")
+            }
+            ValidationErrorKind::TemplateType(types) => {
+                write!(
+                    f,
+                    "template sets several template types: {}",
+                    types.join(", ")
+                )
+            }
+            ValidationErrorKind::DuplicateName { kind, name } => {
+                write!(f, "duplicate {} name '{}'", kind, name)
+            }
+            ValidationErrorKind::UnknownTask { task, dependency } => {
+                write!(
+                    f,
+                    "task '{}' depends on unknown task '{}'",
+                    task, dependency
+                )
+            }
+            ValidationErrorKind::Cycle(path) => {
+                write!(f, "dependency cycle: {}", path.join(" -> "))
+            }
+            ValidationErrorKind::UnresolvedReference(tag) => {
+                write!(f, "unresolved reference '{{{{{}}}}}'", tag)
+            }
+            ValidationErrorKind::InvalidValue { value, allowed } => {
+                write!(
+                    f,
+                    "invalid value '{}', must be one of: {}",
+                    value,
+                    allowed.join(", ")
+                )
+            }
+            ValidationErrorKind::UnusedField { field, action } => {
+                write!(f, "'{}' is not used by action '{}'", field, action)
+            }
+            ValidationErrorKind::InvalidManifest(message) => {
+                write!(f, "invalid manifest: {}", message)
+            }
+            ValidationErrorKind::InvalidCondition { position, message } => {
+                write!(f, "invalid condition at offset {}: {}", position, message)
+            }
+            ValidationErrorKind::InvalidExpression { position, message } => {
+                write!(f, "invalid expression at offset {}: {}", position, message)
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::error;
+use std::fmt;
+
+use super::workflow::{CreateWorkflowError, GetWorkflowError, WatchWorkflowsError};
+use super::Error;
+use crate::types::workflow::Workflow;
+
+/// Error returned by `wait_for_completion()` and `submit_and_wait()`.
+#[derive(Debug)]
+pub enum WaitError {
+    /// The workflow could not be created.
+    Create(Error<CreateWorkflowError>),
+    /// The workflow could not be read.
+    Get(Error<GetWorkflowError>),
+    /// The workflow could not be watched, e.g. for lack of permission.
+    Watch(Error<WatchWorkflowsError>),
+    /// The workflow was not completed before the timeout. Holds the
+    /// workflow as last seen.
+    Timeout(Box<Workflow>),
+    /// The workflow was deleted before it completed.
+    Deleted,
+    /// The workflow completed with the `Failed` or `Error` phase. See
+    /// `Workflow::failure_report()` for the nodes which caused it.
+    Failed(Box<Workflow>),
+}
+
+impl fmt::Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitError::Create(e) => write!(f, "failed to create workflow: {}", e),
+            WaitError::Get(e) => write!(f, "failed to get workflow: {}", e),
+            WaitError::Watch(e) => write!(f, "failed to watch workflow: {}", e),
+            WaitError::Timeout(workflow) => {
+                let status = workflow.status.as_deref();
+                let phase = status.and_then(|s| s.phase.as_deref());
+                write!(
+                    f,
+                    "timed out waiting for workflow, in phase '{}'",
+                    phase.unwrap_or("Pending")
+                )
+            }
+            WaitError::Deleted => write!(f, "workflow was deleted"),
+            WaitError::Failed(workflow) => {
+                let status = workflow.status.as_deref();
+                let phase = status.and_then(|s| s.phase.as_deref()).unwrap_or_default();
+                write!(f, "workflow {}", phase.to_lowercase())?;
+                if let Some(message) = status.and_then(|s| s.message.as_deref()) {
+                    write!(f, ": {}", message)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for WaitError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            WaitError::Create(e) => Some(e),
+            WaitError::Get(e) => Some(e),
+            WaitError::Watch(e) => Some(e),
+            _ => None,
+        }
+    }
+}
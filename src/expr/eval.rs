@@ -0,0 +1,659 @@
+use std::cmp::Ordering;
+
+use regex::Regex;
+use serde_json::{Map, Number, Value};
+
+use super::parser::{Kind, Node};
+use super::{functions, sprig, Context};
+use crate::error::ExpressionError;
+
+/// Maximum number of items of an array built by an expression, e.g. with
+/// `..` or `sprig.until()`.
+const MAX_ITEMS: usize = 100_000;
+
+/// Maximum length in bytes of a string built by repeating or padding
+/// another, e.g. with `repeat()` or `sprig.indent()`.
+const MAX_LENGTH: usize = 1 << 20;
+
+pub(super) fn evaluation(position: usize, message: &str) -> ExpressionError {
+    ExpressionError::Evaluation {
+        position,
+        message: String::from(message),
+    }
+}
+
+/// Returns an error unless an array of `count` items can be built:
+/// expressions come from workflow specs, which must not be able to make the
+/// evaluator allocate without bounds.
+pub(super) fn limit_items(position: usize, count: usize) -> Result<(), ExpressionError> {
+    if count > MAX_ITEMS {
+        let message = format!(
+            "array of {} items exceeds the limit of {}",
+            count, MAX_ITEMS
+        );
+        return Err(evaluation(position, &message));
+    }
+    Ok(())
+}
+
+/// Returns an error unless a string of `len` bytes can be built. See
+/// `limit_items()`.
+pub(super) fn limit_length(position: usize, len: usize) -> Result<(), ExpressionError> {
+    if len > MAX_LENGTH {
+        let message = format!(
+            "string of {} bytes exceeds the limit of {}",
+            len, MAX_LENGTH
+        );
+        return Err(evaluation(position, &message));
+    }
+    Ok(())
+}
+
+/// Returns the name of the type of `value`, as shown in errors.
+pub(super) fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "nil",
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "int",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "map",
+    }
+}
+
+pub(super) fn float(value: f64) -> Value {
+    Number::from_f64(value).map_or(Value::Null, Value::Number)
+}
+
+/// Returns `value` as a string the way Go formats it with `fmt.Sprint`.
+pub(super) fn to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::from("<nil>"),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(to_string).collect();
+            format!("[{}]", items.join(" "))
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<String> = map
+                .iter()
+                .map(|(k, v)| format!("{}:{}", k, to_string(v)))
+                .collect();
+            entries.sort();
+            format!("map[{}]", entries.join(" "))
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Compares two values for equality, integers and floats being compared by
+/// their numeric value.
+pub(super) fn equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => match (l.as_i64(), r.as_i64()) {
+            (Some(l), Some(r)) => l == r,
+            _ => l.as_f64() == r.as_f64(),
+        },
+        (Value::Array(l), Value::Array(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(l, r)| equal(l, r))
+        }
+        (Value::Object(l), Value::Object(r)) => {
+            l.len() == r.len() && l.iter().all(|(k, v)| r.get(k).is_some_and(|w| equal(v, w)))
+        }
+        (left, right) => left == right,
+    }
+}
+
+/// Orders numbers and strings; other values are not ordered.
+pub(super) fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => match (l.as_i64(), r.as_i64()) {
+            (Some(l), Some(r)) => Some(l.cmp(&r)),
+            _ => l.as_f64()?.partial_cmp(&r.as_f64()?),
+        },
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        _ => None,
+    }
+}
+
+pub(super) fn regex(position: usize, pattern: &str) -> Result<Regex, ExpressionError> {
+    Regex::new(pattern).map_err(|e| evaluation(position, &e.to_string()))
+}
+
+/// Evaluates syntax tree nodes against a context.
+pub(super) struct Evaluator<'a> {
+    pub(super) context: &'a Context,
+}
+
+impl Evaluator<'_> {
+    /// Evaluates `node`, `pointer` being the value of `#` within predicates.
+    pub(super) fn eval(
+        &self,
+        node: &Node,
+        pointer: Option<&Value>,
+    ) -> Result<Value, ExpressionError> {
+        let position = node.position;
+        match &node.kind {
+            Kind::Nil => Ok(Value::Null),
+            Kind::Bool(value) => Ok(Value::Bool(*value)),
+            Kind::Integer(value) => Ok(Value::from(*value)),
+            Kind::Float(value) => Ok(float(*value)),
+            Kind::String(value) => Ok(Value::String(value.clone())),
+            Kind::Variable(name) => self.context.variables.get(name).cloned().ok_or_else(|| {
+                ExpressionError::UnknownVariable {
+                    position,
+                    name: name.clone(),
+                }
+            }),
+            Kind::Pointer => pointer
+                .cloned()
+                .ok_or_else(|| evaluation(position, "'#' used outside of a predicate")),
+            Kind::Array(items) => items
+                .iter()
+                .map(|item| self.eval(item, pointer))
+                .collect::<Result<_, _>>()
+                .map(Value::Array),
+            Kind::Map(entries) => {
+                let mut map = Map::new();
+                for (key, value) in entries {
+                    map.insert(key.clone(), self.eval(value, pointer)?);
+                }
+                Ok(Value::Object(map))
+            }
+            Kind::Unary(operator, operand) => {
+                let value = self.eval(operand, pointer)?;
+                unary(position, operator, value)
+            }
+            Kind::Binary(operator, left, right) => {
+                self.binary(position, operator, left, right, pointer)
+            }
+            Kind::Member {
+                object,
+                property,
+                optional,
+            } => {
+                let object = self.eval(object, pointer)?;
+                if object.is_null() && *optional {
+                    return Ok(Value::Null);
+                }
+                let property = self.eval(property, pointer)?;
+                member(position, &object, &property)
+            }
+            Kind::Slice { object, from, to } => {
+                let object = self.eval(object, pointer)?;
+                let from = match from {
+                    Some(from) => Some(self.eval(from, pointer)?),
+                    None => None,
+                };
+                let to = match to {
+                    Some(to) => Some(self.eval(to, pointer)?),
+                    None => None,
+                };
+                slice(position, &object, from.as_ref(), to.as_ref())
+            }
+            Kind::Call(name, arguments) => {
+                if let Some(name) = name.strip_prefix("sprig.") {
+                    let arguments = self.arguments(arguments, pointer)?;
+                    return sprig::call(position, name, arguments);
+                }
+                if functions::is_predicate(name) {
+                    return self.predicate_call(position, name, arguments, pointer);
+                }
+                let arguments = self.arguments(arguments, pointer)?;
+                functions::call(position, name, arguments)
+            }
+            Kind::Predicate(body) => self.eval(body, pointer),
+            Kind::Conditional(condition, then, otherwise) => {
+                match self.eval(condition, pointer)? {
+                    Value::Bool(true) => self.eval(then, pointer),
+                    Value::Bool(false) => self.eval(otherwise, pointer),
+                    value => Err(evaluation(
+                        condition.position,
+                        &format!("condition is {}, expected bool", type_name(&value)),
+                    )),
+                }
+            }
+        }
+    }
+
+    fn arguments(
+        &self,
+        arguments: &[Node],
+        pointer: Option<&Value>,
+    ) -> Result<Vec<Value>, ExpressionError> {
+        arguments.iter().map(|a| self.eval(a, pointer)).collect()
+    }
+
+    fn binary(
+        &self,
+        position: usize,
+        operator: &str,
+        left: &Node,
+        right: &Node,
+        pointer: Option<&Value>,
+    ) -> Result<Value, ExpressionError> {
+        match operator {
+            "and" | "&&" | "or" | "||" => {
+                let is_and = operator == "and" || operator == "&&";
+                let left = self.boolean(left, pointer)?;
+                if left != is_and {
+                    return Ok(Value::Bool(left));
+                }
+                self.boolean(right, pointer).map(Value::Bool)
+            }
+            "??" => match self.eval(left, pointer) {
+                Ok(Value::Null) | Err(ExpressionError::UnknownVariable { .. }) => {
+                    self.eval(right, pointer)
+                }
+                result => result,
+            },
+            _ => {
+                let left = self.eval(left, pointer)?;
+                let right = self.eval(right, pointer)?;
+                binary(position, operator, left, right)
+            }
+        }
+    }
+
+    fn boolean(&self, node: &Node, pointer: Option<&Value>) -> Result<bool, ExpressionError> {
+        match self.eval(node, pointer)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(evaluation(
+                node.position,
+                &format!("operand is {}, expected bool", type_name(&value)),
+            )),
+        }
+    }
+
+    fn predicate_call(
+        &self,
+        position: usize,
+        name: &str,
+        arguments: &[Node],
+        pointer: Option<&Value>,
+    ) -> Result<Value, ExpressionError> {
+        let (collection, predicate) = match arguments {
+            [collection, predicate] => (collection, predicate),
+            _ => {
+                return Err(evaluation(
+                    position,
+                    &format!("{}() takes 2 arguments", name),
+                ))
+            }
+        };
+        let items = match self.eval(collection, pointer)? {
+            Value::Array(items) => items,
+            Value::Null => Vec::new(),
+            value => {
+                return Err(evaluation(
+                    collection.position,
+                    &format!("{}() expects an array, got {}", name, type_name(&value)),
+                ))
+            }
+        };
+        let mut results = Vec::with_capacity(items.len());
+        for item in &items {
+            results.push(self.eval(predicate, Some(item))?);
+        }
+        functions::apply_predicate(predicate.position, name, items, results)
+    }
+}
+
+fn unary(position: usize, operator: &str, value: Value) -> Result<Value, ExpressionError> {
+    match (operator, &value) {
+        ("!", Value::Bool(b)) => Ok(Value::Bool(!b)),
+        ("-", Value::Number(n)) => match n.as_i64() {
+            Some(i) => i
+                .checked_neg()
+                .map(Value::from)
+                .ok_or_else(|| evaluation(position, "integer overflow")),
+            None => Ok(float(-n.as_f64().unwrap_or_default())),
+        },
+        ("+", Value::Number(_)) => Ok(value),
+        _ => Err(evaluation(
+            position,
+            &format!("invalid operation: {}{}", operator, type_name(&value)),
+        )),
+    }
+}
+
+fn binary(
+    position: usize,
+    operator: &str,
+    left: Value,
+    right: Value,
+) -> Result<Value, ExpressionError> {
+    let invalid = || {
+        evaluation(
+            position,
+            &format!(
+                "invalid operation: {} {} {}",
+                type_name(&left),
+                operator,
+                type_name(&right)
+            ),
+        )
+    };
+    let result = match operator {
+        "==" => Value::Bool(equal(&left, &right)),
+        "!=" => Value::Bool(!equal(&left, &right)),
+        "<" | ">" | "<=" | ">=" => {
+            let ordering = compare(&left, &right).ok_or_else(invalid)?;
+            Value::Bool(match operator {
+                "<" => ordering.is_lt(),
+                ">" => ordering.is_gt(),
+                "<=" => ordering.is_le(),
+                _ => ordering.is_ge(),
+            })
+        }
+        "in" | "not in" => {
+            let found = match (&left, &right) {
+                (_, Value::Array(items)) => items.iter().any(|item| equal(item, &left)),
+                (Value::String(key), Value::Object(map)) => map.contains_key(key),
+                (_, Value::Null) => false,
+                _ => return Err(invalid()),
+            };
+            Value::Bool(found == (operator == "in"))
+        }
+        "matches" | "=~" | "!~" | "contains" | "startsWith" | "endsWith" => {
+            let (Value::String(l), Value::String(r)) = (&left, &right) else {
+                return Err(invalid());
+            };
+            Value::Bool(match operator {
+                "matches" | "=~" => regex(position, r)?.is_match(l),
+                "!~" => !regex(position, r)?.is_match(l),
+                "contains" => l.contains(r.as_str()),
+                "startsWith" => l.starts_with(r.as_str()),
+                _ => l.ends_with(r.as_str()),
+            })
+        }
+        ".." => match (left.as_i64(), right.as_i64()) {
+            (Some(from), Some(to)) => {
+                let count = (i128::from(to) - i128::from(from) + 1).max(0);
+                limit_items(position, usize::try_from(count).unwrap_or(usize::MAX))?;
+                Value::Array((from..=to).map(Value::from).collect())
+            }
+            _ => return Err(invalid()),
+        },
+        "+" => match (&left, &right) {
+            (Value::String(l), Value::String(r)) => Value::String(format!("{}{}", l, r)),
+            (Value::Array(l), Value::Array(r)) => {
+                Value::Array(l.iter().chain(r.iter()).cloned().collect())
+            }
+            _ => arithmetic(position, operator, &left, &right)?.ok_or_else(invalid)?,
+        },
+        "/" => match (left.as_f64(), right.as_f64()) {
+            (Some(_), Some(r)) if r == 0.0 && left.is_i64() && right.is_i64() => {
+                return Err(evaluation(position, "integer divide by zero"))
+            }
+            (Some(l), Some(r)) => float(l / r),
+            _ => return Err(invalid()),
+        },
+        "%" => match (left.as_i64(), right.as_i64()) {
+            (Some(_), Some(0)) => return Err(evaluation(position, "integer divide by zero")),
+            (Some(l), Some(r)) => l
+                .checked_rem(r)
+                .map(Value::from)
+                .ok_or_else(|| evaluation(position, "integer overflow"))?,
+            _ => return Err(invalid()),
+        },
+        "**" | "^" => match (left.as_f64(), right.as_f64()) {
+            (Some(l), Some(r)) => float(l.powf(r)),
+            _ => return Err(invalid()),
+        },
+        _ => arithmetic(position, operator, &left, &right)?.ok_or_else(invalid)?,
+    };
+    Ok(result)
+}
+
+/// Applies `+`, `-` or `*` to numbers, keeping integers when both are, or
+/// returns `None` when the operands are not numbers. Integer overflow is an
+/// error, as for the other integer operators.
+fn arithmetic(
+    position: usize,
+    operator: &str,
+    left: &Value,
+    right: &Value,
+) -> Result<Option<Value>, ExpressionError> {
+    if let (Some(l), Some(r)) = (left.as_i64(), right.as_i64()) {
+        let value = match operator {
+            "+" => l.checked_add(r),
+            "-" => l.checked_sub(r),
+            "*" => l.checked_mul(r),
+            _ => return Ok(None),
+        };
+        return value
+            .map(|v| Some(Value::from(v)))
+            .ok_or_else(|| evaluation(position, "integer overflow"));
+    }
+    let (Some(l), Some(r)) = (left.as_f64(), right.as_f64()) else {
+        return Ok(None);
+    };
+    let value = match operator {
+        "+" => l + r,
+        "-" => l - r,
+        "*" => l * r,
+        _ => return Ok(None),
+    };
+    Ok(Some(float(value)))
+}
+
+/// Resolves a negative `index` from the end of a sequence of `len` items.
+fn resolve_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { index + len as i64 } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+fn member(position: usize, object: &Value, property: &Value) -> Result<Value, ExpressionError> {
+    match (object, property) {
+        (Value::Object(map), Value::String(key)) => {
+            Ok(map.get(key).cloned().unwrap_or(Value::Null))
+        }
+        (Value::Array(items), Value::Number(index)) => index
+            .as_i64()
+            .and_then(|i| resolve_index(i, items.len()))
+            .map(|i| items[i].clone())
+            .ok_or_else(|| evaluation(position, &format!("index {} out of range", index))),
+        (Value::String(s), Value::Number(index)) => {
+            let chars: Vec<char> = s.chars().collect();
+            index
+                .as_i64()
+                .and_then(|i| resolve_index(i, chars.len()))
+                .map(|i| Value::String(chars[i].to_string()))
+                .ok_or_else(|| evaluation(position, &format!("index {} out of range", index)))
+        }
+        (Value::Null, _) => Err(evaluation(
+            position,
+            &format!("cannot fetch {} from nil", to_string(property)),
+        )),
+        _ => Err(evaluation(
+            position,
+            &format!(
+                "cannot fetch {} from {}",
+                to_string(property),
+                type_name(object)
+            ),
+        )),
+    }
+}
+
+fn slice(
+    position: usize,
+    object: &Value,
+    from: Option<&Value>,
+    to: Option<&Value>,
+) -> Result<Value, ExpressionError> {
+    let bound =
+        |value: Option<&Value>, default: usize, len: usize| -> Result<usize, ExpressionError> {
+            match value {
+                None => Ok(default),
+                Some(value) => {
+                    let index = value
+                        .as_i64()
+                        .ok_or_else(|| evaluation(position, "slice bounds must be integers"))?;
+                    let index = if index < 0 { index + len as i64 } else { index };
+                    Ok(index.clamp(0, len as i64) as usize)
+                }
+            }
+        };
+    match object {
+        Value::Array(items) => {
+            let from = bound(from, 0, items.len())?;
+            let to = bound(to, items.len(), items.len())?.max(from);
+            Ok(Value::Array(items[from..to].to_vec()))
+        }
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let from = bound(from, 0, chars.len())?;
+            let to = bound(to, chars.len(), chars.len())?.max(from);
+            Ok(Value::String(chars[from..to].iter().collect()))
+        }
+        value => Err(evaluation(
+            position,
+            &format!("cannot slice {}", type_name(value)),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::expr::{evaluate, Context};
+
+    fn eval(source: &str) -> Result<Value, ExpressionError> {
+        let context = Context::new()
+            .variable("retries", 2)
+            .variable("lastRetry.status", "Error")
+            .variable("items", json!([1, 2, 3, 4]))
+            .variable(
+                "steps",
+                json!({"flip-coin": {"outputs": {"result": "heads"}}}),
+            );
+        evaluate(source, &context)
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(json!(7)));
+        assert_eq!(eval("7 / 2"), Ok(json!(3.5)));
+        assert_eq!(eval("7 % 3"), Ok(json!(1)));
+        assert_eq!(eval("2 ** 10"), Ok(json!(1024.0)));
+        assert_eq!(eval("1.5 + 1"), Ok(json!(2.5)));
+        assert_eq!(eval("'a' + 'b'"), Ok(json!("ab")));
+        assert_eq!(eval("-retries"), Ok(json!(-2)));
+    }
+
+    #[test]
+    fn integer_overflow_is_an_error() {
+        let overflow = |source: &str| {
+            assert!(
+                matches!(eval(source), Err(ExpressionError::Evaluation { ref message, .. }) if message == "integer overflow"),
+                "{}",
+                source
+            )
+        };
+        overflow("-(-9223372036854775807 - 1)");
+        overflow("(-9223372036854775807 - 1) % -1");
+        overflow("9223372036854775807 + 1");
+        overflow("-9223372036854775808 - 1");
+        overflow("4294967296 * 4294967296");
+        assert_eq!(eval("-9223372036854775808"), Ok(json!(i64::MIN)));
+        assert_eq!(
+            eval("9223372036854775807.0 + 1"),
+            Ok(json!(9223372036854775808.0))
+        );
+        assert_eq!(eval("1 % 0"), Err(evaluation(2, "integer divide by zero")));
+        assert_eq!(eval("1 / 0"), Err(evaluation(2, "integer divide by zero")));
+    }
+
+    #[test]
+    fn allocations_are_limited() {
+        assert!(matches!(
+            eval("1..9223372036854775807"),
+            Err(ExpressionError::Evaluation { position: 1, .. })
+        ));
+        assert!(matches!(
+            eval("repeat('ab', 1000000000000)"),
+            Err(ExpressionError::Evaluation { .. })
+        ));
+        assert_eq!(eval("len(1..100000)"), Ok(json!(100000)));
+        assert_eq!(eval("3..1"), Ok(json!([])));
+    }
+
+    #[test]
+    fn comparisons_and_logic() {
+        assert_eq!(
+            eval("lastRetry.status == 'Error' && retries < 3"),
+            Ok(json!(true))
+        );
+        assert_eq!(eval("1 == 1.0"), Ok(json!(true)));
+        assert_eq!(eval("3 in items && 5 not in items"), Ok(json!(true)));
+        assert_eq!(
+            eval("'abc' matches '^a' and 'abc' =~ 'c$'"),
+            Ok(json!(true))
+        );
+        assert_eq!(eval("false || nil ?? true"), Ok(json!(true)));
+        assert_eq!(eval("retries > 1 ? 'many' : 'few'"), Ok(json!("many")));
+    }
+
+    #[test]
+    fn members_and_slices() {
+        assert_eq!(
+            eval("steps['flip-coin'].outputs.result"),
+            Ok(json!("heads"))
+        );
+        assert_eq!(eval("steps.missing?.outputs"), Ok(Value::Null));
+        assert_eq!(eval("items[-1]"), Ok(json!(4)));
+        assert_eq!(eval("items[1:3]"), Ok(json!([2, 3])));
+        assert_eq!(
+            eval("items[-9223372036854775807:]"),
+            Ok(json!([1, 2, 3, 4]))
+        );
+        assert_eq!(eval("1..3"), Ok(json!([1, 2, 3])));
+    }
+
+    #[test]
+    fn predicates() {
+        assert_eq!(eval("filter(items, # % 2 == 0)"), Ok(json!([2, 4])));
+        assert_eq!(eval("all(items, {# > 0})"), Ok(json!(true)));
+        assert_eq!(eval("map(items, # * 10)[0]"), Ok(json!(10)));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            eval("unknown > 1"),
+            Err(ExpressionError::UnknownVariable {
+                position: 0,
+                name: String::from("unknown"),
+            })
+        );
+        assert!(matches!(
+            eval("nope(1)"),
+            Err(ExpressionError::UnknownFunction { .. })
+        ));
+        assert!(matches!(
+            eval("'a' - 1"),
+            Err(ExpressionError::Evaluation { position: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn functions() {
+        assert_eq!(eval("abs(-3)"), Ok(json!(3)));
+        assert!(matches!(
+            eval("abs(-9223372036854775807 - 1)"),
+            Err(ExpressionError::Evaluation { .. })
+        ));
+        assert_eq!(eval("asInt('42') + 1"), Ok(json!(43)));
+        assert_eq!(eval("len(items)"), Ok(json!(4)));
+        assert_eq!(eval("toJson({a: 1})"), Ok(json!("{\"a\":1}")));
+        assert_eq!(
+            eval("jsonpath('{\"a\": {\"b\": [5, 6]}}', '$.a.b[1]')"),
+            Ok(json!(6))
+        );
+    }
+}
@@ -0,0 +1,568 @@
+//! Builtin functions of the expr language, and the ones Argo adds to them.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{Map, Value};
+
+use super::eval::{compare, equal, evaluation, float, limit_length, to_string, type_name};
+use crate::error::ExpressionError;
+
+pub(super) fn is_predicate(name: &str) -> bool {
+    matches!(
+        name,
+        "all"
+            | "any"
+            | "none"
+            | "one"
+            | "filter"
+            | "map"
+            | "count"
+            | "find"
+            | "findIndex"
+            | "findLast"
+            | "findLastIndex"
+            | "groupBy"
+            | "sortBy"
+    )
+}
+
+/// Combines the `items` given to a predicate function with the `results` of
+/// the predicate for each of them.
+pub(super) fn apply_predicate(
+    position: usize,
+    name: &str,
+    items: Vec<Value>,
+    results: Vec<Value>,
+) -> Result<Value, ExpressionError> {
+    if name == "map" {
+        return Ok(Value::Array(results));
+    }
+    if name == "groupBy" {
+        let mut groups = Map::new();
+        for (item, key) in items.into_iter().zip(results) {
+            let group = groups
+                .entry(to_string(&key))
+                .or_insert_with(|| Value::Array(Vec::new()));
+            if let Value::Array(group) = group {
+                group.push(item);
+            }
+        }
+        return Ok(Value::Object(groups));
+    }
+    if name == "sortBy" {
+        let mut pairs: Vec<(Value, Value)> = items.into_iter().zip(results).collect();
+        pairs.sort_by(|(_, a), (_, b)| compare(a, b).unwrap_or(std::cmp::Ordering::Equal));
+        return Ok(Value::Array(
+            pairs.into_iter().map(|(item, _)| item).collect(),
+        ));
+    }
+
+    let mut matched = Vec::with_capacity(results.len());
+    for result in results {
+        match result {
+            Value::Bool(b) => matched.push(b),
+            value => {
+                return Err(evaluation(
+                    position,
+                    &format!("predicate returned {}, expected bool", type_name(&value)),
+                ))
+            }
+        }
+    }
+    let count = matched.iter().filter(|m| **m).count();
+    let first = matched.iter().position(|m| *m);
+    let last = matched.iter().rposition(|m| *m);
+    let value = match name {
+        "all" => Value::Bool(count == matched.len()),
+        "any" => Value::Bool(count > 0),
+        "none" => Value::Bool(count == 0),
+        "one" => Value::Bool(count == 1),
+        "count" => Value::from(count),
+        "filter" => Value::Array(
+            items
+                .into_iter()
+                .zip(matched)
+                .filter_map(|(item, m)| m.then_some(item))
+                .collect(),
+        ),
+        "find" => first.map_or(Value::Null, |i| items[i].clone()),
+        "findLast" => last.map_or(Value::Null, |i| items[i].clone()),
+        "findIndex" => Value::from(first.map_or(-1, |i| i as i64)),
+        _ => Value::from(last.map_or(-1, |i| i as i64)),
+    };
+    Ok(value)
+}
+
+/// Checks the number of arguments given to the function `name`.
+pub(super) fn arity(
+    position: usize,
+    name: &str,
+    arguments: &[Value],
+    min: usize,
+    max: usize,
+) -> Result<(), ExpressionError> {
+    if (min..=max).contains(&arguments.len()) {
+        return Ok(());
+    }
+    let expected = if min == max {
+        format!("{}", min)
+    } else {
+        format!("{} to {}", min, max)
+    };
+    Err(evaluation(
+        position,
+        &format!(
+            "{}() takes {} arguments, got {}",
+            name,
+            expected,
+            arguments.len()
+        ),
+    ))
+}
+
+pub(super) fn string_arg<'a>(
+    position: usize,
+    name: &str,
+    value: &'a Value,
+) -> Result<&'a str, ExpressionError> {
+    value.as_str().ok_or_else(|| {
+        evaluation(
+            position,
+            &format!("{}() expects a string, got {}", name, type_name(value)),
+        )
+    })
+}
+
+pub(super) fn int_arg(position: usize, name: &str, value: &Value) -> Result<i64, ExpressionError> {
+    value
+        .as_i64()
+        .or_else(|| value.as_f64().map(|f| f as i64))
+        .ok_or_else(|| {
+            evaluation(
+                position,
+                &format!("{}() expects an int, got {}", name, type_name(value)),
+            )
+        })
+}
+
+pub(super) fn array_arg<'a>(
+    position: usize,
+    name: &str,
+    value: &'a Value,
+) -> Result<&'a [Value], ExpressionError> {
+    match value {
+        Value::Array(items) => Ok(items),
+        Value::Null => Ok(&[]),
+        value => Err(evaluation(
+            position,
+            &format!("{}() expects an array, got {}", name, type_name(value)),
+        )),
+    }
+}
+
+/// Parses `value` as an integer, the way Argo's `asInt()` does.
+pub(super) fn as_int(position: usize, value: &Value) -> Result<i64, ExpressionError> {
+    match value {
+        Value::Number(n) => n
+            .as_i64()
+            .or_else(|| n.as_f64().map(|f| f as i64))
+            .ok_or_else(|| evaluation(position, "number out of range")),
+        Value::String(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| evaluation(position, &format!("cannot convert '{}' to int", s))),
+        Value::Bool(b) => Ok(*b as i64),
+        value => Err(evaluation(
+            position,
+            &format!("cannot convert {} to int", type_name(value)),
+        )),
+    }
+}
+
+/// Parses `value` as a float, the way Argo's `asFloat()` does.
+pub(super) fn as_float(position: usize, value: &Value) -> Result<f64, ExpressionError> {
+    match value {
+        Value::Number(n) => n
+            .as_f64()
+            .ok_or_else(|| evaluation(position, "number out of range")),
+        Value::String(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| evaluation(position, &format!("cannot convert '{}' to float", s))),
+        value => Err(evaluation(
+            position,
+            &format!("cannot convert {} to float", type_name(value)),
+        )),
+    }
+}
+
+pub(super) fn to_json(position: usize, value: &Value) -> Result<Value, ExpressionError> {
+    serde_json::to_string(value)
+        .map(Value::String)
+        .map_err(|e| evaluation(position, &e.to_string()))
+}
+
+pub(super) fn from_json(position: usize, text: &str) -> Result<Value, ExpressionError> {
+    serde_json::from_str(text).map_err(|e| evaluation(position, &format!("invalid JSON: {}", e)))
+}
+
+pub(super) fn base64_decode(position: usize, text: &str) -> Result<Value, ExpressionError> {
+    let bytes = STANDARD
+        .decode(text)
+        .map_err(|e| evaluation(position, &format!("invalid base64: {}", e)))?;
+    String::from_utf8(bytes)
+        .map(Value::String)
+        .map_err(|_| evaluation(position, "decoded base64 is not UTF-8"))
+}
+
+pub(super) fn base64_encode(text: &str) -> Value {
+    Value::String(STANDARD.encode(text))
+}
+
+/// Returns the number of items, characters or entries of `value`.
+fn len(position: usize, value: &Value) -> Result<Value, ExpressionError> {
+    let len = match value {
+        Value::Array(items) => items.len(),
+        Value::Object(map) => map.len(),
+        Value::String(s) => s.chars().count(),
+        Value::Null => 0,
+        value => {
+            return Err(evaluation(
+                position,
+                &format!("invalid argument for len (type {})", type_name(value)),
+            ))
+        }
+    };
+    Ok(Value::from(len))
+}
+
+/// Finds the extreme of `arguments`, or of the items of a single array
+/// argument, keeping the one for which `keep` returns true.
+fn extreme(
+    position: usize,
+    name: &str,
+    arguments: &[Value],
+    keep: fn(std::cmp::Ordering) -> bool,
+) -> Result<Value, ExpressionError> {
+    let values = match arguments {
+        [Value::Array(items)] => items.as_slice(),
+        values => values,
+    };
+    let mut best: Option<&Value> = None;
+    for value in values {
+        if !value.is_number() {
+            return Err(evaluation(
+                position,
+                &format!("{}() expects numbers, got {}", name, type_name(value)),
+            ));
+        }
+        best = match best {
+            Some(b) if !compare(value, b).is_some_and(keep) => Some(b),
+            _ => Some(value),
+        };
+    }
+    Ok(best.cloned().unwrap_or(Value::Null))
+}
+
+/// Evaluates a simple JSONPath (`$.a.b[0]`, `$.items[*].name`) against
+/// `value`.
+pub(super) fn jsonpath(
+    position: usize,
+    value: Value,
+    path: &str,
+) -> Result<Value, ExpressionError> {
+    let invalid = || evaluation(position, &format!("invalid JSONPath '{}'", path));
+    let mut rest = path.trim().strip_prefix('$').ok_or_else(invalid)?;
+    let mut current = vec![value];
+    let mut wildcard = false;
+    while !rest.is_empty() {
+        let (segment, next) = if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(invalid)?;
+            let segment = after[..end].trim_matches(|c| c == '\'' || c == '"');
+            (segment, &after[end + 1..])
+        } else {
+            return Err(invalid());
+        };
+        rest = next;
+        current = if segment == "*" {
+            wildcard = true;
+            current
+                .into_iter()
+                .flat_map(|v| match v {
+                    Value::Array(items) => items,
+                    Value::Object(map) => map.into_iter().map(|(_, v)| v).collect(),
+                    _ => Vec::new(),
+                })
+                .collect()
+        } else {
+            current
+                .into_iter()
+                .filter_map(|v| match (v, segment.parse::<i64>()) {
+                    (Value::Array(items), Ok(i)) => {
+                        let i = if i < 0 { i + items.len() as i64 } else { i };
+                        usize::try_from(i).ok().and_then(|i| items.get(i).cloned())
+                    }
+                    (Value::Object(mut map), _) => map.remove(segment),
+                    _ => None,
+                })
+                .collect()
+        };
+    }
+    if wildcard {
+        Ok(Value::Array(current))
+    } else {
+        Ok(current.into_iter().next().unwrap_or(Value::Null))
+    }
+}
+
+/// Calls the builtin function `name`.
+pub(super) fn call(
+    position: usize,
+    name: &str,
+    args: Vec<Value>,
+) -> Result<Value, ExpressionError> {
+    let s = |i: usize| string_arg(position, name, &args[i]);
+    let value = match name {
+        "len" => {
+            arity(position, name, &args, 1, 1)?;
+            len(position, &args[0])?
+        }
+        "abs" => {
+            arity(position, name, &args, 1, 1)?;
+            match args[0].as_i64() {
+                Some(i) => Value::from(
+                    i.checked_abs()
+                        .ok_or_else(|| evaluation(position, "integer overflow"))?,
+                ),
+                None => float(as_float(position, &args[0])?.abs()),
+            }
+        }
+        "int" | "asInt" => {
+            arity(position, name, &args, 1, 1)?;
+            Value::from(as_int(position, &args[0])?)
+        }
+        "float" | "asFloat" => {
+            arity(position, name, &args, 1, 1)?;
+            float(as_float(position, &args[0])?)
+        }
+        "string" => {
+            arity(position, name, &args, 1, 1)?;
+            Value::String(to_string(&args[0]))
+        }
+        "ceil" | "floor" | "round" => {
+            arity(position, name, &args, 1, 1)?;
+            let f = as_float(position, &args[0])?;
+            float(match name {
+                "ceil" => f.ceil(),
+                "floor" => f.floor(),
+                _ => f.round(),
+            })
+        }
+        "trim" => {
+            arity(position, name, &args, 1, 2)?;
+            match args.get(1) {
+                Some(cutset) => {
+                    let cutset = string_arg(position, name, cutset)?;
+                    Value::String(String::from(s(0)?.trim_matches(|c| cutset.contains(c))))
+                }
+                None => Value::String(String::from(s(0)?.trim())),
+            }
+        }
+        "trimPrefix" | "trimSuffix" => {
+            arity(position, name, &args, 1, 2)?;
+            let affix = match args.get(1) {
+                Some(affix) => string_arg(position, name, affix)?,
+                None => " ",
+            };
+            let text = s(0)?;
+            let trimmed = if name == "trimPrefix" {
+                text.strip_prefix(affix)
+            } else {
+                text.strip_suffix(affix)
+            };
+            Value::String(String::from(trimmed.unwrap_or(text)))
+        }
+        "upper" | "lower" => {
+            arity(position, name, &args, 1, 1)?;
+            Value::String(if name == "upper" {
+                s(0)?.to_uppercase()
+            } else {
+                s(0)?.to_lowercase()
+            })
+        }
+        "split" => {
+            arity(position, name, &args, 2, 3)?;
+            let parts: Vec<Value> = match args.get(2) {
+                Some(n) => {
+                    let n = int_arg(position, name, n)?;
+                    if n < 0 {
+                        s(0)?.split(s(1)?).map(Value::from).collect()
+                    } else {
+                        s(0)?.splitn(n as usize, s(1)?).map(Value::from).collect()
+                    }
+                }
+                None => s(0)?.split(s(1)?).map(Value::from).collect(),
+            };
+            Value::Array(parts)
+        }
+        "join" => {
+            arity(position, name, &args, 1, 2)?;
+            let separator = match args.get(1) {
+                Some(separator) => string_arg(position, name, separator)?,
+                None => "",
+            };
+            let items: Vec<String> = array_arg(position, name, &args[0])?
+                .iter()
+                .map(to_string)
+                .collect();
+            Value::String(items.join(separator))
+        }
+        "replace" => {
+            arity(position, name, &args, 3, 3)?;
+            Value::String(s(0)?.replace(s(1)?, s(2)?))
+        }
+        "repeat" => {
+            arity(position, name, &args, 2, 2)?;
+            let (text, count) = (s(0)?, int_arg(position, name, &args[1])?.max(0) as usize);
+            limit_length(position, text.len().saturating_mul(count))?;
+            Value::String(text.repeat(count))
+        }
+        "indexOf" | "lastIndexOf" => {
+            arity(position, name, &args, 2, 2)?;
+            let (text, needle) = (s(0)?, s(1)?);
+            let index = if name == "indexOf" {
+                text.find(needle)
+            } else {
+                text.rfind(needle)
+            };
+            Value::from(index.map_or(-1, |i| text[..i].chars().count() as i64))
+        }
+        "hasPrefix" | "hasSuffix" => {
+            arity(position, name, &args, 2, 2)?;
+            Value::Bool(if name == "hasPrefix" {
+                s(0)?.starts_with(s(1)?)
+            } else {
+                s(0)?.ends_with(s(1)?)
+            })
+        }
+        "max" => extreme(position, name, &args, |o| o.is_gt())?,
+        "min" => extreme(position, name, &args, |o| o.is_lt())?,
+        "sum" | "mean" => {
+            arity(position, name, &args, 1, 1)?;
+            let items = array_arg(position, name, &args[0])?;
+            let mut total = Value::from(0);
+            for item in items {
+                total = match (total.as_i64(), item.as_i64()) {
+                    (Some(t), Some(i)) => Value::from(t + i),
+                    _ => float(total.as_f64().unwrap_or_default() + as_float(position, item)?),
+                };
+            }
+            if name == "mean" {
+                match items.len() {
+                    0 => float(0.0),
+                    n => float(total.as_f64().unwrap_or_default() / n as f64),
+                }
+            } else {
+                total
+            }
+        }
+        "first" | "last" => {
+            arity(position, name, &args, 1, 1)?;
+            let items = array_arg(position, name, &args[0])?;
+            let item = if name == "first" {
+                items.first()
+            } else {
+                items.last()
+            };
+            item.cloned().unwrap_or(Value::Null)
+        }
+        "reverse" => {
+            arity(position, name, &args, 1, 1)?;
+            Value::Array(
+                array_arg(position, name, &args[0])?
+                    .iter()
+                    .rev()
+                    .cloned()
+                    .collect(),
+            )
+        }
+        "sort" => {
+            arity(position, name, &args, 1, 2)?;
+            let mut items = array_arg(position, name, &args[0])?.to_vec();
+            items.sort_by(|a, b| compare(a, b).unwrap_or(std::cmp::Ordering::Equal));
+            if args.get(1).and_then(Value::as_str) == Some("desc") {
+                items.reverse();
+            }
+            Value::Array(items)
+        }
+        "uniq" => {
+            arity(position, name, &args, 1, 1)?;
+            let mut items: Vec<Value> = Vec::new();
+            for item in array_arg(position, name, &args[0])? {
+                if !items.iter().any(|i| equal(i, item)) {
+                    items.push(item.clone());
+                }
+            }
+            Value::Array(items)
+        }
+        "concat" => {
+            let mut items = Vec::new();
+            for arg in &args {
+                items.extend(array_arg(position, name, arg)?.iter().cloned());
+            }
+            Value::Array(items)
+        }
+        "keys" | "values" => {
+            arity(position, name, &args, 1, 1)?;
+            let map = args[0].as_object().ok_or_else(|| {
+                evaluation(
+                    position,
+                    &format!("{}() expects a map, got {}", name, type_name(&args[0])),
+                )
+            })?;
+            if name == "keys" {
+                Value::Array(map.keys().cloned().map(Value::String).collect())
+            } else {
+                Value::Array(map.values().cloned().collect())
+            }
+        }
+        "type" => {
+            arity(position, name, &args, 1, 1)?;
+            Value::from(type_name(&args[0]))
+        }
+        "toJSON" | "toJson" => {
+            arity(position, name, &args, 1, 1)?;
+            to_json(position, &args[0])?
+        }
+        "fromJSON" => {
+            arity(position, name, &args, 1, 1)?;
+            from_json(position, s(0)?)?
+        }
+        "toBase64" => {
+            arity(position, name, &args, 1, 1)?;
+            base64_encode(s(0)?)
+        }
+        "fromBase64" => {
+            arity(position, name, &args, 1, 1)?;
+            base64_decode(position, s(0)?)?
+        }
+        "jsonpath" => {
+            arity(position, name, &args, 2, 2)?;
+            let document = match &args[0] {
+                Value::String(text) => from_json(position, text)?,
+                value => value.clone(),
+            };
+            jsonpath(position, document, s(1)?)?
+        }
+        _ => {
+            return Err(ExpressionError::UnknownFunction {
+                position,
+                name: String::from(name),
+            })
+        }
+    };
+    Ok(value)
+}
@@ -0,0 +1,243 @@
+use crate::error::ExpressionError;
+
+/// Operators and punctuation, longest first so that `**` is not read as two
+/// `*`.
+const OPERATORS: &[&str] = &[
+    "**", "==", "!=", "=~", "!~", "<=", ">=", "&&", "||", "..", "?.", "??", "+", "-", "*", "/",
+    "%", "^", "<", ">", "!", "?", ":", ".", ",", "(", ")", "[", "]", "{", "}", "#", "|",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Token {
+    /// An integer literal, which may only fit in an `i64` once negated.
+    Integer(u64),
+    Float(f64),
+    String(String),
+    Identifier(String),
+    Operator(&'static str),
+    End,
+}
+
+impl Token {
+    pub(super) fn describe(&self) -> String {
+        match self {
+            Token::Integer(value) => format!("'{}'", value),
+            Token::Float(value) => format!("'{}'", value),
+            Token::String(value) => format!("{:?}", value),
+            Token::Identifier(name) => format!("'{}'", name),
+            Token::Operator(operator) => format!("'{}'", operator),
+            Token::End => String::from("end of expression"),
+        }
+    }
+}
+
+/// Splits `source` into tokens, each with its byte offset.
+pub(super) fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < source.len() {
+        let rest = &source[i..];
+        let c = rest.chars().next().expect("rest is not empty");
+        if c.is_whitespace() {
+            i += c.len_utf8();
+            continue;
+        }
+
+        let start = i;
+        let token = if c.is_ascii_digit() {
+            let (token, len) = number(rest).ok_or_else(|| syntax(start, "invalid number"))?;
+            i += len;
+            token
+        } else if c == '"' || c == '\'' || c == '`' {
+            let (value, len) =
+                string(rest, c).ok_or_else(|| syntax(start, "unterminated string"))?;
+            i += len;
+            Token::String(value)
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                .unwrap_or(rest.len());
+            i += len;
+            Token::Identifier(String::from(&rest[..len]))
+        } else {
+            let operator = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| syntax(start, &format!("unexpected character '{}'", c)))?;
+            i += operator.len();
+            Token::Operator(operator)
+        };
+        tokens.push((start, token));
+    }
+    tokens.push((source.len(), Token::End));
+    Ok(tokens)
+}
+
+pub(super) fn syntax(position: usize, message: &str) -> ExpressionError {
+    ExpressionError::Syntax {
+        position,
+        message: String::from(message),
+    }
+}
+
+/// Reads the number at the start of `text`, returning it along with its
+/// length.
+fn number(text: &str) -> Option<(Token, usize)> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        let len = hex
+            .find(|c: char| !(c.is_ascii_hexdigit() || c == '_'))
+            .unwrap_or(hex.len());
+        let digits = hex[..len].replace('_', "");
+        return u64::from_str_radix(&digits, 16)
+            .ok()
+            .map(|v| (Token::Integer(v), len + 2));
+    }
+
+    let bytes = text.as_bytes();
+    let mut len = 0;
+    let mut float = false;
+    while len < bytes.len() {
+        match bytes[len] {
+            b'0'..=b'9' | b'_' => len += 1,
+            // A `.` is part of the number unless it starts a `..` range.
+            b'.' if !float && bytes.get(len + 1).is_some_and(u8::is_ascii_digit) => {
+                float = true;
+                len += 1;
+            }
+            b'e' | b'E' => {
+                let sign = matches!(bytes.get(len + 1), Some(b'+' | b'-')) as usize;
+                if !bytes.get(len + 1 + sign).is_some_and(u8::is_ascii_digit) {
+                    break;
+                }
+                float = true;
+                len += 1 + sign;
+            }
+            _ => break,
+        }
+    }
+    let digits = text[..len].replace('_', "");
+    if float {
+        digits.parse().ok().map(|v| (Token::Float(v), len))
+    } else {
+        digits.parse().ok().map(|v| (Token::Integer(v), len))
+    }
+}
+
+/// Reads the string quoted by `quote` at the start of `text`, returning its
+/// unescaped value along with its length. Backquoted strings are raw.
+fn string(text: &str, quote: char) -> Option<(String, usize)> {
+    let mut value = String::new();
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Some((value, i + 1)),
+            '\\' if quote != '`' => {
+                let (_, escaped) = chars.next()?;
+                value.push(match escaped {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    '0' => '\0',
+                    'u' => {
+                        let code: String = (0..4)
+                            .filter_map(|_| chars.next())
+                            .map(|(_, c)| c)
+                            .collect();
+                        char::from_u32(u32::from_str_radix(&code, 16).ok()?)?
+                    }
+                    c => c,
+                });
+            }
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<Token> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|(_, token)| token)
+            .collect()
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(
+            tokens("42 1_000 0x1F 1.5 2e3"),
+            vec![
+                Token::Integer(42),
+                Token::Integer(1000),
+                Token::Integer(31),
+                Token::Float(1.5),
+                Token::Float(2000.0),
+                Token::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn range_is_not_a_float() {
+        assert_eq!(
+            tokens("1..3"),
+            vec![
+                Token::Integer(1),
+                Token::Operator(".."),
+                Token::Integer(3),
+                Token::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            tokens(r#"'a' "b\n" `c\n`"#),
+            vec![
+                Token::String(String::from("a")),
+                Token::String(String::from("b\n")),
+                Token::String(String::from("c\\n")),
+                Token::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn longest_operator_first() {
+        assert_eq!(
+            tokens("a ** b != c"),
+            vec![
+                Token::Identifier(String::from("a")),
+                Token::Operator("**"),
+                Token::Identifier(String::from("b")),
+                Token::Operator("!="),
+                Token::Identifier(String::from("c")),
+                Token::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn positions() {
+        let positions: Vec<usize> = tokenize("a  +b").unwrap().iter().map(|t| t.0).collect();
+        assert_eq!(positions, vec![0, 3, 4, 5]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(tokenize("'open"), Err(syntax(0, "unterminated string")));
+        assert_eq!(
+            tokenize("a @ b"),
+            Err(syntax(2, "unexpected character '@'"))
+        );
+        assert_eq!(
+            tokenize("99999999999999999999"),
+            Err(syntax(0, "invalid number"))
+        );
+    }
+}
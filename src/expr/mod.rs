@@ -0,0 +1,146 @@
+//! A local evaluator for the [expr language](https://expr-lang.org) used by
+//! Argo in `when` conditions, `RetryStrategy::expression`,
+//! `LifecycleHook::expression`, `HTTP::success_condition`,
+//! `TransformationStep::expression`, `ValueFrom::expression` and `{{=...}}`
+//! tags.
+//!
+//! Values are JSON values. Besides the builtin functions of the language,
+//! the functions Argo adds are available (`asInt`, `asFloat`, `string`,
+//! `jsonpath`, `toJson`), along with the Sprig functions as
+//! `sprig.<name>()`, but for those depending on the environment, random or
+//! cryptographic ones. The `=~` and `!~` regular expression operators of
+//! the govaluate language, which Argo used for `when` conditions, are
+//! accepted as well.
+//!
+//! Integer overflow is an error, and so is building an array of more than
+//! 100,000 items or repeating a string into more than 1 MiB, as expressions
+//! come from workflow specs.
+//!
+//! ```
+//! use argoflows::expr::{Context, Expression};
+//!
+//! let context = Context::new()
+//!     .variable("lastRetry.status", "Error")
+//!     .variable("retries", 1);
+//! let expression = Expression::parse("lastRetry.status == 'Error' && retries < 3").unwrap();
+//! assert_eq!(expression.evaluate_bool(&context), Ok(true));
+//! ```
+
+use serde_json::{Map, Value};
+
+use crate::error::ExpressionError;
+
+mod eval;
+mod functions;
+mod hash;
+mod lexer;
+mod parser;
+mod sprig;
+
+/// A parsed expression.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expression {
+    source: String,
+    root: parser::Node,
+}
+
+impl Expression {
+    /// Parses `source`, returning a `ExpressionError::Syntax` locating the
+    /// first problem found.
+    pub fn parse(source: &str) -> Result<Self, ExpressionError> {
+        Ok(Expression {
+            source: String::from(source),
+            root: parser::parse(source)?,
+        })
+    }
+
+    /// Returns the source of the expression.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluates the expression against the variables of `context`.
+    pub fn evaluate(&self, context: &Context) -> Result<Value, ExpressionError> {
+        eval::Evaluator { context }.eval(&self.root, None)
+    }
+
+    /// Evaluates the expression, which must return a bool, as conditions do.
+    pub fn evaluate_bool(&self, context: &Context) -> Result<bool, ExpressionError> {
+        match self.evaluate(context)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(eval::evaluation(
+                self.root.position,
+                &format!(
+                    "expression returned {}, expected bool",
+                    eval::type_name(&value)
+                ),
+            )),
+        }
+    }
+}
+
+/// Parses and evaluates `source` against the variables of `context`.
+pub fn evaluate(source: &str, context: &Context) -> Result<Value, ExpressionError> {
+    Expression::parse(source)?.evaluate(context)
+}
+
+/// `Context` holds the variables an expression is evaluated against.
+///
+/// Variables with a dotted name are stored in nested maps, the way Argo
+/// exposes them: setting `inputs.parameters.count` makes `inputs` a map
+/// holding a `parameters` map. Names which are not identifiers can be
+/// accessed by index, e.g. `steps['flip-coin'].outputs.result`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Context {
+    variables: Map<String, Value>,
+}
+
+impl Context {
+    /// Constructs a new `Context` with no variable.
+    pub fn new() -> Self {
+        Context {
+            variables: Map::new(),
+        }
+    }
+
+    /// Sets the variable `name` to `value`.
+    pub fn variable<V: Into<Value>>(mut self, name: &str, value: V) -> Self {
+        self.insert(name, value);
+        self
+    }
+
+    /// Sets the variable `name` to `value`.
+    pub fn insert<V: Into<Value>>(&mut self, name: &str, value: V) {
+        let mut map = &mut self.variables;
+        let mut segments = name.split('.').peekable();
+        while let Some(segment) = segments.next() {
+            if segments.peek().is_none() {
+                map.insert(String::from(segment), value.into());
+                return;
+            }
+            let entry = map
+                .entry(segment)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+            map = entry.as_object_mut().expect("entry is a map");
+        }
+    }
+
+    /// Returns the value of the variable `name`, given as a dotted name.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        let mut segments = name.split('.');
+        let mut value = self.variables.get(segments.next()?)?;
+        for segment in segments {
+            value = value.get(segment)?;
+        }
+        Some(value)
+    }
+}
+
+impl From<Map<String, Value>> for Context {
+    fn from(variables: Map<String, Value>) -> Self {
+        Context { variables }
+    }
+}
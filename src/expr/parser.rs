@@ -0,0 +1,594 @@
+use super::lexer::{syntax, tokenize, Token};
+use crate::error::ExpressionError;
+
+/// Functions whose second argument is a predicate, evaluated for each item
+/// of the first one with `#` set to the item.
+const PREDICATE_FUNCTIONS: &[&str] = &[
+    "all",
+    "any",
+    "none",
+    "one",
+    "filter",
+    "map",
+    "count",
+    "find",
+    "findIndex",
+    "findLast",
+    "findLastIndex",
+    "groupBy",
+    "sortBy",
+];
+
+/// A node of the syntax tree of an expression, with the byte offset of the
+/// part of the expression it was parsed from.
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Node {
+    pub(super) kind: Kind,
+    pub(super) position: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum Kind {
+    Nil,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Variable(String),
+    /// `#`, the item given to a predicate.
+    Pointer,
+    Array(Vec<Node>),
+    Map(Vec<(String, Node)>),
+    Unary(&'static str, Box<Node>),
+    Binary(&'static str, Box<Node>, Box<Node>),
+    Member {
+        object: Box<Node>,
+        property: Box<Node>,
+        optional: bool,
+    },
+    Slice {
+        object: Box<Node>,
+        from: Option<Box<Node>>,
+        to: Option<Box<Node>>,
+    },
+    Call(String, Vec<Node>),
+    Predicate(Box<Node>),
+    Conditional(Box<Node>, Box<Node>, Box<Node>),
+}
+
+/// Returns the precedence of a binary operator, and whether it is right
+/// associative.
+fn binary(operator: &str) -> Option<(u16, bool)> {
+    let precedence = match operator {
+        "or" | "||" => (10, false),
+        "and" | "&&" => (15, false),
+        "==" | "!=" | "<" | ">" | "<=" | ">=" | "in" | "not in" | "matches" | "=~" | "!~"
+        | "contains" | "startsWith" | "endsWith" => (20, false),
+        ".." => (25, false),
+        "+" | "-" => (30, false),
+        "*" | "/" | "%" => (60, false),
+        "**" | "^" => (100, true),
+        "??" => (500, false),
+        _ => return None,
+    };
+    Some(precedence)
+}
+
+pub(super) fn parse(source: &str) -> Result<Node, ExpressionError> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        current: 0,
+    };
+    let node = parser.conditional()?;
+    match parser.peek() {
+        Token::End => Ok(node),
+        token => Err(parser.unexpected(token.clone(), "an operator")),
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    current: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current].1
+    }
+
+    fn peek_at(&self, offset: usize) -> &Token {
+        let index = (self.current + offset).min(self.tokens.len() - 1);
+        &self.tokens[index].1
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.current].0
+    }
+
+    fn next(&mut self) -> (usize, Token) {
+        let token = self.tokens[self.current].clone();
+        if self.current < self.tokens.len() - 1 {
+            self.current += 1;
+        }
+        token
+    }
+
+    fn is_operator(&self, operator: &str) -> bool {
+        matches!(self.peek(), Token::Operator(op) if *op == operator)
+    }
+
+    fn accept(&mut self, operator: &str) -> bool {
+        let accepted = self.is_operator(operator);
+        if accepted {
+            self.next();
+        }
+        accepted
+    }
+
+    fn expect(&mut self, operator: &str) -> Result<(), ExpressionError> {
+        if self.accept(operator) {
+            Ok(())
+        } else {
+            let token = self.peek().clone();
+            Err(self.unexpected(token, &format!("'{}'", operator)))
+        }
+    }
+
+    fn unexpected(&self, token: Token, expected: &str) -> ExpressionError {
+        syntax(
+            self.position(),
+            &format!("found {}, expected {}", token.describe(), expected),
+        )
+    }
+
+    /// Returns the binary operator at the current token, and the number of
+    /// tokens it is made of.
+    fn binary_operator(&self) -> Option<(&'static str, usize)> {
+        match self.peek() {
+            Token::Operator(operator) => binary(operator).map(|_| (*operator, 1)),
+            Token::Identifier(name) if name == "not" => match self.peek_at(1) {
+                Token::Identifier(next) if next == "in" => Some(("not in", 2)),
+                _ => None,
+            },
+            Token::Identifier(name) => {
+                let operator = [
+                    "or",
+                    "and",
+                    "in",
+                    "matches",
+                    "contains",
+                    "startsWith",
+                    "endsWith",
+                ]
+                .into_iter()
+                .find(|op| op == name)?;
+                Some((operator, 1))
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses an expression, with pipes: `x | f(y)` is `f(x, y)`.
+    fn conditional(&mut self) -> Result<Node, ExpressionError> {
+        let mut node = self.ternary()?;
+        while self.is_operator("|") {
+            let position = self.position();
+            self.next();
+            let mut call = self.postfix()?;
+            match &mut call.kind {
+                Kind::Call(_, arguments) => arguments.insert(0, node),
+                _ => return Err(syntax(position, "expected a function call after '|'")),
+            }
+            node = call;
+        }
+        Ok(node)
+    }
+
+    fn ternary(&mut self) -> Result<Node, ExpressionError> {
+        let position = self.position();
+        let condition = self.expression(0)?;
+        if !self.accept("?") {
+            return Ok(condition);
+        }
+        let then = self.conditional()?;
+        self.expect(":")?;
+        let otherwise = self.conditional()?;
+        Ok(Node {
+            kind: Kind::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)),
+            position,
+        })
+    }
+
+    fn expression(&mut self, min_precedence: u16) -> Result<Node, ExpressionError> {
+        let mut left = self.unary()?;
+        while let Some((operator, len)) = self.binary_operator() {
+            let (precedence, right_associative) = binary(operator).expect("operator is binary");
+            if precedence < min_precedence {
+                break;
+            }
+            let position = self.position();
+            for _ in 0..len {
+                self.next();
+            }
+            let next_precedence = if right_associative {
+                precedence
+            } else {
+                precedence + 1
+            };
+            let right = self.expression(next_precedence)?;
+            left = Node {
+                kind: Kind::Binary(operator, Box::new(left), Box::new(right)),
+                position,
+            };
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Node, ExpressionError> {
+        let position = self.position();
+        let (operator, precedence) = match self.peek() {
+            Token::Operator("!") => ("!", 50),
+            Token::Identifier(name) if name == "not" => ("!", 50),
+            Token::Operator("-") => ("-", 90),
+            Token::Operator("+") => ("+", 90),
+            _ => return self.postfix(),
+        };
+        self.next();
+        // `-9223372036854775808` is the only literal fitting once negated,
+        // unless a `**` applies to the literal first.
+        if let (Token::Integer(value), "-") = (self.peek(), operator) {
+            let power = matches!(self.peek_at(1), Token::Operator("**" | "^"));
+            if *value == i64::MIN.unsigned_abs() && !power {
+                self.next();
+                return Ok(Node {
+                    kind: Kind::Integer(i64::MIN),
+                    position,
+                });
+            }
+        }
+        let operand = self.expression(precedence)?;
+        Ok(Node {
+            kind: Kind::Unary(operator, Box::new(operand)),
+            position,
+        })
+    }
+
+    fn postfix(&mut self) -> Result<Node, ExpressionError> {
+        let mut node = self.primary()?;
+        loop {
+            let position = self.position();
+            if self.is_operator(".") || self.is_operator("?.") {
+                let optional = self.is_operator("?.");
+                self.next();
+                if optional && self.is_operator("[") {
+                    node = self.index(node, true)?;
+                    continue;
+                }
+                let name = self.property_name()?;
+                node = Node {
+                    kind: Kind::Member {
+                        object: Box::new(node),
+                        property: Box::new(Node {
+                            kind: Kind::String(name),
+                            position,
+                        }),
+                        optional,
+                    },
+                    position,
+                };
+            } else if self.is_operator("[") {
+                node = self.index(node, false)?;
+            } else if self.is_operator("(") {
+                let (name, start) = match &node.kind {
+                    Kind::Member {
+                        object, property, ..
+                    } => match (&object.kind, &property.kind) {
+                        (Kind::Variable(name), Kind::String(function)) => {
+                            (format!("{}.{}", name, function), object.position)
+                        }
+                        _ => return Err(syntax(position, "expression is not callable")),
+                    },
+                    _ => return Err(syntax(position, "expression is not callable")),
+                };
+                node = self.call(name, start)?;
+            } else {
+                return Ok(node);
+            }
+        }
+    }
+
+    fn property_name(&mut self) -> Result<String, ExpressionError> {
+        match self.next() {
+            (_, Token::Identifier(name)) => Ok(name),
+            (position, token) => Err(syntax(
+                position,
+                &format!("found {}, expected a property name", token.describe()),
+            )),
+        }
+    }
+
+    /// Parses `[index]` or `[from:to]` following `object`.
+    fn index(&mut self, object: Node, optional: bool) -> Result<Node, ExpressionError> {
+        let position = self.position();
+        self.expect("[")?;
+        let from = if self.is_operator(":") {
+            None
+        } else {
+            Some(Box::new(self.conditional()?))
+        };
+        if !self.accept(":") {
+            self.expect("]")?;
+            let property = from.ok_or_else(|| syntax(position, "missing index"))?;
+            return Ok(Node {
+                kind: Kind::Member {
+                    object: Box::new(object),
+                    property,
+                    optional,
+                },
+                position,
+            });
+        }
+        let to = if self.is_operator("]") {
+            None
+        } else {
+            Some(Box::new(self.conditional()?))
+        };
+        self.expect("]")?;
+        Ok(Node {
+            kind: Kind::Slice {
+                object: Box::new(object),
+                from,
+                to,
+            },
+            position,
+        })
+    }
+
+    fn call(&mut self, name: String, position: usize) -> Result<Node, ExpressionError> {
+        self.expect("(")?;
+        let mut arguments = Vec::new();
+        while !self.is_operator(")") {
+            if !arguments.is_empty() {
+                self.expect(",")?;
+                if self.is_operator(")") {
+                    break;
+                }
+            }
+            let argument = if arguments.len() == 1 && PREDICATE_FUNCTIONS.contains(&name.as_str()) {
+                self.predicate()?
+            } else {
+                self.conditional()?
+            };
+            arguments.push(argument);
+        }
+        self.expect(")")?;
+        Ok(Node {
+            kind: Kind::Call(name, arguments),
+            position,
+        })
+    }
+
+    /// Parses a predicate, with or without braces: `{# > 1}` or `# > 1`.
+    fn predicate(&mut self) -> Result<Node, ExpressionError> {
+        let position = self.position();
+        let body = if self.accept("{") {
+            let body = self.conditional()?;
+            self.expect("}")?;
+            body
+        } else {
+            self.conditional()?
+        };
+        Ok(Node {
+            kind: Kind::Predicate(Box::new(body)),
+            position,
+        })
+    }
+
+    fn primary(&mut self) -> Result<Node, ExpressionError> {
+        let (position, token) = self.next();
+        let kind = match token {
+            Token::Integer(value) => Kind::Integer(
+                i64::try_from(value)
+                    .map_err(|_| syntax(position, "integer literal out of range"))?,
+            ),
+            Token::Float(value) => Kind::Float(value),
+            Token::String(value) => Kind::String(value),
+            Token::Identifier(name) => match name.as_str() {
+                "true" => Kind::Bool(true),
+                "false" => Kind::Bool(false),
+                "nil" => Kind::Nil,
+                _ if self.is_operator("(") => return self.call(name, position),
+                _ => Kind::Variable(name),
+            },
+            Token::Operator("#") => Kind::Pointer,
+            // `.name` within a predicate is a property of `#`.
+            Token::Operator(".") => {
+                let property_position = self.position();
+                let name = self.property_name()?;
+                Kind::Member {
+                    object: Box::new(Node {
+                        kind: Kind::Pointer,
+                        position,
+                    }),
+                    property: Box::new(Node {
+                        kind: Kind::String(name),
+                        position: property_position,
+                    }),
+                    optional: false,
+                }
+            }
+            Token::Operator("(") => {
+                let node = self.conditional()?;
+                self.expect(")")?;
+                return Ok(node);
+            }
+            Token::Operator("[") => {
+                let mut items = Vec::new();
+                while !self.is_operator("]") {
+                    items.push(self.conditional()?);
+                    if !self.accept(",") {
+                        break;
+                    }
+                }
+                self.expect("]")?;
+                Kind::Array(items)
+            }
+            Token::Operator("{") => {
+                let mut entries = Vec::new();
+                while !self.is_operator("}") {
+                    let key = match self.next() {
+                        (_, Token::Identifier(key)) | (_, Token::String(key)) => key,
+                        (_, Token::Integer(key)) => key.to_string(),
+                        (position, token) => {
+                            return Err(syntax(
+                                position,
+                                &format!("found {}, expected a map key", token.describe()),
+                            ))
+                        }
+                    };
+                    self.expect(":")?;
+                    entries.push((key, self.conditional()?));
+                    if !self.accept(",") {
+                        break;
+                    }
+                }
+                self.expect("}")?;
+                Kind::Map(entries)
+            }
+            token => {
+                return Err(syntax(
+                    position,
+                    &format!("found {}, expected a value", token.describe()),
+                ))
+            }
+        };
+        Ok(Node { kind, position })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renders a syntax tree as an s-expression, to compare trees tersely.
+    fn sexp(node: &Node) -> String {
+        let list = |nodes: &[Node]| nodes.iter().map(sexp).collect::<Vec<_>>().join(" ");
+        match &node.kind {
+            Kind::Nil => String::from("nil"),
+            Kind::Bool(b) => b.to_string(),
+            Kind::Integer(i) => i.to_string(),
+            Kind::Float(f) => format!("{:?}", f),
+            Kind::String(s) => format!("{:?}", s),
+            Kind::Variable(name) => name.clone(),
+            Kind::Pointer => String::from("#"),
+            Kind::Array(items) => format!("[{}]", list(items)),
+            Kind::Map(entries) => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, sexp(value)))
+                    .collect();
+                format!("{{{}}}", entries.join(", "))
+            }
+            Kind::Unary(operator, operand) => format!("({} {})", operator, sexp(operand)),
+            Kind::Binary(operator, left, right) => {
+                format!("({} {} {})", operator, sexp(left), sexp(right))
+            }
+            Kind::Member {
+                object,
+                property,
+                optional,
+            } => format!(
+                "({} {} {})",
+                if *optional { "?." } else { "." },
+                sexp(object),
+                sexp(property)
+            ),
+            Kind::Slice { object, from, to } => {
+                let bound = |b: &Option<Box<Node>>| b.as_deref().map_or(String::from("_"), sexp);
+                format!("(slice {} {} {})", sexp(object), bound(from), bound(to))
+            }
+            Kind::Call(name, arguments) => format!("({} {})", name, list(arguments)),
+            Kind::Predicate(body) => format!("{{{}}}", sexp(body)),
+            Kind::Conditional(condition, then, otherwise) => {
+                format!("(? {} {} {})", sexp(condition), sexp(then), sexp(otherwise))
+            }
+        }
+    }
+
+    fn parsed(source: &str) -> String {
+        sexp(&parse(source).unwrap())
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(parsed("1 + 2 * 3"), "(+ 1 (* 2 3))");
+        assert_eq!(parsed("1 - 2 - 3"), "(- (- 1 2) 3)");
+        assert_eq!(parsed("2 ** 3 ** 2"), "(** 2 (** 3 2))");
+        assert_eq!(parsed("a || b && !c"), "(|| a (&& b (! c)))");
+        assert_eq!(parsed("-a ** 2"), "(- (** a 2))");
+        assert_eq!(parsed("(1 + 2) * 3"), "(* (+ 1 2) 3)");
+    }
+
+    #[test]
+    fn word_operators() {
+        assert_eq!(parsed("a not in b"), "(not in a b)");
+        assert_eq!(parsed("not a and b"), "(and (! a) b)");
+        assert_eq!(parsed("s startsWith 'x'"), "(startsWith s \"x\")");
+        assert_eq!(parsed("s =~ '^a'"), "(=~ s \"^a\")");
+    }
+
+    #[test]
+    fn members_and_slices() {
+        assert_eq!(
+            parsed("steps['flip-coin'].outputs?.result"),
+            "(?. (. (. steps \"flip-coin\") \"outputs\") \"result\")"
+        );
+        assert_eq!(parsed("items[1:]"), "(slice items 1 _)");
+        assert_eq!(parsed("items[:-1]"), "(slice items _ (- 1))");
+    }
+
+    #[test]
+    fn calls_pipes_and_predicates() {
+        assert_eq!(parsed("sprig.trim(x) | upper()"), "(upper (sprig.trim x))");
+        assert_eq!(parsed("filter(xs, # > 1)"), "(filter xs {(> # 1)})");
+        assert_eq!(parsed("all(xs, {.ok})"), "(all xs {(. # \"ok\")})");
+    }
+
+    #[test]
+    fn literals_and_conditional() {
+        assert_eq!(
+            parsed("{a: [1, 2.5, 'x'], 'b': nil}"),
+            "{a: [1 2.5 \"x\"], b: nil}"
+        );
+        assert_eq!(parsed("a ? b : c ? d : e"), "(? a b (? c d e))");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse("1 +"),
+            Err(syntax(3, "found end of expression, expected a value"))
+        );
+        assert_eq!(
+            parse("a b"),
+            Err(syntax(2, "found 'b', expected an operator"))
+        );
+        assert_eq!(
+            parse("(1 + 2"),
+            Err(syntax(6, "found end of expression, expected ')'"))
+        );
+        assert_eq!(
+            parse("a | b"),
+            Err(syntax(2, "expected a function call after '|'"))
+        );
+        assert_eq!(parse("1()"), Err(syntax(1, "expression is not callable")));
+        assert_eq!(
+            parse("9223372036854775808"),
+            Err(syntax(0, "integer literal out of range"))
+        );
+        assert_eq!(
+            parse("-9223372036854775808 ** 2"),
+            Err(syntax(1, "integer literal out of range"))
+        );
+    }
+}
@@ -0,0 +1,855 @@
+//! The Sprig functions Argo exposes as `sprig.<name>()`.
+//!
+//! Like in Go templates, the value a function operates on is its last
+//! argument (`sprig.trimPrefix("v", version)`), and arguments are converted
+//! to the expected type leniently: numbers given as strings are parsed, and
+//! values that can't be converted become the zero value of the type.
+//!
+//! Left out are the functions whose result depends on the environment or
+//! is random (dates, `now`, `env`, `uuidv4`, `rand*`, `shuffle`,
+//! `getHostByName`), the cryptographic functions (`bcrypt`, `htpasswd`,
+//! `derivePassword`, `encryptAES`, `decryptAES`, `gen*` keys and
+//! certificates), and the semver, path, URL and reflection functions.
+//! They return `ExpressionError::UnknownFunction`.
+
+use serde_json::{Map, Value};
+
+use super::eval::{
+    equal, evaluation, float, limit_items, limit_length, regex, to_string, type_name,
+};
+use super::functions::{arity, base64_decode, base64_encode, from_json, to_json};
+use super::hash;
+use crate::error::ExpressionError;
+
+/// Converts `value` to a string; `nil` is the empty string.
+fn strval(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        value => to_string(value),
+    }
+}
+
+/// Converts `value` to an integer, or 0.
+fn toint(value: &Value) -> i64 {
+    match value {
+        Value::Number(n) => n
+            .as_i64()
+            .or_else(|| n.as_f64().map(|f| f as i64))
+            .unwrap_or(0),
+        Value::String(s) => s.trim().parse().unwrap_or(0),
+        Value::Bool(b) => *b as i64,
+        _ => 0,
+    }
+}
+
+/// Converts `value` to a float, or 0.
+fn tofloat(value: &Value) -> f64 {
+    match value {
+        Value::Number(n) => n.as_f64().unwrap_or(0.0),
+        Value::String(s) => s.trim().parse().unwrap_or(0.0),
+        Value::Bool(b) => *b as i64 as f64,
+        _ => 0.0,
+    }
+}
+
+/// Converts `value` to a list; `nil` is the empty list.
+fn tolist(position: usize, name: &str, value: &Value) -> Result<Vec<Value>, ExpressionError> {
+    match value {
+        Value::Array(items) => Ok(items.clone()),
+        Value::Null => Ok(Vec::new()),
+        value => Err(evaluation(
+            position,
+            &format!("{}() expects a list, got {}", name, type_name(value)),
+        )),
+    }
+}
+
+fn todict(
+    position: usize,
+    name: &str,
+    value: &Value,
+) -> Result<Map<String, Value>, ExpressionError> {
+    match value {
+        Value::Object(map) => Ok(map.clone()),
+        Value::Null => Ok(Map::new()),
+        value => Err(evaluation(
+            position,
+            &format!("{}() expects a dict, got {}", name, type_name(value)),
+        )),
+    }
+}
+
+/// Returns whether `value` is the zero value of its type.
+fn empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Bool(b) => !b,
+        Value::Number(n) => n.as_f64() == Some(0.0),
+        Value::String(s) => s.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+    }
+}
+
+/// Splits `text` into words on non alphanumeric characters and case changes.
+fn words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous_lower = false;
+    for c in text.chars() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            previous_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && previous_lower && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Returns the characters of `text` from `start` to `end`, Sprig style: a
+/// negative `start` is 0 and a negative `end` is the end of the string.
+fn substr(text: &str, start: i64, end: i64) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let len = chars.len() as i64;
+    let start = start.clamp(0, len) as usize;
+    let end = if end < 0 { len } else { end.min(len) } as usize;
+    if start >= end {
+        return String::new();
+    }
+    chars[start..end].iter().collect()
+}
+
+/// Calls the Sprig function `name`.
+pub(super) fn call(
+    position: usize,
+    name: &str,
+    args: Vec<Value>,
+) -> Result<Value, ExpressionError> {
+    let sprig = format!("sprig.{}", name);
+    let check = |min: usize, max: usize| arity(position, &sprig, &args, min, max);
+    let s = |i: usize| strval(&args[i]);
+    let value = match name {
+        // Strings.
+        "trim" => {
+            check(1, 1)?;
+            Value::String(String::from(s(0).trim()))
+        }
+        "trimAll" => {
+            check(2, 2)?;
+            let cutset = s(0);
+            Value::String(String::from(s(1).trim_matches(|c| cutset.contains(c))))
+        }
+        "trimPrefix" => {
+            check(2, 2)?;
+            let text = s(1);
+            Value::String(String::from(
+                text.strip_prefix(s(0).as_str()).unwrap_or(&text),
+            ))
+        }
+        "trimSuffix" => {
+            check(2, 2)?;
+            let text = s(1);
+            Value::String(String::from(
+                text.strip_suffix(s(0).as_str()).unwrap_or(&text),
+            ))
+        }
+        "upper" => {
+            check(1, 1)?;
+            Value::String(s(0).to_uppercase())
+        }
+        "lower" => {
+            check(1, 1)?;
+            Value::String(s(0).to_lowercase())
+        }
+        "title" => {
+            check(1, 1)?;
+            let title: Vec<String> = s(0).split(' ').map(capitalize).collect();
+            Value::String(title.join(" "))
+        }
+        "untitle" => {
+            check(1, 1)?;
+            let words: Vec<String> = s(0)
+                .split(' ')
+                .map(|w| {
+                    let mut chars = w.chars();
+                    match chars.next() {
+                        Some(first) => first.to_lowercase().chain(chars).collect(),
+                        None => String::new(),
+                    }
+                })
+                .collect();
+            Value::String(words.join(" "))
+        }
+        "repeat" => {
+            check(2, 2)?;
+            let (text, count) = (s(1), toint(&args[0]).max(0) as usize);
+            limit_length(position, text.len().saturating_mul(count))?;
+            Value::String(text.repeat(count))
+        }
+        "substr" => {
+            check(3, 3)?;
+            Value::String(substr(&s(2), toint(&args[0]), toint(&args[1])))
+        }
+        "trunc" => {
+            check(2, 2)?;
+            let text = s(1);
+            let len = text.chars().count() as i64;
+            let n = toint(&args[0]);
+            Value::String(if n >= 0 {
+                substr(&text, 0, n)
+            } else {
+                substr(&text, len + n, len)
+            })
+        }
+        "nospace" => {
+            check(1, 1)?;
+            Value::String(s(0).chars().filter(|c| !c.is_whitespace()).collect())
+        }
+        "contains" => {
+            check(2, 2)?;
+            Value::Bool(s(1).contains(s(0).as_str()))
+        }
+        "hasPrefix" => {
+            check(2, 2)?;
+            Value::Bool(s(1).starts_with(s(0).as_str()))
+        }
+        "hasSuffix" => {
+            check(2, 2)?;
+            Value::Bool(s(1).ends_with(s(0).as_str()))
+        }
+        "quote" | "squote" => {
+            let quote = if name == "quote" { '"' } else { '\'' };
+            let quoted: Vec<String> = args
+                .iter()
+                .filter(|a| !a.is_null())
+                .map(|a| {
+                    let text = strval(a);
+                    if quote == '"' {
+                        format!("{:?}", text)
+                    } else {
+                        format!("'{}'", text)
+                    }
+                })
+                .collect();
+            Value::String(quoted.join(" "))
+        }
+        "cat" => {
+            let parts: Vec<String> = args.iter().filter(|a| !a.is_null()).map(strval).collect();
+            Value::String(parts.join(" "))
+        }
+        "indent" | "nindent" => {
+            check(2, 2)?;
+            let (text, width) = (s(1), toint(&args[0]).max(0) as usize);
+            let lines = text.matches('\n').count() + 1;
+            limit_length(
+                position,
+                width.saturating_mul(lines).saturating_add(text.len()),
+            )?;
+            let padding = " ".repeat(width);
+            let indented = text
+                .split('\n')
+                .map(|line| format!("{}{}", padding, line))
+                .collect::<Vec<_>>()
+                .join("\n");
+            Value::String(if name == "nindent" {
+                format!("\n{}", indented)
+            } else {
+                indented
+            })
+        }
+        "replace" => {
+            check(3, 3)?;
+            Value::String(s(2).replace(s(0).as_str(), s(1).as_str()))
+        }
+        "snakecase" | "kebabcase" => {
+            check(1, 1)?;
+            let separator = if name == "snakecase" { "_" } else { "-" };
+            let words: Vec<String> = words(&s(0)).iter().map(|w| w.to_lowercase()).collect();
+            Value::String(words.join(separator))
+        }
+        "camelcase" => {
+            check(1, 1)?;
+            let words: Vec<String> = words(&s(0)).iter().map(|w| capitalize(w)).collect();
+            Value::String(words.concat())
+        }
+        "plural" => {
+            check(3, 3)?;
+            if toint(&args[2]) == 1 {
+                Value::String(s(0))
+            } else {
+                Value::String(s(1))
+            }
+        }
+        "splitList" => {
+            check(2, 2)?;
+            Value::Array(s(1).split(s(0).as_str()).map(Value::from).collect())
+        }
+        "split" => {
+            check(2, 2)?;
+            let parts = s(1)
+                .split(s(0).as_str())
+                .enumerate()
+                .map(|(i, part)| (format!("_{}", i), Value::from(part)))
+                .collect();
+            Value::Object(parts)
+        }
+        "join" => {
+            check(2, 2)?;
+            let items: Vec<String> = match &args[1] {
+                Value::Array(items) => items.iter().map(strval).collect(),
+                value => vec![strval(value)],
+            };
+            Value::String(items.join(s(0).as_str()))
+        }
+        "toString" => {
+            check(1, 1)?;
+            Value::String(s(0))
+        }
+        "toStrings" => {
+            check(1, 1)?;
+            let items = tolist(position, &sprig, &args[0])?;
+            Value::Array(items.iter().map(|i| Value::String(strval(i))).collect())
+        }
+
+        // Conversions and encodings.
+        "atoi" | "int" | "int64" => {
+            check(1, 1)?;
+            Value::from(toint(&args[0]))
+        }
+        "float64" => {
+            check(1, 1)?;
+            float(tofloat(&args[0]))
+        }
+        "toJson" | "mustToJson" => {
+            check(1, 1)?;
+            to_json(position, &args[0])?
+        }
+        "toPrettyJson" => {
+            check(1, 1)?;
+            serde_json::to_string_pretty(&args[0])
+                .map(Value::String)
+                .map_err(|e| evaluation(position, &e.to_string()))?
+        }
+        "fromJson" | "mustFromJson" => {
+            check(1, 1)?;
+            from_json(position, &s(0))?
+        }
+        "b64enc" => {
+            check(1, 1)?;
+            base64_encode(&s(0))
+        }
+        "sha1sum" => {
+            check(1, 1)?;
+            Value::String(hash::hex(&hash::sha1(s(0).as_bytes())))
+        }
+        "sha256sum" => {
+            check(1, 1)?;
+            Value::String(hash::hex(&hash::sha256(s(0).as_bytes())))
+        }
+        "adler32sum" => {
+            check(1, 1)?;
+            Value::String(hash::adler32(s(0).as_bytes()).to_string())
+        }
+        "b64dec" => {
+            check(1, 1)?;
+            base64_decode(position, &s(0))?
+        }
+
+        // Defaults and flow control.
+        "default" => {
+            check(1, 2)?;
+            match args.get(1) {
+                Some(value) if !empty(value) => value.clone(),
+                _ => args[0].clone(),
+            }
+        }
+        "empty" => {
+            check(1, 1)?;
+            Value::Bool(empty(&args[0]))
+        }
+        "coalesce" => args
+            .iter()
+            .find(|a| !empty(a))
+            .cloned()
+            .unwrap_or(Value::Null),
+        "ternary" => {
+            check(3, 3)?;
+            if args[2].as_bool().unwrap_or(false) {
+                args[0].clone()
+            } else {
+                args[1].clone()
+            }
+        }
+
+        // Math.
+        "add" | "mul" => {
+            let mut integers = args.iter().map(toint);
+            let value = if name == "add" {
+                integers.try_fold(0i64, i64::checked_add)
+            } else {
+                integers.try_fold(1i64, i64::checked_mul)
+            };
+            Value::from(value.ok_or_else(|| evaluation(position, "integer overflow"))?)
+        }
+        "add1" => {
+            check(1, 1)?;
+            let value = toint(&args[0]).checked_add(1);
+            Value::from(value.ok_or_else(|| evaluation(position, "integer overflow"))?)
+        }
+        "sub" | "div" | "mod" => {
+            check(2, 2)?;
+            let (a, b) = (toint(&args[0]), toint(&args[1]));
+            if name != "sub" && b == 0 {
+                return Err(evaluation(position, "integer divide by zero"));
+            }
+            let value = match name {
+                "sub" => a.checked_sub(b),
+                "div" => a.checked_div(b),
+                _ => a.checked_rem(b),
+            };
+            Value::from(value.ok_or_else(|| evaluation(position, "integer overflow"))?)
+        }
+        "addf" | "mulf" => {
+            let floats = args.iter().map(tofloat);
+            float(if name == "addf" {
+                floats.sum()
+            } else {
+                floats.product()
+            })
+        }
+        "subf" | "divf" => {
+            check(2, 2)?;
+            let (a, b) = (tofloat(&args[0]), tofloat(&args[1]));
+            float(if name == "subf" { a - b } else { a / b })
+        }
+        "max" | "min" => {
+            check(1, usize::MAX)?;
+            let integers = args.iter().map(toint);
+            Value::from(if name == "max" {
+                integers.max()
+            } else {
+                integers.min()
+            })
+        }
+        "floor" | "ceil" | "round" => {
+            check(1, 1)?;
+            let f = tofloat(&args[0]);
+            float(match name {
+                "floor" => f.floor(),
+                "ceil" => f.ceil(),
+                _ => f.round(),
+            })
+        }
+
+        // Lists.
+        "list" => Value::Array(args),
+        "first" | "last" | "rest" | "initial" => {
+            check(1, 1)?;
+            let items = tolist(position, &sprig, &args[0])?;
+            match name {
+                "first" => items.first().cloned().unwrap_or(Value::Null),
+                "last" => items.last().cloned().unwrap_or(Value::Null),
+                "rest" => Value::Array(items.iter().skip(1).cloned().collect()),
+                _ => Value::Array(items[..items.len().saturating_sub(1)].to_vec()),
+            }
+        }
+        "append" | "push" => {
+            check(2, 2)?;
+            let mut items = tolist(position, &sprig, &args[0])?;
+            items.push(args[1].clone());
+            Value::Array(items)
+        }
+        "prepend" => {
+            check(2, 2)?;
+            let mut items = tolist(position, &sprig, &args[0])?;
+            items.insert(0, args[1].clone());
+            Value::Array(items)
+        }
+        "concat" => {
+            let mut items = Vec::new();
+            for arg in &args {
+                items.extend(tolist(position, &sprig, arg)?);
+            }
+            Value::Array(items)
+        }
+        "reverse" => {
+            check(1, 1)?;
+            let mut items = tolist(position, &sprig, &args[0])?;
+            items.reverse();
+            Value::Array(items)
+        }
+        "uniq" => {
+            check(1, 1)?;
+            let mut items: Vec<Value> = Vec::new();
+            for item in tolist(position, &sprig, &args[0])? {
+                if !items.iter().any(|i| equal(i, &item)) {
+                    items.push(item);
+                }
+            }
+            Value::Array(items)
+        }
+        "compact" => {
+            check(1, 1)?;
+            let items = tolist(position, &sprig, &args[0])?;
+            Value::Array(items.into_iter().filter(|i| !empty(i)).collect())
+        }
+        "has" => {
+            check(2, 2)?;
+            let items = tolist(position, &sprig, &args[1])?;
+            Value::Bool(items.iter().any(|i| equal(i, &args[0])))
+        }
+        "without" => {
+            check(1, usize::MAX)?;
+            let items = tolist(position, &sprig, &args[0])?;
+            let removed = &args[1..];
+            Value::Array(
+                items
+                    .into_iter()
+                    .filter(|i| !removed.iter().any(|r| equal(i, r)))
+                    .collect(),
+            )
+        }
+        "slice" => {
+            check(1, 3)?;
+            let items = tolist(position, &sprig, &args[0])?;
+            let start = args.get(1).map_or(0, toint).clamp(0, items.len() as i64) as usize;
+            let end = args
+                .get(2)
+                .map_or(items.len() as i64, toint)
+                .clamp(start as i64, items.len() as i64) as usize;
+            Value::Array(items[start..end].to_vec())
+        }
+        "sortAlpha" => {
+            check(1, 1)?;
+            let mut items: Vec<String> = tolist(position, &sprig, &args[0])?
+                .iter()
+                .map(strval)
+                .collect();
+            items.sort();
+            Value::Array(items.into_iter().map(Value::String).collect())
+        }
+        "until" => {
+            check(1, 1)?;
+            let count = toint(&args[0]).max(0);
+            limit_items(position, usize::try_from(count).unwrap_or(usize::MAX))?;
+            Value::Array((0..count).map(Value::from).collect())
+        }
+        "untilStep" => {
+            check(3, 3)?;
+            let (start, stop, step) = (toint(&args[0]), toint(&args[1]), toint(&args[2]));
+            let mut items = Vec::new();
+            let mut next = Some(start);
+            while let Some(i) = next.filter(|i| (step > 0 && *i < stop) || (step < 0 && *i > stop))
+            {
+                limit_items(position, items.len() + 1)?;
+                items.push(Value::from(i));
+                next = i.checked_add(step);
+            }
+            Value::Array(items)
+        }
+        "seq" => {
+            check(1, 3)?;
+            let bounds: Vec<i64> = args.iter().map(toint).collect();
+            let (start, step, end) = match bounds.as_slice() {
+                [end] => (1, 1, *end),
+                [start, end] => (*start, if start <= end { 1 } else { -1 }, *end),
+                [start, step, end] => (*start, *step, *end),
+                _ => unreachable!("arity is checked"),
+            };
+            let mut items = Vec::new();
+            let mut next = Some(start);
+            while let Some(i) = next.filter(|i| (step > 0 && *i <= end) || (step < 0 && *i >= end))
+            {
+                limit_items(position, items.len() + 1)?;
+                items.push(i.to_string());
+                next = i.checked_add(step);
+            }
+            Value::String(items.join(" "))
+        }
+
+        // Dicts.
+        "dict" => {
+            let mut map = Map::new();
+            for pair in args.chunks(2) {
+                let value = pair.get(1).cloned().unwrap_or_default();
+                map.insert(strval(&pair[0]), value);
+            }
+            Value::Object(map)
+        }
+        "get" => {
+            check(2, 2)?;
+            let map = todict(position, &sprig, &args[0])?;
+            map.get(&s(1))
+                .cloned()
+                .unwrap_or(Value::String(String::new()))
+        }
+        "set" => {
+            check(3, 3)?;
+            let mut map = todict(position, &sprig, &args[0])?;
+            map.insert(s(1), args[2].clone());
+            Value::Object(map)
+        }
+        "unset" => {
+            check(2, 2)?;
+            let mut map = todict(position, &sprig, &args[0])?;
+            map.remove(&s(1));
+            Value::Object(map)
+        }
+        "hasKey" => {
+            check(2, 2)?;
+            Value::Bool(todict(position, &sprig, &args[0])?.contains_key(&s(1)))
+        }
+        "keys" => {
+            let mut keys = Vec::new();
+            for arg in &args {
+                keys.extend(
+                    todict(position, &sprig, arg)?
+                        .keys()
+                        .cloned()
+                        .map(Value::String),
+                );
+            }
+            Value::Array(keys)
+        }
+        "values" => {
+            check(1, 1)?;
+            Value::Array(
+                todict(position, &sprig, &args[0])?
+                    .values()
+                    .cloned()
+                    .collect(),
+            )
+        }
+        "pick" | "omit" => {
+            check(1, usize::MAX)?;
+            let map = todict(position, &sprig, &args[0])?;
+            let names: Vec<String> = args[1..].iter().map(strval).collect();
+            let keep = name == "pick";
+            Value::Object(
+                map.into_iter()
+                    .filter(|(k, _)| names.contains(k) == keep)
+                    .collect(),
+            )
+        }
+        "merge" => {
+            check(1, usize::MAX)?;
+            let mut merged = todict(position, &sprig, &args[0])?;
+            for arg in &args[1..] {
+                for (key, value) in todict(position, &sprig, arg)? {
+                    merged.entry(key).or_insert(value);
+                }
+            }
+            Value::Object(merged)
+        }
+
+        // Regular expressions.
+        "regexMatch" => {
+            check(2, 2)?;
+            Value::Bool(regex(position, &s(0))?.is_match(&s(1)))
+        }
+        "regexFind" => {
+            check(2, 2)?;
+            let text = s(1);
+            let found = regex(position, &s(0))?
+                .find(&text)
+                .map(|m| m.as_str().to_string());
+            Value::String(found.unwrap_or_default())
+        }
+        "regexFindAll" => {
+            check(3, 3)?;
+            let text = s(1);
+            let n = toint(&args[2]);
+            let re = regex(position, &s(0))?;
+            let found = re.find_iter(&text).map(|m| Value::from(m.as_str()));
+            Value::Array(if n < 0 {
+                found.collect()
+            } else {
+                found.take(n as usize).collect()
+            })
+        }
+        "regexReplaceAll" | "regexReplaceAllLiteral" => {
+            check(3, 3)?;
+            let re = regex(position, &s(0))?;
+            let (text, replacement) = (s(1), s(2));
+            let replaced = if name == "regexReplaceAll" {
+                re.replace_all(&text, replacement.as_str())
+            } else {
+                re.replace_all(&text, regex::NoExpand(&replacement))
+            };
+            Value::String(replaced.into_owned())
+        }
+        "regexSplit" => {
+            check(3, 3)?;
+            let text = s(1);
+            let n = toint(&args[2]);
+            let re = regex(position, &s(0))?;
+            let parts: Vec<Value> = if n < 0 {
+                re.split(&text).map(Value::from).collect()
+            } else {
+                re.splitn(&text, n as usize).map(Value::from).collect()
+            };
+            Value::Array(parts)
+        }
+        _ => {
+            return Err(ExpressionError::UnknownFunction {
+                position,
+                name: sprig,
+            })
+        }
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::error::ExpressionError;
+    use crate::expr::{evaluate, Context};
+
+    fn eval(source: &str) -> Result<Value, ExpressionError> {
+        evaluate(source, &Context::new().variable("name", "Hello World"))
+    }
+
+    fn is_overflow(source: &str) -> bool {
+        matches!(
+            eval(source),
+            Err(ExpressionError::Evaluation { ref message, .. }) if message == "integer overflow"
+        )
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(eval("sprig.upper(name)"), Ok(json!("HELLO WORLD")));
+        assert_eq!(eval("sprig.trunc(5, name)"), Ok(json!("Hello")));
+        assert_eq!(eval("sprig.trunc(-5, name)"), Ok(json!("World")));
+        assert_eq!(eval("sprig.substr(6, -1, name)"), Ok(json!("World")));
+        assert_eq!(eval("sprig.kebabcase('fooBar')"), Ok(json!("foo-bar")));
+        assert_eq!(eval("sprig.quote('a', 1)"), Ok(json!("\"a\" \"1\"")));
+        assert_eq!(
+            eval("sprig.replace(' ', '-', name)"),
+            Ok(json!("Hello-World"))
+        );
+    }
+
+    #[test]
+    fn math() {
+        assert_eq!(eval("sprig.add(1, '2', 3)"), Ok(json!(6)));
+        assert_eq!(eval("sprig.mul(2, 3)"), Ok(json!(6)));
+        assert_eq!(eval("sprig.add1(41)"), Ok(json!(42)));
+        assert_eq!(eval("sprig.sub(1, 3)"), Ok(json!(-2)));
+        assert_eq!(eval("sprig.div(7, 2)"), Ok(json!(3)));
+        assert_eq!(eval("sprig.mod(7, 2)"), Ok(json!(1)));
+        assert_eq!(eval("sprig.max(1, 5, 3)"), Ok(json!(5)));
+        assert_eq!(eval("sprig.divf(1, 4)"), Ok(json!(0.25)));
+        assert!(matches!(
+            eval("sprig.div(1, 0)"),
+            Err(ExpressionError::Evaluation { .. })
+        ));
+    }
+
+    #[test]
+    fn integer_overflow_is_an_error() {
+        let min = "(-9223372036854775807 - 1)";
+        assert!(is_overflow(&format!("sprig.div({}, -1)", min)));
+        assert!(is_overflow(&format!("sprig.mod({}, -1)", min)));
+        assert!(is_overflow(&format!("sprig.sub({}, 1)", min)));
+        assert!(is_overflow("sprig.add1(9223372036854775807)"));
+        assert!(is_overflow("sprig.add(9223372036854775807, 1)"));
+        assert!(is_overflow("sprig.mul(9223372036854775807, 2)"));
+        assert_eq!(
+            eval("sprig.untilStep(9223372036854775806, 9223372036854775807, 2)"),
+            Ok(json!([9223372036854775806i64]))
+        );
+        assert_eq!(
+            eval("sprig.seq(9223372036854775806, 5, 9223372036854775807)"),
+            Ok(json!("9223372036854775806"))
+        );
+    }
+
+    #[test]
+    fn allocations_are_limited() {
+        let limited = |source: &str| {
+            assert!(
+                matches!(eval(source), Err(ExpressionError::Evaluation { ref message, .. }) if message.contains("exceeds the limit")),
+                "{}",
+                source
+            )
+        };
+        limited("sprig.repeat(1000000000000, 'ab')");
+        limited("sprig.indent(9223372036854775807, 'a')");
+        limited("sprig.nindent(600000, 'a\\nb')");
+        limited("sprig.until(9223372036854775807)");
+        limited("sprig.untilStep(0, 9223372036854775807, 1)");
+        limited("sprig.seq(9223372036854775807)");
+        assert_eq!(eval("sprig.repeat(3, 'ab')"), Ok(json!("ababab")));
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(
+            eval("sprig.sha256sum('abc')"),
+            Ok(json!(
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+            ))
+        );
+        assert_eq!(
+            eval("sprig.sha1sum('abc')"),
+            Ok(json!("a9993e364706816aba3e25717850c26c9cd0d89d"))
+        );
+        assert_eq!(
+            eval("sprig.adler32sum('Wikipedia')"),
+            Ok(json!("300286872"))
+        );
+    }
+
+    #[test]
+    fn lists_and_dicts() {
+        assert_eq!(eval("sprig.until(3)"), Ok(json!([0, 1, 2])));
+        assert_eq!(eval("sprig.untilStep(0, 10, 4)"), Ok(json!([0, 4, 8])));
+        assert_eq!(eval("sprig.seq(3)"), Ok(json!("1 2 3")));
+        assert_eq!(eval("sprig.seq(3, 1)"), Ok(json!("3 2 1")));
+        assert_eq!(eval("sprig.uniq(sprig.list(1, 2, 1))"), Ok(json!([1, 2])));
+        assert_eq!(
+            eval("sprig.get(sprig.dict('a', 1, 'b', 2), 'b')"),
+            Ok(json!(2))
+        );
+        assert_eq!(
+            eval("sprig.keys(sprig.dict('b', 1, 'a', 2))"),
+            Ok(json!(["a", "b"]))
+        );
+    }
+
+    #[test]
+    fn defaults_and_encoding() {
+        assert_eq!(eval("sprig.default('x', '')"), Ok(json!("x")));
+        assert_eq!(eval("sprig.default('x', 'y')"), Ok(json!("y")));
+        assert_eq!(eval("sprig.empty(sprig.list())"), Ok(json!(true)));
+        assert_eq!(
+            eval("sprig.b64dec(sprig.b64enc(name))"),
+            Ok(json!("Hello World"))
+        );
+        assert_eq!(
+            eval("sprig.regexFind('[0-9]+', 'abc123def')"),
+            Ok(json!("123"))
+        );
+    }
+}
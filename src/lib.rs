@@ -1,4 +1,9 @@
+#![allow(clippy::module_inception)]
+
+pub mod accounting;
 pub mod api;
 pub mod config;
 pub mod error;
+pub mod expr;
+pub mod policy;
 pub mod types;
@@ -0,0 +1,563 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use k8s_openapi::api::core::v1 as corev1;
+use serde::{Deserialize, Serialize};
+
+use crate::types::template::Template;
+use crate::types::workflow::{Workflow, WorkflowSpec};
+use crate::types::workflow_template::WorkflowTemplate;
+
+/// `Severity` of a policy violation. Severities are ordered, so that the
+/// violations at or above a given severity can be made to fail a build.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// `Rule` is a check performed by a `Policy`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    /// A container runs privileged.
+    Privileged,
+    /// The pods use the network namespace of the host.
+    HostNetwork,
+    /// A container sets no CPU or memory limit.
+    MissingLimits,
+    /// A container image is not pinned by digest (`image@sha256:...`).
+    ImageDigest,
+    /// The service account token is mounted in the pods.
+    AutomountServiceAccountToken,
+    /// A volume mounts a path of the host.
+    HostPath,
+    /// A container or pod runs as root.
+    RunAsRoot,
+}
+
+impl Rule {
+    /// All the rules, with their default severity.
+    const DEFAULTS: [(Rule, Severity); 7] = [
+        (Rule::Privileged, Severity::Error),
+        (Rule::HostNetwork, Severity::Error),
+        (Rule::MissingLimits, Severity::Error),
+        (Rule::ImageDigest, Severity::Error),
+        (Rule::AutomountServiceAccountToken, Severity::Warning),
+        (Rule::HostPath, Severity::Error),
+        (Rule::RunAsRoot, Severity::Warning),
+    ];
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rule::Privileged => "privileged",
+            Rule::HostNetwork => "host-network",
+            Rule::MissingLimits => "missing-limits",
+            Rule::ImageDigest => "image-digest",
+            Rule::AutomountServiceAccountToken => "automount-service-account-token",
+            Rule::HostPath => "host-path",
+            Rule::RunAsRoot => "run-as-root",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A `Violation` of a policy rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub rule: Rule,
+    pub severity: Severity,
+    /// JSON pointer to the offending field, relative to the checked spec.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] {}: {}",
+            self.severity, self.rule, self.pointer, self.message
+        )
+    }
+}
+
+/// A `Policy` checks workflow specs against a set of rules, each with a
+/// severity.
+///
+/// It can be built in code, or deserialized from a map of rule names to
+/// severities, e.g. `{"rules": {"privileged": "error", "run-as-root": "info"}}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    rules: BTreeMap<Rule, Severity>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy::new()
+    }
+}
+
+impl Policy {
+    /// Constructs a new `Policy` enabling every rule with its default
+    /// severity.
+    pub fn new() -> Self {
+        Policy {
+            rules: Rule::DEFAULTS.into_iter().collect(),
+        }
+    }
+
+    /// Constructs a new `Policy` with no rule enabled.
+    pub fn empty() -> Self {
+        Policy {
+            rules: BTreeMap::new(),
+        }
+    }
+
+    /// Enables `rule` with `severity`.
+    pub fn rule(mut self, rule: Rule, severity: Severity) -> Self {
+        self.rules.insert(rule, severity);
+        self
+    }
+
+    /// Disables `rule`.
+    pub fn disable(mut self, rule: Rule) -> Self {
+        self.rules.remove(&rule);
+        self
+    }
+
+    /// Checks `spec` and returns the violations found. Templates are
+    /// checked with their effective settings (see
+    /// `WorkflowSpec::effective_template()`), so settings inherited from the
+    /// workflow or from `template_defaults` are taken into account, along
+    /// with the templates inlined in their steps and tasks.
+    ///
+    /// The `pod_spec_patch` of the workflow and of the templates are checked
+    /// too, for the rules they can break: `privileged` and root containers,
+    /// images, host network and host path volumes. A patch which is not a
+    /// valid pod spec is skipped, as the controller fails the pods it
+    /// applies to.
+    pub fn check(&self, spec: &WorkflowSpec) -> Vec<Violation> {
+        let mut checker = Checker {
+            policy: self,
+            violations: Vec::new(),
+        };
+
+        if spec.host_network == Some(true) {
+            checker.report(
+                Rule::HostNetwork,
+                String::from("/hostNetwork"),
+                "pods use the host network",
+            );
+        }
+        checker.volumes("", spec.volumes.as_deref());
+        if let Some(patch) = spec.pod_spec_patch.as_deref() {
+            checker.pod_spec_patch("", patch);
+        }
+        for (i, template) in spec.templates.iter().flatten().enumerate() {
+            checker.templates(spec, &format!("/templates/{}", i), template);
+        }
+        checker.violations
+    }
+
+    /// Checks the spec of `workflow`. See `Policy::check()`.
+    pub fn check_workflow(&self, workflow: &Workflow) -> Vec<Violation> {
+        prefix("/spec", self.check(&workflow.spec))
+    }
+
+    /// Checks the spec of `template`. See `Policy::check()`.
+    pub fn check_workflow_template(&self, template: &WorkflowTemplate) -> Vec<Violation> {
+        prefix("/spec", self.check(&template.spec))
+    }
+}
+
+fn prefix(pointer: &str, mut violations: Vec<Violation>) -> Vec<Violation> {
+    for violation in violations.iter_mut() {
+        violation.pointer.insert_str(0, pointer);
+    }
+    violations
+}
+
+/// The fields of a container checked by the rules.
+struct ContainerFields<'a> {
+    image: Option<&'a str>,
+    resources: Option<&'a corev1::ResourceRequirements>,
+    security_context: Option<&'a corev1::SecurityContext>,
+}
+
+struct Checker<'a> {
+    policy: &'a Policy,
+    violations: Vec<Violation>,
+}
+
+impl Checker<'_> {
+    fn report(&mut self, rule: Rule, pointer: String, message: &str) {
+        if let Some(severity) = self.policy.rules.get(&rule) {
+            self.violations.push(Violation {
+                rule,
+                severity: *severity,
+                pointer,
+                message: String::from(message),
+            });
+        }
+    }
+
+    /// Checks `template` and the templates inlined in its steps and tasks.
+    fn templates(&mut self, spec: &WorkflowSpec, pointer: &str, template: &Template) {
+        let effective = spec.effective_template(template).template;
+        self.template(pointer, &effective);
+        if let Some(patch) = effective.pod_spec_patch.as_deref() {
+            self.pod_spec_patch(pointer, patch);
+        }
+
+        for (i, group) in template.steps.iter().flatten().enumerate() {
+            for (j, step) in group.iter().enumerate() {
+                if let Some(inline) = step.inline.as_deref() {
+                    let pointer = format!("{}/steps/{}/{}/inline", pointer, i, j);
+                    self.templates(spec, &pointer, inline);
+                }
+            }
+        }
+        let tasks = template.dag.iter().flat_map(|d| d.tasks.iter());
+        for (i, task) in tasks.enumerate() {
+            if let Some(inline) = task.inline.as_deref() {
+                let pointer = format!("{}/dag/tasks/{}/inline", pointer, i);
+                self.templates(spec, &pointer, inline);
+            }
+        }
+    }
+
+    fn template(&mut self, pointer: &str, template: &Template) {
+        let mut containers = Vec::new();
+        if let Some(c) = template.container.as_deref() {
+            containers.push((
+                format!("{}/container", pointer),
+                ContainerFields {
+                    image: c.image.as_deref(),
+                    resources: c.resources.as_ref(),
+                    security_context: c.security_context.as_ref(),
+                },
+            ));
+        }
+        if let Some(s) = template.script.as_deref() {
+            containers.push((
+                format!("{}/script", pointer),
+                ContainerFields {
+                    image: Some(s.image.as_str()),
+                    resources: s.resources.as_deref(),
+                    security_context: s.security_context.as_deref(),
+                },
+            ));
+        }
+        if let Some(set) = template.container_set.as_deref() {
+            for (j, c) in set.containers.iter().enumerate() {
+                containers.push((
+                    format!("{}/containerSet/containers/{}", pointer, j),
+                    ContainerFields {
+                        image: c.image.as_deref(),
+                        resources: c.resources.as_deref(),
+                        security_context: c.security_context.as_deref(),
+                    },
+                ));
+            }
+        }
+        for (field, list) in [
+            ("initContainers", &template.init_containers),
+            ("sidecars", &template.sidecars),
+        ] {
+            for (j, c) in list.iter().flatten().enumerate() {
+                containers.push((
+                    format!("{}/{}/{}", pointer, field, j),
+                    ContainerFields {
+                        image: c.image.as_deref(),
+                        resources: c.resources.as_deref(),
+                        security_context: c.security_context.as_deref(),
+                    },
+                ));
+            }
+        }
+
+        for (pointer, container) in &containers {
+            self.container(pointer, container);
+        }
+
+        self.pod_security_context(pointer, template.security_context.as_deref());
+        let runs_pod =
+            !containers.is_empty() || template.resource.is_some() || template.data.is_some();
+        if runs_pod && template.automount_service_account_token != Some(false) {
+            self.report(
+                Rule::AutomountServiceAccountToken,
+                format!("{}/automountServiceAccountToken", pointer),
+                "service account token is mounted",
+            );
+        }
+        self.volumes(pointer, template.volumes.as_deref());
+    }
+
+    /// Checks the pod spec patch `patch` of the workflow or template at
+    /// `pointer`. Patches are partial, so missing limits are not reported.
+    fn pod_spec_patch(&mut self, pointer: &str, patch: &str) {
+        let Ok(pod) = serde_yaml::from_str::<corev1::PodSpec>(patch) else {
+            return;
+        };
+        let pointer = format!("{}/podSpecPatch", pointer);
+        if pod.host_network == Some(true) {
+            self.report(
+                Rule::HostNetwork,
+                format!("{}/hostNetwork", pointer),
+                "pods use the host network",
+            );
+        }
+        for (field, list) in [
+            ("containers", Some(&pod.containers)),
+            ("initContainers", pod.init_containers.as_ref()),
+        ] {
+            for (i, c) in list.into_iter().flatten().enumerate() {
+                let pointer = format!("{}/{}/{}", pointer, field, i);
+                self.security_context(&pointer, c.security_context.as_ref());
+                self.image(&pointer, c.image.as_deref());
+            }
+        }
+        self.pod_security_context(&pointer, pod.security_context.as_ref());
+        self.volumes(&pointer, pod.volumes.as_deref());
+    }
+
+    fn pod_security_context(
+        &mut self,
+        pointer: &str,
+        context: Option<&corev1::PodSecurityContext>,
+    ) {
+        if let Some(context) = context {
+            if context.run_as_user == Some(0) || context.run_as_non_root == Some(false) {
+                self.report(
+                    Rule::RunAsRoot,
+                    format!("{}/securityContext", pointer),
+                    "pod runs as root",
+                );
+            }
+        }
+    }
+
+    fn container(&mut self, pointer: &str, container: &ContainerFields) {
+        self.security_context(pointer, container.security_context);
+
+        let limits = container.resources.and_then(|r| r.limits.as_ref());
+        let missing: Vec<&str> = ["cpu", "memory"]
+            .into_iter()
+            .filter(|r| !limits.is_some_and(|l| l.contains_key(*r)))
+            .collect();
+        if !missing.is_empty() {
+            let message = format!("no {} limit", missing.join(" or "));
+            self.report(
+                Rule::MissingLimits,
+                format!("{}/resources/limits", pointer),
+                &message,
+            );
+        }
+
+        self.image(pointer, container.image);
+    }
+
+    fn security_context(&mut self, pointer: &str, context: Option<&corev1::SecurityContext>) {
+        if let Some(context) = context {
+            if context.privileged == Some(true) {
+                self.report(
+                    Rule::Privileged,
+                    format!("{}/securityContext/privileged", pointer),
+                    "container runs privileged",
+                );
+            }
+            if context.run_as_user == Some(0) || context.run_as_non_root == Some(false) {
+                self.report(
+                    Rule::RunAsRoot,
+                    format!("{}/securityContext", pointer),
+                    "container runs as root",
+                );
+            }
+        }
+    }
+
+    fn image(&mut self, pointer: &str, image: Option<&str>) {
+        match image {
+            Some(image) if image.contains("@sha256:") => {}
+            Some(image) => {
+                let message = format!("image '{}' is not pinned by digest", image);
+                self.report(Rule::ImageDigest, format!("{}/image", pointer), &message);
+            }
+            None => {}
+        }
+    }
+
+    fn volumes(&mut self, pointer: &str, volumes: Option<&[corev1::Volume]>) {
+        for (i, volume) in volumes.into_iter().flatten().enumerate() {
+            if let Some(host_path) = &volume.host_path {
+                let message = format!(
+                    "volume '{}' mounts host path '{}'",
+                    volume.name, host_path.path
+                );
+                self.report(
+                    Rule::HostPath,
+                    format!("{}/volumes/{}/hostPath", pointer, i),
+                    &message,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn check(spec: serde_json::Value) -> Vec<(Rule, String)> {
+        let spec: WorkflowSpec = serde_json::from_value(spec).unwrap();
+        let policy = Policy::empty()
+            .rule(Rule::Privileged, Severity::Error)
+            .rule(Rule::HostNetwork, Severity::Error)
+            .rule(Rule::HostPath, Severity::Error)
+            .rule(Rule::RunAsRoot, Severity::Warning);
+        policy
+            .check(&spec)
+            .into_iter()
+            .map(|v| (v.rule, v.pointer))
+            .collect()
+    }
+
+    #[test]
+    fn templates() {
+        let violations = check(json!({
+            "hostNetwork": true,
+            "templates": [{
+                "name": "main",
+                "securityContext": {"runAsUser": 0},
+                "container": {"image": "alpine", "securityContext": {"privileged": true}},
+                "volumes": [{"name": "docker", "hostPath": {"path": "/var/run/docker.sock"}}]
+            }]
+        }));
+        assert_eq!(
+            violations,
+            [
+                (Rule::HostNetwork, String::from("/hostNetwork")),
+                (
+                    Rule::Privileged,
+                    String::from("/templates/0/container/securityContext/privileged")
+                ),
+                (
+                    Rule::RunAsRoot,
+                    String::from("/templates/0/securityContext")
+                ),
+                (
+                    Rule::HostPath,
+                    String::from("/templates/0/volumes/0/hostPath")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn inline_templates() {
+        let privileged = json!({"image": "alpine", "securityContext": {"privileged": true}});
+        let mut script = privileged.clone();
+        script["source"] = json!("id");
+        let violations = check(json!({
+            "templates": [
+                {"name": "steps", "steps": [[
+                    {"name": "ok", "template": "dag"},
+                    {"name": "inline", "inline": {"container": privileged}}
+                ]]},
+                {"name": "dag", "dag": {"tasks": [
+                    {"name": "inline", "inline": {"steps": [[
+                        {"name": "nested", "inline": {"script": script}}
+                    ]]}}
+                ]}}
+            ]
+        }));
+        assert_eq!(
+            violations,
+            [
+                (
+                    Rule::Privileged,
+                    String::from("/templates/0/steps/0/1/inline/container/securityContext/privileged")
+                ),
+                (
+                    Rule::Privileged,
+                    String::from(
+                        "/templates/1/dag/tasks/0/inline/steps/0/0/inline/script/securityContext/privileged"
+                    )
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pod_spec_patches() {
+        let violations = check(json!({
+            "podSpecPatch": "hostNetwork: true\n",
+            "templates": [{
+                "name": "main",
+                "container": {"image": "alpine"},
+                "podSpecPatch": r#"{"containers": [{"name": "main", "securityContext": {"privileged": true}}],
+                                    "volumes": [{"name": "root", "hostPath": {"path": "/"}}]}"#
+            }, {
+                "name": "invalid",
+                "container": {"image": "alpine"},
+                "podSpecPatch": "containers: 1"
+            }]
+        }));
+        assert_eq!(
+            violations,
+            [
+                (Rule::HostNetwork, String::from("/podSpecPatch/hostNetwork")),
+                (
+                    Rule::Privileged,
+                    String::from(
+                        "/templates/0/podSpecPatch/containers/0/securityContext/privileged"
+                    )
+                ),
+                (
+                    Rule::HostPath,
+                    String::from("/templates/0/podSpecPatch/volumes/0/hostPath")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn severities() {
+        let spec: WorkflowSpec = serde_json::from_value(json!({
+            "templates": [{"name": "main", "container": {"image": "alpine:3"}}]
+        }))
+        .unwrap();
+        let violations = Policy::new().check(&spec);
+        let rules: Vec<(Rule, Severity)> =
+            violations.iter().map(|v| (v.rule, v.severity)).collect();
+        assert_eq!(
+            rules,
+            [
+                (Rule::MissingLimits, Severity::Error),
+                (Rule::ImageDigest, Severity::Error),
+                (Rule::AutomountServiceAccountToken, Severity::Warning),
+            ]
+        );
+        assert_eq!(
+            Policy::new().disable(Rule::ImageDigest).check(&spec).len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+
+/// `Fields` projects the workflows returned by `get_workflow`,
+/// `list_workflows` and `watch_workflows` on some of their fields, as the
+/// `fields` query parameter. Paths are relative to a workflow, e.g.
+/// `status.phase`; they are prefixed as each API needs.
+///
+/// Projecting on a few fields makes listing large namespaces much cheaper,
+/// nodes making most of the size of workflows. The names and namespaces of
+/// listed and watched workflows are always kept. Empty `Fields` are not
+/// sent, returning whole workflows.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fields {
+    exclude: bool,
+    paths: Vec<String>,
+}
+
+impl Fields {
+    /// Constructs new `Fields` keeping only the fields added.
+    pub fn new() -> Self {
+        Fields::default()
+    }
+
+    /// Constructs new `Fields` keeping all the fields but the ones added,
+    /// e.g. `Fields::excluding().nodes()`.
+    pub fn excluding() -> Self {
+        Fields {
+            exclude: true,
+            paths: Vec::new(),
+        }
+    }
+
+    /// Adds the field at `path`, e.g. `spec.arguments`.
+    pub fn field(mut self, path: &str) -> Self {
+        self.paths.push(String::from(path));
+        self
+    }
+
+    /// Adds `metadata`.
+    pub fn metadata(self) -> Self {
+        self.field("metadata")
+    }
+
+    /// Adds `metadata.name`.
+    pub fn name(self) -> Self {
+        self.field("metadata.name")
+    }
+
+    /// Adds `metadata.namespace`.
+    pub fn namespace(self) -> Self {
+        self.field("metadata.namespace")
+    }
+
+    /// Adds `metadata.uid`.
+    pub fn uid(self) -> Self {
+        self.field("metadata.uid")
+    }
+
+    /// Adds `metadata.resourceVersion`.
+    pub fn resource_version(self) -> Self {
+        self.field("metadata.resourceVersion")
+    }
+
+    /// Adds `metadata.labels`.
+    pub fn labels(self) -> Self {
+        self.field("metadata.labels")
+    }
+
+    /// Adds `metadata.annotations`.
+    pub fn annotations(self) -> Self {
+        self.field("metadata.annotations")
+    }
+
+    /// Adds `metadata.creationTimestamp`.
+    pub fn creation_timestamp(self) -> Self {
+        self.field("metadata.creationTimestamp")
+    }
+
+    /// Adds `spec`.
+    pub fn spec(self) -> Self {
+        self.field("spec")
+    }
+
+    /// Adds `status`.
+    pub fn status(self) -> Self {
+        self.field("status")
+    }
+
+    /// Adds `status.phase`.
+    pub fn phase(self) -> Self {
+        self.field("status.phase")
+    }
+
+    /// Adds `status.message`.
+    pub fn message(self) -> Self {
+        self.field("status.message")
+    }
+
+    /// Adds `status.progress`.
+    pub fn progress(self) -> Self {
+        self.field("status.progress")
+    }
+
+    /// Adds `status.startedAt`.
+    pub fn started_at(self) -> Self {
+        self.field("status.startedAt")
+    }
+
+    /// Adds `status.finishedAt`.
+    pub fn finished_at(self) -> Self {
+        self.field("status.finishedAt")
+    }
+
+    /// Adds `status.estimatedDuration`.
+    pub fn estimated_duration(self) -> Self {
+        self.field("status.estimatedDuration")
+    }
+
+    /// Adds `status.resourcesDuration`.
+    pub fn resources_duration(self) -> Self {
+        self.field("status.resourcesDuration")
+    }
+
+    /// Adds `status.outputs`.
+    pub fn outputs(self) -> Self {
+        self.field("status.outputs")
+    }
+
+    /// Adds `status.nodes`.
+    pub fn nodes(self) -> Self {
+        self.field("status.nodes")
+    }
+
+    /// Returns whether no field was added.
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Renders the fields with each path under `prefix`. Unless fields are
+    /// excluded, the paths of `required` are added as is.
+    pub(crate) fn render(&self, prefix: &str, required: &[&str]) -> String {
+        let mut paths: Vec<String> = self
+            .paths
+            .iter()
+            .map(|path| format!("{}{}", prefix, path))
+            .collect();
+        if self.exclude {
+            return format!("-{}", paths.join(","));
+        }
+        for path in required {
+            if !paths.iter().any(|p| p == path) {
+                paths.push(String::from(*path));
+            }
+        }
+        paths.join(",")
+    }
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render("", &[]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_adds_required_paths() {
+        let fields = Fields::new().phase().name();
+        assert_eq!(
+            fields.render("items.", &["metadata.continue", "items.metadata.name"]),
+            "items.status.phase,items.metadata.name,metadata.continue"
+        );
+        assert_eq!(fields.to_string(), "status.phase,metadata.name");
+    }
+
+    #[test]
+    fn render_excluded_paths() {
+        let fields = Fields::excluding().nodes().outputs();
+        assert_eq!(
+            fields.render("items.", &["items.metadata.name"]),
+            "-items.status.nodes,items.status.outputs"
+        );
+    }
+
+    #[test]
+    fn empty() {
+        assert!(Fields::new().is_empty());
+        assert!(Fields::excluding().is_empty());
+        assert!(!Fields::new().name().is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use serde_json::Value;
+
+/// Merges `overlay` into `base`: objects are merged recursively, lists for
+/// which `merge_key` returns a key are merged by the value of that key in
+/// their items, and any other value of `overlay` replaces the one of `base`.
+///
+/// The directives of strategic merge patches are supported: a `null` value
+/// deletes a field, `"$patch": "replace"` replaces an object or a list
+/// instead of merging it, and `"$patch": "delete"` deletes an object or a
+/// list item.
+pub(crate) fn merge<F>(base: &mut Value, overlay: Value, merge_key: &F)
+where
+    F: Fn(&str) -> Option<&'static str>,
+{
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (field, value) in overlay {
+                if field.starts_with('$') {
+                    continue;
+                }
+                match (base.get_mut(&field), merge_key(&field), value) {
+                    (_, _, Value::Null) => {
+                        base.remove(&field);
+                    }
+                    (_, _, value) if directive(&value) == Some("delete") => {
+                        base.remove(&field);
+                    }
+                    (_, _, value) if directive(&value) == Some("replace") => {
+                        base.insert(field, without_directive(value));
+                    }
+                    (Some(Value::Array(items)), Some(key), Value::Array(overlay)) => {
+                        merge_by_key(items, overlay, key, merge_key)
+                    }
+                    (Some(existing), _, value) => merge(existing, value, merge_key),
+                    (None, _, value) => {
+                        base.insert(field, without_directive(value));
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn merge_by_key<F>(items: &mut Vec<Value>, overlay: Vec<Value>, key: &str, merge_key: &F)
+where
+    F: Fn(&str) -> Option<&'static str>,
+{
+    if overlay.iter().any(|v| directive(v) == Some("replace")) {
+        *items = overlay
+            .into_iter()
+            .filter(|v| directive(v).is_none())
+            .collect();
+        return;
+    }
+
+    for value in overlay {
+        let position = items
+            .iter()
+            .position(|item| value.get(key).is_some() && item.get(key) == value.get(key));
+        match (position, directive(&value)) {
+            (Some(i), Some("delete")) => {
+                items.remove(i);
+            }
+            (None, Some("delete")) => {}
+            (Some(i), _) => merge(&mut items[i], value, merge_key),
+            (None, _) => items.push(without_directive(value)),
+        }
+    }
+}
+
+fn directive(value: &Value) -> Option<&str> {
+    value.get("$patch").and_then(Value::as_str)
+}
+
+fn without_directive(mut value: Value) -> Value {
+    if let Value::Object(fields) = &mut value {
+        fields.retain(|field, _| !field.starts_with('$'));
+    }
+    value
+}
+
+/// Returns the merge key of the lists of a pod spec, as declared by the
+/// Kubernetes API for strategic merge patches.
+pub(crate) fn pod_merge_key(field: &str) -> Option<&'static str> {
+    match field {
+        "containers"
+        | "initContainers"
+        | "ephemeralContainers"
+        | "env"
+        | "volumes"
+        | "imagePullSecrets"
+        | "resourceClaims"
+        | "schedulingGates" => Some("name"),
+        "volumeMounts" => Some("mountPath"),
+        "volumeDevices" => Some("devicePath"),
+        "ports" => Some("containerPort"),
+        "hostAliases" => Some("ip"),
+        _ => None,
+    }
+}
@@ -1,12 +1,22 @@
 mod backoff;
 pub use self::backoff::Backoff;
 
+mod fields;
+pub use self::fields::Fields;
+
+mod merge;
+
 mod metadata;
 pub use self::metadata::Metadata;
 
 mod retry;
 pub use self::retry::*;
 
+mod selector;
+pub use self::selector::{FieldSelector, LabelSelector, LabelSelectorBuilder, SelectableField};
+
+mod time;
+
 mod types;
 pub use self::types::*;
 
@@ -0,0 +1,407 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::SelectorError;
+
+/// Maximum length of the name part of a label key, and of a label value.
+const MAX_NAME_LENGTH: usize = 63;
+
+/// Maximum length of the prefix of a label key.
+const MAX_PREFIX_LENGTH: usize = 253;
+
+/// `LabelSelector` selects resources by their labels, as the
+/// `label_selector` of `ListOptions`. It is built with a
+/// `LabelSelectorBuilder`, or parsed from a string, which both check keys and
+/// values, and rendered as the requirements it holds, e.g.
+/// `app=web,tier in (api,db),!canary`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LabelSelector {
+    requirements: Vec<String>,
+}
+
+impl LabelSelector {
+    /// Creates a `LabelSelectorBuilder` to build a `LabelSelector`.
+    pub fn builder() -> LabelSelectorBuilder {
+        LabelSelectorBuilder::new()
+    }
+
+    /// Returns whether the selector has no requirement, selecting every
+    /// resource.
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+}
+
+impl fmt::Display for LabelSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.requirements.join(","))
+    }
+}
+
+impl FromStr for LabelSelector {
+    type Err = SelectorError;
+
+    /// Parses a selector written as `kubectl` takes it, e.g.
+    /// `app=web,tier in (api,db),!canary`. An empty string selects every
+    /// resource.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut builder = LabelSelectorBuilder::new();
+        if s.trim().is_empty() {
+            return builder.build();
+        }
+        for requirement in split_requirements(s) {
+            let requirement = requirement.trim();
+            let syntax = || SelectorError::Syntax(String::from(requirement));
+            builder = if requirement.is_empty() {
+                return Err(syntax());
+            } else if let Some(key) = requirement.strip_prefix('!') {
+                builder.does_not_exist(key.trim())
+            } else if let Some((head, set)) = requirement.split_once('(') {
+                let set = set.strip_suffix(')').ok_or_else(syntax)?;
+                let values: Vec<&str> = match set.trim() {
+                    "" => Vec::new(),
+                    set => set.split(',').map(str::trim).collect(),
+                };
+                let (key, operator) = head
+                    .trim_end()
+                    .rsplit_once(char::is_whitespace)
+                    .ok_or_else(syntax)?;
+                match operator {
+                    "in" => builder.is_in(key.trim(), &values),
+                    "notin" => builder.not_in(key.trim(), &values),
+                    _ => return Err(syntax()),
+                }
+            } else if let Some((key, value)) = requirement.split_once("!=") {
+                builder.not_equals(key.trim(), value.trim())
+            } else if let Some((key, value)) = requirement.split_once('=') {
+                let value = value.strip_prefix('=').unwrap_or(value);
+                builder.equals(key.trim(), value.trim())
+            } else {
+                builder.exists(requirement)
+            };
+        }
+        builder.build()
+    }
+}
+
+/// Splits a selector at the commas which are not within a set of values.
+fn split_requirements(s: &str) -> Vec<&str> {
+    let mut requirements = Vec::new();
+    let (mut start, mut depth) = (0, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                requirements.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    requirements.push(&s[start..]);
+    requirements
+}
+
+/// An operator of a label requirement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Operator {
+    Equals,
+    NotEquals,
+    In,
+    NotIn,
+    Exists,
+    DoesNotExist,
+}
+
+/// A `LabelSelectorBuilder` can be used to create a `LabelSelector`. All its
+/// requirements must be met for a resource to be selected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LabelSelectorBuilder {
+    requirements: Vec<(String, Operator, Vec<String>)>,
+}
+
+impl LabelSelectorBuilder {
+    /// Constructs a new `LabelSelectorBuilder`, with no requirement.
+    pub fn new() -> Self {
+        LabelSelectorBuilder::default()
+    }
+
+    /// Requires the label `key` to be set to `value`.
+    pub fn equals(self, key: &str, value: &str) -> Self {
+        self.requirement(key, Operator::Equals, &[value])
+    }
+
+    /// Requires the label `key` not to be set to `value`. Resources without
+    /// the label are selected.
+    pub fn not_equals(self, key: &str, value: &str) -> Self {
+        self.requirement(key, Operator::NotEquals, &[value])
+    }
+
+    /// Requires the label `key` to be set to one of `values`.
+    pub fn is_in(self, key: &str, values: &[&str]) -> Self {
+        self.requirement(key, Operator::In, values)
+    }
+
+    /// Requires the label `key` not to be set to any of `values`. Resources
+    /// without the label are selected.
+    pub fn not_in(self, key: &str, values: &[&str]) -> Self {
+        self.requirement(key, Operator::NotIn, values)
+    }
+
+    /// Requires the label `key` to be set.
+    pub fn exists(self, key: &str) -> Self {
+        self.requirement(key, Operator::Exists, &[])
+    }
+
+    /// Requires the label `key` not to be set.
+    pub fn does_not_exist(self, key: &str) -> Self {
+        self.requirement(key, Operator::DoesNotExist, &[])
+    }
+
+    fn requirement(mut self, key: &str, operator: Operator, values: &[&str]) -> Self {
+        let values = values.iter().map(|v| String::from(*v)).collect();
+        self.requirements
+            .push((String::from(key), operator, values));
+        self
+    }
+
+    /// Returns the `LabelSelector`, or the first invalid key or value.
+    pub fn build(self) -> Result<LabelSelector, SelectorError> {
+        let mut requirements = Vec::new();
+        for (key, operator, values) in self.requirements {
+            if !is_label_key(&key) {
+                return Err(SelectorError::InvalidKey(key));
+            }
+            if let Some(value) = values.iter().find(|v| !is_label_value(v)) {
+                return Err(SelectorError::InvalidValue {
+                    value: value.clone(),
+                    key,
+                });
+            }
+            if matches!(operator, Operator::In | Operator::NotIn) && values.is_empty() {
+                return Err(SelectorError::NoValues(key));
+            }
+            requirements.push(match operator {
+                Operator::Equals => format!("{}={}", key, values[0]),
+                Operator::NotEquals => format!("{}!={}", key, values[0]),
+                Operator::In => format!("{} in ({})", key, values.join(",")),
+                Operator::NotIn => format!("{} notin ({})", key, values.join(",")),
+                Operator::Exists => key,
+                Operator::DoesNotExist => format!("!{}", key),
+            });
+        }
+        Ok(LabelSelector { requirements })
+    }
+}
+
+/// Returns whether `key` is a valid label key: a name, optionally prefixed
+/// by a DNS subdomain and `/`.
+fn is_label_key(key: &str) -> bool {
+    let (prefix, name) = match key.split_once('/') {
+        Some((prefix, name)) => (Some(prefix), name),
+        None => (None, key),
+    };
+    let valid_prefix = prefix.is_none_or(|prefix| {
+        prefix.len() <= MAX_PREFIX_LENGTH
+            && prefix.split('.').all(|part| {
+                !part.is_empty()
+                    && part.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                    && part.ends_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            })
+    });
+    valid_prefix && !name.is_empty() && is_label_value(name)
+}
+
+/// Returns whether `value` is a valid label value: empty, or at most 63
+/// alphanumeric characters, `-`, `_` or `.`, starting and ending with an
+/// alphanumeric character.
+fn is_label_value(value: &str) -> bool {
+    value.is_empty()
+        || (value.len() <= MAX_NAME_LENGTH
+            && value.starts_with(|c: char| c.is_ascii_alphanumeric())
+            && value.ends_with(|c: char| c.is_ascii_alphanumeric())
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+}
+
+/// `SelectableField` is a field workflows can be selected by in a
+/// `FieldSelector`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SelectableField {
+    /// `metadata.name`.
+    Name,
+    /// `metadata.namespace`.
+    Namespace,
+    /// `status.phase`, e.g. `Running`.
+    Phase,
+}
+
+impl fmt::Display for SelectableField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = match self {
+            SelectableField::Name => "metadata.name",
+            SelectableField::Namespace => "metadata.namespace",
+            SelectableField::Phase => "status.phase",
+        };
+        f.write_str(path)
+    }
+}
+
+/// `FieldSelector` selects resources by the value of their fields, as the
+/// `field_selector` of `ListOptions`, e.g. `metadata.name=my-workflow`.
+/// All its requirements must be met for a resource to be selected.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FieldSelector {
+    requirements: Vec<String>,
+}
+
+impl FieldSelector {
+    /// Constructs a new `FieldSelector`, with no requirement.
+    pub fn new() -> Self {
+        FieldSelector::default()
+    }
+
+    /// Requires `field` to be equal to `value`.
+    pub fn equals(mut self, field: SelectableField, value: &str) -> Self {
+        let requirement = format!("{}={}", field, escape(value));
+        self.requirements.push(requirement);
+        self
+    }
+
+    /// Requires `field` not to be equal to `value`.
+    pub fn not_equals(mut self, field: SelectableField, value: &str) -> Self {
+        let requirement = format!("{}!={}", field, escape(value));
+        self.requirements.push(requirement);
+        self
+    }
+
+    /// Returns whether the selector has no requirement, selecting every
+    /// resource.
+    pub fn is_empty(&self) -> bool {
+        self.requirements.is_empty()
+    }
+}
+
+impl fmt::Display for FieldSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.requirements.join(","))
+    }
+}
+
+/// Escapes the characters of a field selector value with a meaning in the
+/// selector syntax.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | ',' | '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_keys() {
+        let name = "a".repeat(MAX_NAME_LENGTH);
+        assert!(is_label_key(&name));
+        assert!(!is_label_key(&format!("{}a", name)));
+        assert!(is_label_key("App_Name.v1"));
+        assert!(!is_label_key(""));
+        assert!(!is_label_key("-app"));
+
+        let prefix = [
+            "a".repeat(63),
+            "b".repeat(63),
+            "c".repeat(63),
+            "d".repeat(61),
+        ]
+        .join(".");
+        assert_eq!(prefix.len(), MAX_PREFIX_LENGTH);
+        assert!(is_label_key(&format!("{}/app", prefix)));
+        assert!(!is_label_key(&format!("{}d/app", prefix)));
+        assert!(is_label_key("workflows.argoproj.io/phase"));
+        assert!(!is_label_key("Workflows.argoproj.io/phase"));
+        assert!(!is_label_key("workflows..io/phase"));
+        assert!(!is_label_key("workflows.argoproj.io/"));
+        assert!(!is_label_key("/phase"));
+        assert!(!is_label_key("a/b/c"));
+    }
+
+    #[test]
+    fn label_values() {
+        assert!(is_label_value(""));
+        assert!(is_label_value("Running"));
+        assert!(is_label_value("v1.2_3-rc"));
+        assert!(is_label_value(&"a".repeat(MAX_NAME_LENGTH)));
+        assert!(!is_label_value(&"a".repeat(MAX_NAME_LENGTH + 1)));
+        assert!(!is_label_value("-a"));
+        assert!(!is_label_value("a."));
+        assert!(!is_label_value("a b"));
+    }
+
+    #[test]
+    fn empty_sets() {
+        assert_eq!(
+            LabelSelector::builder().is_in("tier", &[]).build(),
+            Err(SelectorError::NoValues(String::from("tier")))
+        );
+        assert_eq!(
+            LabelSelector::builder().not_in("tier", &[]).build(),
+            Err(SelectorError::NoValues(String::from("tier")))
+        );
+        assert_eq!(
+            "tier in ()".parse::<LabelSelector>(),
+            Err(SelectorError::NoValues(String::from("tier")))
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let selector: LabelSelector =
+            " app = web,tier in (api, db),env notin (dev),!canary,team,x==y,a!=b"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            selector.to_string(),
+            "app=web,tier in (api,db),env notin (dev),!canary,team,x=y,a!=b"
+        );
+        let built = LabelSelector::builder()
+            .equals("app", "web")
+            .is_in("tier", &["api", "db"])
+            .build()
+            .unwrap();
+        assert_eq!(built.to_string().parse(), Ok(built));
+        assert!("".parse::<LabelSelector>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parse = |s: &str| s.parse::<LabelSelector>().unwrap_err();
+        let syntax = |s: &str| SelectorError::Syntax(String::from(s));
+        assert_eq!(parse("app=web,,tier"), syntax(""));
+        assert_eq!(parse("tier in (api"), syntax("tier in (api"));
+        assert_eq!(parse("tier within (api)"), syntax("tier within (api)"));
+        assert_eq!(parse("(api)"), syntax("(api)"));
+        assert_eq!(
+            parse("App/x=y"),
+            SelectorError::InvalidKey(String::from("App/x"))
+        );
+        assert_eq!(
+            parse("app=-web"),
+            SelectorError::InvalidValue {
+                key: String::from("app"),
+                value: String::from("-web"),
+            }
+        );
+    }
+}
@@ -5,11 +5,12 @@ use serde::{Deserialize, Serialize};
 pub struct MutexHolding {
     /// Holder is a reference to the object which holds the Mutex.
     /// Holding Scenario:
-    ///    1. Current workflow's NodeID which is holding the lock.
-    ///       e.g: ${NodeID}
+    ///   1. Current workflow's NodeID which is holding the lock.
+    ///      e.g: ${NodeID}
+    ///
     /// Waiting Scenario:
-    ///     1. Current workflow or other workflow NodeID which is holding the lock.
-    ///       e.g: ${WorkflowName}/${NodeID}
+    ///   1. Current workflow or other workflow NodeID which is holding the lock.
+    ///      e.g: ${WorkflowName}/${NodeID}
     #[serde(rename = "holder", skip_serializing_if = "Option::is_none")]
     pub holder: Option<String>,
 
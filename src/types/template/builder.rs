@@ -0,0 +1,524 @@
+use std::collections::{HashMap, HashSet};
+
+use k8s_openapi::api::core::v1 as corev1;
+
+use super::{
+    DagBuilder, Inputs, Outputs, ResourceTemplate, ScriptTemplate, StepsBuilder, SuspendTemplate,
+    Template,
+};
+use crate::error::BuildError;
+use crate::types::{
+    artifact::Artifact,
+    workflow::{Parameter, ValueFrom},
+    RetryStrategy,
+};
+
+/// The body of the template being built, which decides the kind of the template.
+#[derive(Debug, Clone)]
+enum Body {
+    Container(Box<corev1::Container>),
+    Script(Box<ScriptTemplate>),
+    Resource(Box<ResourceTemplate>),
+    Suspend(SuspendTemplate),
+    Dag(DagBuilder),
+    Steps(StepsBuilder),
+}
+
+impl Body {
+    fn kind(&self) -> &'static str {
+        match self {
+            Body::Container(_) => "container",
+            Body::Script(_) => "script",
+            Body::Resource(_) => "resource",
+            Body::Suspend(_) => "suspend",
+            Body::Dag(_) => "dag",
+            Body::Steps(_) => "steps",
+        }
+    }
+}
+
+/// A `TemplateBuilder` can be used to create a `Template` with custom options.
+///
+/// Use one of `Template::container()`, `Template::script()`,
+/// `Template::resource()` or `Template::suspend()` to create a builder.
+/// An existing `Template` can be converted into a builder with `From`.
+#[derive(Debug, Clone)]
+pub struct TemplateBuilder {
+    template: Template,
+    body: Option<Body>,
+    error: Option<BuildError>,
+}
+
+impl From<Template> for TemplateBuilder {
+    fn from(template: Template) -> Self {
+        TemplateBuilder {
+            template,
+            body: None,
+            error: None,
+        }
+    }
+}
+
+impl Template {
+    /// Creates a `TemplateBuilder` for a template running `image` as its
+    /// main container.
+    pub fn container(name: &str, image: &str) -> TemplateBuilder {
+        let container = corev1::Container {
+            name: String::from("main"),
+            image: Some(String::from(image)),
+            ..Default::default()
+        };
+        TemplateBuilder::new(name, Body::Container(Box::new(container)))
+    }
+
+    /// Creates a `TemplateBuilder` for a template running the `source`
+    /// script in `image`.
+    pub fn script(name: &str, image: &str, source: &str) -> TemplateBuilder {
+        TemplateBuilder::new(
+            name,
+            Body::Script(Box::new(ScriptTemplate::new(image, source))),
+        )
+    }
+
+    /// Creates a `TemplateBuilder` for a template performing `action`
+    /// (e.g. `create`, `apply`) on the Kubernetes `manifest`.
+    pub fn resource(name: &str, action: &str, manifest: &str) -> TemplateBuilder {
+        let mut resource = ResourceTemplate::new(action);
+        resource.manifest = Some(String::from(manifest));
+        TemplateBuilder::new(name, Body::Resource(Box::new(resource)))
+    }
+
+    /// Creates a `TemplateBuilder` for a template suspending the workflow.
+    pub fn suspend(name: &str) -> TemplateBuilder {
+        TemplateBuilder::new(name, Body::Suspend(SuspendTemplate::new()))
+    }
+
+    /// Creates a `TemplateBuilder` for a template running the tasks of `dag`.
+    pub fn dag(name: &str, dag: DagBuilder) -> TemplateBuilder {
+        TemplateBuilder::new(name, Body::Dag(dag))
+    }
+
+    /// Creates a `TemplateBuilder` for a template running the step groups
+    /// of `steps`.
+    pub fn steps(name: &str, steps: StepsBuilder) -> TemplateBuilder {
+        TemplateBuilder::new(name, Body::Steps(steps))
+    }
+}
+
+impl TemplateBuilder {
+    fn new(name: &str, body: Body) -> Self {
+        TemplateBuilder {
+            template: Template {
+                name: Some(String::from(name)),
+                ..Default::default()
+            },
+            body: Some(body),
+            error: None,
+        }
+    }
+
+    /// Returns the name of the template being built.
+    pub fn name(&self) -> Option<&str> {
+        self.template.name.as_deref()
+    }
+
+    pub(crate) fn template(&self) -> &Template {
+        &self.template
+    }
+
+    /// Returns whether the template runs a container, and so has an
+    /// `outputs.result`.
+    pub(crate) fn has_result(&self) -> bool {
+        match &self.body {
+            Some(Body::Container(_)) | Some(Body::Script(_)) => true,
+            Some(_) => false,
+            None => self.template.container.is_some() || self.template.script.is_some(),
+        }
+    }
+
+    /// Records that `option` can not be applied to the current template body.
+    /// Only the first such error is kept and reported by `build()`.
+    fn unsupported(&mut self, option: &'static str) {
+        let kind = self.body.as_ref().map(Body::kind).unwrap_or("prebuilt");
+        self.error
+            .get_or_insert(BuildError::Unsupported { kind, option });
+    }
+
+    /// Sets the entrypoint of the main container.
+    pub fn command(mut self, command: &[&str]) -> Self {
+        let command = Some(command.iter().map(|c| c.to_string()).collect());
+        match &mut self.body {
+            Some(Body::Container(c)) => c.command = command,
+            Some(Body::Script(s)) => s.command = command,
+            _ => self.unsupported("command"),
+        }
+        self
+    }
+
+    /// Sets the arguments to the entrypoint of the main container.
+    pub fn args(mut self, args: &[&str]) -> Self {
+        let args = Some(args.iter().map(|a| a.to_string()).collect());
+        match &mut self.body {
+            Some(Body::Container(c)) => c.args = args,
+            Some(Body::Script(s)) => s.args = args,
+            _ => self.unsupported("args"),
+        }
+        self
+    }
+
+    /// Adds an environment variable to the main container.
+    pub fn env(mut self, name: &str, value: &str) -> Self {
+        let var = corev1::EnvVar {
+            name: String::from(name),
+            value: Some(String::from(value)),
+            ..Default::default()
+        };
+        match &mut self.body {
+            Some(Body::Container(c)) => c.env.get_or_insert_with(Vec::new).push(var),
+            Some(Body::Script(s)) => s.env.get_or_insert_with(Vec::new).push(var),
+            _ => self.unsupported("env"),
+        }
+        self
+    }
+
+    /// Sets the working directory of the main container.
+    pub fn working_dir(mut self, dir: &str) -> Self {
+        let dir = Some(String::from(dir));
+        match &mut self.body {
+            Some(Body::Container(c)) => c.working_dir = dir,
+            Some(Body::Script(s)) => s.working_dir = dir,
+            _ => self.unsupported("working_dir"),
+        }
+        self
+    }
+
+    /// Sets the image pull policy of the main container.
+    /// One of `Always`, `Never` or `IfNotPresent`.
+    pub fn image_pull_policy(mut self, policy: &str) -> Self {
+        let policy = Some(String::from(policy));
+        match &mut self.body {
+            Some(Body::Container(c)) => c.image_pull_policy = policy,
+            Some(Body::Script(s)) => s.image_pull_policy = policy,
+            _ => self.unsupported("image_pull_policy"),
+        }
+        self
+    }
+
+    /// Sets the compute resources required by the main container.
+    pub fn resources(mut self, resources: corev1::ResourceRequirements) -> Self {
+        match &mut self.body {
+            Some(Body::Container(c)) => c.resources = Some(resources),
+            Some(Body::Script(s)) => s.resources = Some(Box::new(resources)),
+            _ => self.unsupported("resources"),
+        }
+        self
+    }
+
+    /// Mounts the volume `name` at `mount_path` in the main container.
+    pub fn volume_mount(mut self, name: &str, mount_path: &str) -> Self {
+        let mount = corev1::VolumeMount {
+            name: String::from(name),
+            mount_path: String::from(mount_path),
+            ..Default::default()
+        };
+        match &mut self.body {
+            Some(Body::Container(c)) => c.volume_mounts.get_or_insert_with(Vec::new).push(mount),
+            Some(Body::Script(s)) => s.volume_mounts.get_or_insert_with(Vec::new).push(mount),
+            _ => self.unsupported("volume_mount"),
+        }
+        self
+    }
+
+    /// Sets the duration of a suspend template (e.g. `30s`, `1h`).
+    pub fn duration(mut self, duration: &str) -> Self {
+        match &mut self.body {
+            Some(Body::Suspend(s)) => s.duration = Some(String::from(duration)),
+            _ => self.unsupported("duration"),
+        }
+        self
+    }
+
+    /// Sets the label selector expression of a resource template, which
+    /// marks the step as succeeded when it matches.
+    pub fn success_condition(mut self, condition: &str) -> Self {
+        match &mut self.body {
+            Some(Body::Resource(r)) => r.success_condition = Some(String::from(condition)),
+            _ => self.unsupported("success_condition"),
+        }
+        self
+    }
+
+    /// Sets the label selector expression of a resource template, which
+    /// marks the step as failed when it matches.
+    pub fn failure_condition(mut self, condition: &str) -> Self {
+        match &mut self.body {
+            Some(Body::Resource(r)) => r.failure_condition = Some(String::from(condition)),
+            _ => self.unsupported("failure_condition"),
+        }
+        self
+    }
+
+    /// Declares an input parameter named `name`.
+    pub fn input_parameter(self, name: &str) -> Self {
+        self.input(Parameter::new(name))
+    }
+
+    /// Declares an input parameter named `name` with a `default` value.
+    pub fn input_parameter_with_default(self, name: &str, default: &str) -> Self {
+        let mut param = Parameter::new(name);
+        param.default = Some(String::from(default));
+        self.input(param)
+    }
+
+    /// Declares an input parameter.
+    pub fn input(mut self, param: Parameter) -> Self {
+        let inputs = self.template.inputs.get_or_insert_with(Box::default);
+        inputs.parameters.get_or_insert_with(Vec::new).push(param);
+        self
+    }
+
+    /// Declares an input artifact named `name`, placed at `path`.
+    pub fn input_artifact(mut self, name: &str, path: &str) -> Self {
+        let mut artifact = Artifact::new(name);
+        artifact.path = Some(String::from(path));
+        let inputs = self.template.inputs.get_or_insert_with(Box::default);
+        inputs.artifacts.get_or_insert_with(Vec::new).push(artifact);
+        self
+    }
+
+    /// Declares an output parameter named `name`, read from the file at `path`.
+    pub fn output_parameter(self, name: &str, path: &str) -> Self {
+        let mut param = Parameter::new(name);
+        param.value_from = Some(Box::new(ValueFrom {
+            path: Some(String::from(path)),
+            ..Default::default()
+        }));
+        self.output(param)
+    }
+
+    /// Declares an output parameter.
+    pub fn output(mut self, param: Parameter) -> Self {
+        let outputs = self.template.outputs.get_or_insert_with(Box::default);
+        outputs.parameters.get_or_insert_with(Vec::new).push(param);
+        self
+    }
+
+    /// Declares an output artifact named `name`, collected from `path`.
+    pub fn output_artifact(mut self, name: &str, path: &str) -> Self {
+        let mut artifact = Artifact::new(name);
+        artifact.path = Some(String::from(path));
+        let outputs = self.template.outputs.get_or_insert_with(Box::default);
+        outputs
+            .artifacts
+            .get_or_insert_with(Vec::new)
+            .push(artifact);
+        self
+    }
+
+    /// Adds a label to the pod metadata of the template.
+    pub fn label(mut self, key: &str, value: &str) -> Self {
+        let metadata = self.template.metadata.get_or_insert_with(Box::default);
+        metadata
+            .labels
+            .get_or_insert_with(HashMap::new)
+            .insert(String::from(key), String::from(value));
+        self
+    }
+
+    /// Adds an annotation to the pod metadata of the template.
+    pub fn annotation(mut self, key: &str, value: &str) -> Self {
+        let metadata = self.template.metadata.get_or_insert_with(Box::default);
+        metadata
+            .annotations
+            .get_or_insert_with(HashMap::new)
+            .insert(String::from(key), String::from(value));
+        self
+    }
+
+    /// Adds a node selector entry, overriding the one set at the workflow level.
+    pub fn node_selector(mut self, key: &str, value: &str) -> Self {
+        self.template
+            .node_selector
+            .get_or_insert_with(HashMap::new)
+            .insert(String::from(key), String::from(value));
+        self
+    }
+
+    /// Adds a toleration to the template pod.
+    pub fn toleration(mut self, toleration: corev1::Toleration) -> Self {
+        self.template
+            .tolerations
+            .get_or_insert_with(Vec::new)
+            .push(toleration);
+        self
+    }
+
+    /// Adds a volume that can be mounted by the containers of the template.
+    pub fn volume(mut self, volume: corev1::Volume) -> Self {
+        self.template
+            .volumes
+            .get_or_insert_with(Vec::new)
+            .push(volume);
+        self
+    }
+
+    /// Sets the `retry_strategy` of the template.
+    pub fn retry_strategy(mut self, strategy: RetryStrategy) -> Self {
+        self.template.retry_strategy = Some(Box::new(strategy));
+        self
+    }
+
+    /// Sets the total node execution `timeout` (e.g. `10m`).
+    pub fn timeout(mut self, timeout: &str) -> Self {
+        self.template.timeout = Some(String::from(timeout));
+        self
+    }
+
+    /// Sets the duration in seconds the template is allowed to run for.
+    pub fn active_deadline_seconds(mut self, seconds: i64) -> Self {
+        self.template.active_deadline_seconds = Some(seconds.to_string());
+        self
+    }
+
+    /// Sets the `service_account_name` to apply to the template pod.
+    pub fn service_account_name(mut self, name: &str) -> Self {
+        self.template.service_account_name = Some(String::from(name));
+        self
+    }
+
+    /// Limits the max total parallel pods within this template invocation.
+    pub fn parallelism(mut self, parallelism: i32) -> Self {
+        self.template.parallelism = Some(parallelism);
+        self
+    }
+
+    /// Returns a `Template` that uses this `TemplateBuilder` options.
+    pub fn build(self) -> Result<Template, BuildError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let mut template = self.template;
+        if template.name.as_deref().unwrap_or_default().is_empty() {
+            return Err(BuildError::MissingField {
+                kind: "Template",
+                field: "name",
+            });
+        }
+
+        match self.body {
+            Some(Body::Container(container)) => {
+                if container.image.as_deref().unwrap_or_default().is_empty() {
+                    return Err(BuildError::MissingField {
+                        kind: "Template",
+                        field: "container.image",
+                    });
+                }
+                template.container = Some(container);
+            }
+            Some(Body::Script(script)) => {
+                if script.image.is_empty() {
+                    return Err(BuildError::MissingField {
+                        kind: "Template",
+                        field: "script.image",
+                    });
+                }
+                if script.source.is_empty() {
+                    return Err(BuildError::MissingField {
+                        kind: "Template",
+                        field: "script.source",
+                    });
+                }
+                template.script = Some(script);
+            }
+            Some(Body::Resource(resource)) => {
+                if resource.action.is_empty() {
+                    return Err(BuildError::MissingField {
+                        kind: "Template",
+                        field: "resource.action",
+                    });
+                }
+                template.resource = Some(resource);
+            }
+            Some(Body::Suspend(suspend)) => template.suspend = Some(Box::new(suspend)),
+            Some(Body::Dag(dag)) => template.dag = Some(Box::new(dag.build()?)),
+            Some(Body::Steps(steps)) => template.steps = Some(steps.build()?),
+            None => {}
+        }
+
+        if let Some(inputs) = &template.inputs {
+            check_io_names("input parameter", "input artifact", inputs_names(inputs))?;
+        }
+        if let Some(outputs) = &template.outputs {
+            check_io_names(
+                "output parameter",
+                "output artifact",
+                outputs_names(outputs),
+            )?;
+        }
+
+        Ok(template)
+    }
+}
+
+type IoNames<'a> = (Vec<&'a str>, Vec<&'a str>);
+
+fn inputs_names(inputs: &Inputs) -> IoNames<'_> {
+    (
+        inputs
+            .parameters
+            .iter()
+            .flatten()
+            .map(|p| p.name.as_str())
+            .collect(),
+        inputs
+            .artifacts
+            .iter()
+            .flatten()
+            .map(|a| a.name.as_str())
+            .collect(),
+    )
+}
+
+fn outputs_names(outputs: &Outputs) -> IoNames<'_> {
+    (
+        outputs
+            .parameters
+            .iter()
+            .flatten()
+            .map(|p| p.name.as_str())
+            .collect(),
+        outputs
+            .artifacts
+            .iter()
+            .flatten()
+            .map(|a| a.name.as_str())
+            .collect(),
+    )
+}
+
+fn check_io_names(
+    param_kind: &'static str,
+    artifact_kind: &'static str,
+    (params, artifacts): IoNames<'_>,
+) -> Result<(), BuildError> {
+    check_unique(param_kind, params)?;
+    check_unique(artifact_kind, artifacts)
+}
+
+/// Returns an error for the first name in `names` that is seen twice.
+pub(crate) fn check_unique<'a, I>(kind: &'static str, names: I) -> Result<(), BuildError>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut seen = HashSet::new();
+    for name in names {
+        if !seen.insert(name) {
+            return Err(BuildError::DuplicateName {
+                kind,
+                name: String::from(name),
+            });
+        }
+    }
+    Ok(())
+}
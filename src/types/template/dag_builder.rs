@@ -0,0 +1,490 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops;
+
+use super::{
+    check_unique, Arguments, ContinueOn, DAGTask, DAGTemplate, OutputRefs, Reference, Sequence,
+    Target,
+};
+use crate::error::BuildError;
+use crate::types::{
+    artifact::Artifact,
+    workflow::{LifecycleHook, Parameter},
+};
+
+/// `TaskHandle` refers to a task added to a `DagBuilder`. It is used to
+/// declare dependencies between the tasks of the DAG, and to reference
+/// the outputs of the task.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskHandle {
+    name: String,
+    target: Target,
+}
+
+impl TaskHandle {
+    /// Returns the name of the task.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn outputs(&self) -> OutputRefs<'_> {
+        OutputRefs {
+            scope: "tasks",
+            node: &self.name,
+            target: &self.target,
+        }
+    }
+
+    /// `{{tasks.<task>.outputs.parameters.<name>}}`. Fails if the template
+    /// of the task does not declare the output parameter `name`.
+    pub fn output_param(&self, name: &str) -> Result<Reference, BuildError> {
+        self.outputs().parameter(name)
+    }
+
+    /// `{{tasks.<task>.outputs.artifacts.<name>}}`. Fails if the template
+    /// of the task does not declare the output artifact `name`.
+    pub fn output_artifact(&self, name: &str) -> Result<Reference, BuildError> {
+        self.outputs().artifact(name)
+    }
+
+    /// `{{tasks.<task>.outputs.result}}`, the standard output of the task.
+    /// Fails if the template of the task does not run a container or a script.
+    pub fn output_result(&self) -> Result<Reference, BuildError> {
+        self.outputs().result()
+    }
+
+    /// `{{tasks.<task>.exitCode}}`
+    pub fn exit_code(&self) -> Reference {
+        self.outputs().field("exitCode")
+    }
+
+    /// `{{tasks.<task>.status}}`
+    pub fn status(&self) -> Reference {
+        self.outputs().field("status")
+    }
+
+    fn result(&self, result: &'static str) -> Depends {
+        Depends::Task {
+            task: self.name.clone(),
+            result: Some(result),
+        }
+    }
+
+    /// The task succeeded.
+    pub fn succeeded(&self) -> Depends {
+        self.result("Succeeded")
+    }
+
+    /// The task failed.
+    pub fn failed(&self) -> Depends {
+        self.result("Failed")
+    }
+
+    /// The task errored.
+    pub fn errored(&self) -> Depends {
+        self.result("Errored")
+    }
+
+    /// The task was skipped.
+    pub fn skipped(&self) -> Depends {
+        self.result("Skipped")
+    }
+
+    /// The task was omitted.
+    pub fn omitted(&self) -> Depends {
+        self.result("Omitted")
+    }
+
+    /// The task was daemoned and is not pending.
+    pub fn daemoned(&self) -> Depends {
+        self.result("Daemoned")
+    }
+
+    /// At least one of the expanded tasks (e.g. `with_items`) succeeded.
+    pub fn any_succeeded(&self) -> Depends {
+        self.result("AnySucceeded")
+    }
+
+    /// All of the expanded tasks (e.g. `with_items`) failed.
+    pub fn all_failed(&self) -> Depends {
+        self.result("AllFailed")
+    }
+}
+
+/// `Depends` is a typed `depends` expression of a DAG task.
+///
+/// A plain task reference, created with `Depends::from(&task)`, is
+/// equivalent to `task.Succeeded || task.Skipped || task.Daemoned`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Depends {
+    Task {
+        task: String,
+        result: Option<&'static str>,
+    },
+    And(Box<Depends>, Box<Depends>),
+    Or(Box<Depends>, Box<Depends>),
+    Not(Box<Depends>),
+}
+
+impl Depends {
+    /// Both `self` and `other` must hold.
+    pub fn and(self, other: Depends) -> Depends {
+        Depends::And(Box::new(self), Box::new(other))
+    }
+
+    /// Either `self` or `other` must hold.
+    pub fn or(self, other: Depends) -> Depends {
+        Depends::Or(Box::new(self), Box::new(other))
+    }
+
+    /// Calls `f` with the name of every task referenced by the expression.
+    fn for_each_task<'a>(&'a self, f: &mut dyn FnMut(&'a str)) {
+        match self {
+            Depends::Task { task, .. } => f(task),
+            Depends::And(l, r) | Depends::Or(l, r) => {
+                l.for_each_task(f);
+                r.for_each_task(f);
+            }
+            Depends::Not(e) => e.for_each_task(f),
+        }
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Depends::Or(..) => 1,
+            Depends::And(..) => 2,
+            Depends::Not(_) | Depends::Task { .. } => 3,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>, parent: u8) -> fmt::Result {
+        if self.precedence() < parent {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl From<&TaskHandle> for Depends {
+    fn from(task: &TaskHandle) -> Self {
+        Depends::Task {
+            task: task.name.clone(),
+            result: None,
+        }
+    }
+}
+
+impl ops::Not for Depends {
+    type Output = Depends;
+
+    fn not(self) -> Depends {
+        Depends::Not(Box::new(self))
+    }
+}
+
+impl fmt::Display for Depends {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Depends::Task { task, result: None } => write!(f, "{}", task),
+            Depends::Task {
+                task,
+                result: Some(result),
+            } => write!(f, "{}.{}", task, result),
+            Depends::And(l, r) => {
+                l.fmt_operand(f, 2)?;
+                write!(f, " && ")?;
+                r.fmt_operand(f, 2)
+            }
+            Depends::Or(l, r) => {
+                l.fmt_operand(f, 1)?;
+                write!(f, " || ")?;
+                r.fmt_operand(f, 1)
+            }
+            Depends::Not(e) => {
+                write!(f, "!")?;
+                e.fmt_operand(f, 3)
+            }
+        }
+    }
+}
+
+/// Returns the names of the tasks referenced by the `depends` expression
+/// `expr` (e.g. `a && (b.Failed || !c)` references `a`, `b` and `c`).
+pub(crate) fn depends_tasks(expr: &str) -> Vec<&str> {
+    expr.split(|c: char| c.is_whitespace() || "&|!()".contains(c))
+        .filter(|operand| !operand.is_empty())
+        .map(|operand| operand.split('.').next().unwrap_or(operand))
+        .collect()
+}
+
+/// A `TaskBuilder` configures a single task of a `DagBuilder`.
+#[derive(Clone, Debug)]
+pub struct TaskBuilder {
+    task: DAGTask,
+    target: Target,
+    after: Vec<String>,
+    depends: Option<Depends>,
+}
+
+impl TaskBuilder {
+    /// Returns a `TaskHandle` to reference this task from other tasks.
+    pub fn handle(&self) -> TaskHandle {
+        TaskHandle {
+            name: self.task.name.clone(),
+            target: self.target.clone(),
+        }
+    }
+
+    /// Runs the task once `task` has completed successfully. Can be
+    /// called more than once.
+    pub fn after(&mut self, task: &TaskHandle) -> &mut Self {
+        self.after.push(task.name.clone());
+        self
+    }
+
+    /// Sets the `depends` expression of the task. It can not be combined
+    /// with `after()`.
+    pub fn depends<D: Into<Depends>>(&mut self, depends: D) -> &mut Self {
+        self.depends = Some(depends.into());
+        self
+    }
+
+    /// Sets the expression in which the task should conditionally execute.
+    pub fn when(&mut self, expression: &str) -> &mut Self {
+        self.task.when = Some(String::from(expression));
+        self
+    }
+
+    /// Passes the argument parameter `name` with `value` to the template.
+    pub fn parameter(&mut self, name: &str, value: &str) -> &mut Self {
+        let mut param = Parameter::new(name);
+        param.value = Some(String::from(value));
+        self.arguments()
+            .parameters
+            .get_or_insert_with(Vec::new)
+            .push(param);
+        self
+    }
+
+    /// Passes an argument artifact to the template.
+    pub fn artifact(&mut self, artifact: Artifact) -> &mut Self {
+        self.arguments()
+            .artifacts
+            .get_or_insert_with(Vec::new)
+            .push(artifact);
+        self
+    }
+
+    /// Passes the argument artifact `name` to the template, taken from
+    /// the referenced output artifact.
+    pub fn artifact_from(&mut self, name: &str, from: &Reference) -> &mut Self {
+        let mut artifact = Artifact::new(name);
+        artifact.from = Some(String::from(from.as_str()));
+        self.artifact(artifact)
+    }
+
+    fn arguments(&mut self) -> &mut Arguments {
+        self.task.arguments.get_or_insert_with(Box::default)
+    }
+
+    /// Sets whether the DAG should continue when the task fails or errors.
+    pub fn continue_on(&mut self, continue_on: ContinueOn) -> &mut Self {
+        self.task.continue_on = Some(Box::new(continue_on));
+        self
+    }
+
+    /// Expands the task into one task per item of `items`.
+    /// The current item is available as `{{item}}`.
+    pub fn with_items<I, T>(&mut self, items: I) -> &mut Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<serde_json::Value>,
+    {
+        self.task.with_items = Some(items.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Expands the task into one task per item of the JSON list in `param`.
+    pub fn with_param(&mut self, param: &str) -> &mut Self {
+        self.task.with_param = Some(String::from(param));
+        self
+    }
+
+    /// Expands the task into one task per number of the `sequence`.
+    pub fn with_sequence(&mut self, sequence: Sequence) -> &mut Self {
+        self.task.with_sequence = Some(Box::new(sequence));
+        self
+    }
+
+    /// Adds a lifecycle hook to the task (e.g. `exit`).
+    pub fn hook(&mut self, name: &str, hook: LifecycleHook) -> &mut Self {
+        self.task
+            .hooks
+            .get_or_insert_with(HashMap::new)
+            .insert(String::from(name), hook);
+        self
+    }
+
+    /// Returns the names of the tasks this task depends on.
+    fn references(&self) -> Vec<&str> {
+        let mut refs: Vec<&str> = self.after.iter().map(String::as_str).collect();
+        if let Some(depends) = &self.depends {
+            depends.for_each_task(&mut |t| refs.push(t));
+        }
+        refs
+    }
+}
+
+/// A `DagBuilder` can be used to create a `DAGTemplate` whose task
+/// dependencies are checked before submission.
+#[derive(Clone, Debug, Default)]
+pub struct DagBuilder {
+    tasks: Vec<TaskBuilder>,
+    fail_fast: Option<bool>,
+    target: Option<String>,
+}
+
+impl DagBuilder {
+    /// Constructs a new `DagBuilder`.
+    pub fn new() -> Self {
+        DagBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// Adds a task named `name` running `template`, and returns its
+    /// `TaskBuilder` for further configuration.
+    ///
+    /// `template` is either a template name, or a `Template` (or its
+    /// builder) whose declared outputs can then be referenced through the
+    /// task handle.
+    pub fn task<T: Into<Target>>(&mut self, name: &str, template: T) -> &mut TaskBuilder {
+        let target = template.into();
+        let mut task = DAGTask::new(name);
+        task.template = Some(String::from(target.name()));
+        self.tasks.push(TaskBuilder {
+            task,
+            target,
+            after: Vec::new(),
+            depends: None,
+        });
+        self.tasks.last_mut().unwrap()
+    }
+
+    /// Returns the `TaskBuilder` of an already added `task`, to change its
+    /// configuration.
+    pub fn get(&mut self, task: &TaskHandle) -> Option<&mut TaskBuilder> {
+        self.tasks.iter_mut().find(|t| t.task.name == task.name)
+    }
+
+    /// Controls whether the DAG stops scheduling new tasks as soon as
+    /// one of them has failed. Defaults to `true`.
+    pub fn fail_fast(&mut self, fail_fast: bool) -> &mut Self {
+        self.fail_fast = Some(fail_fast);
+        self
+    }
+
+    /// Limits the execution to `tasks` and their dependencies.
+    pub fn target(&mut self, tasks: &[&TaskHandle]) -> &mut Self {
+        let names: Vec<&str> = tasks.iter().map(|t| t.name.as_str()).collect();
+        self.target = Some(names.join(" "));
+        self
+    }
+
+    /// Returns a `DAGTemplate` that uses this `DagBuilder` options.
+    ///
+    /// Fails if a task depends on an unknown task, or if the dependencies
+    /// between the tasks form a cycle.
+    pub fn build(self) -> Result<DAGTemplate, BuildError> {
+        check_unique("DAG task", self.tasks.iter().map(|t| t.task.name.as_str()))?;
+
+        let known: HashSet<&str> = self.tasks.iter().map(|t| t.task.name.as_str()).collect();
+        let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
+        for task in &self.tasks {
+            let name = task.task.name.as_str();
+            if !task.after.is_empty() && task.depends.is_some() {
+                return Err(BuildError::MixedDependencies(String::from(name)));
+            }
+            let refs = task.references();
+            for dependency in &refs {
+                if !known.contains(dependency) {
+                    return Err(BuildError::UnknownTask {
+                        task: String::from(name),
+                        dependency: String::from(*dependency),
+                    });
+                }
+            }
+            graph.insert(name, refs);
+        }
+        for target in self.target.iter().flat_map(|t| t.split_whitespace()) {
+            if !known.contains(target) {
+                return Err(BuildError::UnknownTask {
+                    task: String::from("target"),
+                    dependency: String::from(target),
+                });
+            }
+        }
+
+        let order: Vec<&str> = self.tasks.iter().map(|t| t.task.name.as_str()).collect();
+        if let Some(cycle) = find_cycle(&order, &graph) {
+            return Err(BuildError::Cycle(cycle));
+        }
+
+        let tasks = self
+            .tasks
+            .into_iter()
+            .map(|t| {
+                let mut task = t.task;
+                if !t.after.is_empty() {
+                    task.dependencies = Some(t.after);
+                }
+                task.depends = t.depends.map(|d| d.to_string());
+                task
+            })
+            .collect();
+
+        let mut dag = DAGTemplate::new(tasks);
+        dag.fail_fast = self.fail_fast;
+        dag.target = self.target;
+        Ok(dag)
+    }
+}
+
+/// Returns the first cycle found in `graph`, visiting the nodes in `order`.
+/// The returned path starts and ends with the same node.
+pub(crate) fn find_cycle(order: &[&str], graph: &HashMap<&str, Vec<&str>>) -> Option<Vec<String>> {
+    fn visit<'a>(
+        node: &'a str,
+        graph: &HashMap<&'a str, Vec<&'a str>>,
+        done: &mut HashSet<&'a str>,
+        path: &mut Vec<&'a str>,
+    ) -> Option<Vec<String>> {
+        if let Some(start) = path.iter().position(|n| *n == node) {
+            let mut cycle: Vec<String> = path[start..].iter().map(|n| n.to_string()).collect();
+            cycle.push(node.to_string());
+            return Some(cycle);
+        }
+        if done.contains(node) {
+            return None;
+        }
+
+        path.push(node);
+        for next in graph.get(node).into_iter().flatten() {
+            if let Some(cycle) = visit(next, graph, done, path) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        done.insert(node);
+        None
+    }
+
+    let mut done = HashSet::new();
+    for node in order {
+        if let Some(cycle) = visit(node, graph, &mut done, &mut Vec::new()) {
+            return Some(cycle);
+        }
+    }
+    None
+}
@@ -1,6 +1,10 @@
 mod arguments;
 pub use self::arguments::Arguments;
 
+mod builder;
+pub(crate) use self::builder::check_unique;
+pub use self::builder::TemplateBuilder;
+
 mod cache;
 pub use self::cache::Cache;
 
@@ -16,6 +20,10 @@ pub use self::container_set_template::ContainerSetTemplate;
 mod continue_on;
 pub use self::continue_on::ContinueOn;
 
+mod dag_builder;
+pub(crate) use self::dag_builder::{depends_tasks, find_cycle};
+pub use self::dag_builder::{DagBuilder, Depends, TaskBuilder, TaskHandle};
+
 mod dag_task;
 pub use self::dag_task::DAGTask;
 
@@ -46,15 +54,25 @@ pub use self::memoization_status::MemoizationStatus;
 mod outputs;
 pub use self::outputs::Outputs;
 
+mod reference;
+pub(crate) use self::reference::OutputRefs;
+pub use self::reference::{InputRefs, Reference, Target};
+
 mod resource_template;
 pub use self::resource_template::ResourceTemplate;
 
+mod resource_validate;
+pub(crate) use self::resource_validate::mask_tags;
+
 mod script_template;
 pub use self::script_template::ScriptTemplate;
 
 mod sequence;
 pub use self::sequence::Sequence;
 
+mod steps_builder;
+pub use self::steps_builder::{StepBuilder, StepGroup, StepHandle, StepsBuilder};
+
 mod suspend_template;
 pub use self::suspend_template::SuspendTemplate;
 
@@ -0,0 +1,243 @@
+use std::fmt;
+
+use super::{Inputs, Outputs, Template, TemplateBuilder};
+use crate::error::BuildError;
+
+/// `Reference` is a template tag such as `{{inputs.parameters.x}}`,
+/// which the controller substitutes at runtime.
+///
+/// It can be used as the value of a parameter, the `from` of an
+/// artifact, or inside `when` expressions and container arguments.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Reference(String);
+
+impl Reference {
+    fn new(path: String) -> Self {
+        Reference(format!("{{{{{}}}}}", path))
+    }
+
+    /// `{{workflow.parameters.<name>}}`
+    pub fn workflow_parameter(name: &str) -> Self {
+        Reference::new(format!("workflow.parameters.{}", name))
+    }
+
+    /// `{{workflow.name}}`
+    pub fn workflow_name() -> Self {
+        Reference::new(String::from("workflow.name"))
+    }
+
+    /// `{{workflow.namespace}}`
+    pub fn workflow_namespace() -> Self {
+        Reference::new(String::from("workflow.namespace"))
+    }
+
+    /// `{{item}}`, the current item of a `with_items`, `with_param` or
+    /// `with_sequence` loop.
+    pub fn item() -> Self {
+        Reference::new(String::from("item"))
+    }
+
+    /// `{{item.<field>}}`, a field of the current item of a loop over objects.
+    pub fn item_field(field: &str) -> Self {
+        Reference::new(format!("item.{}", field))
+    }
+
+    /// Returns the tag, including the surrounding braces.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Reference {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<Reference> for String {
+    fn from(reference: Reference) -> Self {
+        reference.0
+    }
+}
+
+/// `Target` is the template run by a DAG task or a step.
+///
+/// When created from a `Template` or a `TemplateBuilder`, the outputs
+/// declared by the template are known, and references to them are checked.
+/// When created from a template name only, no output can be referenced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    name: String,
+    outputs: Option<Outputs>,
+    has_result: bool,
+}
+
+impl Target {
+    /// Returns the name of the template.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl From<&str> for Target {
+    fn from(name: &str) -> Self {
+        Target {
+            name: String::from(name),
+            outputs: None,
+            has_result: false,
+        }
+    }
+}
+
+impl From<&Template> for Target {
+    fn from(template: &Template) -> Self {
+        Target {
+            name: template.name.clone().unwrap_or_default(),
+            outputs: Some(template.outputs.as_deref().cloned().unwrap_or_default()),
+            has_result: template.container.is_some() || template.script.is_some(),
+        }
+    }
+}
+
+impl From<&TemplateBuilder> for Target {
+    fn from(builder: &TemplateBuilder) -> Self {
+        Target {
+            has_result: builder.has_result(),
+            ..Target::from(builder.template())
+        }
+    }
+}
+
+/// Builds the references to the outputs of `target`, run by the task or
+/// step `node` of the given `scope` (`tasks` or `steps`).
+#[derive(Clone, Copy)]
+pub(crate) struct OutputRefs<'a> {
+    pub(crate) scope: &'static str,
+    pub(crate) node: &'a str,
+    pub(crate) target: &'a Target,
+}
+
+impl OutputRefs<'_> {
+    fn undeclared(&self, kind: &'static str, name: &str) -> BuildError {
+        BuildError::Undeclared {
+            template: self.target.name.clone(),
+            kind,
+            name: String::from(name),
+        }
+    }
+
+    pub(crate) fn parameter(self, name: &str) -> Result<Reference, BuildError> {
+        let declared = self
+            .target
+            .outputs
+            .as_ref()
+            .is_some_and(|o| o.parameters.iter().flatten().any(|p| p.name == name));
+        if !declared {
+            return Err(self.undeclared("output parameter", name));
+        }
+        Ok(Reference::new(format!(
+            "{}.{}.outputs.parameters.{}",
+            self.scope, self.node, name
+        )))
+    }
+
+    pub(crate) fn artifact(self, name: &str) -> Result<Reference, BuildError> {
+        let declared = self
+            .target
+            .outputs
+            .as_ref()
+            .is_some_and(|o| o.artifacts.iter().flatten().any(|a| a.name == name));
+        if !declared {
+            return Err(self.undeclared("output artifact", name));
+        }
+        Ok(Reference::new(format!(
+            "{}.{}.outputs.artifacts.{}",
+            self.scope, self.node, name
+        )))
+    }
+
+    pub(crate) fn result(self) -> Result<Reference, BuildError> {
+        if !self.target.has_result {
+            return Err(self.undeclared("output", "result"));
+        }
+        Ok(Reference::new(format!(
+            "{}.{}.outputs.result",
+            self.scope, self.node
+        )))
+    }
+
+    pub(crate) fn field(self, field: &str) -> Reference {
+        Reference::new(format!("{}.{}.{}", self.scope, self.node, field))
+    }
+}
+
+/// `InputRefs` builds references to the inputs of a template, to be used
+/// within that template (e.g. in container arguments).
+#[derive(Clone, Debug)]
+pub struct InputRefs {
+    template: String,
+    inputs: Inputs,
+}
+
+impl InputRefs {
+    /// `{{inputs.parameters.<name>}}`. Fails if the template does not
+    /// declare the input parameter `name`.
+    pub fn param(&self, name: &str) -> Result<Reference, BuildError> {
+        if !self
+            .inputs
+            .parameters
+            .iter()
+            .flatten()
+            .any(|p| p.name == name)
+        {
+            return Err(self.undeclared("input parameter", name));
+        }
+        Ok(Reference::new(format!("inputs.parameters.{}", name)))
+    }
+
+    /// `{{inputs.artifacts.<name>.path}}`. Fails if the template does not
+    /// declare the input artifact `name`.
+    pub fn artifact_path(&self, name: &str) -> Result<Reference, BuildError> {
+        if !self
+            .inputs
+            .artifacts
+            .iter()
+            .flatten()
+            .any(|a| a.name == name)
+        {
+            return Err(self.undeclared("input artifact", name));
+        }
+        Ok(Reference::new(format!("inputs.artifacts.{}.path", name)))
+    }
+
+    fn undeclared(&self, kind: &'static str, name: &str) -> BuildError {
+        BuildError::Undeclared {
+            template: self.template.clone(),
+            kind,
+            name: String::from(name),
+        }
+    }
+}
+
+impl Template {
+    /// Returns the references to the inputs declared by the template.
+    pub fn input_refs(&self) -> InputRefs {
+        InputRefs {
+            template: self.name.clone().unwrap_or_default(),
+            inputs: self.inputs.as_deref().cloned().unwrap_or_default(),
+        }
+    }
+}
+
+impl TemplateBuilder {
+    /// Returns the references to the inputs declared so far.
+    pub fn input_refs(&self) -> InputRefs {
+        self.template().input_refs()
+    }
+}
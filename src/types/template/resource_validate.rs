@@ -0,0 +1,292 @@
+use serde_json::Value;
+
+use super::ResourceTemplate;
+use crate::error::{ValidationError, ValidationErrorKind};
+
+/// Actions a resource template can perform.
+const ACTIONS: &[&str] = &["get", "create", "apply", "delete", "replace", "patch"];
+
+/// Strategies a `patch` resource template can merge its patch with.
+const MERGE_STRATEGIES: &[&str] = &["strategic", "merge", "json"];
+
+impl ResourceTemplate {
+    /// Checks the resource template offline and returns every problem found.
+    /// Each problem is located by a JSON pointer relative to the resource
+    /// template.
+    ///
+    /// The checks are:
+    /// - `action` is one of get, create, apply, delete, replace or patch.
+    /// - `merge_strategy` is one of strategic, merge or json, and is only
+    ///   set for the patch action.
+    /// - `manifest` or `manifest_from` is set. A `manifest` parses as YAML or
+    ///   JSON into a Kubernetes object with `apiVersion`, `kind` and
+    ///   `metadata`, or into a list of operations for a json patch. Patches
+    ///   naming their target with `flags` only need to be objects.
+    /// - `success_condition` and `failure_condition` are valid selectors,
+    ///   e.g. `status.phase in (Failed, Error), status.failed > 2`.
+    ///
+    /// Template tags (`{{...}}`) are not resolved, they are taken as plain
+    /// values.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+
+        if !ACTIONS.contains(&self.action.as_str()) {
+            errors.push(ValidationError::new(
+                String::from("/action"),
+                ValidationErrorKind::InvalidValue {
+                    value: self.action.clone(),
+                    allowed: ACTIONS,
+                },
+            ));
+        }
+        match self.merge_strategy.as_deref() {
+            Some(_) if self.action != "patch" => errors.push(ValidationError::new(
+                String::from("/mergeStrategy"),
+                ValidationErrorKind::UnusedField {
+                    field: "mergeStrategy",
+                    action: self.action.clone(),
+                },
+            )),
+            Some(strategy) if !MERGE_STRATEGIES.contains(&strategy) => {
+                errors.push(ValidationError::new(
+                    String::from("/mergeStrategy"),
+                    ValidationErrorKind::InvalidValue {
+                        value: String::from(strategy),
+                        allowed: MERGE_STRATEGIES,
+                    },
+                ))
+            }
+            _ => {}
+        }
+
+        match (self.manifest.as_deref(), &self.manifest_from) {
+            (Some(manifest), _) => {
+                if let Err(message) = self.check_manifest(manifest) {
+                    errors.push(ValidationError::new(
+                        String::from("/manifest"),
+                        ValidationErrorKind::InvalidManifest(message),
+                    ));
+                }
+            }
+            (None, Some(_)) => {}
+            (None, None) => errors.push(ValidationError::new(
+                String::from("/manifest"),
+                ValidationErrorKind::MissingField("manifest"),
+            )),
+        }
+
+        for (pointer, condition) in [
+            ("/successCondition", &self.success_condition),
+            ("/failureCondition", &self.failure_condition),
+        ] {
+            if let Some(condition) = condition.as_deref() {
+                if let Err((position, message)) = check_condition(&mask_tags(condition)) {
+                    errors.push(ValidationError::new(
+                        String::from(pointer),
+                        ValidationErrorKind::InvalidCondition { position, message },
+                    ));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check_manifest(&self, manifest: &str) -> Result<(), String> {
+        let value: Value = serde_yaml::from_str(&mask_tags(manifest)).map_err(|e| e.to_string())?;
+        let json_patch = self.action == "patch" && self.merge_strategy.as_deref() == Some("json");
+        if json_patch {
+            let operations = value
+                .as_array()
+                .ok_or("a json patch must be a list of operations")?;
+            for operation in operations {
+                for field in ["op", "path"] {
+                    if !operation.get(field).is_some_and(Value::is_string) {
+                        return Err(format!("json patch operation without '{}'", field));
+                    }
+                }
+            }
+            return Ok(());
+        }
+
+        let object = value
+            .as_object()
+            .ok_or("the manifest must be a Kubernetes object")?;
+        if self.action == "patch" && self.flags.as_ref().is_some_and(|f| !f.is_empty()) {
+            return Ok(());
+        }
+        for field in ["apiVersion", "kind"] {
+            if !object.get(field).is_some_and(Value::is_string) {
+                return Err(format!("missing '{}'", field));
+            }
+        }
+        let metadata = object
+            .get("metadata")
+            .and_then(Value::as_object)
+            .ok_or("missing 'metadata'")?;
+        let named = metadata.get("name").is_some_and(Value::is_string)
+            || (self.action == "create"
+                && metadata.get("generateName").is_some_and(Value::is_string));
+        if !named {
+            return Err(String::from("missing 'metadata.name'"));
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the template tags of `text` with placeholders of the same
+/// length, so that offsets in the result match the ones in `text`.
+pub(crate) fn mask_tags(text: &str) -> String {
+    let mut masked = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end + 2,
+            None => break,
+        };
+        masked.push_str(&rest[..start]);
+        masked.extend(std::iter::repeat_n('x', end - start));
+        rest = &rest[end..];
+    }
+    masked.push_str(rest);
+    masked
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Operator(&'a str),
+    Comma,
+    Open,
+    Close,
+    End,
+}
+
+impl Token<'_> {
+    fn describe(&self) -> String {
+        match self {
+            Token::Word(word) => format!("'{}'", word),
+            Token::Operator(operator) => format!("'{}'", operator),
+            Token::Comma => String::from("','"),
+            Token::Open => String::from("'('"),
+            Token::Close => String::from("')'"),
+            Token::End => String::from("end of condition"),
+        }
+    }
+}
+
+/// Splits a condition into tokens, each with its byte offset.
+fn tokenize(condition: &str) -> Vec<(usize, Token<'_>)> {
+    let mut tokens = Vec::new();
+    let mut chars = condition.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            ',' => Token::Comma,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '!' | '=' => {
+                let end = match chars.next_if(|(_, c)| *c == '=') {
+                    Some((j, _)) => j + 1,
+                    None => i + 1,
+                };
+                Token::Operator(&condition[i..end])
+            }
+            '<' | '>' => Token::Operator(&condition[i..i + 1]),
+            _ => {
+                let mut end = condition.len();
+                while let Some((j, c)) = chars.peek() {
+                    if c.is_whitespace() || ",()!=<>".contains(*c) {
+                        end = *j;
+                        break;
+                    }
+                    chars.next();
+                }
+                Token::Word(&condition[i..end])
+            }
+        };
+        tokens.push((i, token));
+    }
+    tokens.push((condition.len(), Token::End));
+    tokens
+}
+
+/// Checks that `condition` is a comma separated list of requirements
+/// of the form `key`, `!key`, `key = value`, `key == value`,
+/// `key != value`, `key > number`, `key < number`, `key in (values)` and
+/// `key notin (values)`. Returns the offset of the first problem found.
+fn check_condition(condition: &str) -> Result<(), (usize, String)> {
+    if condition.trim().is_empty() {
+        return Ok(());
+    }
+    let tokens = tokenize(condition);
+    let mut tokens = tokens.into_iter().peekable();
+    let mut next = move || tokens.next().expect("tokens end with Token::End");
+    let unexpected = |(position, token): (usize, Token), expected: &str| {
+        Err((
+            position,
+            format!("found {}, expected {}", token.describe(), expected),
+        ))
+    };
+
+    loop {
+        let mut token = next();
+        let negated = token.1 == Token::Operator("!");
+        if negated {
+            token = next();
+        }
+        if !matches!(token.1, Token::Word(_)) {
+            return unexpected(token, "a key");
+        }
+
+        let mut token = next();
+        if !negated {
+            token = match token.1 {
+                Token::Operator("=" | "==" | "!=") => match next() {
+                    (_, Token::Word(_)) => next(),
+                    token @ (_, Token::Comma | Token::End) => token,
+                    token => return unexpected(token, "a value"),
+                },
+                Token::Operator(">" | "<") => match next() {
+                    (position, Token::Word(value)) => {
+                        if value.parse::<i64>().is_err() {
+                            return Err((position, format!("'{}' is not an integer", value)));
+                        }
+                        next()
+                    }
+                    token => return unexpected(token, "an integer"),
+                },
+                Token::Word("in" | "notin") => {
+                    let open = next();
+                    if open.1 != Token::Open {
+                        return unexpected(open, "'('");
+                    }
+                    loop {
+                        match next() {
+                            (_, Token::Word(_)) => {}
+                            token => return unexpected(token, "a value"),
+                        }
+                        match next() {
+                            (_, Token::Comma) => {}
+                            (_, Token::Close) => break,
+                            token => return unexpected(token, "',' or ')'"),
+                        }
+                    }
+                    next()
+                }
+                Token::Comma | Token::End => token,
+                _ => return unexpected(token, "an operator"),
+            };
+        }
+
+        match token.1 {
+            Token::Comma => {}
+            Token::End => return Ok(()),
+            _ => return unexpected(token, "',' or end of condition"),
+        }
+    }
+}
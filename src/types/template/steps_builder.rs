@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use super::{check_unique, ContinueOn, OutputRefs, Reference, Sequence, Target};
+use crate::error::BuildError;
+use crate::types::{
+    artifact::Artifact,
+    workflow::{Arguments, LifecycleHook, Parameter, WorkflowStep},
+};
+
+/// `StepHandle` refers to a step of a `StepsBuilder`, to reference the
+/// outputs of the step.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StepHandle {
+    name: String,
+    target: Target,
+}
+
+impl StepHandle {
+    /// Returns the name of the step.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn outputs(&self) -> OutputRefs<'_> {
+        OutputRefs {
+            scope: "steps",
+            node: &self.name,
+            target: &self.target,
+        }
+    }
+
+    /// `{{steps.<step>.outputs.parameters.<name>}}`. Fails if the template
+    /// of the step does not declare the output parameter `name`.
+    pub fn output_param(&self, name: &str) -> Result<Reference, BuildError> {
+        self.outputs().parameter(name)
+    }
+
+    /// `{{steps.<step>.outputs.artifacts.<name>}}`. Fails if the template
+    /// of the step does not declare the output artifact `name`.
+    pub fn output_artifact(&self, name: &str) -> Result<Reference, BuildError> {
+        self.outputs().artifact(name)
+    }
+
+    /// `{{steps.<step>.outputs.result}}`, the standard output of the step.
+    /// Fails if the template of the step does not run a container or a script.
+    pub fn output_result(&self) -> Result<Reference, BuildError> {
+        self.outputs().result()
+    }
+
+    /// `{{steps.<step>.exitCode}}`
+    pub fn exit_code(&self) -> Reference {
+        self.outputs().field("exitCode")
+    }
+
+    /// `{{steps.<step>.status}}`
+    pub fn status(&self) -> Reference {
+        self.outputs().field("status")
+    }
+}
+
+/// A `StepBuilder` can be used to create a single `WorkflowStep`.
+#[derive(Clone, Debug)]
+pub struct StepBuilder {
+    step: WorkflowStep,
+    target: Target,
+}
+
+impl StepBuilder {
+    /// Constructs a new `StepBuilder` for a step named `name` running `template`.
+    ///
+    /// `template` is either a template name, or a `Template` (or its
+    /// builder) whose declared outputs can then be referenced through the
+    /// step handle.
+    pub fn new<T: Into<Target>>(name: &str, template: T) -> Self {
+        let target = template.into();
+        StepBuilder {
+            step: WorkflowStep {
+                name: Some(String::from(name)),
+                template: Some(String::from(target.name())),
+                ..Default::default()
+            },
+            target,
+        }
+    }
+
+    /// Returns a `StepHandle` to reference this step.
+    pub fn handle(&self) -> StepHandle {
+        StepHandle {
+            name: self.step.name.clone().unwrap_or_default(),
+            target: self.target.clone(),
+        }
+    }
+
+    /// Sets the expression in which the step should conditionally execute.
+    pub fn when(mut self, expression: &str) -> Self {
+        self.step.when = Some(String::from(expression));
+        self
+    }
+
+    /// Passes the argument parameter `name` with `value` to the template.
+    pub fn parameter(mut self, name: &str, value: &str) -> Self {
+        let mut param = Parameter::new(name);
+        param.value = Some(String::from(value));
+        self.arguments()
+            .parameters
+            .get_or_insert_with(Vec::new)
+            .push(param);
+        self
+    }
+
+    /// Passes an argument artifact to the template.
+    pub fn artifact(mut self, artifact: Artifact) -> Self {
+        self.arguments()
+            .artifacts
+            .get_or_insert_with(Vec::new)
+            .push(artifact);
+        self
+    }
+
+    /// Passes the argument artifact `name` to the template, taken from
+    /// the referenced output artifact.
+    pub fn artifact_from(self, name: &str, from: &Reference) -> Self {
+        let mut artifact = Artifact::new(name);
+        artifact.from = Some(String::from(from.as_str()));
+        self.artifact(artifact)
+    }
+
+    fn arguments(&mut self) -> &mut Arguments {
+        self.step.arguments.get_or_insert_with(Box::default)
+    }
+
+    /// Sets whether the workflow should continue when the step fails or errors.
+    pub fn continue_on(mut self, continue_on: ContinueOn) -> Self {
+        self.step.continue_on = Some(Box::new(continue_on));
+        self
+    }
+
+    /// Expands the step into one parallel step per item of `items`.
+    /// The current item is available as `{{item}}`.
+    pub fn with_items<I, T>(mut self, items: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<serde_json::Value>,
+    {
+        self.step.with_items = Some(items.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Expands the step into one parallel step per item of the JSON list
+    /// in `param` (e.g. `{{steps.gen.outputs.result}}`).
+    pub fn with_param(mut self, param: &str) -> Self {
+        self.step.with_param = Some(String::from(param));
+        self
+    }
+
+    /// Expands the step into one parallel step per number of the `sequence`.
+    pub fn with_sequence(mut self, sequence: Sequence) -> Self {
+        self.step.with_sequence = Some(Box::new(sequence));
+        self
+    }
+
+    /// Expands the step into `count` parallel steps, numbered from zero.
+    pub fn with_count(self, count: u32) -> Self {
+        self.with_sequence(Sequence {
+            count: Some(count.to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Expands the step into one parallel step per number from `start` to
+    /// `end`, inclusive.
+    pub fn with_range(self, start: i64, end: i64) -> Self {
+        self.with_sequence(Sequence {
+            start: Some(start.to_string()),
+            end: Some(end.to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Adds a lifecycle hook to the step (e.g. `exit`).
+    pub fn hook(mut self, name: &str, hook: LifecycleHook) -> Self {
+        self.step
+            .hooks
+            .get_or_insert_with(HashMap::new)
+            .insert(String::from(name), hook);
+        self
+    }
+}
+
+/// `StepGroup` is a set of steps which run in parallel.
+#[derive(Clone, Debug, Default)]
+pub struct StepGroup {
+    steps: Vec<WorkflowStep>,
+}
+
+impl StepGroup {
+    /// Adds a step to the group.
+    pub fn step(mut self, step: StepBuilder) -> Self {
+        self.steps.push(step.step);
+        self
+    }
+}
+
+/// A `StepsBuilder` can be used to create the step groups of a steps
+/// template. Groups run one after the other, while the steps of a group
+/// run in parallel.
+#[derive(Clone, Debug, Default)]
+pub struct StepsBuilder {
+    groups: Vec<StepGroup>,
+}
+
+impl StepsBuilder {
+    /// Constructs a new `StepsBuilder`.
+    pub fn new() -> Self {
+        StepsBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// Adds a group of parallel steps, configured by `f`.
+    pub fn group<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(StepGroup) -> StepGroup,
+    {
+        self.groups.push(f(StepGroup::default()));
+        self
+    }
+
+    /// Adds a group made of the single `step`.
+    pub fn step(self, step: StepBuilder) -> Self {
+        self.group(|g| g.step(step))
+    }
+
+    /// Returns the step groups that use this `StepsBuilder` options.
+    ///
+    /// Fails if a group is empty, if a step has no name, or if two steps
+    /// share a name.
+    pub fn build(self) -> Result<Vec<Vec<WorkflowStep>>, BuildError> {
+        let steps = self.groups.iter().flat_map(|g| g.steps.iter());
+        for step in steps.clone() {
+            if step.name.as_deref().unwrap_or_default().is_empty() {
+                return Err(BuildError::MissingField {
+                    kind: "WorkflowStep",
+                    field: "name",
+                });
+            }
+        }
+        check_unique("step", steps.filter_map(|s| s.name.as_deref()))?;
+
+        if self.groups.iter().any(|g| g.steps.is_empty()) {
+            return Err(BuildError::MissingField {
+                kind: "StepGroup",
+                field: "steps",
+            });
+        }
+        Ok(self.groups.into_iter().map(|g| g.steps).collect())
+    }
+}
@@ -1,6 +1,8 @@
 use reqwest;
 use serde::{Deserialize, Serialize};
 
+use super::{FieldSelector, LabelSelector};
+
 #[derive(Debug)]
 pub struct ResponseContent<T> {
     pub status: reqwest::StatusCode,
@@ -21,20 +23,61 @@ pub struct CreateOptions {
     pub field_validation: Option<String>,
 }
 
-#[derive(Default)]
+/// `ListOptions` may be provided when listing or watching API objects.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct ListOptions {
-    pub label_selector: Option<String>,
-    pub field_selector: Option<String>,
+    pub label_selector: Option<LabelSelector>,
+    pub field_selector: Option<FieldSelector>,
     pub watch: Option<bool>,
     pub allow_watch_bookmarks: Option<bool>,
     pub resource_version: Option<String>,
     pub resource_version_match: Option<String>,
-    pub timeout_seconds: Option<String>,
-    pub limit: Option<String>,
+    /// Seconds after which a watch is closed.
+    pub timeout_seconds: Option<i64>,
+    /// Maximum number of objects to return. The `continue` token of the
+    /// returned list gives the next ones.
+    pub limit: Option<i64>,
     pub r#continue: Option<String>,
     pub send_initial_events: Option<bool>,
 }
 
+impl ListOptions {
+    /// Constructs new `ListOptions`, listing every object.
+    pub fn new() -> Self {
+        ListOptions::default()
+    }
+
+    /// Sets the `label_selector`.
+    pub fn label_selector(mut self, selector: LabelSelector) -> Self {
+        self.label_selector = Some(selector);
+        self
+    }
+
+    /// Sets the `field_selector`.
+    pub fn field_selector(mut self, selector: FieldSelector) -> Self {
+        self.field_selector = Some(selector);
+        self
+    }
+
+    /// Sets the `resource_version`.
+    pub fn resource_version(mut self, version: &str) -> Self {
+        self.resource_version = Some(String::from(version));
+        self
+    }
+
+    /// Sets the `timeout_seconds`.
+    pub fn timeout_seconds(mut self, seconds: i64) -> Self {
+        self.timeout_seconds = Some(seconds);
+        self
+    }
+
+    /// Sets the `limit`.
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
 #[derive(Default)]
 pub struct Preconditions {
     pub uid: Option<String>,
@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+
+use k8s_openapi::api::core::v1 as corev1;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+
+use super::{Arguments, LifecycleHook, Parameter, PodGC, TTLStrategy, Workflow, WorkflowSpec};
+use crate::error::BuildError;
+use crate::types::{
+    artifact::Artifact,
+    template::{check_unique, Template, TemplateBuilder},
+    workflow_template::WorkflowTemplateRef,
+    RetryStrategy,
+};
+
+const API_VERSION: &str = "argoproj.io/v1alpha1";
+
+/// A `WorkflowSpecBuilder` can be used to create a `WorkflowSpec` with
+/// custom options.
+#[derive(Debug, Default)]
+pub struct WorkflowSpecBuilder {
+    spec: WorkflowSpec,
+    templates: Vec<TemplateBuilder>,
+}
+
+impl WorkflowSpec {
+    /// Creates a `WorkflowSpecBuilder` to build the `WorkflowSpec`.
+    pub fn builder() -> WorkflowSpecBuilder {
+        WorkflowSpecBuilder::new()
+    }
+}
+
+impl WorkflowSpecBuilder {
+    /// Constructs a new `WorkflowSpecBuilder`.
+    pub fn new() -> Self {
+        WorkflowSpecBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// Sets the name of the template the workflow starts with.
+    pub fn entrypoint(mut self, template: &str) -> Self {
+        self.spec.entrypoint = Some(String::from(template));
+        self
+    }
+
+    /// Sets the name of the template invoked at the end of the workflow.
+    pub fn on_exit(mut self, template: &str) -> Self {
+        self.spec.on_exit = Some(String::from(template));
+        self
+    }
+
+    /// Adds a workflow argument parameter named `name` with `value`.
+    pub fn parameter(self, name: &str, value: &str) -> Self {
+        let mut param = Parameter::new(name);
+        param.value = Some(String::from(value));
+        self.argument(param)
+    }
+
+    /// Adds a workflow argument parameter.
+    pub fn argument(mut self, param: Parameter) -> Self {
+        let arguments = self.spec.arguments.get_or_insert_with(Box::default);
+        arguments
+            .parameters
+            .get_or_insert_with(Vec::new)
+            .push(param);
+        self
+    }
+
+    /// Adds a workflow argument artifact.
+    pub fn artifact(mut self, artifact: Artifact) -> Self {
+        let arguments = self.spec.arguments.get_or_insert_with(Box::default);
+        arguments
+            .artifacts
+            .get_or_insert_with(Vec::new)
+            .push(artifact);
+        self
+    }
+
+    /// Adds a template to the spec. Accepts either a `TemplateBuilder`,
+    /// which is built along with the spec, or an already built `Template`.
+    pub fn template<T: Into<TemplateBuilder>>(mut self, template: T) -> Self {
+        self.templates.push(template.into());
+        self
+    }
+
+    /// Sets the template whose fields are merged into every template.
+    pub fn template_defaults(mut self, defaults: Template) -> Self {
+        self.spec.template_defaults = Some(Box::new(defaults));
+        self
+    }
+
+    /// Runs the workflow from the referenced `WorkflowTemplate` instead of
+    /// local templates.
+    pub fn workflow_template_ref(mut self, name: &str, cluster_scope: bool) -> Self {
+        self.spec.workflow_template_ref = Some(Box::new(WorkflowTemplateRef {
+            name: Some(String::from(name)),
+            cluster_scope: cluster_scope.then_some(true),
+        }));
+        self
+    }
+
+    /// Sets the name of the ServiceAccount to run all pods of the workflow as.
+    pub fn service_account_name(mut self, name: &str) -> Self {
+        self.spec.service_account_name = Some(String::from(name));
+        self
+    }
+
+    /// Limits the max total parallel pods of the workflow.
+    pub fn parallelism(mut self, parallelism: i32) -> Self {
+        self.spec.parallelism = Some(parallelism);
+        self
+    }
+
+    /// Sets the duration in seconds the workflow is allowed to run for.
+    pub fn active_deadline_seconds(mut self, seconds: i32) -> Self {
+        self.spec.active_deadline_seconds = Some(seconds);
+        self
+    }
+
+    /// Adds a node selector entry applied to all pods of the workflow.
+    pub fn node_selector(mut self, key: &str, value: &str) -> Self {
+        self.spec
+            .node_selector
+            .get_or_insert_with(HashMap::new)
+            .insert(String::from(key), String::from(value));
+        self
+    }
+
+    /// Adds a toleration applied to all pods of the workflow.
+    pub fn toleration(mut self, toleration: corev1::Toleration) -> Self {
+        self.spec
+            .tolerations
+            .get_or_insert_with(Vec::new)
+            .push(toleration);
+        self
+    }
+
+    /// Adds a volume that can be mounted by containers in the workflow.
+    pub fn volume(mut self, volume: corev1::Volume) -> Self {
+        self.spec.volumes.get_or_insert_with(Vec::new).push(volume);
+        self
+    }
+
+    /// Adds an image pull secret used for the pods of the workflow.
+    pub fn image_pull_secret(mut self, name: &str) -> Self {
+        self.spec
+            .image_pull_secrets
+            .get_or_insert_with(Vec::new)
+            .push(corev1::LocalObjectReference {
+                name: String::from(name),
+            });
+        self
+    }
+
+    /// Adds a lifecycle hook to the workflow (e.g. `exit`).
+    pub fn hook(mut self, name: &str, hook: LifecycleHook) -> Self {
+        self.spec
+            .hooks
+            .get_or_insert_with(HashMap::new)
+            .insert(String::from(name), hook);
+        self
+    }
+
+    /// Sets the default `retry_strategy` of the workflow.
+    pub fn retry_strategy(mut self, strategy: RetryStrategy) -> Self {
+        self.spec.retry_strategy = Some(Box::new(strategy));
+        self
+    }
+
+    /// Sets the strategy used to garbage collect completed pods.
+    pub fn pod_gc(mut self, pod_gc: PodGC) -> Self {
+        self.spec.pod_gc = Some(Box::new(pod_gc));
+        self
+    }
+
+    /// Sets the strategy used to delete the workflow once completed.
+    pub fn ttl_strategy(mut self, ttl_strategy: TTLStrategy) -> Self {
+        self.spec.ttl_strategy = Some(Box::new(ttl_strategy));
+        self
+    }
+
+    /// Returns a `WorkflowSpec` that uses this `WorkflowSpecBuilder` options.
+    pub fn build(self) -> Result<WorkflowSpec, BuildError> {
+        let mut spec = self.spec;
+        let templates = self
+            .templates
+            .into_iter()
+            .map(TemplateBuilder::build)
+            .collect::<Result<Vec<Template>, BuildError>>()?;
+
+        check_unique(
+            "template",
+            templates.iter().filter_map(|t| t.name.as_deref()),
+        )?;
+        if let Some(Arguments {
+            parameters: Some(params),
+            ..
+        }) = spec.arguments.as_deref()
+        {
+            check_unique("workflow parameter", params.iter().map(|p| p.name.as_str()))?;
+        }
+
+        // Templates come from the referenced WorkflowTemplate, so none of the
+        // template references can be checked here.
+        if spec.workflow_template_ref.is_none() {
+            let entrypoint = spec.entrypoint.as_deref().ok_or(BuildError::MissingField {
+                kind: "WorkflowSpec",
+                field: "entrypoint",
+            })?;
+
+            let tasks = templates
+                .iter()
+                .filter_map(|t| t.dag.as_deref())
+                .flat_map(|dag| dag.tasks.iter())
+                .filter_map(|task| task.template.as_deref());
+            let steps = templates
+                .iter()
+                .filter_map(|t| t.steps.as_ref())
+                .flatten()
+                .flatten()
+                .filter_map(|step| step.template.as_deref());
+
+            for name in std::iter::once(entrypoint)
+                .chain(spec.on_exit.as_deref())
+                .chain(tasks)
+                .chain(steps)
+            {
+                if !templates.iter().any(|t| t.name.as_deref() == Some(name)) {
+                    return Err(BuildError::UnknownTemplate(String::from(name)));
+                }
+            }
+        }
+
+        if !templates.is_empty() {
+            spec.templates = Some(templates);
+        }
+        Ok(spec)
+    }
+}
+
+/// A `WorkflowBuilder` can be used to create a `Workflow` with custom options.
+///
+/// The spec related options are the same as the ones of `WorkflowSpecBuilder`.
+#[derive(Debug, Default)]
+pub struct WorkflowBuilder {
+    metadata: ObjectMeta,
+    spec: WorkflowSpecBuilder,
+}
+
+impl Workflow {
+    /// Creates a `WorkflowBuilder` to build the `Workflow`.
+    ///
+    /// This is the same as `WorkflowBuilder::new()`.
+    pub fn builder() -> WorkflowBuilder {
+        WorkflowBuilder::new()
+    }
+}
+
+impl WorkflowBuilder {
+    /// Constructs a new `WorkflowBuilder`.
+    pub fn new() -> Self {
+        WorkflowBuilder {
+            ..Default::default()
+        }
+    }
+
+    /// Sets the `name` of the workflow.
+    pub fn name(mut self, name: &str) -> Self {
+        self.metadata.name = Some(String::from(name));
+        self
+    }
+
+    /// Sets the prefix used by the server to generate a unique name.
+    pub fn generate_name(mut self, prefix: &str) -> Self {
+        self.metadata.generate_name = Some(String::from(prefix));
+        self
+    }
+
+    /// Sets the `namespace` of the workflow.
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.metadata.namespace = Some(String::from(namespace));
+        self
+    }
+
+    /// Adds a label to the workflow.
+    pub fn label(mut self, key: &str, value: &str) -> Self {
+        self.metadata
+            .labels
+            .get_or_insert_with(Default::default)
+            .insert(String::from(key), String::from(value));
+        self
+    }
+
+    /// Adds an annotation to the workflow.
+    pub fn annotation(mut self, key: &str, value: &str) -> Self {
+        self.metadata
+            .annotations
+            .get_or_insert_with(Default::default)
+            .insert(String::from(key), String::from(value));
+        self
+    }
+
+    pub fn entrypoint(mut self, template: &str) -> Self {
+        self.spec = self.spec.entrypoint(template);
+        self
+    }
+
+    pub fn on_exit(mut self, template: &str) -> Self {
+        self.spec = self.spec.on_exit(template);
+        self
+    }
+
+    pub fn parameter(mut self, name: &str, value: &str) -> Self {
+        self.spec = self.spec.parameter(name, value);
+        self
+    }
+
+    pub fn argument(mut self, param: Parameter) -> Self {
+        self.spec = self.spec.argument(param);
+        self
+    }
+
+    pub fn artifact(mut self, artifact: Artifact) -> Self {
+        self.spec = self.spec.artifact(artifact);
+        self
+    }
+
+    pub fn template<T: Into<TemplateBuilder>>(mut self, template: T) -> Self {
+        self.spec = self.spec.template(template);
+        self
+    }
+
+    pub fn template_defaults(mut self, defaults: Template) -> Self {
+        self.spec = self.spec.template_defaults(defaults);
+        self
+    }
+
+    pub fn workflow_template_ref(mut self, name: &str, cluster_scope: bool) -> Self {
+        self.spec = self.spec.workflow_template_ref(name, cluster_scope);
+        self
+    }
+
+    pub fn service_account_name(mut self, name: &str) -> Self {
+        self.spec = self.spec.service_account_name(name);
+        self
+    }
+
+    pub fn parallelism(mut self, parallelism: i32) -> Self {
+        self.spec = self.spec.parallelism(parallelism);
+        self
+    }
+
+    pub fn active_deadline_seconds(mut self, seconds: i32) -> Self {
+        self.spec = self.spec.active_deadline_seconds(seconds);
+        self
+    }
+
+    pub fn node_selector(mut self, key: &str, value: &str) -> Self {
+        self.spec = self.spec.node_selector(key, value);
+        self
+    }
+
+    pub fn toleration(mut self, toleration: corev1::Toleration) -> Self {
+        self.spec = self.spec.toleration(toleration);
+        self
+    }
+
+    pub fn volume(mut self, volume: corev1::Volume) -> Self {
+        self.spec = self.spec.volume(volume);
+        self
+    }
+
+    pub fn image_pull_secret(mut self, name: &str) -> Self {
+        self.spec = self.spec.image_pull_secret(name);
+        self
+    }
+
+    pub fn hook(mut self, name: &str, hook: LifecycleHook) -> Self {
+        self.spec = self.spec.hook(name, hook);
+        self
+    }
+
+    pub fn retry_strategy(mut self, strategy: RetryStrategy) -> Self {
+        self.spec = self.spec.retry_strategy(strategy);
+        self
+    }
+
+    pub fn pod_gc(mut self, pod_gc: PodGC) -> Self {
+        self.spec = self.spec.pod_gc(pod_gc);
+        self
+    }
+
+    pub fn ttl_strategy(mut self, ttl_strategy: TTLStrategy) -> Self {
+        self.spec = self.spec.ttl_strategy(ttl_strategy);
+        self
+    }
+
+    /// Returns a `Workflow` that uses this `WorkflowBuilder` options.
+    pub fn build(self) -> Result<Workflow, BuildError> {
+        let has_name = self.metadata.name.as_deref().is_some_and(|n| !n.is_empty());
+        let has_prefix = self
+            .metadata
+            .generate_name
+            .as_deref()
+            .is_some_and(|n| !n.is_empty());
+        if !has_name && !has_prefix {
+            return Err(BuildError::MissingField {
+                kind: "Workflow",
+                field: "metadata.name",
+            });
+        }
+
+        let mut workflow = Workflow::new(self.metadata, self.spec.build()?);
+        workflow.api_version = Some(String::from(API_VERSION));
+        workflow.kind = Some(String::from("Workflow"));
+        Ok(workflow)
+    }
+}
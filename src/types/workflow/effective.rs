@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+use super::WorkflowSpec;
+use crate::types::merge::{merge, pod_merge_key};
+use crate::types::template::{ExecutorConfig, Template};
+use crate::types::Metadata;
+
+/// `EffectiveTemplate` is a template with the settings it inherits from
+/// `WorkflowSpec::template_defaults` and from the workflow-level settings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EffectiveTemplate {
+    /// The template, with its inherited settings set.
+    pub template: Template,
+    /// The pod spec patches applied to the pods of the template, in the
+    /// order the controller applies them: the workflow one, then the
+    /// template one.
+    pub pod_spec_patches: Vec<String>,
+}
+
+impl WorkflowSpec {
+    /// Returns the effective settings of `template`, following the
+    /// precedence rules of the controller:
+    ///
+    /// - fields set by the template take precedence over the ones of
+    ///   `template_defaults`, which are merged into it field by field.
+    /// - `pod_metadata` labels and annotations of the workflow are added when
+    ///   the template does not set the same key.
+    /// - `host_aliases` of the workflow come first, followed by the ones of
+    ///   the template.
+    /// - `node_selector` and `tolerations` of the workflow apply when the
+    ///   template sets none; they are not merged.
+    /// - `affinity`, `security_context`, `retry_strategy`,
+    ///   `service_account_name`, `automount_service_account_token`,
+    ///   `executor`, `scheduler_name`, `priority_class_name` and `priority`
+    ///   of the workflow apply when the template does not set them.
+    /// - both the workflow and the template `pod_spec_patch` are applied.
+    pub fn effective_template(&self, template: &Template) -> EffectiveTemplate {
+        let mut effective = match self.template_defaults.as_deref() {
+            Some(defaults) => with_defaults(template, defaults),
+            None => template.clone(),
+        };
+
+        if let Some(pod_metadata) = self.pod_metadata.as_deref() {
+            let metadata = effective
+                .metadata
+                .get_or_insert_with(|| Box::new(Metadata::new(None, None)));
+            extend_missing(&mut metadata.labels, &pod_metadata.labels);
+            extend_missing(&mut metadata.annotations, &pod_metadata.annotations);
+        }
+        if let Some(aliases) = &self.host_aliases {
+            let template = effective.host_aliases.take().unwrap_or_default();
+            effective.host_aliases = Some(aliases.iter().cloned().chain(template).collect());
+        }
+
+        if effective
+            .node_selector
+            .as_ref()
+            .is_none_or(HashMap::is_empty)
+        {
+            effective.node_selector = self.node_selector.clone();
+        }
+        if effective.tolerations.as_ref().is_none_or(Vec::is_empty) {
+            effective.tolerations = self.tolerations.clone();
+        }
+        inherit(&mut effective.affinity, &self.affinity);
+        inherit(&mut effective.security_context, &self.security_context);
+        inherit(&mut effective.retry_strategy, &self.retry_strategy);
+        inherit(
+            &mut effective.service_account_name,
+            &self.service_account_name,
+        );
+        inherit(
+            &mut effective.automount_service_account_token,
+            &self.automount_service_account_token,
+        );
+        inherit(&mut effective.scheduler_name, &self.scheduler_name);
+        inherit(
+            &mut effective.priority_class_name,
+            &self.pod_priority_class_name,
+        );
+        inherit(&mut effective.priority, &self.pod_priority);
+        if effective.executor.is_none() {
+            effective.executor = self.executor.as_deref().map(|e| {
+                Box::new(ExecutorConfig {
+                    service_account_name: e.service_account_name.clone(),
+                })
+            });
+        }
+
+        let pod_spec_patches = [&self.pod_spec_patch, &effective.pod_spec_patch]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+
+        EffectiveTemplate {
+            template: effective,
+            pod_spec_patches,
+        }
+    }
+
+    /// Returns the effective settings of the template `name`. See
+    /// `WorkflowSpec::effective_template()`.
+    pub fn effective_template_by_name(&self, name: &str) -> Option<EffectiveTemplate> {
+        self.templates
+            .iter()
+            .flatten()
+            .find(|t| t.name.as_deref() == Some(name))
+            .map(|t| self.effective_template(t))
+    }
+}
+
+/// Merges `template` into `defaults`, the fields of `template` taking
+/// precedence.
+fn with_defaults(template: &Template, defaults: &Template) -> Template {
+    let mut value = serde_json::to_value(defaults).expect("template serializes to JSON");
+    let overlay = serde_json::to_value(template).expect("template serializes to JSON");
+    merge(&mut value, overlay, &pod_merge_key);
+    serde_json::from_value(value).expect("merged template deserializes")
+}
+
+fn inherit<T: Clone>(field: &mut Option<T>, workflow: &Option<T>) {
+    if field.is_none() {
+        *field = workflow.clone();
+    }
+}
+
+fn extend_missing(
+    map: &mut Option<HashMap<String, String>>,
+    workflow: &Option<HashMap<String, String>>,
+) {
+    if let Some(workflow) = workflow {
+        let map = map.get_or_insert_with(HashMap::new);
+        for (key, value) in workflow {
+            map.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{NodeGraph, NodeStatus, Workflow, WorkflowStatus, WorkflowStep};
+use crate::types::template::{ContinueOn, Template};
+
+/// `FailureReport` lists the nodes at the root of the failure of a workflow.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FailureReport {
+    /// Name of the workflow.
+    #[serde(rename = "workflow")]
+    pub workflow: String,
+
+    /// Phase of the workflow: `Failed` or `Error`.
+    #[serde(rename = "phase")]
+    pub phase: String,
+
+    /// Message of the workflow, e.g. `child 'x' failed`.
+    #[serde(rename = "message", skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// The failed nodes, in the order they finished.
+    #[serde(rename = "failures")]
+    pub failures: Vec<NodeFailure>,
+}
+
+/// `NodeFailure` describes a failed node of a `FailureReport`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeFailure {
+    /// ID of the node, which is the name of its pod.
+    #[serde(rename = "id")]
+    pub id: String,
+
+    /// Name of the node, e.g. `my-workflow[1].build(0:linux)`.
+    #[serde(rename = "name")]
+    pub name: String,
+
+    /// Display name of the node, e.g. `build(0:linux)`.
+    #[serde(rename = "displayName", skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+
+    /// Phase of the node: `Failed` or `Error`.
+    #[serde(rename = "phase")]
+    pub phase: String,
+
+    /// Template run by the node. For a `template_ref`, the name of the
+    /// `WorkflowTemplate` and of the template, as `<name>/<template>`.
+    #[serde(rename = "template", skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    #[serde(rename = "message", skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// Exit code of the main container.
+    #[serde(rename = "exitCode", skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<String>,
+
+    /// Name of the Kubernetes node the pod ran on.
+    #[serde(rename = "hostNodeName", skip_serializing_if = "Option::is_none")]
+    pub host_node_name: Option<String>,
+
+    /// Number of times the node was retried before failing for good.
+    #[serde(rename = "retries")]
+    pub retries: usize,
+
+    #[serde(rename = "startedAt", skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+
+    #[serde(rename = "finishedAt", skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
+
+impl fmt::Display for FailureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Workflow {} {}", self.workflow, self.phase)?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        for failure in &self.failures {
+            write!(f, "\n- {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for NodeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.display_name.as_deref().unwrap_or(&self.name);
+        write!(f, "{} ({}) {}", name, self.id, self.phase)?;
+        if let Some(template) = &self.template {
+            write!(f, ", template {}", template)?;
+        }
+        if let Some(exit_code) = &self.exit_code {
+            write!(f, ", exit code {}", exit_code)?;
+        }
+        if let Some(host) = &self.host_node_name {
+            write!(f, ", on {}", host)?;
+        }
+        match self.retries {
+            0 => {}
+            1 => write!(f, ", after 1 retry")?,
+            n => write!(f, ", after {} retries", n)?,
+        }
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+impl Workflow {
+    /// Returns the nodes at the root of the failure of the workflow, or
+    /// `None` unless it ended `Failed` or `Error`.
+    ///
+    /// Left out are the attempts followed by another attempt, and the nodes
+    /// whose failure was tolerated by the `ContinueOn` of their step or
+    /// task, or of a step or task holding them. Of the other failed nodes,
+    /// these are the pods, or when no pod is left, the deepest nodes, e.g.
+    /// HTTP, plugin or suspend nodes: those holding no other failed node.
+    ///
+    /// Steps and tasks run through a `template_ref` are found in the
+    /// `stored_templates` of the status.
+    pub fn failure_report(&self) -> Option<FailureReport> {
+        let status = self.status.as_deref()?;
+        let phase = status.phase.as_deref()?;
+        if phase != "Failed" && phase != "Error" {
+            return None;
+        }
+
+        let graph = status.node_graph();
+        let failed = |n: &NodeStatus| {
+            if !matches!(n.phase.as_deref(), Some("Failed" | "Error")) {
+                return false;
+            }
+            if let Some(retry) = graph.retry_of(&n.id) {
+                let last = retry.last_attempt().map(|n| n.id.as_str());
+                if last != Some(n.id.as_str()) {
+                    return false;
+                }
+            }
+            !self.is_tolerated(status, &graph, n)
+        };
+        let mut nodes: Vec<&NodeStatus> = graph
+            .leaf_pods()
+            .into_iter()
+            .filter(|n| failed(n))
+            .collect();
+        if nodes.is_empty() {
+            let all: Vec<&NodeStatus> = graph
+                .nodes()
+                .into_iter()
+                .filter(|n| !matches!(n.r#type.as_str(), "Retry" | "StepGroup" | "TaskGroup"))
+                .filter(|n| failed(n))
+                .collect();
+            let holders: Vec<&str> = all
+                .iter()
+                .filter_map(|n| n.boundary_id.as_deref())
+                .collect();
+            nodes = all
+                .into_iter()
+                .filter(|n| !holders.contains(&n.id.as_str()))
+                .collect();
+        }
+
+        let mut failures = Vec::new();
+        for node in nodes {
+            let retry = graph.retry_of(&node.id);
+            let template = match node.template_ref.as_deref() {
+                Some(reference) => Some(format!(
+                    "{}/{}",
+                    reference.name.as_deref().unwrap_or_default(),
+                    reference.template.as_deref().unwrap_or_default()
+                )),
+                None => node.template_name.clone(),
+            };
+            failures.push(NodeFailure {
+                id: node.id.clone(),
+                name: node.name.clone(),
+                display_name: node.display_name.clone(),
+                phase: node.phase.clone().unwrap_or_default(),
+                template,
+                message: node.message.clone(),
+                exit_code: node.outputs.as_deref().and_then(|o| o.exit_code.clone()),
+                host_node_name: node.host_node_name.clone(),
+                retries: retry.map_or(0, |r| r.retries()),
+                started_at: node.started_at.clone(),
+                finished_at: node.finished_at.clone(),
+            });
+        }
+        failures.sort_by(|a, b| (&a.finished_at, &a.name).cmp(&(&b.finished_at, &b.name)));
+
+        Some(FailureReport {
+            workflow: self.metadata.name.clone().unwrap_or_default(),
+            phase: String::from(phase),
+            message: status.message.clone(),
+            failures,
+        })
+    }
+
+    /// Returns whether the failure of `node` is tolerated by the
+    /// `ContinueOn` of the step or task running it, or of a step or task
+    /// holding it.
+    fn is_tolerated(
+        &self,
+        status: &WorkflowStatus,
+        graph: &NodeGraph<'_>,
+        node: &NodeStatus,
+    ) -> bool {
+        let Some(phase) = node.phase.as_deref() else {
+            return false;
+        };
+        let mut seen = HashSet::new();
+        let mut current = Some(node);
+        while let Some(node) = current {
+            if !seen.insert(node.id.as_str()) {
+                break;
+            }
+            // Attempts take the step or task of their Retry node.
+            let call = graph.retry_of(&node.id).map_or(node, |r| r.node);
+            let holder = graph.boundary(&call.id);
+            let template = holder.and_then(|h| self.template_of(status, h));
+            let name = call.display_name.as_deref().unwrap_or_default();
+            // Loop expansions are named `<name>(<index>:<item>)`.
+            let name = name.split('(').next().unwrap_or_default();
+            if let Some(continue_on) = template.and_then(|t| continue_on(t, name)) {
+                let tolerated = match phase {
+                    "Failed" => continue_on.failed == Some(true),
+                    "Error" => continue_on.error == Some(true),
+                    _ => false,
+                };
+                if tolerated {
+                    return true;
+                }
+            }
+            current = holder;
+        }
+        false
+    }
+
+    /// Returns the template run by `node`: the template its `template_ref`
+    /// points to, stored in `stored_templates`, or the template named by
+    /// its `template_name` within its `template_scope`.
+    fn template_of<'w>(
+        &'w self,
+        status: &'w WorkflowStatus,
+        node: &NodeStatus,
+    ) -> Option<&'w Template> {
+        let stored = status.stored_templates.as_ref();
+        if let Some(reference) = node.template_ref.as_deref() {
+            let scope = match reference.cluster_scope {
+                Some(true) => "cluster",
+                _ => "namespaced",
+            };
+            let name = reference.name.as_deref().unwrap_or_default();
+            let template = reference.template.as_deref()?;
+            let key = format!("{}/{}/{}", scope, name, template);
+            return stored.and_then(|s| s.get(&key)).or_else(|| {
+                stored
+                    .into_iter()
+                    .flat_map(|s| s.values())
+                    .find(|t| t.name.as_deref() == Some(template))
+            });
+        }
+
+        let name = node.template_name.as_deref()?;
+        // Templates of a referenced `WorkflowTemplate` are stored under its
+        // scope, e.g. `namespaced/my-template`.
+        let scoped = node
+            .template_scope
+            .as_deref()
+            .filter(|s| !s.starts_with("local/"))
+            .and_then(|scope| stored?.get(&format!("{}/{}", scope, name)));
+        scoped.or_else(|| self.template(status, name))
+    }
+
+    /// Returns the template `name` of the workflow, or of the
+    /// `WorkflowTemplate` it was submitted from.
+    fn template<'w>(&'w self, status: &'w WorkflowStatus, name: &str) -> Option<&'w Template> {
+        let stored = status
+            .stored_workflow_template_spec
+            .as_deref()
+            .and_then(|s| s.templates.as_ref());
+        self.spec
+            .templates
+            .iter()
+            .flatten()
+            .chain(stored.into_iter().flatten())
+            .chain(status.stored_templates.iter().flat_map(|t| t.values()))
+            .find(|t| t.name.as_deref() == Some(name))
+    }
+}
+
+/// Returns the `ContinueOn` of the step or task `name` of a template.
+fn continue_on<'t>(template: &'t Template, name: &str) -> Option<&'t ContinueOn> {
+    let steps = template.steps.iter().flatten().flatten();
+    let step = steps
+        .filter(|s: &&WorkflowStep| s.name.as_deref() == Some(name))
+        .find_map(|s| s.continue_on.as_deref());
+    let tasks = template.dag.iter().flat_map(|d| d.tasks.iter());
+    step.or_else(|| {
+        tasks
+            .filter(|t| t.name == name)
+            .find_map(|t| t.continue_on.as_deref())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn node(id: &str, r#type: &str, phase: &str, boundary: &str, children: &[&str]) -> Value {
+        json!({
+            "id": id,
+            "name": id,
+            "displayName": id.rsplit('.').next(),
+            "type": r#type,
+            "phase": phase,
+            "boundaryID": boundary,
+            "children": children,
+            "finishedAt": "2026-01-01T00:00:00Z",
+        })
+    }
+
+    fn workflow(templates: Value, nodes: Vec<Value>, stored: Value) -> Workflow {
+        let nodes: serde_json::Map<String, Value> = nodes
+            .into_iter()
+            .map(|n| (n["id"].as_str().unwrap().to_string(), n))
+            .collect();
+        serde_json::from_value(json!({
+            "metadata": {"name": "wf"},
+            "spec": {"templates": templates},
+            "status": {"phase": "Failed", "nodes": nodes, "storedTemplates": stored},
+        }))
+        .unwrap()
+    }
+
+    fn failures(workflow: &Workflow) -> Vec<(String, usize)> {
+        let report = workflow.failure_report().unwrap();
+        report
+            .failures
+            .into_iter()
+            .map(|f| (f.id, f.retries))
+            .collect()
+    }
+
+    #[test]
+    fn retried_failures() {
+        let templates = json!([{"name": "main", "steps": [
+            [{"name": "a", "template": "work"}],
+            [{"name": "b", "template": "work"}]
+        ]}]);
+        let mut nodes = vec![
+            node("wf", "Steps", "Failed", "", &["wf[0]"]),
+            node("wf[0]", "StepGroup", "Succeeded", "wf", &["wf.a"]),
+            node("wf.a", "Retry", "Succeeded", "wf", &["wf.a(0)", "wf.a(1)"]),
+            node("wf.a(0)", "Pod", "Failed", "wf", &[]),
+            node("wf.a(1)", "Pod", "Succeeded", "wf", &["wf[1]"]),
+            node("wf[1]", "StepGroup", "Failed", "wf", &["wf.b"]),
+            node("wf.b", "Retry", "Failed", "wf", &["wf.b(0)", "wf.b(1)"]),
+            node("wf.b(0)", "Pod", "Failed", "wf", &[]),
+            node("wf.b(1)", "Pod", "Failed", "wf", &[]),
+        ];
+        nodes[0]["templateName"] = json!("main");
+        let workflow = workflow(templates, nodes, json!({}));
+        assert_eq!(failures(&workflow), [(String::from("wf.b(1)"), 1)]);
+    }
+
+    #[test]
+    fn non_pod_failures() {
+        let templates = json!([{"name": "main", "steps": [
+            [{"name": "a", "template": "work"}],
+            [{"name": "call", "template": "http"}]
+        ]}]);
+        let mut nodes = vec![
+            node("wf", "Steps", "Failed", "", &["wf[0]"]),
+            node("wf[0]", "StepGroup", "Succeeded", "wf", &["wf.a"]),
+            node("wf.a", "Retry", "Succeeded", "wf", &["wf.a(0)", "wf.a(1)"]),
+            node("wf.a(0)", "Pod", "Failed", "wf", &[]),
+            node("wf.a(1)", "Pod", "Succeeded", "wf", &["wf[1]"]),
+            node("wf[1]", "StepGroup", "Failed", "wf", &["wf.call"]),
+            node("wf.call", "HTTP", "Failed", "wf", &[]),
+        ];
+        nodes[0]["templateName"] = json!("main");
+        let workflow = workflow(templates, nodes, json!({}));
+        assert_eq!(failures(&workflow), [(String::from("wf.call"), 0)]);
+    }
+
+    #[test]
+    fn tolerated_failures() {
+        let templates = json!([{"name": "main", "steps": [[
+            {"name": "a", "template": "work", "continueOn": {"failed": true}},
+            {"name": "b", "template": "work"}
+        ]]}]);
+        let mut nodes = vec![
+            node("wf", "Steps", "Failed", "", &["wf[0]"]),
+            node("wf[0]", "StepGroup", "Failed", "wf", &["wf.a", "wf.b"]),
+            node("wf.a", "Pod", "Failed", "wf", &[]),
+            node("wf.b", "Pod", "Failed", "wf", &[]),
+        ];
+        nodes[0]["templateName"] = json!("main");
+        let workflow = workflow(templates, nodes, json!({}));
+        assert_eq!(failures(&workflow), [(String::from("wf.b"), 0)]);
+    }
+
+    #[test]
+    fn tolerated_in_template_ref() {
+        let templates = json!([{"name": "main", "steps": [[
+            {"name": "lib", "templateRef": {"name": "shared", "template": "pipeline"}}
+        ]]}]);
+        let stored = json!({"namespaced/shared/pipeline": {"name": "pipeline", "steps": [[
+            {"name": "a", "template": "work", "continueOn": {"failed": true}},
+            {"name": "call", "template": "http"}
+        ]]}});
+        let mut nodes = vec![
+            node("wf", "Steps", "Failed", "", &["wf[0]"]),
+            node("wf[0]", "StepGroup", "Failed", "wf", &["wf.lib"]),
+            node("wf.lib", "Steps", "Failed", "wf", &["wf.lib[0]"]),
+            node(
+                "wf.lib[0]",
+                "StepGroup",
+                "Failed",
+                "wf.lib",
+                &["wf.lib.a", "wf.lib.call"],
+            ),
+            node("wf.lib.a", "Pod", "Failed", "wf.lib", &[]),
+            node("wf.lib.call", "HTTP", "Failed", "wf.lib", &[]),
+        ];
+        nodes[0]["templateName"] = json!("main");
+        nodes[2]["templateRef"] = json!({"name": "shared", "template": "pipeline"});
+        let workflow = workflow(templates, nodes, stored);
+        assert_eq!(failures(&workflow), [(String::from("wf.lib.call"), 0)]);
+    }
+
+    #[test]
+    fn boundary_cycle() {
+        let nodes = vec![
+            node("a", "Steps", "Failed", "b", &[]),
+            node("b", "Steps", "Failed", "a", &[]),
+            node("a.pod", "Pod", "Failed", "a", &[]),
+        ];
+        let workflow = workflow(json!([]), nodes, json!({}));
+        assert_eq!(failures(&workflow), [(String::from("a.pod"), 0)]);
+    }
+}
@@ -21,7 +21,7 @@ pub struct WorkflowList {
     #[serde(rename = "kind", skip_serializing_if = "Option::is_none")]
     pub kind: Option<String>,
 
-    #[serde(rename = "metadata")]
+    #[serde(rename = "metadata", default)]
     pub metadata: Box<metav1::ListMeta>,
 }
 
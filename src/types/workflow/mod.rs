@@ -4,15 +4,24 @@ pub use self::arguments::Arguments;
 mod artifact_repository_ref;
 pub use self::artifact_repository_ref::ArtifactRepositoryRef;
 
+mod builder;
+pub use self::builder::{WorkflowBuilder, WorkflowSpecBuilder};
+
 mod condition;
 pub use self::condition::Condition;
 
 mod create_request;
 pub use self::create_request::CreateRequest;
 
+mod effective;
+pub use self::effective::EffectiveTemplate;
+
 mod executor_config;
 pub use self::executor_config::ExecutorConfig;
 
+mod failure;
+pub use self::failure::{FailureReport, NodeFailure};
+
 mod label_value_from;
 pub use label_value_from::LabelValueFrom;
 
@@ -25,9 +34,14 @@ pub use self::list::WorkflowList;
 mod metadata;
 pub use self::metadata::WorkflowMetadata;
 
+mod nodes;
+
 mod node_flag;
 pub use self::node_flag::NodeFlag;
 
+mod node_graph;
+pub use self::node_graph::{NodeGraph, RetryGroup};
+
 mod node_status;
 pub use self::node_status::NodeStatus;
 
@@ -37,24 +51,65 @@ pub use self::node_synchronization_status::NodeSynchronizationStatus;
 mod parameter;
 pub use self::parameter::Parameter;
 
+mod params;
+#[doc(hidden)]
+pub use self::params::{json_from_param, json_to_param, parse_param};
+pub use self::params::{ParamValue, WorkflowParams};
+#[cfg(feature = "derive")]
+pub use argoflows_derive::WorkflowParams;
+
+mod pod;
+pub use self::pod::DEFAULT_EXECUTOR_IMAGE;
+
 mod pod_gc;
 pub use self::pod_gc::PodGC;
 
+mod references;
+
+mod render;
+pub use self::render::{RenderedTemplate, Renderer};
+
+mod resume_request;
+pub use self::resume_request::ResumeRequest;
+
+mod retry_request;
+pub use self::retry_request::RetryRequest;
+
+mod simulate;
+pub use self::simulate::{Leaf, Outcome, Simulator};
+
 mod spec;
 pub use self::spec::WorkflowSpec;
 
+mod stop_request;
+pub use self::stop_request::StopRequest;
+
+mod suspend_request;
+pub use self::suspend_request::SuspendRequest;
+
 mod template_ref;
 pub use self::template_ref::TemplateRef;
 
+mod terminate_request;
+pub use self::terminate_request::TerminateRequest;
+
+mod timing;
+pub use self::timing::{NodeTiming, ParallelismSample, TimingReport};
+
 mod ttl_strategy;
 pub use self::ttl_strategy::TTLStrategy;
 
+mod validate;
+
 mod value_from;
 pub use self::value_from::ValueFrom;
 
 mod volume_claim_gc;
 pub use self::volume_claim_gc::VolumeClaimGC;
 
+mod watch_event;
+pub use self::watch_event::WatchEvent;
+
 mod workflow;
 pub use self::workflow::Workflow;
 
@@ -0,0 +1,380 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use super::{NodeStatus, WorkflowStatus};
+
+/// `NodeGraph` is a view of the nodes of a `WorkflowStatus`, following the
+/// links between them: `children`, `outbound_nodes` and `boundary_id`.
+///
+/// Nodes listed by a scan of the graph are returned in the order they
+/// started, nodes without a start time coming first, and then by name.
+#[derive(Clone, Debug)]
+pub struct NodeGraph<'a> {
+    nodes: HashMap<&'a str, &'a NodeStatus>,
+    parents: HashMap<&'a str, Vec<&'a str>>,
+}
+
+/// `RetryGroup` is a Retry node with the attempts it made.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryGroup<'a> {
+    /// The Retry node.
+    pub node: &'a NodeStatus,
+    /// The attempts, in the order they were made.
+    pub attempts: Vec<&'a NodeStatus>,
+}
+
+impl<'a> RetryGroup<'a> {
+    /// Returns the last attempt, whose phase is the phase of the Retry node.
+    pub fn last_attempt(&self) -> Option<&'a NodeStatus> {
+        self.attempts.last().copied()
+    }
+
+    /// Returns the number of retries, i.e. the attempts after the first one.
+    pub fn retries(&self) -> usize {
+        self.attempts.len().saturating_sub(1)
+    }
+}
+
+impl WorkflowStatus {
+    /// Returns a `NodeGraph` over the nodes of the status.
+    pub fn node_graph(&self) -> NodeGraph<'_> {
+        let mut graph = NodeGraph {
+            nodes: HashMap::new(),
+            parents: HashMap::new(),
+        };
+        if let Some(nodes) = &self.nodes {
+            graph = NodeGraph::new(nodes);
+        }
+        graph
+    }
+}
+
+impl<'a> NodeGraph<'a> {
+    /// Constructs a new `NodeGraph` over `nodes`, keyed by node ID.
+    pub fn new(nodes: &'a HashMap<String, NodeStatus>) -> Self {
+        let mut parents: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (id, node) in nodes {
+            for child in node.children.iter().flatten() {
+                if !nodes.contains_key(child) {
+                    continue;
+                }
+                let ids = parents.entry(child.as_str()).or_default();
+                if !ids.contains(&id.as_str()) {
+                    ids.push(id.as_str());
+                }
+            }
+        }
+        let nodes: HashMap<&str, &NodeStatus> =
+            nodes.iter().map(|(id, node)| (id.as_str(), node)).collect();
+        for ids in parents.values_mut() {
+            ids.sort_by_key(|id| order(nodes[id]));
+        }
+        NodeGraph { nodes, parents }
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns whether the graph has no node.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the node `id`.
+    pub fn get(&self, id: &str) -> Option<&'a NodeStatus> {
+        self.nodes.get(id).copied()
+    }
+
+    /// Returns all the nodes.
+    pub fn nodes(&self) -> Vec<&'a NodeStatus> {
+        sorted(self.nodes.values().copied())
+    }
+
+    /// Returns the root node of the workflow, whose ID is the workflow name.
+    /// When the status does not hold it, returns the first node without
+    /// parent which is not a hook.
+    pub fn root(&self) -> Option<&'a NodeStatus> {
+        self.nodes
+            .values()
+            .find(|n| n.id == n.name && self.is_root(&n.id))
+            .copied()
+            .or_else(|| {
+                self.roots()
+                    .into_iter()
+                    .find(|n| n.node_flag.as_ref().and_then(|f| f.hooked) != Some(true))
+            })
+    }
+
+    /// Returns the nodes without parent: the root node, and the nodes of
+    /// the exit handler and hooks of the workflow.
+    pub fn roots(&self) -> Vec<&'a NodeStatus> {
+        sorted(self.nodes.values().copied().filter(|n| self.is_root(&n.id)))
+    }
+
+    fn is_root(&self, id: &str) -> bool {
+        !self.parents.contains_key(id)
+    }
+
+    /// Returns the children of the node `id`, in the order it lists them.
+    pub fn children(&self, id: &str) -> Vec<&'a NodeStatus> {
+        let children = self.get(id).and_then(|n| n.children.as_ref());
+        children
+            .into_iter()
+            .flatten()
+            .filter_map(|child| self.get(child))
+            .collect()
+    }
+
+    /// Returns the parents of the node `id`. Steps of a group following
+    /// another one, and DAG tasks depending on others, have several.
+    pub fn parents(&self, id: &str) -> Vec<&'a NodeStatus> {
+        let parents = self.parents.get(id);
+        parents
+            .into_iter()
+            .flatten()
+            .filter_map(|parent| self.get(parent))
+            .collect()
+    }
+
+    /// Returns the nodes the children of the node `id` hang from: the
+    /// `outbound_nodes` of steps and DAG nodes, and the node itself for
+    /// the others.
+    pub fn outbound(&self, id: &str) -> Vec<&'a NodeStatus> {
+        let Some(node) = self.get(id) else {
+            return Vec::new();
+        };
+        match node.outbound_nodes.as_ref() {
+            Some(outbound) if !outbound.is_empty() => {
+                outbound.iter().filter_map(|o| self.get(o)).collect()
+            }
+            _ => vec![node],
+        }
+    }
+
+    /// Returns the node holding the node `id`: the steps or DAG node whose
+    /// template runs it, named by its `boundary_id`.
+    pub fn boundary(&self, id: &str) -> Option<&'a NodeStatus> {
+        self.get(self.get(id)?.boundary_id.as_deref()?)
+    }
+
+    /// Returns the ancestors of the node `id`, nearest first. When
+    /// `boundary` is given, ancestors are not followed past the node
+    /// `boundary`, which is the last one returned; e.g. giving the
+    /// `boundary_id` of a node stops at the steps or DAG node holding it.
+    pub fn ancestors(&self, id: &str, boundary: Option<&str>) -> Vec<&'a NodeStatus> {
+        let mut ancestors = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue: Vec<&str> = vec![id];
+        while !queue.is_empty() {
+            let mut next = Vec::new();
+            for current in queue {
+                for parent in self.parents.get(current).into_iter().flatten() {
+                    if !seen.insert(*parent) {
+                        continue;
+                    }
+                    ancestors.push(self.nodes[parent]);
+                    if Some(*parent) != boundary {
+                        next.push(*parent);
+                    }
+                }
+            }
+            queue = next;
+        }
+        ancestors
+    }
+
+    /// Returns the descendants of the node `id`, following its children.
+    /// As the children of steps and DAG tasks include the steps and tasks
+    /// run after them, these are the nodes which could only run after the
+    /// node `id` started.
+    pub fn descendants(&self, id: &str) -> Vec<&'a NodeStatus> {
+        let mut seen = HashSet::new();
+        let mut stack: Vec<&'a NodeStatus> = self.children(id);
+        let mut descendants = Vec::new();
+        while let Some(node) = stack.pop() {
+            if seen.insert(node.id.as_str()) {
+                descendants.push(node);
+                stack.extend(self.children(&node.id));
+            }
+        }
+        sorted(descendants)
+    }
+
+    /// Returns the nodes in topological order: each node comes after its
+    /// parents, nodes ready at the same time being ordered as in scans.
+    /// Nodes linked in a cycle, which a valid status does not have, are
+    /// left out.
+    pub fn topological(&self) -> impl Iterator<Item = &'a NodeStatus> {
+        let mut pending: HashMap<&str, usize> = self
+            .parents
+            .iter()
+            .map(|(id, parents)| (*id, parents.len()))
+            .collect();
+        let mut ready: BinaryHeap<Reverse<(Order<'a>, &'a str)>> = self
+            .nodes
+            .iter()
+            .filter(|(id, _)| self.is_root(id))
+            .map(|(id, node)| Reverse((order(node), *id)))
+            .collect();
+        let mut sorted = Vec::with_capacity(self.nodes.len());
+        while let Some(Reverse((_, id))) = ready.pop() {
+            let node = self.nodes[id];
+            sorted.push(node);
+            let mut children: Vec<&str> = Vec::new();
+            for child in node.children.iter().flatten() {
+                if self.nodes.contains_key(child.as_str()) && !children.contains(&child.as_str()) {
+                    children.push(child);
+                }
+            }
+            for child in children {
+                let count = pending.get_mut(child).expect("child has parents");
+                *count -= 1;
+                if *count == 0 {
+                    let (id, node) = self.nodes.get_key_value(child).expect("child exists");
+                    ready.push(Reverse((order(node), *id)));
+                }
+            }
+        }
+        sorted.into_iter()
+    }
+
+    /// Returns the nodes with the display name `name`, e.g. the name of a
+    /// step or task.
+    pub fn find_by_display_name(&self, name: &str) -> Vec<&'a NodeStatus> {
+        sorted(
+            self.nodes
+                .values()
+                .copied()
+                .filter(|n| n.display_name.as_deref() == Some(name)),
+        )
+    }
+
+    /// Returns the nodes running the template `name`, declared by the
+    /// workflow or referenced by a `template_ref`.
+    pub fn find_by_template(&self, name: &str) -> Vec<&'a NodeStatus> {
+        sorted(self.nodes.values().copied().filter(|n| {
+            let referenced = n
+                .template_ref
+                .as_deref()
+                .and_then(|r| r.template.as_deref());
+            n.template_name.as_deref() == Some(name) || referenced == Some(name)
+        }))
+    }
+
+    /// Returns the Retry nodes with their attempts.
+    pub fn retries(&self) -> Vec<RetryGroup<'a>> {
+        let nodes = self.nodes.values().copied().filter(|n| n.r#type == "Retry");
+        sorted(nodes)
+            .into_iter()
+            .map(|node| RetryGroup {
+                node,
+                attempts: self.children(&node.id),
+            })
+            .collect()
+    }
+
+    /// Returns the Retry node the node `id` is an attempt of.
+    pub fn retry_of(&self, id: &str) -> Option<RetryGroup<'a>> {
+        let node = self.parents(id).into_iter().find(|p| p.r#type == "Retry")?;
+        Some(RetryGroup {
+            node,
+            attempts: self.children(&node.id),
+        })
+    }
+
+    /// Returns the nodes which ran a pod.
+    pub fn leaf_pods(&self) -> Vec<&'a NodeStatus> {
+        sorted(self.nodes.values().copied().filter(|n| n.r#type == "Pod"))
+    }
+
+    /// Returns the nodes which ran a pod within the node `id`: the pods
+    /// whose boundaries, or the boundaries of their Retry node, include the
+    /// node `id`, and the node itself when it ran a pod.
+    pub fn leaf_pods_within(&self, id: &str) -> Vec<&'a NodeStatus> {
+        let within = |node: &NodeStatus| {
+            let mut seen = HashSet::new();
+            let mut current = Some(node);
+            while let Some(node) = current {
+                if !seen.insert(node.id.as_str()) {
+                    break;
+                }
+                if node.id == id || self.retry_of(&node.id).is_some_and(|r| r.node.id == id) {
+                    return true;
+                }
+                current = node.boundary_id.as_deref().and_then(|b| self.get(b));
+            }
+            false
+        };
+        sorted(
+            self.nodes
+                .values()
+                .copied()
+                .filter(|n| n.r#type == "Pod" && within(n)),
+        )
+    }
+}
+
+/// Key ordering the nodes of scans.
+type Order<'a> = (Option<&'a str>, &'a str);
+
+fn order(node: &NodeStatus) -> Order<'_> {
+    (node.started_at.as_deref(), node.name.as_str())
+}
+
+fn sorted<'a>(nodes: impl IntoIterator<Item = &'a NodeStatus>) -> Vec<&'a NodeStatus> {
+    let mut nodes: Vec<&NodeStatus> = nodes.into_iter().collect();
+    nodes.sort_by_key(|n| order(n));
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, r#type: &str, boundary: Option<&str>) -> (String, NodeStatus) {
+        let node = NodeStatus {
+            id: String::from(id),
+            name: String::from(id),
+            r#type: String::from(r#type),
+            boundary_id: boundary.map(String::from),
+            ..Default::default()
+        };
+        (String::from(id), node)
+    }
+
+    #[test]
+    fn leaf_pods_within() {
+        let nodes = HashMap::from([
+            node("wf", "Steps", None),
+            node("a", "Steps", Some("wf")),
+            node("a.pod", "Pod", Some("a")),
+            node("b.pod", "Pod", Some("wf")),
+        ]);
+        let graph = NodeGraph::new(&nodes);
+        let ids = |id| -> Vec<&str> {
+            graph
+                .leaf_pods_within(id)
+                .iter()
+                .map(|n| n.id.as_str())
+                .collect()
+        };
+        assert_eq!(ids("wf"), ["a.pod", "b.pod"]);
+        assert_eq!(ids("a"), ["a.pod"]);
+        assert_eq!(ids("b.pod"), ["b.pod"]);
+    }
+
+    #[test]
+    fn leaf_pods_within_boundary_cycle() {
+        let nodes = HashMap::from([
+            node("wf", "Steps", None),
+            node("a", "Steps", Some("b")),
+            node("b", "Steps", Some("a")),
+            node("a.pod", "Pod", Some("a")),
+        ]);
+        let graph = NodeGraph::new(&nodes);
+        assert!(graph.leaf_pods_within("wf").is_empty());
+        assert_eq!(graph.leaf_pods_within("b").len(), 1);
+    }
+}
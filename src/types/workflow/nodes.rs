@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use flate2::read::GzDecoder;
+
+use super::{NodeStatus, WorkflowStatus};
+use crate::error::NodesError;
+
+impl WorkflowStatus {
+    /// Decompresses `compressed_nodes`, the gzipped and base64 encoded JSON
+    /// of the nodes, into `nodes`, and clears it. The status is left as is
+    /// when it has no compressed nodes, or when they cannot be decoded.
+    ///
+    /// The workflow APIs decompress the nodes of the workflows they return,
+    /// keeping `compressed_nodes` when this fails, which `nodes_complete()`
+    /// then reports.
+    pub fn decompress_nodes(&mut self) -> Result<(), NodesError> {
+        let compressed = match self.compressed_nodes.as_deref().map(str::trim) {
+            Some(compressed) if !compressed.is_empty() => compressed,
+            _ => return Ok(()),
+        };
+        let gzipped = STANDARD.decode(compressed).map_err(NodesError::Base64)?;
+        let mut json = String::new();
+        GzDecoder::new(gzipped.as_slice())
+            .read_to_string(&mut json)
+            .map_err(NodesError::Gzip)?;
+        let nodes: HashMap<String, NodeStatus> =
+            serde_json::from_str(&json).map_err(NodesError::Serde)?;
+        self.nodes = Some(nodes);
+        self.compressed_nodes = None;
+        Ok(())
+    }
+
+    /// Returns whether `nodes` holds all the nodes of the workflow: `false`
+    /// when they were offloaded, or when `compressed_nodes` is still set
+    /// because it could not be decoded (see `decompress_nodes()`). Either
+    /// way, `hydrate_workflow()` completes the nodes or returns why it
+    /// cannot.
+    pub fn nodes_complete(&self) -> bool {
+        !self.is_offloaded() && self.compressed_nodes.as_deref().is_none_or(str::is_empty)
+    }
+
+    /// Returns whether the nodes of the workflow were offloaded to the
+    /// database of the controller, and are missing from `nodes`. They can
+    /// be read with `hydrate_workflow()`.
+    pub fn is_offloaded(&self) -> bool {
+        let offloaded = self.offload_node_status_version.as_deref();
+        offloaded.is_some_and(|version| !version.is_empty())
+            && self.nodes.as_ref().is_none_or(HashMap::is_empty)
+            && self.compressed_nodes.as_deref().is_none_or(str::is_empty)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    fn compress(json: &str) -> String {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(json.as_bytes()).unwrap();
+        STANDARD.encode(encoder.finish().unwrap())
+    }
+
+    #[test]
+    fn decompress_nodes() {
+        let mut status = WorkflowStatus {
+            compressed_nodes: Some(compress(
+                r#"{"n1": {"id": "n1", "name": "wf", "type": "Pod"}}"#,
+            )),
+            ..Default::default()
+        };
+        assert!(!status.nodes_complete());
+        status.decompress_nodes().unwrap();
+        assert_eq!(status.compressed_nodes, None);
+        assert_eq!(status.nodes.as_ref().map(HashMap::len), Some(1));
+        assert!(status.nodes_complete());
+    }
+
+    #[test]
+    fn invalid_compressed_nodes_are_kept() {
+        let mut status = WorkflowStatus {
+            compressed_nodes: Some(String::from("not base64!")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            status.decompress_nodes(),
+            Err(NodesError::Base64(_))
+        ));
+        assert!(status.compressed_nodes.is_some());
+        assert!(!status.nodes_complete());
+
+        status.compressed_nodes = Some(STANDARD.encode("not gzip"));
+        assert!(matches!(
+            status.decompress_nodes(),
+            Err(NodesError::Gzip(_))
+        ));
+    }
+
+    #[test]
+    fn offloaded() {
+        let mut status = WorkflowStatus {
+            offload_node_status_version: Some(String::from("fnv:1")),
+            ..Default::default()
+        };
+        assert!(status.is_offloaded());
+        assert!(!status.nodes_complete());
+        status.nodes = Some(HashMap::from([(String::from("n1"), NodeStatus::default())]));
+        assert!(!status.is_offloaded());
+        assert!(status.nodes_complete());
+    }
+}
@@ -0,0 +1,164 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{Arguments, NodeStatus, Parameter};
+use crate::error::ParamsError;
+use crate::types::template::Outputs;
+
+/// `WorkflowParams` maps a type to a list of workflow parameters and back.
+///
+/// It is usually implemented with `#[derive(WorkflowParams)]`, available
+/// with the `derive` feature:
+///
+/// ```ignore
+/// #[derive(WorkflowParams)]
+/// struct Etl {
+///     /// Day to process.
+///     #[param(default = "2026-01-01")]
+///     date: String,
+///     #[param(rename = "log-level", enum = ["info", "debug"])]
+///     log_level: String,
+///     retries: Option<u32>,
+/// }
+/// ```
+///
+/// Field attributes are `rename`, `default`, `description` (defaults to the
+/// doc comment of the field), `enum` and `json`, which encodes the field
+/// as a JSON document instead of a plain string.
+pub trait WorkflowParams: Sized {
+    /// Returns the declaration of the parameters, without any value.
+    fn parameters() -> Vec<Parameter>;
+
+    /// Returns the parameters holding the values of `self`.
+    fn to_parameters(&self) -> Vec<Parameter>;
+
+    /// Parses `params` into `Self`. A parameter without a value falls back
+    /// to its default.
+    fn from_parameters(params: &[Parameter]) -> Result<Self, ParamsError>;
+
+    /// Returns `Arguments` passing the values of `self` as parameters.
+    fn to_arguments(&self) -> Arguments {
+        Arguments {
+            parameters: Some(self.to_parameters()),
+            ..Default::default()
+        }
+    }
+
+    /// Parses the output parameters of a template into `Self`.
+    fn from_outputs(outputs: &Outputs) -> Result<Self, ParamsError> {
+        Self::from_parameters(outputs.parameters.as_deref().unwrap_or_default())
+    }
+
+    /// Parses the output parameters of a finished node into `Self`.
+    fn from_node(node: &NodeStatus) -> Result<Self, ParamsError> {
+        match node.outputs.as_deref() {
+            Some(outputs) => Self::from_outputs(outputs),
+            None => Self::from_parameters(&[]),
+        }
+    }
+}
+
+/// `ParamValue` converts a field of a `WorkflowParams` type to the string
+/// value of a parameter and back.
+pub trait ParamValue: Sized {
+    /// Returns the parameter value, or `None` when there is none to send.
+    fn to_param(&self) -> Option<String>;
+
+    /// Parses a parameter value.
+    fn from_param(value: &str) -> Result<Self, String>;
+
+    /// Returns the value to use when the parameter is missing, if any.
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+impl ParamValue for String {
+    fn to_param(&self) -> Option<String> {
+        Some(self.clone())
+    }
+
+    fn from_param(value: &str) -> Result<Self, String> {
+        Ok(String::from(value))
+    }
+}
+
+impl<T: ParamValue> ParamValue for Option<T> {
+    fn to_param(&self) -> Option<String> {
+        self.as_ref().and_then(T::to_param)
+    }
+
+    fn from_param(value: &str) -> Result<Self, String> {
+        T::from_param(value).map(Some)
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+macro_rules! impl_param_value {
+    ($($t:ty),*) => {
+        $(
+            impl ParamValue for $t {
+                fn to_param(&self) -> Option<String> {
+                    Some(self.to_string())
+                }
+
+                fn from_param(value: &str) -> Result<Self, String> {
+                    value.trim().parse().map_err(|e| format!("{}", e))
+                }
+            }
+        )*
+    };
+}
+
+impl_param_value!(
+    bool, char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64
+);
+
+#[doc(hidden)]
+pub fn json_to_param<T: Serialize>(value: &T) -> Option<String> {
+    serde_json::to_string(value).ok()
+}
+
+#[doc(hidden)]
+pub fn json_from_param<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_str(value).map_err(|e| e.to_string())
+}
+
+/// Returns the value of the parameter `name` in `params`, falling back to
+/// its default. Used by the code generated by `#[derive(WorkflowParams)]`.
+#[doc(hidden)]
+pub fn param_value<'a>(params: &'a [Parameter], name: &str) -> Option<&'a str> {
+    let param = params.iter().find(|p| p.name == name)?;
+    param.value.as_deref().or(param.default.as_deref())
+}
+
+/// Parses the parameter `name` of `params` with `parse`, applying the
+/// declared `default` and `allowed` values. Used by the code generated by
+/// `#[derive(WorkflowParams)]`.
+#[doc(hidden)]
+pub fn parse_param<T>(
+    params: &[Parameter],
+    name: &str,
+    default: Option<&str>,
+    allowed: &[&str],
+    missing: Option<T>,
+    parse: fn(&str) -> Result<T, String>,
+) -> Result<T, ParamsError> {
+    let value = match param_value(params, name).or(default) {
+        Some(value) => value,
+        None => return missing.ok_or_else(|| ParamsError::Missing(String::from(name))),
+    };
+    if !allowed.is_empty() && !allowed.contains(&value) {
+        return Err(ParamsError::NotAllowed {
+            name: String::from(name),
+            value: String::from(value),
+        });
+    }
+    parse(value).map_err(|message| ParamsError::Invalid {
+        name: String::from(name),
+        value: String::from(value),
+        message,
+    })
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1 as corev1;
+use serde::Serialize;
+
+use super::WorkflowSpec;
+use crate::error::PodPreviewError;
+use crate::types::merge::{merge, pod_merge_key};
+use crate::types::template::{Template, UserContainer};
+
+/// Image of the executor run by the `init` and `wait` containers when no
+/// other image is configured on the controller.
+pub const DEFAULT_EXECUTOR_IMAGE: &str = "quay.io/argoproj/argoexec:latest";
+
+/// Path where the executor writes the source of a script template.
+const SCRIPT_PATH: &str = "/argo/staging/script";
+
+/// Volume shared by the executor with the other containers of the pod.
+const VAR_RUN_ARGO: &str = "var-run-argo";
+
+impl WorkflowSpec {
+    /// Returns a preview of the spec of the pod running `template`, or
+    /// `None` when the template does not run a pod of its own (dag, steps,
+    /// suspend, http and plugin templates).
+    ///
+    /// The template is first given its effective settings (see
+    /// `WorkflowSpec::effective_template()`). The pod is made of the `init`
+    /// executor container followed by the template `init_containers`, and of
+    /// the `wait` executor container, the main containers and the
+    /// `sidecars`. Volumes of the workflow mounted by a container are added
+    /// to the ones of the template. The workflow and template
+    /// `pod_spec_patch` are then applied as strategic merge patches.
+    ///
+    /// The commands of the main containers are shown as declared, without
+    /// the wrapper the executor adds to them.
+    pub fn pod_spec_preview(
+        &self,
+        template: &Template,
+        executor_image: &str,
+    ) -> Result<Option<corev1::PodSpec>, PodPreviewError> {
+        let effective = self.effective_template(template);
+        let template = &effective.template;
+        let mut main = match main_containers(template, executor_image) {
+            Some(containers) => containers,
+            None => return Ok(None),
+        };
+        let main_mounts: Vec<corev1::VolumeMount> = main
+            .iter()
+            .flat_map(|c| c.volume_mounts.iter().flatten().cloned())
+            .collect();
+        let init_containers = user_containers(&template.init_containers, &main_mounts);
+        let sidecars = user_containers(&template.sidecars, &main_mounts);
+
+        for container in main.iter_mut() {
+            container
+                .volume_mounts
+                .get_or_insert_with(Vec::new)
+                .push(var_run_argo_mount());
+        }
+        let mut containers = vec![executor("wait", executor_image)];
+        containers.append(&mut main);
+        containers.extend(sidecars);
+        let mut all_init = vec![executor("init", executor_image)];
+        all_init.extend(init_containers);
+
+        let mut volumes = template.volumes.clone().unwrap_or_default();
+        for volume in self.volumes.iter().flatten() {
+            let mounted = containers
+                .iter()
+                .chain(all_init.iter())
+                .flat_map(|c| c.volume_mounts.iter().flatten())
+                .any(|m| m.name == volume.name);
+            if mounted && !volumes.iter().any(|v| v.name == volume.name) {
+                volumes.push(volume.clone());
+            }
+        }
+        volumes.push(corev1::Volume {
+            name: String::from(VAR_RUN_ARGO),
+            empty_dir: Some(corev1::EmptyDirVolumeSource::default()),
+            ..Default::default()
+        });
+
+        let pod = corev1::PodSpec {
+            containers,
+            init_containers: Some(all_init),
+            volumes: Some(volumes),
+            restart_policy: Some(String::from("Never")),
+            active_deadline_seconds: template
+                .active_deadline_seconds
+                .as_deref()
+                .and_then(|s| s.parse().ok()),
+            node_selector: template
+                .node_selector
+                .as_ref()
+                .map(|s| s.clone().into_iter().collect::<BTreeMap<_, _>>()),
+            tolerations: template.tolerations.clone(),
+            affinity: template.affinity.as_deref().cloned(),
+            security_context: template.security_context.as_deref().cloned(),
+            host_aliases: template.host_aliases.clone(),
+            scheduler_name: template.scheduler_name.clone(),
+            priority_class_name: template.priority_class_name.clone(),
+            priority: template.priority,
+            service_account_name: template.service_account_name.clone(),
+            automount_service_account_token: template.automount_service_account_token,
+            image_pull_secrets: self.image_pull_secrets.clone(),
+            host_network: self.host_network,
+            dns_policy: self.dns_policy.clone(),
+            dns_config: self.dns_config.as_deref().cloned(),
+            ..Default::default()
+        };
+
+        if effective.pod_spec_patches.is_empty() {
+            return Ok(Some(pod));
+        }
+        let mut value = serde_json::to_value(&pod).expect("pod spec serializes to JSON");
+        for patch in &effective.pod_spec_patches {
+            let patch: serde_json::Value =
+                serde_yaml::from_str(patch).map_err(|e| PodPreviewError::InvalidPatch {
+                    patch: patch.clone(),
+                    message: e.to_string(),
+                })?;
+            merge(&mut value, patch, &pod_merge_key);
+        }
+        serde_json::from_value(value)
+            .map(Some)
+            .map_err(|e| PodPreviewError::InvalidPatch {
+                patch: effective.pod_spec_patches.join("\n---\n"),
+                message: e.to_string(),
+            })
+    }
+}
+
+/// Returns the main containers of `template`, or `None` if it does not run
+/// a pod of its own.
+fn main_containers(template: &Template, executor_image: &str) -> Option<Vec<corev1::Container>> {
+    if let Some(container) = template.container.as_deref() {
+        return Some(vec![corev1::Container {
+            name: String::from("main"),
+            ..container.clone()
+        }]);
+    }
+    if let Some(script) = template.script.as_deref() {
+        let mut container: corev1::Container = convert(script);
+        container.name = String::from("main");
+        container
+            .args
+            .get_or_insert_with(Vec::new)
+            .push(String::from(SCRIPT_PATH));
+        return Some(vec![container]);
+    }
+    if let Some(set) = template.container_set.as_deref() {
+        return Some(
+            set.containers
+                .iter()
+                .map(|node| {
+                    let mut container: corev1::Container = convert(node);
+                    if let Some(mounts) = &set.volume_mounts {
+                        container
+                            .volume_mounts
+                            .get_or_insert_with(Vec::new)
+                            .extend(mounts.iter().cloned());
+                    }
+                    container
+                })
+                .collect(),
+        );
+    }
+    let command = match (template.resource.as_deref(), template.data.as_deref()) {
+        (Some(resource), _) => vec!["argoexec", "resource", resource.action.as_str()],
+        (None, Some(_)) => vec!["argoexec", "data"],
+        (None, None) => return None,
+    };
+    Some(vec![corev1::Container {
+        name: String::from("main"),
+        image: Some(String::from(executor_image)),
+        command: Some(command.into_iter().map(String::from).collect()),
+        ..Default::default()
+    }])
+}
+
+fn executor(name: &str, image: &str) -> corev1::Container {
+    corev1::Container {
+        name: String::from(name),
+        image: Some(String::from(image)),
+        command: Some(vec![
+            String::from("argoexec"),
+            String::from(name),
+            String::from("--loglevel"),
+            String::from("info"),
+        ]),
+        volume_mounts: Some(vec![var_run_argo_mount()]),
+        ..Default::default()
+    }
+}
+
+fn var_run_argo_mount() -> corev1::VolumeMount {
+    corev1::VolumeMount {
+        name: String::from(VAR_RUN_ARGO),
+        mount_path: String::from("/var/run/argo"),
+        ..Default::default()
+    }
+}
+
+/// Converts the init containers or sidecars of a template, giving the
+/// `main_mounts` to the ones setting `mirror_volume_mounts`.
+fn user_containers(
+    containers: &Option<Vec<UserContainer>>,
+    main_mounts: &[corev1::VolumeMount],
+) -> Vec<corev1::Container> {
+    containers
+        .iter()
+        .flatten()
+        .map(|c| {
+            let mut container: corev1::Container = convert(c);
+            if c.mirror_volume_mounts == Some(true) {
+                container
+                    .volume_mounts
+                    .get_or_insert_with(Vec::new)
+                    .extend(main_mounts.iter().cloned());
+            }
+            container
+        })
+        .collect()
+}
+
+/// Converts a container-like type of a template into a `Container`.
+fn convert<T: Serialize>(container: &T) -> corev1::Container {
+    let value = serde_json::to_value(container).expect("container serializes to JSON");
+    serde_json::from_value(value).expect("container deserializes into a Container")
+}
@@ -0,0 +1,528 @@
+use std::collections::{HashMap, HashSet};
+
+use super::validate::prefix;
+use super::{Parameter, ValueFrom, Workflow, WorkflowSpec, WorkflowStep};
+use crate::error::{ValidationError, ValidationErrorKind};
+use crate::types::artifact::Artifact;
+use crate::types::template::{depends_tasks, DAGTask, Template};
+use crate::types::workflow_template::WorkflowTemplate;
+
+/// Variables of the form `{{workflow.<name>}}` set by the controller.
+const WORKFLOW_VARIABLES: &[&str] = &[
+    "name",
+    "namespace",
+    "uid",
+    "serviceAccountName",
+    "mainEntrypoint",
+    "status",
+    "failures",
+    "duration",
+    "priority",
+    "scheduledTime",
+    "creationTimestamp",
+    "parameters",
+];
+
+/// Fields of a step or task node other than its outputs
+/// (e.g. `{{steps.<step>.exitCode}}`).
+const NODE_FIELDS: &[&str] = &[
+    "id",
+    "ip",
+    "status",
+    "exitCode",
+    "startedAt",
+    "finishedAt",
+    "hostNodeName",
+];
+
+/// Returns the template tags found in `text`, without their braces and
+/// surrounding whitespace (`{{ inputs.parameters.x }}` yields
+/// `inputs.parameters.x`).
+pub(crate) fn tags(text: &str) -> impl Iterator<Item = &str> {
+    text.split("{{")
+        .skip(1)
+        .filter_map(|s| s.split_once("}}"))
+        .map(|(tag, _)| tag.trim())
+}
+
+impl WorkflowSpec {
+    /// Finds the template tags (e.g. `{{inputs.parameters.x}}`) which do not
+    /// resolve to a declared input, output, step, task or workflow
+    /// parameter, and returns them with the JSON pointer of the field
+    /// holding them.
+    ///
+    /// Tags are searched in container and script commands, arguments and
+    /// environment, script sources, resource manifests, `ValueFrom`,
+    /// `Artifact::from`, `when` and `with_param`. Expression tags
+    /// (`{{=...}}`) are not checked. Steps may only reference steps of the
+    /// previous groups, and DAG tasks the tasks they depend on.
+    pub fn check_references(&self) -> Result<(), Vec<ValidationError>> {
+        let templates = self.templates.as_deref().unwrap_or_default();
+        let mut globals = (HashSet::new(), HashSet::new());
+        for outputs in templates.iter().filter_map(|t| t.outputs.as_deref()) {
+            for param in outputs.parameters.iter().flatten() {
+                globals.0.extend(param.global_name.as_deref());
+            }
+            for artifact in outputs.artifacts.iter().flatten() {
+                globals.1.extend(artifact.global_name.as_deref());
+            }
+        }
+
+        let mut checker = Checker {
+            templates: templates
+                .iter()
+                .filter_map(|t| Some((t.name.as_deref()?, t)))
+                .collect(),
+            parameters: self
+                .arguments
+                .as_deref()
+                .and_then(|a| a.parameters.as_deref())
+                .map(|p| p.iter().map(|p| p.name.as_str()).collect()),
+            global_parameters: globals.0,
+            global_artifacts: globals.1,
+            errors: Vec::new(),
+        };
+        // Parameters of a referenced workflow template are not known offline.
+        if self.workflow_template_ref.is_some() {
+            checker.parameters = None;
+        }
+
+        for (i, template) in templates.iter().enumerate() {
+            checker.template(&format!("/templates/{}", i), template);
+        }
+
+        if checker.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(checker.errors)
+        }
+    }
+}
+
+impl Workflow {
+    /// Finds the unresolved template tags of the workflow. See
+    /// `WorkflowSpec::check_references()`.
+    pub fn check_references(&self) -> Result<(), Vec<ValidationError>> {
+        self.spec
+            .check_references()
+            .map_err(|errors| prefix("/spec", errors))
+    }
+}
+
+impl WorkflowTemplate {
+    /// Finds the unresolved template tags of the workflow template. See
+    /// `WorkflowSpec::check_references()`.
+    pub fn check_references(&self) -> Result<(), Vec<ValidationError>> {
+        self.spec
+            .check_references()
+            .map_err(|errors| prefix("/spec", errors))
+    }
+}
+
+/// `Scope` holds what a tag may reference at a given location.
+struct Scope<'a> {
+    template: &'a Template,
+    /// `steps` or `tasks`, and the nodes which can be referenced, with the
+    /// template they run when it is known.
+    nodes: Option<(&'static str, HashMap<&'a str, Option<&'a Template>>)>,
+    item: bool,
+}
+
+struct Checker<'a> {
+    templates: HashMap<&'a str, &'a Template>,
+    /// Workflow parameters, `None` when they are not known.
+    parameters: Option<HashSet<&'a str>>,
+    global_parameters: HashSet<&'a str>,
+    global_artifacts: HashSet<&'a str>,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> Checker<'a> {
+    fn template(&mut self, pointer: &str, template: &'a Template) {
+        let mut scope = Scope {
+            template,
+            nodes: None,
+            item: false,
+        };
+
+        if let Some(groups) = template.steps.as_deref() {
+            self.steps(pointer, template, groups);
+            scope.nodes = Some(("steps", self.nodes(groups.iter().flatten().map(step_node))));
+        }
+        if let Some(dag) = template.dag.as_deref() {
+            self.dag(&format!("{}/dag", pointer), template, &dag.tasks);
+            scope.nodes = Some(("tasks", self.nodes(dag.tasks.iter().map(task_node))));
+        }
+
+        if let Some(container) = template.container.as_deref() {
+            let pointer = format!("{}/container", pointer);
+            self.strings(&pointer, "command", container.command.as_deref(), &scope);
+            self.strings(&pointer, "args", container.args.as_deref(), &scope);
+            for (i, env) in container.env.iter().flatten().enumerate() {
+                if let Some(value) = env.value.as_deref() {
+                    self.scan(&format!("{}/env/{}/value", pointer, i), value, &scope);
+                }
+            }
+        }
+        if let Some(script) = template.script.as_deref() {
+            let pointer = format!("{}/script", pointer);
+            self.scan(&format!("{}/source", pointer), &script.source, &scope);
+            self.strings(&pointer, "command", script.command.as_deref(), &scope);
+            self.strings(&pointer, "args", script.args.as_deref(), &scope);
+            for (i, env) in script.env.iter().flatten().enumerate() {
+                if let Some(value) = env.value.as_deref() {
+                    self.scan(&format!("{}/env/{}/value", pointer, i), value, &scope);
+                }
+            }
+        }
+        if let Some(manifest) = template
+            .resource
+            .as_deref()
+            .and_then(|r| r.manifest.as_deref())
+        {
+            self.scan(&format!("{}/resource/manifest", pointer), manifest, &scope);
+        }
+        if let Some(inputs) = template.inputs.as_deref() {
+            let pointer = format!("{}/inputs", pointer);
+            self.parameters(&pointer, inputs.parameters.as_deref(), &scope);
+            self.artifacts(&pointer, inputs.artifacts.as_deref(), &scope);
+        }
+        if let Some(outputs) = template.outputs.as_deref() {
+            let pointer = format!("{}/outputs", pointer);
+            self.parameters(&pointer, outputs.parameters.as_deref(), &scope);
+            self.artifacts(&pointer, outputs.artifacts.as_deref(), &scope);
+        }
+    }
+
+    fn nodes<I>(&self, nodes: I) -> HashMap<&'a str, Option<&'a Template>>
+    where
+        I: Iterator<Item = (&'a str, Node<'a>)>,
+    {
+        nodes
+            .map(|(name, node)| (name, self.target(node)))
+            .collect()
+    }
+
+    /// Returns the template run by a step or task, when it is known.
+    fn target(&self, node: Node<'a>) -> Option<&'a Template> {
+        match node {
+            (Some(inline), _) => Some(inline),
+            (None, Some(name)) => self.templates.get(name).copied(),
+            (None, None) => None,
+        }
+    }
+
+    fn steps(&mut self, pointer: &str, template: &'a Template, groups: &'a [Vec<WorkflowStep>]) {
+        let mut previous = HashMap::new();
+        for (i, group) in groups.iter().enumerate() {
+            for (j, step) in group.iter().enumerate() {
+                let pointer = format!("{}/steps/{}/{}", pointer, i, j);
+                let scope = Scope {
+                    template,
+                    nodes: Some(("steps", previous.clone())),
+                    item: step.with_items.is_some()
+                        || step.with_param.is_some()
+                        || step.with_sequence.is_some(),
+                };
+                if let Some(arguments) = step.arguments.as_deref() {
+                    let pointer = format!("{}/arguments", pointer);
+                    self.parameters(&pointer, arguments.parameters.as_deref(), &scope);
+                    self.artifacts(&pointer, arguments.artifacts.as_deref(), &scope);
+                }
+                self.conditions(
+                    &pointer,
+                    step.when.as_deref(),
+                    step.with_param.as_deref(),
+                    &scope,
+                );
+                if let Some(inline) = step.inline.as_deref() {
+                    self.template(&format!("{}/inline", pointer), inline);
+                }
+            }
+            previous.extend(self.nodes(group.iter().map(step_node)));
+        }
+    }
+
+    fn dag(&mut self, pointer: &str, template: &'a Template, tasks: &'a [DAGTask]) {
+        let targets = self.nodes(tasks.iter().map(task_node));
+        let graph: HashMap<&str, Vec<&str>> = tasks
+            .iter()
+            .map(|t| {
+                let mut deps: Vec<&str> = t
+                    .dependencies
+                    .iter()
+                    .flatten()
+                    .map(String::as_str)
+                    .collect();
+                deps.extend(t.depends.as_deref().map(depends_tasks).unwrap_or_default());
+                (t.name.as_str(), deps)
+            })
+            .collect();
+
+        for (i, task) in tasks.iter().enumerate() {
+            let pointer = format!("{}/tasks/{}", pointer, i);
+            let nodes = ancestors(task.name.as_str(), &graph)
+                .into_iter()
+                .filter_map(|name| Some((name, *targets.get(name)?)))
+                .collect();
+            let scope = Scope {
+                template,
+                nodes: Some(("tasks", nodes)),
+                item: task.with_items.is_some()
+                    || task.with_param.is_some()
+                    || task.with_sequence.is_some(),
+            };
+            if let Some(arguments) = task.arguments.as_deref() {
+                let pointer = format!("{}/arguments", pointer);
+                self.parameters(&pointer, arguments.parameters.as_deref(), &scope);
+                self.artifacts(&pointer, arguments.artifacts.as_deref(), &scope);
+            }
+            self.conditions(
+                &pointer,
+                task.when.as_deref(),
+                task.with_param.as_deref(),
+                &scope,
+            );
+            if let Some(inline) = task.inline.as_deref() {
+                self.template(&format!("{}/inline", pointer), inline);
+            }
+        }
+    }
+
+    fn conditions(
+        &mut self,
+        pointer: &str,
+        when: Option<&str>,
+        with_param: Option<&str>,
+        scope: &Scope,
+    ) {
+        if let Some(when) = when {
+            self.scan(&format!("{}/when", pointer), when, scope);
+        }
+        if let Some(with_param) = with_param {
+            self.scan(&format!("{}/withParam", pointer), with_param, scope);
+        }
+    }
+
+    fn strings(&mut self, pointer: &str, field: &str, values: Option<&[String]>, scope: &Scope) {
+        for (i, value) in values.into_iter().flatten().enumerate() {
+            self.scan(&format!("{}/{}/{}", pointer, field, i), value, scope);
+        }
+    }
+
+    fn parameters(&mut self, pointer: &str, params: Option<&[Parameter]>, scope: &Scope) {
+        for (i, param) in params.into_iter().flatten().enumerate() {
+            let pointer = format!("{}/parameters/{}", pointer, i);
+            if let Some(value) = param.value.as_deref() {
+                self.scan(&format!("{}/value", pointer), value, scope);
+            }
+            if let Some(default) = param.default.as_deref() {
+                self.scan(&format!("{}/default", pointer), default, scope);
+            }
+            if let Some(value_from) = param.value_from.as_deref() {
+                self.value_from(&format!("{}/valueFrom", pointer), value_from, scope);
+            }
+        }
+    }
+
+    fn value_from(&mut self, pointer: &str, value_from: &ValueFrom, scope: &Scope) {
+        let fields = [
+            ("default", value_from.default.as_deref()),
+            ("event", value_from.event.as_deref()),
+            ("jqFilter", value_from.jq_filter.as_deref()),
+            ("jsonPath", value_from.json_path.as_deref()),
+            ("parameter", value_from.parameter.as_deref()),
+            ("path", value_from.path.as_deref()),
+        ];
+        for (field, value) in fields {
+            if let Some(value) = value {
+                self.scan(&format!("{}/{}", pointer, field), value, scope);
+            }
+        }
+    }
+
+    fn artifacts(&mut self, pointer: &str, artifacts: Option<&[Artifact]>, scope: &Scope) {
+        for (i, artifact) in artifacts.into_iter().flatten().enumerate() {
+            if let Some(from) = artifact.from.as_deref() {
+                self.scan(&format!("{}/artifacts/{}/from", pointer, i), from, scope);
+            }
+        }
+    }
+
+    fn scan(&mut self, pointer: &str, text: &str, scope: &Scope) {
+        for tag in tags(text) {
+            if !tag.starts_with('=') && !self.resolve(tag, scope) {
+                self.errors.push(ValidationError::new(
+                    String::from(pointer),
+                    ValidationErrorKind::UnresolvedReference(String::from(tag)),
+                ));
+            }
+        }
+    }
+
+    fn resolve(&self, tag: &str, scope: &Scope) -> bool {
+        let path: Vec<&str> = tag.splitn(4, '.').collect();
+        match path.as_slice() {
+            ["inputs", "parameters"] => true,
+            ["inputs", "parameters", name] => declares_parameter(
+                scope
+                    .template
+                    .inputs
+                    .as_deref()
+                    .and_then(|i| i.parameters.as_deref()),
+                name,
+            ),
+            ["inputs", "artifacts", name] | ["inputs", "artifacts", name, "path"] => {
+                declares_artifact(
+                    scope
+                        .template
+                        .inputs
+                        .as_deref()
+                        .and_then(|i| i.artifacts.as_deref()),
+                    name,
+                )
+            }
+            ["outputs", "parameters", name] | ["outputs", "parameters", name, "path"] => {
+                declares_parameter(
+                    scope
+                        .template
+                        .outputs
+                        .as_deref()
+                        .and_then(|o| o.parameters.as_deref()),
+                    name,
+                )
+            }
+            ["outputs", "artifacts", name] | ["outputs", "artifacts", name, "path"] => {
+                declares_artifact(
+                    scope
+                        .template
+                        .outputs
+                        .as_deref()
+                        .and_then(|o| o.artifacts.as_deref()),
+                    name,
+                )
+            }
+            ["workflow", "parameters", "json"] => true,
+            ["workflow", "parameters", name] => self
+                .parameters
+                .as_ref()
+                .is_none_or(|params| params.contains(name)),
+            ["workflow", "outputs", "parameters", name] => self.global_parameters.contains(name),
+            ["workflow", "outputs", "artifacts", name] => self.global_artifacts.contains(name),
+            ["workflow", "labels" | "annotations", ..] => true,
+            ["workflow", "creationTimestamp", ..] => true,
+            ["workflow", name] => WORKFLOW_VARIABLES.contains(name),
+            ["steps" | "tasks", "name"] => true,
+            [kind @ ("steps" | "tasks"), node, field @ ..] => {
+                let target = match &scope.nodes {
+                    Some((scope_kind, nodes)) if scope_kind == kind => match nodes.get(node) {
+                        Some(target) => *target,
+                        None => return false,
+                    },
+                    _ => return false,
+                };
+                resolve_node_field(field, target)
+            }
+            ["item", ..] => scope.item,
+            ["pod" | "node", "name"] => true,
+            ["retries"] => true,
+            ["lastRetry", "exitCode" | "status" | "duration" | "message"] => true,
+            ["cronworkflow", ..] => true,
+            _ => false,
+        }
+    }
+}
+
+/// Resolves `field` (e.g. `outputs.parameters.x`) against a step or task
+/// running `target`. Outputs of an unknown template are not checked.
+fn resolve_node_field(field: &[&str], target: Option<&Template>) -> bool {
+    let field: Vec<&str> = field.iter().flat_map(|f| f.splitn(3, '.')).collect();
+    let outputs = target.map(|t| t.outputs.as_deref());
+    match field.as_slice() {
+        [name] => NODE_FIELDS.contains(name),
+        ["outputs", "result"] => target.is_none_or(|t| t.container.is_some() || t.script.is_some()),
+        ["outputs", "parameters"] => true,
+        ["outputs", "parameters", name] => outputs
+            .is_none_or(|o| declares_parameter(o.and_then(|o| o.parameters.as_deref()), name)),
+        ["outputs", "artifacts", name] => {
+            outputs.is_none_or(|o| declares_artifact(o.and_then(|o| o.artifacts.as_deref()), name))
+        }
+        _ => false,
+    }
+}
+
+fn declares_parameter(params: Option<&[Parameter]>, name: &str) -> bool {
+    params.into_iter().flatten().any(|p| p.name == name)
+}
+
+fn declares_artifact(artifacts: Option<&[Artifact]>, name: &str) -> bool {
+    artifacts.into_iter().flatten().any(|a| a.name == name)
+}
+
+/// A step or task node: its inline template and the name of its template.
+type Node<'a> = (Option<&'a Template>, Option<&'a str>);
+
+fn step_node(step: &WorkflowStep) -> (&str, Node<'_>) {
+    (
+        step.name.as_deref().unwrap_or_default(),
+        (step.inline.as_deref(), step.template.as_deref()),
+    )
+}
+
+fn task_node(task: &DAGTask) -> (&str, Node<'_>) {
+    (
+        task.name.as_str(),
+        (task.inline.as_deref(), task.template.as_deref()),
+    )
+}
+
+/// Returns the tasks `task` depends on, directly or not.
+fn ancestors<'a>(task: &'a str, graph: &HashMap<&'a str, Vec<&'a str>>) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut stack = vec![task];
+    while let Some(node) = stack.pop() {
+        for dep in graph.get(node).into_iter().flatten() {
+            if seen.insert(*dep) {
+                stack.push(dep);
+            }
+        }
+    }
+    seen.remove(task);
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn unresolved(spec: serde_json::Value) -> Vec<String> {
+        let spec: WorkflowSpec = serde_json::from_value(spec).unwrap();
+        let errors = spec.check_references().err().unwrap_or_default();
+        errors.into_iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn workflow_parameters() {
+        let errors = unresolved(json!({
+            "entrypoint": "main",
+            "arguments": {"parameters": [{"name": "env", "value": "prod"}]},
+            "templates": [{
+                "name": "main",
+                "inputs": {"parameters": [{"name": "msg"}]},
+                "container": {
+                    "image": "alpine",
+                    "args": [
+                        "{{workflow.parameters.env}}",
+                        "{{workflow.parameters.json}}",
+                        "{{workflow.parameters.missing}}",
+                        "{{inputs.parameters}}",
+                    ],
+                },
+            }],
+        }));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("workflow.parameters.missing"));
+    }
+}
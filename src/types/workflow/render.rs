@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use super::references::tags;
+use super::{Arguments, Parameter, WorkflowSpec, WorkflowStep};
+use crate::error::RenderError;
+use crate::types::template::{DAGTask, Template};
+
+/// `RenderedTemplate` is the template run by one node of a workflow, with
+/// the tags known before the workflow runs substituted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderedTemplate {
+    /// Path of the node: the entrypoint followed by the step or task names,
+    /// with the index and value of the item for loops (e.g. `main.build(0:linux)`).
+    pub path: String,
+    /// The substituted copy of the template. Its input parameters hold the
+    /// values they were given.
+    pub template: Template,
+    /// The tags left in `template`, which are only known at runtime
+    /// (e.g. `tasks.gen.outputs.result` or `pod.name`), without their braces.
+    pub runtime_tags: Vec<String>,
+    /// The tags left in `template` which should have been substituted: those
+    /// of the `inputs`, `workflow.parameters` and `item` namespaces naming a
+    /// missing input, parameter or item field, often from a typo (e.g.
+    /// `inputs.parameters.tpyo`).
+    pub unresolved_tags: Vec<String>,
+}
+
+/// A `Renderer` substitutes the tags of the templates of a `WorkflowSpec`,
+/// to show what each step or task would run.
+///
+/// It resolves `{{workflow.parameters.*}}`, `{{inputs.parameters.*}}`,
+/// `{{inputs.artifacts.*.path}}`, `{{workflow.name}}`, `{{workflow.namespace}}`, `{{workflow.uid}}` and,
+/// for `with_items` loops, `{{item}}` and `{{item.<field>}}`. Starting at
+/// the entrypoint, every step and task is rendered, whatever its `when`
+/// condition. Steps and tasks using a `template_ref` are not rendered.
+#[derive(Clone, Debug)]
+pub struct Renderer<'a> {
+    spec: &'a WorkflowSpec,
+    parameters: HashMap<String, String>,
+    variables: HashMap<String, String>,
+}
+
+impl<'a> Renderer<'a> {
+    /// Constructs a new `Renderer` for `spec`, using the values and defaults
+    /// of the workflow parameters declared by the spec.
+    pub fn new(spec: &'a WorkflowSpec) -> Self {
+        let mut renderer = Renderer {
+            spec,
+            parameters: HashMap::new(),
+            variables: HashMap::new(),
+        };
+        let params = spec
+            .arguments
+            .as_deref()
+            .and_then(|a| a.parameters.as_deref());
+        renderer.set_parameters(params.unwrap_or_default());
+        renderer
+    }
+
+    fn set_parameters(&mut self, params: &[Parameter]) {
+        for param in params {
+            if let Some(value) = param.value.as_ref().or(param.default.as_ref()) {
+                self.parameters.insert(param.name.clone(), value.clone());
+            }
+        }
+    }
+
+    /// Sets the workflow arguments, overriding the values declared by the spec.
+    pub fn arguments(mut self, arguments: &Arguments) -> Self {
+        self.set_parameters(arguments.parameters.as_deref().unwrap_or_default());
+        self
+    }
+
+    /// Sets the value of `{{workflow.name}}`.
+    pub fn workflow_name(mut self, name: &str) -> Self {
+        self.variables
+            .insert(String::from("workflow.name"), String::from(name));
+        self
+    }
+
+    /// Sets the value of `{{workflow.namespace}}`.
+    pub fn workflow_namespace(mut self, namespace: &str) -> Self {
+        self.variables
+            .insert(String::from("workflow.namespace"), String::from(namespace));
+        self
+    }
+
+    /// Sets the value of `{{workflow.uid}}`.
+    pub fn workflow_uid(mut self, uid: &str) -> Self {
+        self.variables
+            .insert(String::from("workflow.uid"), String::from(uid));
+        self
+    }
+
+    /// Renders the templates run by the workflow, starting at the
+    /// entrypoint, in the order the nodes are declared.
+    ///
+    /// A template calling itself is rendered once per call, but the calls
+    /// it makes in turn are not followed.
+    pub fn render(&self) -> Result<Vec<RenderedTemplate>, RenderError> {
+        let entrypoint = self
+            .spec
+            .entrypoint
+            .as_deref()
+            .ok_or(RenderError::MissingEntrypoint)?;
+        let template = self.template(entrypoint)?;
+
+        let mut variables = self.variables.clone();
+        for (name, value) in &self.parameters {
+            variables.insert(format!("workflow.parameters.{}", name), value.clone());
+        }
+
+        let mut node = Node {
+            renderer: self,
+            variables: &variables,
+            stack: Vec::new(),
+            rendered: Vec::new(),
+        };
+        node.render(String::from(entrypoint), template, &self.parameters)?;
+        Ok(node.rendered)
+    }
+
+    fn template(&self, name: &str) -> Result<&'a Template, RenderError> {
+        self.spec
+            .templates
+            .iter()
+            .flatten()
+            .find(|t| t.name.as_deref() == Some(name))
+            .ok_or_else(|| RenderError::UnknownTemplate(String::from(name)))
+    }
+}
+
+struct Node<'r, 'a> {
+    renderer: &'r Renderer<'a>,
+    /// Workflow variables, including the workflow parameters.
+    variables: &'r HashMap<String, String>,
+    /// Names of the templates being rendered, to stop at recursive calls.
+    stack: Vec<&'a str>,
+    rendered: Vec<RenderedTemplate>,
+}
+
+impl<'a> Node<'_, 'a> {
+    fn render(
+        &mut self,
+        path: String,
+        template: &'a Template,
+        arguments: &HashMap<String, String>,
+    ) -> Result<(), RenderError> {
+        let name = template.name.as_deref().unwrap_or_default();
+        let mut variables = self.variables.clone();
+        let mut copy = template.clone();
+
+        let inputs = copy
+            .inputs
+            .as_deref_mut()
+            .and_then(|i| i.parameters.as_mut());
+        for param in inputs.into_iter().flatten() {
+            let value = arguments
+                .get(&param.name)
+                .or(param.value.as_ref())
+                .or(param.default.as_ref())
+                .ok_or_else(|| RenderError::MissingParameter {
+                    template: String::from(name),
+                    name: param.name.clone(),
+                })?;
+            let value = substitute_str(value, self.variables);
+            variables.insert(format!("inputs.parameters.{}", param.name), value.clone());
+            param.value = Some(value);
+        }
+        let artifacts = template
+            .inputs
+            .as_deref()
+            .and_then(|i| i.artifacts.as_ref());
+        for artifact in artifacts.into_iter().flatten() {
+            if let Some(path) = &artifact.path {
+                let tag = format!("inputs.artifacts.{}.path", artifact.name);
+                variables.insert(tag, path.clone());
+            }
+        }
+
+        let mut value = serde_json::to_value(&copy).expect("template serializes to JSON");
+        substitute(&mut value, &variables);
+        let mut tags = Vec::new();
+        collect_tags(&value, false, &mut tags);
+        let (unresolved_tags, runtime_tags) = tags.into_iter().partition(|tag| is_resolved(tag));
+        self.rendered.push(RenderedTemplate {
+            path: path.clone(),
+            template: serde_json::from_value(value).expect("substituted template deserializes"),
+            runtime_tags,
+            unresolved_tags,
+        });
+
+        if self.stack.contains(&name) {
+            return Ok(());
+        }
+        self.stack.push(name);
+        for step in template.steps.iter().flatten().flatten() {
+            self.call(&path, Call::from(step), &variables)?;
+        }
+        for task in template.dag.iter().flat_map(|d| d.tasks.iter()) {
+            self.call(&path, Call::from(task), &variables)?;
+        }
+        self.stack.pop();
+        Ok(())
+    }
+
+    /// Renders the template run by a step or task, once per item of its loop.
+    fn call(
+        &mut self,
+        path: &str,
+        call: Call<'a>,
+        variables: &HashMap<String, String>,
+    ) -> Result<(), RenderError> {
+        let template = match (call.inline, call.template) {
+            (Some(inline), _) => inline,
+            (None, Some(name)) => self.renderer.template(name)?,
+            (None, None) => return Ok(()),
+        };
+
+        let iterations: Vec<(String, HashMap<String, String>)> = match call.with_items {
+            Some(items) => items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let mut variables = variables.clone();
+                    variables.insert(String::from("item"), item_value(item));
+                    if let Value::Object(fields) = item {
+                        for (field, value) in fields {
+                            variables.insert(format!("item.{}", field), item_value(value));
+                        }
+                    }
+                    (format!("({}:{})", i, item_value(item)), variables)
+                })
+                .collect(),
+            None => vec![(String::new(), variables.clone())],
+        };
+
+        for (suffix, variables) in iterations {
+            let arguments = call
+                .parameters
+                .iter()
+                .filter_map(|p| {
+                    Some((
+                        p.name.clone(),
+                        substitute_str(p.value.as_ref()?, &variables),
+                    ))
+                })
+                .collect();
+            let path = format!("{}.{}{}", path, call.name, suffix);
+            self.render(path, template, &arguments)?;
+        }
+        Ok(())
+    }
+}
+
+/// The part of a step or a DAG task needed to render its template.
+struct Call<'a> {
+    name: &'a str,
+    template: Option<&'a str>,
+    inline: Option<&'a Template>,
+    parameters: &'a [Parameter],
+    with_items: Option<&'a [Value]>,
+}
+
+impl<'a> From<&'a WorkflowStep> for Call<'a> {
+    fn from(step: &'a WorkflowStep) -> Self {
+        Call {
+            name: step.name.as_deref().unwrap_or_default(),
+            template: step.template.as_deref(),
+            inline: step.inline.as_deref(),
+            parameters: step
+                .arguments
+                .as_deref()
+                .and_then(|a| a.parameters.as_deref())
+                .unwrap_or_default(),
+            with_items: step.with_items.as_deref(),
+        }
+    }
+}
+
+impl<'a> From<&'a DAGTask> for Call<'a> {
+    fn from(task: &'a DAGTask) -> Self {
+        Call {
+            name: &task.name,
+            template: task.template.as_deref(),
+            inline: task.inline.as_deref(),
+            parameters: task
+                .arguments
+                .as_deref()
+                .and_then(|a| a.parameters.as_deref())
+                .unwrap_or_default(),
+            with_items: task.with_items.as_deref(),
+        }
+    }
+}
+
+/// Returns the value of an item as substituted by the controller: strings
+/// as is, and anything else as JSON.
+pub(super) fn item_value(item: &Value) -> String {
+    match item {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Replaces the tags of `text` found in `variables`, and keeps the others.
+pub(super) fn substitute_str(text: &str, variables: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start..].find("}}") else {
+            break;
+        };
+        let end = start + len + 2;
+        out.push_str(&rest[..start]);
+        match variables.get(rest[start + 2..start + len].trim()) {
+            Some(value) => out.push_str(value),
+            None => out.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Substitutes the strings of a serialized template. Inline templates are
+/// skipped, as their tags refer to their own inputs.
+fn substitute(value: &mut Value, variables: &HashMap<String, String>) {
+    match value {
+        Value::String(s) => *s = substitute_str(s, variables),
+        Value::Array(values) => values.iter_mut().for_each(|v| substitute(v, variables)),
+        Value::Object(fields) => fields
+            .iter_mut()
+            .filter(|(key, _)| *key != "inline")
+            .for_each(|(_, v)| substitute(v, variables)),
+        _ => {}
+    }
+}
+
+/// Returns whether `tag` is in a namespace the renderer substitutes, so that
+/// it is a mistake rather than a runtime value if left.
+fn is_resolved(tag: &str) -> bool {
+    tag == "item"
+        || tag.starts_with("item.")
+        || tag.starts_with("inputs.")
+        || (tag.starts_with("workflow.parameters.") && tag != "workflow.parameters.json")
+}
+
+/// Collects the tags left in a serialized template. Within steps and DAG
+/// tasks, item tags are substituted by each iteration and are skipped.
+fn collect_tags(value: &Value, nodes: bool, found: &mut Vec<String>) {
+    match value {
+        Value::String(s) => {
+            for tag in tags(s) {
+                let skipped = nodes && (tag == "item" || tag.starts_with("item."));
+                if !skipped && !found.iter().any(|t| t == tag) {
+                    found.push(String::from(tag));
+                }
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|v| collect_tags(v, nodes, found)),
+        Value::Object(fields) => {
+            for (key, v) in fields.iter().filter(|(key, _)| *key != "inline") {
+                collect_tags(v, nodes || key == "steps" || key == "dag", found);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn render(spec: Value) -> Vec<RenderedTemplate> {
+        let spec: WorkflowSpec = serde_json::from_value(spec).unwrap();
+        Renderer::new(&spec).render().unwrap()
+    }
+
+    #[test]
+    fn unresolved_tags() {
+        let rendered = render(json!({
+            "entrypoint": "main",
+            "arguments": {"parameters": [{"name": "env", "value": "prod"}]},
+            "templates": [
+                {
+                    "name": "main",
+                    "dag": {"tasks": [
+                        {"name": "gen", "template": "gen"},
+                        {
+                            "name": "use",
+                            "template": "use",
+                            "arguments": {"parameters": [
+                                {"name": "msg", "value": "{{tasks.gen.outputs.result}}"},
+                            ]},
+                            "withItems": [{"os": "linux"}],
+                        },
+                    ]},
+                },
+                {"name": "gen", "script": {"image": "alpine", "source": "echo {{workflow.parameters.env}}"}},
+                {
+                    "name": "use",
+                    "inputs": {
+                        "parameters": [{"name": "msg"}],
+                        "artifacts": [{"name": "data", "path": "/tmp/data"}],
+                    },
+                    "container": {
+                        "image": "alpine",
+                        "args": [
+                            "{{inputs.parameters.msg}}",
+                            "{{inputs.parameters.tpyo}}",
+                            "{{inputs.artifacts.data.path}}",
+                            "{{workflow.parameters.missing}}",
+                            "{{workflow.parameters.json}}",
+                            "{{pod.name}}",
+                        ],
+                    },
+                },
+            ],
+        }));
+        let paths: Vec<&str> = rendered.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["main", "main.gen", "main.use(0:{\"os\":\"linux\"})"]
+        );
+
+        assert_eq!(rendered[0].runtime_tags, ["tasks.gen.outputs.result"]);
+        assert!(rendered[0].unresolved_tags.is_empty());
+        assert!(rendered[1].runtime_tags.is_empty());
+        assert!(rendered[1].unresolved_tags.is_empty());
+
+        let args = rendered[2]
+            .template
+            .container
+            .as_ref()
+            .unwrap()
+            .args
+            .clone();
+        assert_eq!(
+            args.unwrap()[..3],
+            [
+                "{{tasks.gen.outputs.result}}",
+                "{{inputs.parameters.tpyo}}",
+                "/tmp/data"
+            ]
+        );
+        assert_eq!(
+            rendered[2].runtime_tags,
+            [
+                "tasks.gen.outputs.result",
+                "workflow.parameters.json",
+                "pod.name"
+            ]
+        );
+        assert_eq!(
+            rendered[2].unresolved_tags,
+            ["inputs.parameters.tpyo", "workflow.parameters.missing"]
+        );
+    }
+
+    #[test]
+    fn unresolved_item_tags() {
+        let rendered = render(json!({
+            "entrypoint": "main",
+            "templates": [
+                {
+                    "name": "main",
+                    "steps": [[{
+                        "name": "echo",
+                        "template": "echo",
+                        "arguments": {"parameters": [{"name": "msg", "value": "{{item.name}}"}]},
+                        "withItems": ["a"],
+                    }]],
+                },
+                {
+                    "name": "echo",
+                    "inputs": {"parameters": [{"name": "msg"}]},
+                    "container": {"image": "alpine", "args": ["{{inputs.parameters.msg}}"]},
+                },
+            ],
+        }));
+        assert!(rendered[0].unresolved_tags.is_empty());
+        assert_eq!(rendered[1].unresolved_tags, ["item.name"]);
+    }
+}
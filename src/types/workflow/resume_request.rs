@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// `ResumeRequest` is the body of `resume_workflow`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ResumeRequest {
+    #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(rename = "namespace", skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+
+    /// `NodeFieldSelector` selects the suspended nodes to resume, e.g.
+    /// `displayName=approve`. The whole workflow is resumed when unset.
+    #[serde(rename = "nodeFieldSelector", skip_serializing_if = "Option::is_none")]
+    pub node_field_selector: Option<String>,
+}
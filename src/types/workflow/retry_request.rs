@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// `RetryRequest` is the body of `retry_workflow`, which reruns the failed
+/// nodes of a completed workflow.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RetryRequest {
+    #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(rename = "namespace", skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+
+    /// `NodeFieldSelector` selects succeeded nodes to rerun as well, when
+    /// `restart_successful` is set.
+    #[serde(rename = "nodeFieldSelector", skip_serializing_if = "Option::is_none")]
+    pub node_field_selector: Option<String>,
+
+    /// `Parameters` overrides global parameters, as `name=value`.
+    #[serde(rename = "parameters", skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Vec<String>>,
+
+    /// `RestartSuccessful` reruns the succeeded nodes matching
+    /// `node_field_selector`.
+    #[serde(rename = "restartSuccessful", skip_serializing_if = "Option::is_none")]
+    pub restart_successful: Option<bool>,
+}
@@ -0,0 +1,2133 @@
+use std::collections::{BTreeMap, HashMap};
+
+use k8s_openapi::api::core::v1::ResourceRequirements;
+use serde_json::{json, Map, Value};
+
+use super::references::tags;
+use super::render::{item_value, substitute_str};
+use super::{LifecycleHook, NodeFlag, NodeStatus, Parameter, TemplateRef};
+use super::{WorkflowSpec, WorkflowStatus, WorkflowStep};
+use crate::error::{ExpressionError, SimulationError};
+use crate::expr::{self, Context, Expression};
+use crate::types::template::{depends_tasks, ContinueOn, DAGTask, Inputs, Outputs, Template};
+use crate::types::time::format_timestamp;
+use crate::types::{Backoff, RetryStrategy};
+
+/// Duration of a leaf node, in seconds, when its `Outcome` does not set one.
+const DEFAULT_DURATION: u64 = 10;
+
+/// Depth of nested template calls after which a simulation stops, to catch
+/// recursive templates which never end.
+const MAX_DEPTH: usize = 100;
+
+/// Number of attempts after which a node retried without a limit fails the
+/// simulation.
+const MAX_ATTEMPTS: u32 = 100;
+
+/// `Outcome` is the result a stub of a `Simulator` gives to a leaf node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+    /// Phase of the node: `Succeeded`, `Failed` or `Error`.
+    pub phase: String,
+    /// Message of the node, e.g. the reason it failed.
+    pub message: Option<String>,
+    /// Outputs of the node. Pods are given an exit code matching their
+    /// phase unless one is set.
+    pub outputs: Outputs,
+    /// How long the node runs, in seconds.
+    pub duration: u64,
+    /// Name of the Kubernetes node the pod runs on.
+    pub host_node_name: Option<String>,
+}
+
+impl Outcome {
+    fn new(phase: &str, message: Option<&str>) -> Self {
+        Outcome {
+            phase: String::from(phase),
+            message: message.map(String::from),
+            outputs: Outputs::new(),
+            duration: DEFAULT_DURATION,
+            host_node_name: None,
+        }
+    }
+
+    /// Constructs an `Outcome` for a node which succeeds.
+    pub fn succeeded() -> Self {
+        Outcome::new("Succeeded", None)
+    }
+
+    /// Constructs an `Outcome` for a node which fails with `message`.
+    pub fn failed(message: &str) -> Self {
+        Outcome::new("Failed", Some(message))
+    }
+
+    /// Constructs an `Outcome` for a node which errors with `message`.
+    pub fn errored(message: &str) -> Self {
+        Outcome::new("Error", Some(message))
+    }
+
+    /// Sets the result of the node, e.g. the standard output of a script.
+    pub fn result(mut self, result: &str) -> Self {
+        self.outputs.result = Some(String::from(result));
+        self
+    }
+
+    /// Adds the output parameter `name`.
+    pub fn parameter(mut self, name: &str, value: &str) -> Self {
+        let mut param = Parameter::new(name);
+        param.value = Some(String::from(value));
+        self.outputs
+            .parameters
+            .get_or_insert_with(Vec::new)
+            .push(param);
+        self
+    }
+
+    /// Sets the exit code of the main container of the pod.
+    pub fn exit_code(mut self, code: i32) -> Self {
+        self.outputs.exit_code = Some(code.to_string());
+        self
+    }
+
+    /// Sets how long the node runs, in seconds.
+    pub fn duration(mut self, seconds: u64) -> Self {
+        self.duration = seconds;
+        self
+    }
+
+    /// Sets the name of the Kubernetes node the pod runs on.
+    pub fn host_node_name(mut self, name: &str) -> Self {
+        self.host_node_name = Some(String::from(name));
+        self
+    }
+}
+
+/// `Leaf` describes the leaf node a stub gives an `Outcome` to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Leaf<'a> {
+    /// Name of the node, e.g. `my-workflow[0].build(0:linux)`.
+    pub name: &'a str,
+    /// Display name of the node, e.g. `build(0:linux)`.
+    pub display_name: &'a str,
+    /// Name of the template run by the node. For a `template_ref`, the name
+    /// of the template in the referenced `WorkflowTemplate`.
+    pub template: &'a str,
+    /// Values of the input parameters of the node.
+    pub parameters: &'a HashMap<String, String>,
+    /// Index of the attempt, counting from 0, for templates with a
+    /// `RetryStrategy`.
+    pub attempt: u32,
+}
+
+type Stub<'a> = Box<dyn Fn(&Leaf<'_>) -> Outcome + 'a>;
+
+/// Values of the tags of a template, by name (e.g. `inputs.parameters.x`).
+type Scope = HashMap<String, String>;
+
+/// A `Simulator` runs a `WorkflowSpec` without a cluster, to test its
+/// control flow.
+///
+/// Starting at the entrypoint, it expands steps groups, DAG tasks and their
+/// `with_items`, `with_param` and `with_sequence` loops, evaluates `when`
+/// and `depends` conditions, and applies `ContinueOn`, `RetryStrategy`,
+/// `fail_fast`, `parallelism`, lifecycle hooks and exit handlers. Leaf nodes
+/// (pods, suspend, HTTP and plugin templates, and templates of a
+/// `template_ref`) are not run: the stub registered for their template
+/// decides their `Outcome`, and they succeed when there is none.
+///
+/// The result is the `WorkflowStatus` the controller would report, with
+/// node names, IDs, types and children following the same conventions, and
+/// times counted from the start time of the simulation.
+///
+/// As in the controller, a `when` condition is evaluated once its tags are
+/// substituted, words which are not variables being strings, so that
+/// `{{steps.flip.outputs.result}} == heads` can be written.
+pub struct Simulator<'a> {
+    spec: &'a WorkflowSpec,
+    name: String,
+    parameters: HashMap<String, String>,
+    start: u64,
+    stubs: HashMap<String, Stub<'a>>,
+    default_stub: Option<Stub<'a>>,
+}
+
+impl<'a> Simulator<'a> {
+    /// Constructs a new `Simulator` for `spec`, using the values and defaults
+    /// of the workflow parameters declared by the spec.
+    pub fn new(spec: &'a WorkflowSpec) -> Self {
+        let mut parameters = HashMap::new();
+        let params = spec
+            .arguments
+            .as_deref()
+            .and_then(|a| a.parameters.as_deref());
+        set_parameters(&mut parameters, params.unwrap_or_default());
+        Simulator {
+            spec,
+            name: String::from("workflow"),
+            parameters,
+            start: 0,
+            stubs: HashMap::new(),
+            default_stub: None,
+        }
+    }
+
+    /// Sets the workflow arguments, overriding the values declared by the spec.
+    pub fn arguments(mut self, arguments: &super::Arguments) -> Self {
+        let params = arguments.parameters.as_deref().unwrap_or_default();
+        set_parameters(&mut self.parameters, params);
+        self
+    }
+
+    /// Sets the name of the workflow, which prefixes the names and IDs of
+    /// the nodes. Defaults to `workflow`.
+    pub fn workflow_name(mut self, name: &str) -> Self {
+        self.name = String::from(name);
+        self
+    }
+
+    /// Sets when the workflow starts, in seconds since the Unix epoch.
+    /// Defaults to 0.
+    pub fn start_time(mut self, seconds: u64) -> Self {
+        self.start = seconds;
+        self
+    }
+
+    /// Registers the stub deciding the outcome of the leaf nodes running
+    /// `template`.
+    pub fn stub<F>(mut self, template: &str, stub: F) -> Self
+    where
+        F: Fn(&Leaf<'_>) -> Outcome + 'a,
+    {
+        self.stubs.insert(String::from(template), Box::new(stub));
+        self
+    }
+
+    /// Registers the stub deciding the outcome of the leaf nodes whose
+    /// template has no stub of its own.
+    pub fn default_stub<F>(mut self, stub: F) -> Self
+    where
+        F: Fn(&Leaf<'_>) -> Outcome + 'a,
+    {
+        self.default_stub = Some(Box::new(stub));
+        self
+    }
+
+    /// Runs the simulation, returning the status of the completed workflow.
+    pub fn run(&self) -> Result<WorkflowStatus, SimulationError> {
+        let entrypoint = self
+            .spec
+            .entrypoint
+            .as_deref()
+            .ok_or(SimulationError::MissingEntrypoint)?;
+        let template = self.template(entrypoint)?;
+
+        let mut globals = HashMap::new();
+        globals.insert(String::from("workflow.name"), self.name.clone());
+        globals.insert(String::from("workflow.status"), String::from("Running"));
+        for (name, value) in &self.parameters {
+            globals.insert(format!("workflow.parameters.{}", name), value.clone());
+        }
+        let mut run = Run {
+            sim: self,
+            globals,
+            nodes: HashMap::new(),
+            pods: Vec::new(),
+            depth: 0,
+        };
+
+        let mut hooks: Vec<(&String, &LifecycleHook)> = self
+            .spec
+            .hooks
+            .iter()
+            .flatten()
+            .filter(|(name, _)| *name != "exit")
+            .collect();
+        hooks.sort_by_key(|(name, _)| *name);
+        let mut settled = self.start;
+        let mut stats = Stats::default();
+        let mut pending = Vec::new();
+        for (name, hook) in hooks {
+            match run.workflow_hook(name, hook, self.start)? {
+                Some(done) => {
+                    settled = settled.max(done.settled);
+                    stats.add(&done.stats);
+                }
+                None => pending.push((name, hook)),
+            }
+        }
+
+        let root = NodeRef {
+            name: &self.name,
+            display: &self.name,
+            boundary: None,
+            hooked: false,
+        };
+        let target = Target::template(template);
+        let done = run.execute(&root, &target, &self.parameters, self.start)?;
+        settled = settled.max(done.settled);
+        stats.add(&done.stats);
+        let mut phase = done.phase;
+        let mut message = run.nodes[&done.id].message.clone();
+
+        let failures = run.failures();
+        run.globals.insert(
+            String::from("workflow.status"),
+            String::from(phase.as_str()),
+        );
+        run.globals
+            .insert(String::from("workflow.failures"), failures.to_string());
+        for (name, hook) in pending {
+            if let Some(done) = run.workflow_hook(name, hook, done.settled)? {
+                settled = settled.max(done.settled);
+                stats.add(&done.stats);
+            }
+        }
+
+        let exit = self.spec.on_exit.as_deref().map(Target::named).or_else(|| {
+            let hook = self.spec.hooks.as_ref()?.get("exit")?;
+            Some(Target::hook(hook))
+        });
+        if let Some(target) = exit {
+            let name = format!("{}.onExit", self.name);
+            let node = NodeRef {
+                name: &name,
+                display: &name,
+                boundary: None,
+                hooked: true,
+            };
+            let target = run.resolve(&name, target)?;
+            let scope = run.globals.clone();
+            let hook = self.spec.hooks.as_ref().and_then(|h| h.get("exit"));
+            let arguments = arguments_of(hook, &scope);
+            let exit = run.execute(&node, &target, &arguments, done.settled)?;
+            settled = settled.max(exit.settled);
+            stats.add(&exit.stats);
+            if exit.phase.is_failure() && phase == Phase::Succeeded {
+                phase = exit.phase;
+                message = run.nodes[&exit.id].message.clone();
+            }
+        }
+
+        let mut status = WorkflowStatus::new();
+        status.phase = Some(String::from(phase.as_str()));
+        status.message = message;
+        status.started_at = Some(format_timestamp(self.start as i64));
+        status.finished_at = Some(format_timestamp(settled as i64));
+        status.progress = Some(stats.progress());
+        status.resources_duration = Some(stats.resources);
+        status.outputs = run.nodes[&done.id].outputs.clone();
+        status.nodes = Some(run.nodes);
+        Ok(status)
+    }
+
+    fn template(&self, name: &str) -> Result<&'a Template, SimulationError> {
+        self.spec
+            .templates
+            .iter()
+            .flatten()
+            .find(|t| t.name.as_deref() == Some(name))
+            .ok_or_else(|| SimulationError::UnknownTemplate(String::from(name)))
+    }
+
+    fn stub_for(&self, template: &str) -> Option<&Stub<'a>> {
+        self.stubs.get(template).or(self.default_stub.as_ref())
+    }
+
+    /// Returns the ID the controller gives to the node `name`: the
+    /// workflow name for the root node, followed by the FNV-1a hash of the
+    /// node name for the others.
+    fn node_id(&self, name: &str) -> String {
+        if name == self.name {
+            return self.name.clone();
+        }
+        let hash = name.bytes().fold(0x811c_9dc5_u32, |h, b| {
+            (h ^ b as u32).wrapping_mul(0x0100_0193)
+        });
+        format!("{}-{}", self.name, hash)
+    }
+}
+
+fn set_parameters(values: &mut HashMap<String, String>, params: &[Parameter]) {
+    for param in params {
+        if let Some(value) = param.value.as_ref().or(param.default.as_ref()) {
+            values.insert(param.name.clone(), value.clone());
+        }
+    }
+}
+
+/// Phase of a simulated node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    Succeeded,
+    Failed,
+    Error,
+    Skipped,
+    Omitted,
+}
+
+impl Phase {
+    fn as_str(self) -> &'static str {
+        match self {
+            Phase::Succeeded => "Succeeded",
+            Phase::Failed => "Failed",
+            Phase::Error => "Error",
+            Phase::Skipped => "Skipped",
+            Phase::Omitted => "Omitted",
+        }
+    }
+
+    fn is_failure(self) -> bool {
+        matches!(self, Phase::Failed | Phase::Error)
+    }
+
+    /// Returns whether a failure in this phase is tolerated by `continue_on`.
+    fn is_tolerated(self, continue_on: Option<&ContinueOn>) -> bool {
+        let continue_on = continue_on.cloned().unwrap_or_default();
+        match self {
+            Phase::Failed => continue_on.failed == Some(true),
+            Phase::Error => continue_on.error == Some(true),
+            _ => true,
+        }
+    }
+}
+
+/// Number of succeeded and total pods, and resource usage, of a subtree.
+#[derive(Clone, Debug, Default)]
+struct Stats {
+    succeeded: u32,
+    total: u32,
+    resources: HashMap<String, i64>,
+}
+
+impl Stats {
+    fn add(&mut self, other: &Stats) {
+        self.succeeded += other.succeeded;
+        self.total += other.total;
+        for (resource, duration) in &other.resources {
+            *self.resources.entry(resource.clone()).or_default() += duration;
+        }
+    }
+
+    fn progress(&self) -> String {
+        format!("{}/{}", self.succeeded, self.total)
+    }
+}
+
+/// A node run by the simulation.
+#[derive(Clone, Debug)]
+struct Done {
+    id: String,
+    phase: Phase,
+    finished: u64,
+    /// When the node and its hooks have completed.
+    settled: u64,
+    stats: Stats,
+}
+
+/// Name and placement of a node about to be created.
+struct NodeRef<'n> {
+    name: &'n str,
+    display: &'n str,
+    boundary: Option<&'n str>,
+    hooked: bool,
+}
+
+/// The template run by a node: a template of the spec, or a template of a
+/// `WorkflowTemplate` which is simulated as a leaf.
+#[derive(Clone, Debug)]
+struct Target<'a> {
+    template: Option<&'a Template>,
+    name: String,
+    reference: Option<TemplateRef>,
+}
+
+impl<'a> Target<'a> {
+    fn template(template: &'a Template) -> Self {
+        Target {
+            template: Some(template),
+            name: template.name.clone().unwrap_or_default(),
+            reference: None,
+        }
+    }
+
+    /// A target to resolve by name.
+    fn named(name: &str) -> Self {
+        Target {
+            template: None,
+            name: String::from(name),
+            reference: None,
+        }
+    }
+
+    fn reference(reference: TemplateRef) -> Self {
+        Target {
+            template: None,
+            name: reference.template.clone().unwrap_or_default(),
+            reference: Some(reference),
+        }
+    }
+
+    fn hook(hook: &LifecycleHook) -> Self {
+        match hook.template_ref.as_deref() {
+            Some(reference) => Target::reference(reference.clone()),
+            None => Target::named(hook.template.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+/// The part of a step or a DAG task needed to run it.
+struct Invocation<'a> {
+    /// `steps` or `tasks`, the prefix of the variables it sets.
+    prefix: &'static str,
+    name: &'a str,
+    target: Option<Target<'a>>,
+    inline: Option<&'a Template>,
+    parameters: &'a [Parameter],
+    when: Option<&'a str>,
+    continue_on: Option<&'a ContinueOn>,
+    hooks: Option<&'a HashMap<String, LifecycleHook>>,
+    on_exit: Option<&'a str>,
+    with_items: Option<&'a [Value]>,
+    with_param: Option<&'a str>,
+    with_sequence: Option<&'a crate::types::template::Sequence>,
+}
+
+impl<'a> From<&'a WorkflowStep> for Invocation<'a> {
+    fn from(step: &'a WorkflowStep) -> Self {
+        let target = match (step.template_ref.as_deref(), step.template.as_deref()) {
+            (Some(reference), _) => Some(Target::reference(reference.clone())),
+            (None, Some(name)) => Some(Target::named(name)),
+            (None, None) => None,
+        };
+        Invocation {
+            prefix: "steps",
+            name: step.name.as_deref().unwrap_or_default(),
+            target,
+            inline: step.inline.as_deref(),
+            parameters: step
+                .arguments
+                .as_deref()
+                .and_then(|a| a.parameters.as_deref())
+                .unwrap_or_default(),
+            when: step.when.as_deref(),
+            continue_on: step.continue_on.as_deref(),
+            hooks: step.hooks.as_ref(),
+            on_exit: step.on_exit.as_deref(),
+            with_items: step.with_items.as_deref(),
+            with_param: step.with_param.as_deref(),
+            with_sequence: step.with_sequence.as_deref(),
+        }
+    }
+}
+
+impl<'a> From<&'a DAGTask> for Invocation<'a> {
+    fn from(task: &'a DAGTask) -> Self {
+        let target = match (task.template_ref.as_deref(), task.template.as_deref()) {
+            (Some(reference), _) => Some(Target::reference(TemplateRef {
+                cluster_scope: reference.cluster_scope,
+                name: reference.name.clone(),
+                template: reference.template.clone(),
+            })),
+            (None, Some(name)) => Some(Target::named(name)),
+            (None, None) => None,
+        };
+        Invocation {
+            prefix: "tasks",
+            name: &task.name,
+            target,
+            inline: task.inline.as_deref(),
+            parameters: task
+                .arguments
+                .as_deref()
+                .and_then(|a| a.parameters.as_deref())
+                .unwrap_or_default(),
+            when: task.when.as_deref(),
+            continue_on: task.continue_on.as_deref(),
+            hooks: task.hooks.as_ref(),
+            on_exit: task.on_exit.as_deref(),
+            with_items: task.with_items.as_deref(),
+            with_param: task.with_param.as_deref(),
+            with_sequence: task.with_sequence.as_deref(),
+        }
+    }
+}
+
+/// Start times handed out to the children of a node, limited by its
+/// `parallelism`.
+struct Slots {
+    /// When each slot is free, if the parallelism is limited.
+    free: Option<Vec<u64>>,
+}
+
+impl Slots {
+    fn new(parallelism: Option<i32>) -> Self {
+        Slots {
+            free: parallelism.filter(|p| *p > 0).map(|p| vec![0; p as usize]),
+        }
+    }
+
+    /// Returns when a child ready at `ready` can start, and the slot it uses.
+    fn acquire(&self, ready: u64) -> (u64, usize) {
+        match &self.free {
+            Some(free) => {
+                let (slot, time) = free
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, t)| **t)
+                    .expect("parallelism is positive");
+                (ready.max(*time), slot)
+            }
+            None => (ready, 0),
+        }
+    }
+
+    fn release(&mut self, slot: usize, time: u64) {
+        if let Some(free) = &mut self.free {
+            free[slot] = time;
+        }
+    }
+}
+
+struct Run<'s, 'a> {
+    sim: &'s Simulator<'a>,
+    /// Workflow variables, including the workflow parameters.
+    globals: HashMap<String, String>,
+    nodes: HashMap<String, NodeStatus>,
+    /// When the pods run, to apply the workflow `parallelism`.
+    pods: Vec<(u64, u64)>,
+    depth: usize,
+}
+
+impl<'a> Run<'_, 'a> {
+    fn create(&mut self, node: &NodeRef<'_>, kind: &str, start: u64) -> String {
+        let id = self.sim.node_id(node.name);
+        let mut status = NodeStatus::new(&id, node.name, kind);
+        status.display_name = Some(String::from(node.display));
+        status.boundary_id = node.boundary.map(String::from);
+        status.started_at = Some(format_timestamp(start as i64));
+        if node.hooked {
+            status.node_flag = Some(Box::new(NodeFlag {
+                hooked: Some(true),
+                retried: None,
+            }));
+        }
+        self.nodes.insert(id.clone(), status);
+        id
+    }
+
+    fn node(&mut self, id: &str) -> &mut NodeStatus {
+        self.nodes.get_mut(id).expect("node was created")
+    }
+
+    fn add_child(&mut self, parent: &str, child: &str) {
+        let children = self.node(parent).children.get_or_insert_with(Vec::new);
+        if !children.iter().any(|c| c == child) {
+            children.push(String::from(child));
+        }
+    }
+
+    /// Completes the node `id` and returns it as `Done`.
+    fn complete(&mut self, id: &str, phase: Phase, finished: u64, stats: Stats) -> Done {
+        let node = self.node(id);
+        node.phase = Some(String::from(phase.as_str()));
+        node.finished_at = Some(format_timestamp(finished as i64));
+        if stats.total > 0 {
+            node.progress = Some(stats.progress());
+            node.resources_duration = Some(stats.resources.clone());
+        }
+        Done {
+            id: String::from(id),
+            phase,
+            finished,
+            settled: finished,
+            stats,
+        }
+    }
+
+    /// Returns the nodes the children of `id` hang from: the node itself
+    /// for leaves, and the last nodes run within it otherwise.
+    fn outbound(&self, id: &str) -> Vec<String> {
+        let node = &self.nodes[id];
+        match node.r#type.as_str() {
+            "Steps" | "DAG" => node.outbound_nodes.clone().unwrap_or_default(),
+            "Retry" => match node.children.as_deref().and_then(|c| c.last()) {
+                Some(last) => self.outbound(last),
+                None => vec![String::from(id)],
+            },
+            "StepGroup" | "TaskGroup" => {
+                let children = node.children.as_deref().unwrap_or_default();
+                let boundary = node.boundary_id.as_deref();
+                let outbound: Vec<String> = children
+                    .iter()
+                    .filter(|c| self.nodes[*c].boundary_id.as_deref() == boundary)
+                    .flat_map(|c| self.outbound(c))
+                    .collect();
+                if outbound.is_empty() {
+                    vec![String::from(id)]
+                } else {
+                    outbound
+                }
+            }
+            _ => vec![String::from(id)],
+        }
+    }
+
+    /// Resolves a target given by name to the template of the spec.
+    fn resolve(&self, node: &str, target: Target<'a>) -> Result<Target<'a>, SimulationError> {
+        if target.template.is_some() || target.reference.is_some() {
+            return Ok(target);
+        }
+        if target.name.is_empty() {
+            return Err(SimulationError::InvalidValue {
+                node: String::from(node),
+                field: "template",
+                value: String::new(),
+            });
+        }
+        self.sim.template(&target.name).map(Target::template)
+    }
+
+    /// Runs `target` as the node `node`, given the values of its input
+    /// parameters, retrying it as its `RetryStrategy` says.
+    fn execute(
+        &mut self,
+        node: &NodeRef<'_>,
+        target: &Target<'a>,
+        arguments: &HashMap<String, String>,
+        start: u64,
+    ) -> Result<Done, SimulationError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(SimulationError::RecursionLimit(String::from(node.name)));
+        }
+        self.depth += 1;
+
+        let mut inputs = arguments.clone();
+        let mut params = Vec::new();
+        if let Some(template) = target.template {
+            inputs.clear();
+            let declared = template
+                .inputs
+                .as_deref()
+                .and_then(|i| i.parameters.as_ref());
+            for param in declared.into_iter().flatten() {
+                let value = arguments
+                    .get(&param.name)
+                    .or(param.value.as_ref())
+                    .or(param.default.as_ref())
+                    .ok_or_else(|| SimulationError::MissingParameter {
+                        template: target.name.clone(),
+                        name: param.name.clone(),
+                    })?;
+                let value = substitute_str(value, &self.globals);
+                let mut param = param.clone();
+                param.value = Some(value.clone());
+                inputs.insert(param.name.clone(), value);
+                params.push(param);
+            }
+        } else {
+            let mut names: Vec<&String> = arguments.keys().collect();
+            names.sort();
+            for name in names {
+                let mut param = Parameter::new(name);
+                param.value = Some(arguments[name].clone());
+                params.push(param);
+            }
+        }
+
+        let retry = target
+            .template
+            .and_then(|t| t.retry_strategy.as_deref())
+            .or_else(|| {
+                let defaults = self.sim.spec.template_defaults.as_deref()?;
+                defaults.retry_strategy.as_deref()
+            })
+            .or(self.sim.spec.retry_strategy.as_deref());
+        let done = match retry {
+            Some(retry) => self.retry(node, target, &inputs, &params, retry, start),
+            None => self.attempt(node, target, &inputs, &params, 0, start),
+        };
+        self.depth -= 1;
+        done
+    }
+
+    fn retry(
+        &mut self,
+        node: &NodeRef<'_>,
+        target: &Target<'a>,
+        inputs: &HashMap<String, String>,
+        params: &[Parameter],
+        retry: &RetryStrategy,
+        start: u64,
+    ) -> Result<Done, SimulationError> {
+        let id = self.create(node, "Retry", start);
+        self.describe(&id, target, params);
+
+        let scope = self.scope(inputs);
+        let limit = match retry.limit.as_deref() {
+            Some(limit) => {
+                let value = substitute_str(limit, &scope);
+                let limit =
+                    value
+                        .trim()
+                        .parse::<u32>()
+                        .map_err(|_| SimulationError::InvalidValue {
+                            node: String::from(node.name),
+                            field: "retryStrategy.limit",
+                            value,
+                        })?;
+                Some(limit)
+            }
+            None => None,
+        };
+
+        let mut attempt = 0;
+        let mut begin = start;
+        let mut stats = Stats::default();
+        let last = loop {
+            let name = format!("{}({})", node.name, attempt);
+            let display = format!("{}({})", node.display, attempt);
+            let child = NodeRef {
+                name: &name,
+                display: &display,
+                boundary: node.boundary,
+                hooked: node.hooked,
+            };
+            let done = self.attempt(&child, target, inputs, params, attempt, begin)?;
+            self.add_child(&id, &done.id);
+            self.node(&done.id)
+                .node_flag
+                .get_or_insert_with(|| Box::new(NodeFlag::new()))
+                .retried = Some(true);
+            stats.add(&done.stats);
+
+            let retried = match retry.retry_policy.as_deref().unwrap_or("OnFailure") {
+                "Always" => done.phase.is_failure(),
+                "OnError" | "OnTransientError" => done.phase == Phase::Error,
+                _ => done.phase == Phase::Failed,
+            };
+            if !retried || limit.is_some_and(|l| attempt >= l) {
+                break done;
+            }
+            if let Some(expression) = retry.expression.as_deref() {
+                let last = &self.nodes[&done.id];
+                let mut scope = scope.clone();
+                let exit_code = last.outputs.as_deref().and_then(|o| o.exit_code.clone());
+                scope.insert(String::from("retries"), attempt.to_string());
+                scope.insert(String::from("lastRetry.status"), done.phase.as_str().into());
+                scope.insert(
+                    String::from("lastRetry.exitCode"),
+                    exit_code.unwrap_or_default(),
+                );
+                scope.insert(
+                    String::from("lastRetry.duration"),
+                    (done.finished - begin).to_string(),
+                );
+                scope.insert(
+                    String::from("lastRetry.message"),
+                    last.message.clone().unwrap_or_default(),
+                );
+                if !self.condition(&name, expression, &scope)? {
+                    break done;
+                }
+            }
+
+            let delay = backoff(retry.backoff.as_deref(), attempt);
+            if let Some(max) = retry
+                .backoff
+                .as_deref()
+                .and_then(|b| b.max_duration.as_deref())
+                .and_then(duration)
+            {
+                if done.finished + delay - start > max {
+                    break done;
+                }
+            }
+            attempt += 1;
+            if limit.is_none() && attempt >= MAX_ATTEMPTS {
+                return Err(SimulationError::RetryLimit(String::from(node.name)));
+            }
+            begin = done.finished + delay;
+        };
+
+        let (outputs, message) = {
+            let last = &self.nodes[&last.id];
+            (last.outputs.clone(), last.message.clone())
+        };
+        let node = self.node(&id);
+        node.outputs = outputs;
+        node.message = message;
+        Ok(self.complete(&id, last.phase, last.finished, stats))
+    }
+
+    /// Runs one attempt of `target` as the node `node`.
+    fn attempt(
+        &mut self,
+        node: &NodeRef<'_>,
+        target: &Target<'a>,
+        inputs: &HashMap<String, String>,
+        params: &[Parameter],
+        attempt: u32,
+        start: u64,
+    ) -> Result<Done, SimulationError> {
+        let scope = self.scope(inputs);
+        match target.template {
+            Some(template) if template.steps.is_some() => {
+                let id = self.create(node, "Steps", start);
+                self.describe(&id, target, params);
+                self.steps(&id, node, template, scope, start)
+            }
+            Some(template) if template.dag.is_some() => {
+                let id = self.create(node, "DAG", start);
+                self.describe(&id, target, params);
+                self.dag(&id, node, template, scope, start)
+            }
+            _ => self.leaf(node, target, inputs, params, attempt, start),
+        }
+    }
+
+    /// Sets the template and inputs of the node `id`.
+    fn describe(&mut self, id: &str, target: &Target<'a>, params: &[Parameter]) {
+        let node = self.node(id);
+        if target.reference.is_some() {
+            node.template_ref = target.reference.clone().map(Box::new);
+        } else {
+            node.template_name = Some(target.name.clone());
+        }
+        if !params.is_empty() {
+            node.inputs = Some(Box::new(Inputs {
+                artifacts: None,
+                parameters: Some(params.to_vec()),
+            }));
+        }
+        if target.template.and_then(|t| t.daemon) == Some(true) {
+            node.daemoned = Some(true);
+        }
+    }
+
+    /// Returns the variables of a template given its inputs.
+    fn scope(&self, inputs: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut scope = self.globals.clone();
+        for (name, value) in inputs {
+            scope.insert(format!("inputs.parameters.{}", name), value.clone());
+        }
+        scope
+    }
+
+    fn leaf(
+        &mut self,
+        node: &NodeRef<'_>,
+        target: &Target<'a>,
+        inputs: &HashMap<String, String>,
+        params: &[Parameter],
+        attempt: u32,
+        start: u64,
+    ) -> Result<Done, SimulationError> {
+        let template = target.template;
+        let kind = match template {
+            Some(t) if t.suspend.is_some() => "Suspend",
+            Some(t) if t.http.is_some() => "HTTP",
+            Some(t) if t.plugin.is_some() => "Plugin",
+            _ => "Pod",
+        };
+        let leaf = Leaf {
+            name: node.name,
+            display_name: node.display,
+            template: &target.name,
+            parameters: inputs,
+            attempt,
+        };
+        let outcome = match self.sim.stub_for(&target.name) {
+            Some(stub) => stub(&leaf),
+            None => Outcome::succeeded(),
+        };
+        let phase = match outcome.phase.as_str() {
+            "Succeeded" => Phase::Succeeded,
+            "Failed" => Phase::Failed,
+            "Error" => Phase::Error,
+            _ => {
+                return Err(SimulationError::InvalidOutcome {
+                    node: String::from(node.name),
+                    phase: outcome.phase,
+                })
+            }
+        };
+
+        let mut stats = Stats::default();
+        let mut outputs = outcome.outputs;
+        let begin = if kind == "Pod" {
+            if outputs.exit_code.is_none() && phase != Phase::Error {
+                let code = if phase == Phase::Succeeded { "0" } else { "1" };
+                outputs.exit_code = Some(String::from(code));
+            }
+            stats.total = 1;
+            stats.succeeded = u32::from(phase == Phase::Succeeded);
+            let (cpu, memory) = requests(template);
+            let seconds = outcome.duration as f64;
+            stats
+                .resources
+                .insert(String::from("cpu"), (seconds * cpu).ceil() as i64);
+            stats
+                .resources
+                .insert(String::from("memory"), (seconds * memory).ceil() as i64);
+            self.schedule_pod(start, outcome.duration)
+        } else {
+            start
+        };
+
+        let id = self.create(node, kind, begin);
+        self.describe(&id, target, params);
+        let status = self.node(&id);
+        status.message = outcome.message;
+        status.host_node_name = outcome.host_node_name;
+        if outputs != Outputs::new() {
+            status.outputs = Some(Box::new(outputs));
+        }
+        Ok(self.complete(&id, phase, begin + outcome.duration, stats))
+    }
+
+    /// Returns when a pod ready at `ready` starts, given the pods already
+    /// running and the workflow `parallelism`.
+    fn schedule_pod(&mut self, ready: u64, duration: u64) -> u64 {
+        let start = match self.sim.spec.parallelism.filter(|p| *p > 0) {
+            Some(limit) => {
+                let mut candidates: Vec<u64> = self
+                    .pods
+                    .iter()
+                    .map(|(_, end)| *end)
+                    .filter(|end| *end > ready)
+                    .collect();
+                candidates.push(ready);
+                candidates.sort_unstable();
+                candidates
+                    .into_iter()
+                    .find(|t| {
+                        let running = self.pods.iter().filter(|(s, e)| s <= t && t < e);
+                        running.count() < limit as usize
+                    })
+                    .unwrap_or(ready)
+            }
+            None => ready,
+        };
+        self.pods.push((start, start + duration));
+        start
+    }
+
+    fn steps(
+        &mut self,
+        id: &str,
+        node: &NodeRef<'_>,
+        template: &'a Template,
+        mut scope: HashMap<String, String>,
+        start: u64,
+    ) -> Result<Done, SimulationError> {
+        let fail_fast = template.fail_fast == Some(true);
+        let mut time = start;
+        let mut parents = vec![String::from(id)];
+        let mut phase = Phase::Succeeded;
+        let mut stats = Stats::default();
+
+        for (i, group) in template.steps.iter().flatten().enumerate() {
+            let name = format!("{}[{}]", node.name, i);
+            let display = format!("[{}]", i);
+            let group_node = NodeRef {
+                name: &name,
+                display: &display,
+                boundary: Some(id),
+                hooked: false,
+            };
+            let group_id = self.create(&group_node, "StepGroup", time);
+            for parent in &parents {
+                self.add_child(parent, &group_id);
+            }
+
+            let mut slots = Slots::new(template.parallelism);
+            let mut failure: Option<(u64, String)> = None;
+            let mut group_phase = Phase::Succeeded;
+            let mut finished = time;
+            let mut variables = Vec::new();
+            let mut group_stats = Stats::default();
+            for step in group {
+                let call = Invocation::from(step);
+                let step_name = format!("{}.{}", name, call.name);
+                let mut expansion = Vec::new();
+                for (suffix, item_scope) in self.items(&step_name, &call, &scope)? {
+                    let (begin, slot) = slots.acquire(time);
+                    if fail_fast && failure.as_ref().is_some_and(|(t, _)| *t <= begin) {
+                        continue;
+                    }
+                    let name = format!("{}{}", step_name, suffix);
+                    let display = format!("{}{}", call.name, suffix);
+                    let child = NodeRef {
+                        name: &name,
+                        display: &display,
+                        boundary: Some(id),
+                        hooked: false,
+                    };
+                    let done = self.invoke(&call, &child, &item_scope, begin)?;
+                    slots.release(slot, done.settled);
+                    self.add_child(&group_id, &done.id);
+                    finished = finished.max(done.settled);
+                    group_stats.add(&done.stats);
+                    if done.phase.is_failure() && !done.phase.is_tolerated(call.continue_on) {
+                        if group_phase == Phase::Succeeded {
+                            group_phase = done.phase;
+                        }
+                        let message = format!("child '{}' failed", done.id);
+                        if failure.as_ref().is_none_or(|(t, _)| done.finished < *t) {
+                            failure = Some((done.finished, message));
+                        }
+                    }
+                    expansion.push(done);
+                }
+                let looped = call.with_items.is_some()
+                    || call.with_param.is_some()
+                    || call.with_sequence.is_some();
+                variables.extend(self.variables(&call, &expansion, looped));
+            }
+            scope.extend(variables);
+            stats.add(&group_stats);
+
+            let message = failure.map(|(_, message)| message);
+            self.node(&group_id).message = message.clone();
+            self.complete(&group_id, group_phase, finished, group_stats);
+            parents = self.outbound(&group_id);
+            time = finished;
+            if group_phase.is_failure() {
+                phase = group_phase;
+                self.node(id).message = message;
+                break;
+            }
+        }
+
+        let outbound = parents.into_iter().filter(|p| p != id).collect();
+        let outputs = self.outputs(node.name, template, &scope)?;
+        let status = self.node(id);
+        status.outbound_nodes = Some(outbound);
+        status.outputs = outputs;
+        Ok(self.complete(id, phase, time, stats))
+    }
+
+    fn dag(
+        &mut self,
+        id: &str,
+        node: &NodeRef<'_>,
+        template: &'a Template,
+        mut scope: HashMap<String, String>,
+        start: u64,
+    ) -> Result<Done, SimulationError> {
+        let dag = template.dag.as_deref().expect("template is a DAG");
+        let tasks = &dag.tasks;
+        let index: HashMap<&str, usize> = tasks
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t.name.as_str(), i))
+            .collect();
+
+        let mut depends = Vec::with_capacity(tasks.len());
+        let mut dependencies = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            let expression = match (&task.depends, &task.dependencies) {
+                (Some(depends), _) => depends.clone(),
+                (None, Some(dependencies)) => dependencies.join(" && "),
+                (None, None) => String::new(),
+            };
+            let mut deps = Vec::new();
+            for dep in depends_tasks(&expression) {
+                let i = *index
+                    .get(dep)
+                    .ok_or_else(|| SimulationError::UnknownDependency {
+                        node: format!("{}.{}", node.name, task.name),
+                        task: String::from(dep),
+                    })?;
+                if !deps.contains(&i) {
+                    deps.push(i);
+                }
+            }
+            depends.push(expression);
+            dependencies.push(deps);
+        }
+
+        // Only the targets and the tasks they depend on run.
+        let mut needed = vec![dag.target.is_none(); tasks.len()];
+        let mut stack: Vec<usize> = Vec::new();
+        for target in dag.target.iter().flat_map(|t| t.split_whitespace()) {
+            let i = *index
+                .get(target)
+                .ok_or_else(|| SimulationError::UnknownDependency {
+                    node: String::from(node.name),
+                    task: String::from(target),
+                })?;
+            stack.push(i);
+        }
+        while let Some(i) = stack.pop() {
+            if !needed[i] {
+                needed[i] = true;
+                stack.extend(&dependencies[i]);
+            }
+        }
+
+        let fail_fast = dag.fail_fast.unwrap_or(true);
+        let mut slots = Slots::new(template.parallelism);
+        let mut failure: Option<u64> = None;
+        let mut states: Vec<TaskState> = vec![TaskState::Pending; tasks.len()];
+        let mut stats = Stats::default();
+        loop {
+            let ready = |i: usize, states: &[TaskState]| -> Option<u64> {
+                let mut ready = start;
+                for dep in &dependencies[i] {
+                    match &states[*dep] {
+                        TaskState::Pending => return None,
+                        TaskState::Done(task) => ready = ready.max(task.done.finished),
+                        TaskState::NotRun => {}
+                    }
+                }
+                Some(ready)
+            };
+            let next = (0..tasks.len())
+                .filter(|i| needed[*i] && matches!(states[*i], TaskState::Pending))
+                .filter_map(|i| Some((ready(i, &states)?, i)))
+                .min();
+            let Some((time, i)) = next else {
+                break;
+            };
+            let task = &tasks[i];
+            let call = Invocation::from(task);
+            let deps = &dependencies[i];
+
+            let skipped = deps.iter().any(|d| matches!(states[*d], TaskState::NotRun));
+            if skipped || fail_fast && failure.is_some_and(|f| f <= time) {
+                states[i] = TaskState::NotRun;
+                continue;
+            }
+
+            let name = format!("{}.{}", node.name, task.name);
+            let runs = depends[i].is_empty() || {
+                let expression = depends_expression(&depends[i], tasks);
+                let context = depends_context(deps, tasks, &states);
+                Expression::parse(&expression)
+                    .and_then(|e| e.evaluate_bool(&context))
+                    .map_err(|error| SimulationError::Expression {
+                        node: name.clone(),
+                        expression: depends[i].clone(),
+                        error,
+                    })?
+            };
+
+            let mut items = Vec::new();
+            let done = if !runs {
+                let child = NodeRef {
+                    name: &name,
+                    display: &task.name,
+                    boundary: Some(id),
+                    hooked: false,
+                };
+                let omitted = self.create(&child, "Skipped", time);
+                self.node(&omitted).message =
+                    Some(String::from("omitted: depends condition not met"));
+                self.complete(&omitted, Phase::Omitted, time, Stats::default())
+            } else if call.with_items.is_some()
+                || call.with_param.is_some()
+                || call.with_sequence.is_some()
+            {
+                let child = NodeRef {
+                    name: &name,
+                    display: &task.name,
+                    boundary: Some(id),
+                    hooked: false,
+                };
+                let group = self.create(&child, "TaskGroup", time);
+                let mut phase = Phase::Succeeded;
+                let mut finished = time;
+                let mut group_stats = Stats::default();
+                for (suffix, item_scope) in self.items(&name, &call, &scope)? {
+                    let (begin, slot) = slots.acquire(time);
+                    if fail_fast && failure.is_some_and(|f| f <= begin) {
+                        continue;
+                    }
+                    let item_name = format!("{}{}", name, suffix);
+                    let display = format!("{}{}", task.name, suffix);
+                    let item = NodeRef {
+                        name: &item_name,
+                        display: &display,
+                        boundary: Some(id),
+                        hooked: false,
+                    };
+                    let done = self.invoke(&call, &item, &item_scope, begin)?;
+                    slots.release(slot, done.settled);
+                    self.add_child(&group, &done.id);
+                    finished = finished.max(done.settled);
+                    group_stats.add(&done.stats);
+                    if done.phase.is_failure() {
+                        if !done.phase.is_tolerated(call.continue_on) {
+                            failure = Some(failure.map_or(done.finished, |f| f.min(done.finished)));
+                        }
+                        if phase == Phase::Succeeded {
+                            phase = done.phase;
+                        }
+                    }
+                    items.push(done);
+                }
+                self.complete(&group, phase, finished, group_stats)
+            } else {
+                let (begin, slot) = slots.acquire(time);
+                let child = NodeRef {
+                    name: &name,
+                    display: &task.name,
+                    boundary: Some(id),
+                    hooked: false,
+                };
+                let done = self.invoke(&call, &child, &scope, begin)?;
+                slots.release(slot, done.settled);
+                if done.phase.is_failure() && !done.phase.is_tolerated(call.continue_on) {
+                    failure = Some(failure.map_or(done.finished, |f| f.min(done.finished)));
+                }
+                items.push(done.clone());
+                done
+            };
+
+            if deps.is_empty() {
+                self.add_child(id, &done.id);
+            }
+            for dep in deps {
+                if let TaskState::Done(dep) = &states[*dep] {
+                    for parent in self.outbound(&dep.done.id) {
+                        self.add_child(&parent, &done.id);
+                    }
+                }
+            }
+            stats.add(&done.stats);
+            let looped = call.with_items.is_some()
+                || call.with_param.is_some()
+                || call.with_sequence.is_some();
+            let variables = if looped {
+                self.variables(&call, &items, true)
+            } else {
+                self.variables(&call, std::slice::from_ref(&done), false)
+            };
+            scope.extend(variables);
+            let daemoned = self.nodes[&done.id].daemoned == Some(true);
+            states[i] = TaskState::Done(Box::new(TaskDone {
+                done,
+                items: items.iter().map(|d| d.phase).collect(),
+                ran: runs,
+                daemoned,
+            }));
+        }
+        if (0..tasks.len()).any(|i| needed[i] && matches!(states[i], TaskState::Pending)) {
+            return Err(SimulationError::DependencyCycle(String::from(node.name)));
+        }
+
+        // A failure fails the DAG unless it is tolerated, or handled by a
+        // task which ran because of it.
+        let mut phase = Phase::Succeeded;
+        let mut message = None;
+        let mut finished = start;
+        let mut outbound = Vec::new();
+        for (i, state) in states.iter().enumerate() {
+            let TaskState::Done(task) = state else {
+                continue;
+            };
+            finished = finished.max(task.done.settled);
+            let dependents = (0..tasks.len()).filter(|j| dependencies[*j].contains(&i));
+            let mut dependents = dependents.filter_map(|j| match &states[j] {
+                TaskState::Done(dependent) => Some(dependent.ran),
+                _ => None,
+            });
+            let handled = dependents.clone().any(|ran| ran);
+            if dependents.next().is_none() {
+                outbound.extend(self.outbound(&task.done.id));
+            }
+            let task_phase = task.done.phase;
+            if task_phase.is_failure()
+                && !task_phase.is_tolerated(tasks[i].continue_on.as_deref())
+                && !handled
+                && phase == Phase::Succeeded
+            {
+                phase = task_phase;
+                message = Some(format!("child '{}' failed", task.done.id));
+            }
+        }
+
+        let outputs = self.outputs(node.name, template, &scope)?;
+        let status = self.node(id);
+        status.message = message;
+        status.outbound_nodes = Some(outbound);
+        status.outputs = outputs;
+        Ok(self.complete(id, phase, finished, stats))
+    }
+
+    /// Runs one expansion of a step or task: evaluates its `when` condition,
+    /// runs its template and then its hooks.
+    fn invoke(
+        &mut self,
+        call: &Invocation<'a>,
+        node: &NodeRef<'_>,
+        scope: &HashMap<String, String>,
+        start: u64,
+    ) -> Result<Done, SimulationError> {
+        if let Some(when) = call.when {
+            if !self.condition(node.name, when, scope)? {
+                let id = self.create(node, "Skipped", start);
+                let when = substitute_str(when, scope);
+                self.node(&id).message = Some(format!("when '{}' evaluated false", when));
+                return Ok(self.complete(&id, Phase::Skipped, start, Stats::default()));
+            }
+        }
+
+        let target = match (call.inline, &call.target) {
+            (Some(inline), _) => Target::template(inline),
+            (None, Some(target)) => self.resolve(node.name, target.clone())?,
+            (None, None) => self.resolve(node.name, Target::named(""))?,
+        };
+        let arguments = call
+            .parameters
+            .iter()
+            .map(|p| {
+                let value = p.value.as_deref().unwrap_or_default();
+                (p.name.clone(), substitute_str(value, scope))
+            })
+            .collect();
+        let mut done = self.execute(node, &target, &arguments, start)?;
+
+        // Hooks see the variables of the node they are attached to.
+        let mut scope = scope.clone();
+        scope.extend(self.variables(call, std::slice::from_ref(&done), false));
+        let mut hooks: Vec<(&String, &LifecycleHook)> = call.hooks.into_iter().flatten().collect();
+        hooks.sort_by_key(|(name, _)| *name);
+        let exit = call
+            .on_exit
+            .map(|name| (Target::named(name), None))
+            .or_else(|| {
+                let hook = call.hooks?.get("exit")?;
+                Some((Target::hook(hook), Some(hook)))
+            });
+        let mut runs = Vec::new();
+        for (name, hook) in hooks.into_iter().filter(|(name, _)| *name != "exit") {
+            let expression = hook.expression.as_deref().unwrap_or("false");
+            if self.condition(node.name, expression, &scope)? {
+                let name = format!("{}.hooks.{}", node.name, name);
+                runs.push((name, Target::hook(hook), Some(hook)));
+            }
+        }
+        if let Some((target, hook)) = exit {
+            runs.push((format!("{}.onExit", node.name), target, hook));
+        }
+        for (name, target, hook) in runs {
+            let target = self.resolve(&name, target)?;
+            let child = NodeRef {
+                name: &name,
+                display: &name,
+                boundary: node.boundary,
+                hooked: true,
+            };
+            let arguments = arguments_of(hook, &scope);
+            let hook = self.execute(&child, &target, &arguments, done.finished)?;
+            self.add_child(&done.id, &hook.id);
+            done.settled = done.settled.max(hook.settled);
+            done.stats.add(&hook.stats);
+        }
+        Ok(done)
+    }
+
+    /// Runs a workflow lifecycle hook if its expression is true.
+    fn workflow_hook(
+        &mut self,
+        name: &str,
+        hook: &LifecycleHook,
+        start: u64,
+    ) -> Result<Option<Done>, SimulationError> {
+        let node_name = format!("{}.hooks.{}", self.sim.name, name);
+        let scope = self.globals.clone();
+        let expression = hook.expression.as_deref().unwrap_or("false");
+        if !self.condition(&node_name, expression, &scope)? {
+            return Ok(None);
+        }
+        let target = self.resolve(&node_name, Target::hook(hook))?;
+        let node = NodeRef {
+            name: &node_name,
+            display: &node_name,
+            boundary: None,
+            hooked: true,
+        };
+        let arguments = arguments_of(Some(hook), &scope);
+        self.execute(&node, &target, &arguments, start).map(Some)
+    }
+
+    /// Returns the expansions of a step or task: the suffix of their name
+    /// and their variables. Steps and tasks without a loop have a single
+    /// expansion.
+    fn items(
+        &self,
+        node: &str,
+        call: &Invocation<'a>,
+        scope: &HashMap<String, String>,
+    ) -> Result<Vec<(String, Scope)>, SimulationError> {
+        let items = if let Some(items) = call.with_items {
+            items.to_vec()
+        } else if let Some(param) = call.with_param {
+            let value = substitute_str(param, scope);
+            if let Some(tag) = tags(&value).next() {
+                return Err(SimulationError::UnresolvedTag {
+                    node: String::from(node),
+                    tag: String::from(tag),
+                });
+            }
+            match serde_json::from_str(&value) {
+                Ok(Value::Array(items)) => items,
+                _ => {
+                    return Err(SimulationError::InvalidValue {
+                        node: String::from(node),
+                        field: "withParam",
+                        value,
+                    })
+                }
+            }
+        } else if let Some(sequence) = call.with_sequence {
+            let number = |field: &'static str, value: Option<&String>| {
+                let Some(value) = value else {
+                    return Ok(None);
+                };
+                let value = substitute_str(value, scope);
+                match value.trim().parse::<i64>() {
+                    Ok(n) => Ok(Some(n)),
+                    Err(_) => Err(SimulationError::InvalidValue {
+                        node: String::from(node),
+                        field,
+                        value,
+                    }),
+                }
+            };
+            let count = number("withSequence.count", sequence.count.as_ref())?;
+            let start = number("withSequence.start", sequence.start.as_ref())?.unwrap_or(0);
+            let end = number("withSequence.end", sequence.end.as_ref())?;
+            let numbers: Vec<i64> = match (count, end) {
+                (Some(count), _) => (start..start + count).collect(),
+                (None, Some(end)) if end >= start => (start..=end).collect(),
+                (None, Some(end)) => (end..=start).rev().collect(),
+                (None, None) => Vec::new(),
+            };
+            let format = sequence.format.as_deref();
+            numbers
+                .into_iter()
+                .map(|n| Value::String(format.map_or_else(|| n.to_string(), |f| sprintf(f, n))))
+                .collect()
+        } else {
+            return Ok(vec![(String::new(), scope.clone())]);
+        };
+
+        Ok(items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let mut scope = scope.clone();
+                scope.insert(String::from("item"), item_value(item));
+                if let Value::Object(fields) = item {
+                    for (field, value) in fields {
+                        scope.insert(format!("item.{}", field), item_value(value));
+                    }
+                }
+                (format!("({}:{})", i, item_value(item)), scope)
+            })
+            .collect())
+    }
+
+    /// Returns the variables set by a step or task once it has run, e.g.
+    /// `steps.<name>.outputs.result`. The outputs of loops are aggregated
+    /// in JSON lists.
+    fn variables(
+        &self,
+        call: &Invocation<'a>,
+        done: &[Done],
+        looped: bool,
+    ) -> Vec<(String, String)> {
+        let prefix = format!("{}.{}", call.prefix, call.name);
+        let mut variables = Vec::new();
+        if looped {
+            let outputs: Vec<Option<&Outputs>> = done
+                .iter()
+                .map(|d| self.nodes[&d.id].outputs.as_deref())
+                .collect();
+            let results: Vec<Value> = outputs
+                .iter()
+                .filter_map(|o| o.and_then(|o| o.result.clone()))
+                .map(|r| serde_json::from_str(&r).unwrap_or(Value::String(r)))
+                .collect();
+            let parameters: Vec<Value> = outputs
+                .iter()
+                .filter_map(|o| o.and_then(|o| o.parameters.as_ref()))
+                .map(|params| {
+                    let map: Map<String, Value> = params
+                        .iter()
+                        .map(|p| (p.name.clone(), p.value.clone().unwrap_or_default().into()))
+                        .collect();
+                    Value::Object(map)
+                })
+                .collect();
+            variables.push((
+                format!("{}.outputs.result", prefix),
+                Value::Array(results).to_string(),
+            ));
+            variables.push((
+                format!("{}.outputs.parameters", prefix),
+                Value::Array(parameters).to_string(),
+            ));
+            return variables;
+        }
+
+        let Some(done) = done.first() else {
+            return variables;
+        };
+        let node = &self.nodes[&done.id];
+        let mut set = |name: &str, value: Option<String>| {
+            if let Some(value) = value {
+                variables.push((format!("{}.{}", prefix, name), value));
+            }
+        };
+        set("id", Some(node.id.clone()));
+        set("status", node.phase.clone());
+        set("startedAt", node.started_at.clone());
+        set("finishedAt", node.finished_at.clone());
+        set("hostNodeName", node.host_node_name.clone());
+        if let Some(outputs) = node.outputs.as_deref() {
+            set("exitCode", outputs.exit_code.clone());
+            set("outputs.result", outputs.result.clone());
+            for param in outputs.parameters.iter().flatten() {
+                set(
+                    &format!("outputs.parameters.{}", param.name),
+                    param.value.clone(),
+                );
+            }
+        }
+        variables
+    }
+
+    /// Returns the outputs of a steps or DAG template, whose parameters
+    /// take their value from the steps or tasks it runs.
+    fn outputs(
+        &self,
+        node: &str,
+        template: &Template,
+        scope: &HashMap<String, String>,
+    ) -> Result<Option<Box<Outputs>>, SimulationError> {
+        let Some(mut outputs) = template.outputs.clone() else {
+            return Ok(None);
+        };
+        for param in outputs.parameters.iter_mut().flatten() {
+            let value_from = param.value_from.as_deref();
+            let value = if let Some(value) = value_from.and_then(|v| v.parameter.as_deref()) {
+                substitute_str(value, scope)
+            } else if let Some(source) = value_from.and_then(|v| v.expression.as_deref()) {
+                let value = expr::evaluate(source, &context(scope)).map_err(|error| {
+                    SimulationError::Expression {
+                        node: String::from(node),
+                        expression: String::from(source),
+                        error,
+                    }
+                })?;
+                item_value(&value)
+            } else if let Some(value) = param.value.as_deref() {
+                substitute_str(value, scope)
+            } else {
+                continue;
+            };
+            param.value = Some(value);
+        }
+        Ok(Some(outputs))
+    }
+
+    /// Evaluates a `when`, hook or retry condition: its tags are substituted
+    /// and the result is evaluated, words which are not variables being
+    /// strings.
+    fn condition(
+        &self,
+        node: &str,
+        source: &str,
+        scope: &HashMap<String, String>,
+    ) -> Result<bool, SimulationError> {
+        let substituted = substitute_str(source, scope);
+        if let Some(tag) = tags(&substituted).next() {
+            return Err(SimulationError::UnresolvedTag {
+                node: String::from(node),
+                tag: String::from(tag),
+            });
+        }
+        let error = |error| SimulationError::Expression {
+            node: String::from(node),
+            expression: substituted.clone(),
+            error,
+        };
+        let expression = Expression::parse(&substituted).map_err(error)?;
+        let mut context = context(scope);
+        loop {
+            match expression.evaluate_bool(&context) {
+                Err(ExpressionError::UnknownVariable { name, .. })
+                    if context.get(&name).is_none() =>
+                {
+                    context.insert(&name, name.clone());
+                }
+                result => return result.map_err(error),
+            }
+        }
+    }
+
+    /// Returns the failed pods, as in `{{workflow.failures}}`.
+    fn failures(&self) -> Value {
+        let mut failed: Vec<&NodeStatus> = self
+            .nodes
+            .values()
+            .filter(|n| n.r#type == "Pod")
+            .filter(|n| matches!(n.phase.as_deref(), Some("Failed" | "Error")))
+            .collect();
+        failed.sort_by(|a, b| (&a.finished_at, &a.name).cmp(&(&b.finished_at, &b.name)));
+        failed
+            .into_iter()
+            .map(|n| {
+                json!({
+                    "displayName": n.display_name,
+                    "message": n.message,
+                    "templateName": n.template_name,
+                    "phase": n.phase,
+                    "podName": n.id,
+                    "finishedAt": n.finished_at,
+                })
+            })
+            .collect()
+    }
+}
+
+/// State of a DAG task during a simulation.
+#[derive(Clone, Debug)]
+enum TaskState {
+    Pending,
+    /// The task is not run, because the DAG fails fast or a task it depends
+    /// on was not run.
+    NotRun,
+    Done(Box<TaskDone>),
+}
+
+#[derive(Clone, Debug)]
+struct TaskDone {
+    done: Done,
+    /// Phases of the expansions of the task.
+    items: Vec<Phase>,
+    /// Whether the `depends` condition of the task was met.
+    ran: bool,
+    daemoned: bool,
+}
+
+/// Rewrites a `depends` condition as an expression over the `tasks`
+/// variable. A task on its own means that it succeeded, was skipped or is
+/// daemoned, or failed while its `ContinueOn` allows it.
+fn depends_expression(depends: &str, tasks: &[DAGTask]) -> String {
+    let operand = |operand: &str| match operand.split_once('.') {
+        Some((task, result)) => format!("tasks[{:?}].{}", task, result),
+        None => {
+            let mut results = vec!["Succeeded", "Skipped", "Daemoned"];
+            let task = tasks.iter().find(|t| t.name == operand);
+            let continue_on = task.and_then(|t| t.continue_on.as_deref());
+            if continue_on.and_then(|c| c.failed) == Some(true) {
+                results.push("Failed");
+            }
+            if continue_on.and_then(|c| c.error) == Some(true) {
+                results.push("Errored");
+            }
+            let results: Vec<String> = results
+                .into_iter()
+                .map(|result| format!("tasks[{:?}].{}", operand, result))
+                .collect();
+            format!("({})", results.join(" || "))
+        }
+    };
+
+    let mut expression = String::with_capacity(depends.len());
+    let mut current = String::new();
+    for c in depends.chars() {
+        if c.is_whitespace() || "&|!()".contains(c) {
+            if !current.is_empty() {
+                expression.push_str(&operand(&current));
+                current.clear();
+            }
+            expression.push(c);
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        expression.push_str(&operand(&current));
+    }
+    expression
+}
+
+/// Returns the context a `depends` expression is evaluated in, giving the
+/// results of the tasks it depends on.
+fn depends_context(deps: &[usize], tasks: &[DAGTask], states: &[TaskState]) -> Context {
+    let mut results = Map::new();
+    for dep in deps {
+        let TaskState::Done(task) = &states[*dep] else {
+            continue;
+        };
+        let phase = task.done.phase;
+        let items = &task.items;
+        let result = json!({
+            "Succeeded": phase == Phase::Succeeded,
+            "Failed": phase == Phase::Failed,
+            "Errored": phase == Phase::Error,
+            "Skipped": phase == Phase::Skipped,
+            "Omitted": phase == Phase::Omitted,
+            "Daemoned": task.daemoned,
+            "AnySucceeded": items.contains(&Phase::Succeeded),
+            "AllFailed": !items.is_empty() && items.iter().all(|p| p.is_failure()),
+        });
+        results.insert(tasks[*dep].name.clone(), result);
+    }
+    Context::new().variable("tasks", Value::Object(results))
+}
+
+/// Returns the context holding the variables of `scope`.
+fn context(scope: &HashMap<String, String>) -> Context {
+    let mut context = Context::new();
+    let sorted: BTreeMap<&String, &String> = scope.iter().collect();
+    for (name, value) in sorted {
+        context.insert(name, value.as_str());
+    }
+    context
+}
+
+/// Returns the arguments given by a lifecycle hook.
+fn arguments_of(
+    hook: Option<&LifecycleHook>,
+    scope: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    hook.and_then(|h| h.arguments.as_deref())
+        .and_then(|a| a.parameters.as_deref())
+        .unwrap_or_default()
+        .iter()
+        .map(|p| {
+            let value = p.value.as_deref().unwrap_or_default();
+            (p.name.clone(), substitute_str(value, scope))
+        })
+        .collect()
+}
+
+/// Returns the delay, in seconds, before the retry following `attempt`.
+fn backoff(backoff: Option<&Backoff>, attempt: u32) -> u64 {
+    let Some(backoff) = backoff else {
+        return 0;
+    };
+    let base = backoff.duration.as_deref().and_then(duration).unwrap_or(0);
+    let factor = backoff
+        .factor
+        .as_deref()
+        .and_then(|f| f.trim().parse::<f64>().ok())
+        .unwrap_or(1.0);
+    (base as f64 * factor.powi(attempt as i32)) as u64
+}
+
+/// Parses a duration such as `30`, `90s` or `1h30m`, in seconds.
+fn duration(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if let Ok(seconds) = text.parse::<u64>() {
+        return Some(seconds);
+    }
+    let mut total = 0.0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let value: f64 = rest[..len].parse().ok()?;
+        rest = &rest[len..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 0.001,
+            _ => return None,
+        };
+        total += value * unit;
+        rest = &rest[unit_len..];
+    }
+    Some(total.ceil() as u64)
+}
+
+/// Formats `n` with a printf format holding a single integer verb, as
+/// `with_sequence` formats do (e.g. `file-%02d`).
+fn sprintf(format: &str, n: i64) -> String {
+    let Some(start) = format.find('%') else {
+        return String::from(format);
+    };
+    let spec = &format[start + 1..];
+    let Some(verb) = spec.find(|c: char| c.is_ascii_alphabetic()) else {
+        return String::from(format);
+    };
+    let flags = &spec[..verb];
+    let zero = flags.starts_with('0');
+    let width: usize = flags.trim_start_matches('0').parse().unwrap_or(0);
+    let digits = match &spec[verb..verb + 1] {
+        "x" => format!("{:x}", n),
+        "X" => format!("{:X}", n),
+        "o" => format!("{:o}", n),
+        _ => n.to_string(),
+    };
+    let padded = if zero {
+        format!("{:0>width$}", digits, width = width)
+    } else {
+        format!("{:>width$}", digits, width = width)
+    };
+    format!("{}{}{}", &format[..start], padded, &spec[verb + 1..])
+}
+
+/// Returns the CPU, in cores, and memory, in units of 100Mi, requested by
+/// the main container of a template, which is how the controller counts
+/// resource durations. Without requests, one core and 100Mi are counted.
+fn requests(template: Option<&Template>) -> (f64, f64) {
+    let resources: Option<&ResourceRequirements> = template.and_then(|t| {
+        t.container
+            .as_deref()
+            .and_then(|c| c.resources.as_ref())
+            .or_else(|| t.script.as_deref().and_then(|s| s.resources.as_deref()))
+    });
+    let quantity = |name: &str| {
+        let resources = resources?;
+        let requests = resources.requests.as_ref().and_then(|r| r.get(name));
+        let limits = resources.limits.as_ref().and_then(|l| l.get(name));
+        quantity(&requests.or(limits)?.0)
+    };
+    let cpu = quantity("cpu").unwrap_or(1.0);
+    let memory = quantity("memory").map_or(1.0, |m| m / (100.0 * 1024.0 * 1024.0));
+    (cpu, memory)
+}
+
+/// Parses a Kubernetes quantity such as `500m`, `2` or `256Mi`.
+fn quantity(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let suffixes: [(&str, f64); 12] = [
+        ("Ki", 1024.0),
+        ("Mi", 1024.0 * 1024.0),
+        ("Gi", 1024.0 * 1024.0 * 1024.0),
+        ("Ti", 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        ("n", 1e-9),
+        ("u", 1e-6),
+        ("m", 1e-3),
+        ("k", 1e3),
+        ("K", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+    ];
+    for (suffix, factor) in suffixes {
+        if let Some(number) = text.strip_suffix(suffix) {
+            return number.parse::<f64>().ok().map(|n| n * factor);
+        }
+    }
+    text.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn spec(value: Value) -> WorkflowSpec {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn node<'s>(status: &'s WorkflowStatus, name: &str) -> &'s NodeStatus {
+        let nodes = status.nodes.as_ref().unwrap();
+        nodes
+            .values()
+            .find(|n| n.name == name)
+            .unwrap_or_else(|| panic!("no node {}", name))
+    }
+
+    fn children(status: &WorkflowStatus, name: &str) -> Vec<String> {
+        let nodes = status.nodes.as_ref().unwrap();
+        let ids = node(status, name).children.clone().unwrap_or_default();
+        ids.iter().map(|id| nodes[id].name.clone()).collect()
+    }
+
+    fn phase<'s>(status: &'s WorkflowStatus, name: &str) -> &'s str {
+        node(status, name).phase.as_deref().unwrap_or_default()
+    }
+
+    #[test]
+    fn retry_with_limit() {
+        let spec = spec(json!({
+            "entrypoint": "main",
+            "templates": [{
+                "name": "main",
+                "retryStrategy": {"limit": "2"},
+                "container": {"image": "alpine"}
+            }]
+        }));
+
+        let status = Simulator::new(&spec)
+            .stub("main", |leaf| match leaf.attempt {
+                0 => Outcome::failed("flaky"),
+                _ => Outcome::succeeded(),
+            })
+            .run()
+            .unwrap();
+        assert_eq!(status.phase.as_deref(), Some("Succeeded"));
+        assert_eq!(node(&status, "workflow").r#type, "Retry");
+        assert_eq!(
+            children(&status, "workflow"),
+            ["workflow(0)", "workflow(1)"]
+        );
+        assert_eq!(phase(&status, "workflow(0)"), "Failed");
+        assert_eq!(phase(&status, "workflow(1)"), "Succeeded");
+
+        let status = Simulator::new(&spec)
+            .stub("main", |_| Outcome::failed("broken"))
+            .run()
+            .unwrap();
+        assert_eq!(status.phase.as_deref(), Some("Failed"));
+        assert_eq!(
+            children(&status, "workflow"),
+            ["workflow(0)", "workflow(1)", "workflow(2)"]
+        );
+        assert_eq!(phase(&status, "workflow(2)"), "Failed");
+    }
+
+    #[test]
+    fn depends_on_failed() {
+        let spec = spec(json!({
+            "entrypoint": "main",
+            "templates": [
+                {"name": "main", "dag": {"failFast": false, "tasks": [
+                    {"name": "build", "template": "build"},
+                    {"name": "report", "template": "report", "depends": "build.Failed"},
+                    {"name": "deploy", "template": "deploy", "depends": "build.Succeeded"}
+                ]}},
+                {"name": "build", "container": {"image": "alpine"}},
+                {"name": "report", "container": {"image": "alpine"}},
+                {"name": "deploy", "container": {"image": "alpine"}}
+            ]
+        }));
+
+        let status = Simulator::new(&spec)
+            .stub("build", |_| Outcome::failed("compile error"))
+            .run()
+            .unwrap();
+        assert_eq!(status.phase.as_deref(), Some("Succeeded"));
+        assert_eq!(children(&status, "workflow"), ["workflow.build"]);
+        assert_eq!(
+            children(&status, "workflow.build"),
+            ["workflow.report", "workflow.deploy"]
+        );
+        assert_eq!(phase(&status, "workflow.build"), "Failed");
+        assert_eq!(phase(&status, "workflow.report"), "Succeeded");
+        assert_eq!(phase(&status, "workflow.deploy"), "Omitted");
+        assert_eq!(node(&status, "workflow.deploy").r#type, "Skipped");
+    }
+
+    #[test]
+    fn fail_fast() {
+        let spec = spec(json!({
+            "entrypoint": "main",
+            "templates": [
+                {"name": "main", "failFast": true, "parallelism": 1, "steps": [[
+                    {"name": "a", "template": "work"},
+                    {"name": "b", "template": "work"}
+                ]]},
+                {"name": "work", "container": {"image": "alpine"}}
+            ]
+        }));
+
+        let status = Simulator::new(&spec)
+            .stub("work", |leaf| match leaf.display_name {
+                "a" => Outcome::failed("boom"),
+                _ => Outcome::succeeded(),
+            })
+            .run()
+            .unwrap();
+        assert_eq!(status.phase.as_deref(), Some("Failed"));
+        assert_eq!(children(&status, "workflow"), ["workflow[0]"]);
+        assert_eq!(children(&status, "workflow[0]"), ["workflow[0].a"]);
+        assert_eq!(phase(&status, "workflow[0]"), "Failed");
+        assert_eq!(status.nodes.as_ref().map(HashMap::len), Some(3));
+    }
+
+    #[test]
+    fn on_exit() {
+        let spec = spec(json!({
+            "entrypoint": "main",
+            "onExit": "exit-handler",
+            "templates": [
+                {"name": "main", "container": {"image": "alpine"}},
+                {"name": "exit-handler", "steps": [[
+                    {"name": "notify", "template": "notify", "when": "{{workflow.status}} == Failed"},
+                    {"name": "celebrate", "template": "notify", "when": "{{workflow.status}} == Succeeded"}
+                ]]},
+                {"name": "notify", "container": {"image": "alpine"}}
+            ]
+        }));
+
+        let status = Simulator::new(&spec)
+            .stub("main", |_| Outcome::failed("boom"))
+            .run()
+            .unwrap();
+        assert_eq!(status.phase.as_deref(), Some("Failed"));
+        assert_eq!(status.message.as_deref(), Some("boom"));
+        assert_eq!(phase(&status, "workflow"), "Failed");
+        assert_eq!(phase(&status, "workflow.onExit"), "Succeeded");
+        assert_eq!(
+            children(&status, "workflow.onExit[0]"),
+            ["workflow.onExit[0].notify", "workflow.onExit[0].celebrate"]
+        );
+        assert_eq!(phase(&status, "workflow.onExit[0].notify"), "Succeeded");
+        assert_eq!(phase(&status, "workflow.onExit[0].celebrate"), "Skipped");
+    }
+
+    #[test]
+    fn with_items() {
+        let spec = spec(json!({
+            "entrypoint": "main",
+            "templates": [
+                {"name": "main", "steps": [[{
+                    "name": "build",
+                    "template": "build",
+                    "arguments": {"parameters": [{"name": "os", "value": "{{item}}"}]},
+                    "withItems": ["linux", "darwin"]
+                }]]},
+                {
+                    "name": "build",
+                    "inputs": {"parameters": [{"name": "os"}]},
+                    "container": {"image": "alpine"}
+                }
+            ]
+        }));
+
+        let built = Cell::new(0);
+        let status = Simulator::new(&spec)
+            .stub("build", |leaf| {
+                built.set(built.get() + 1);
+                match leaf.parameters["os"].as_str() {
+                    "darwin" => Outcome::failed("no toolchain"),
+                    _ => Outcome::succeeded(),
+                }
+            })
+            .run()
+            .unwrap();
+        assert_eq!(built.get(), 2);
+        assert_eq!(status.phase.as_deref(), Some("Failed"));
+        assert_eq!(
+            children(&status, "workflow[0]"),
+            ["workflow[0].build(0:linux)", "workflow[0].build(1:darwin)"]
+        );
+        assert_eq!(phase(&status, "workflow[0].build(0:linux)"), "Succeeded");
+        assert_eq!(phase(&status, "workflow[0].build(1:darwin)"), "Failed");
+        assert_eq!(
+            node(&status, "workflow[0].build(1:darwin)")
+                .display_name
+                .as_deref(),
+            Some("build(1:darwin)")
+        );
+    }
+}
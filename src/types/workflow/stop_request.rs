@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// `StopRequest` is the body of `stop_workflow`. Unlike terminating it,
+/// stopping a workflow runs its exit handlers.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StopRequest {
+    /// `Message` set on the stopped nodes.
+    #[serde(rename = "message", skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(rename = "namespace", skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+
+    /// `NodeFieldSelector` selects the nodes to stop, e.g.
+    /// `displayName=step-a`. All the nodes are stopped when unset.
+    #[serde(rename = "nodeFieldSelector", skip_serializing_if = "Option::is_none")]
+    pub node_field_selector: Option<String>,
+}
@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// `SuspendRequest` is the body of `suspend_workflow`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SuspendRequest {
+    #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(rename = "namespace", skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// `TerminateRequest` is the body of `terminate_workflow`. Terminating a
+/// workflow does not run its exit handlers.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TerminateRequest {
+    #[serde(rename = "name", skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    #[serde(rename = "namespace", skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+}
@@ -0,0 +1,509 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::{NodeGraph, NodeStatus, WorkflowStatus};
+use crate::types::time::{format_duration, format_timestamp, parse_timestamp};
+
+/// Number of nodes listed by the summary of a `TimingReport`.
+const SLOWEST_NODES: usize = 5;
+
+/// `TimingReport` shows where the time of a completed workflow went. All
+/// durations are in seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimingReport {
+    #[serde(rename = "startedAt")]
+    pub started_at: String,
+
+    #[serde(rename = "finishedAt")]
+    pub finished_at: String,
+
+    #[serde(rename = "duration")]
+    pub duration: i64,
+
+    /// `WorkflowStatus::estimated_duration`, from previous runs.
+    #[serde(rename = "estimatedDuration", skip_serializing_if = "Option::is_none")]
+    pub estimated_duration: Option<i64>,
+
+    /// How much longer than estimated the workflow ran, negative when it
+    /// ran faster.
+    #[serde(rename = "estimateGap", skip_serializing_if = "Option::is_none")]
+    pub estimate_gap: Option<i64>,
+
+    /// The chain of nodes which each waited for the previous one, ending
+    /// with the last node to finish. Making any other node faster does not
+    /// make the workflow faster.
+    #[serde(rename = "criticalPath")]
+    pub critical_path: Vec<NodeTiming>,
+
+    /// The nodes which run something (pods, suspend, HTTP and plugin
+    /// nodes), in the order they started.
+    #[serde(rename = "nodes")]
+    pub nodes: Vec<NodeTiming>,
+
+    /// Number of pods running over time, each sample giving the number
+    /// from its time up to the next sample.
+    #[serde(rename = "parallelism")]
+    pub parallelism: Vec<ParallelismSample>,
+
+    #[serde(rename = "maxParallelism")]
+    pub max_parallelism: usize,
+
+    /// Pod running time divided by the duration of the workflow.
+    #[serde(rename = "averageParallelism")]
+    pub average_parallelism: f64,
+}
+
+/// `NodeTiming` gives the times of a node of a `TimingReport`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeTiming {
+    #[serde(rename = "id")]
+    pub id: String,
+
+    #[serde(rename = "name")]
+    pub name: String,
+
+    #[serde(rename = "displayName", skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+
+    #[serde(rename = "type")]
+    pub r#type: String,
+
+    #[serde(rename = "templateName", skip_serializing_if = "Option::is_none")]
+    pub template_name: Option<String>,
+
+    #[serde(rename = "phase", skip_serializing_if = "Option::is_none")]
+    pub phase: Option<String>,
+
+    #[serde(rename = "startedAt")]
+    pub started_at: String,
+
+    #[serde(rename = "finishedAt")]
+    pub finished_at: String,
+
+    /// Time between when the node could start, i.e. when the nodes it waits
+    /// for finished or its step group or DAG started, and when it started.
+    /// This includes waiting for `parallelism`, synchronization, retry
+    /// backoffs and the controller.
+    #[serde(rename = "queue")]
+    pub queue: i64,
+
+    /// Time between when the node started and finished.
+    #[serde(rename = "run")]
+    pub run: i64,
+
+    /// `NodeStatus::estimated_duration`, from previous runs.
+    #[serde(rename = "estimatedDuration", skip_serializing_if = "Option::is_none")]
+    pub estimated_duration: Option<i64>,
+}
+
+/// `ParallelismSample` is the number of pods running from a point in time.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParallelismSample {
+    #[serde(rename = "time")]
+    pub time: String,
+
+    /// Seconds since the workflow started.
+    #[serde(rename = "offset")]
+    pub offset: i64,
+
+    #[serde(rename = "running")]
+    pub running: usize,
+}
+
+impl WorkflowStatus {
+    /// Returns the `TimingReport` of the workflow, or `None` unless it
+    /// started and finished.
+    pub fn timing_report(&self) -> Option<TimingReport> {
+        let started = parse_timestamp(self.started_at.as_deref()?)?;
+        let finished = parse_timestamp(self.finished_at.as_deref()?)?;
+        let duration = finished - started;
+        let graph = self.node_graph();
+        let timing = Timing { graph: &graph };
+
+        let leaves: Vec<&NodeStatus> = graph
+            .nodes()
+            .into_iter()
+            .filter(|n| !is_container(n) && n.r#type != "Skipped")
+            .filter(|n| times(n).is_some())
+            .collect();
+        let nodes: Vec<NodeTiming> = leaves.iter().filter_map(|n| timing.node(n)).collect();
+
+        let mut critical_path = Vec::new();
+        let last = leaves
+            .iter()
+            .max_by_key(|n| (times(n).map(|(_, end)| end), std::cmp::Reverse(&n.name)));
+        let mut seen = HashSet::new();
+        let mut current = last.copied();
+        while let Some(node) = current {
+            if !seen.insert(node.id.as_str()) {
+                break;
+            }
+            if !is_container(node) && node.r#type != "Skipped" {
+                critical_path.extend(timing.node(node));
+            }
+            current = timing.cause(node).map(|(cause, _)| cause);
+        }
+        critical_path.reverse();
+
+        let mut events: Vec<(i64, i64)> = Vec::new();
+        let mut running_time = 0;
+        for pod in leaves.iter().filter(|n| n.r#type == "Pod") {
+            let (start, end) = times(pod).expect("leaves have times");
+            events.push((start, 1));
+            events.push((end, -1));
+            running_time += end - start;
+        }
+        events.sort();
+        let mut parallelism: Vec<ParallelismSample> = Vec::new();
+        let mut running: i64 = 0;
+        for (i, (time, change)) in events.iter().enumerate() {
+            running += change;
+            if events.get(i + 1).is_some_and(|(next, _)| next == time) {
+                continue;
+            }
+            if parallelism
+                .last()
+                .is_some_and(|s| s.running == running as usize)
+            {
+                continue;
+            }
+            parallelism.push(ParallelismSample {
+                time: format_timestamp(*time),
+                offset: time - started,
+                running: running as usize,
+            });
+        }
+
+        let estimated_duration = self.estimated_duration.map(i64::from);
+        Some(TimingReport {
+            started_at: format_timestamp(started),
+            finished_at: format_timestamp(finished),
+            duration,
+            estimated_duration,
+            estimate_gap: estimated_duration.map(|e| duration - e),
+            critical_path,
+            nodes,
+            max_parallelism: parallelism.iter().map(|s| s.running).max().unwrap_or(0),
+            average_parallelism: if duration > 0 {
+                running_time as f64 / duration as f64
+            } else {
+                0.0
+            },
+            parallelism,
+        })
+    }
+}
+
+impl fmt::Display for TimingReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Duration: {}", format_duration(self.duration))?;
+        if let (Some(estimated), Some(gap)) = (self.estimated_duration, self.estimate_gap) {
+            let (gap, word) = if gap < 0 {
+                (-gap, "under")
+            } else {
+                (gap, "over")
+            };
+            write!(
+                f,
+                ", estimated {} ({} {})",
+                format_duration(estimated),
+                format_duration(gap),
+                word
+            )?;
+        }
+
+        let path: i64 = self.critical_path.iter().map(|n| n.queue + n.run).sum();
+        write!(
+            f,
+            "\nCritical path: {} in {} nodes",
+            format_duration(path),
+            self.critical_path.len()
+        )?;
+        for node in &self.critical_path {
+            write!(f, "\n  {}", node)?;
+        }
+
+        write!(
+            f,
+            "\nParallelism: max {}, average {:.1}",
+            self.max_parallelism, self.average_parallelism
+        )?;
+
+        let mut slowest: Vec<&NodeTiming> = self.nodes.iter().collect();
+        slowest.sort_by_key(|n| std::cmp::Reverse(n.run));
+        write!(f, "\nSlowest nodes:")?;
+        for node in slowest.into_iter().take(SLOWEST_NODES) {
+            write!(f, "\n  {}", node)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for NodeTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.display_name.as_deref().unwrap_or(&self.name);
+        write!(
+            f,
+            "{:>9} run, {:>7} queued  {}",
+            format_duration(self.run),
+            format_duration(self.queue),
+            name
+        )?;
+        if let Some(template) = &self.template_name {
+            write!(f, " [{}]", template)?;
+        }
+        if let Some(estimated) = self.estimated_duration {
+            write!(f, " (estimated {})", format_duration(estimated))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns whether a node holds other nodes rather than running something.
+fn is_container(node: &NodeStatus) -> bool {
+    matches!(
+        node.r#type.as_str(),
+        "Steps" | "DAG" | "StepGroup" | "TaskGroup" | "Retry"
+    )
+}
+
+fn times(node: &NodeStatus) -> Option<(i64, i64)> {
+    let start = parse_timestamp(node.started_at.as_deref()?)?;
+    let end = parse_timestamp(node.finished_at.as_deref()?)?;
+    Some((start, end))
+}
+
+struct Timing<'g, 'a> {
+    graph: &'g NodeGraph<'a>,
+}
+
+impl<'a> Timing<'_, 'a> {
+    fn node(&self, node: &NodeStatus) -> Option<NodeTiming> {
+        let (start, end) = times(node)?;
+        let ready = self.cause(node).map_or(start, |(_, ready)| ready);
+        Some(NodeTiming {
+            id: node.id.clone(),
+            name: node.name.clone(),
+            display_name: node.display_name.clone(),
+            r#type: node.r#type.clone(),
+            template_name: node.template_name.clone(),
+            phase: node.phase.clone(),
+            started_at: format_timestamp(start),
+            finished_at: format_timestamp(end),
+            queue: (start - ready).max(0),
+            run: end - start,
+            estimated_duration: node.estimated_duration.map(i64::from),
+        })
+    }
+
+    /// Returns the node the node `node` waited for before starting, and
+    /// when it could start: the previous attempt of a retried node, the
+    /// parent which finished last, or the step group, DAG or Retry node
+    /// holding it, which starts along with its first nodes. Exit handlers
+    /// of the workflow wait for its root node.
+    fn cause(&self, node: &NodeStatus) -> Option<(&'a NodeStatus, i64)> {
+        if let Some(retry) = self.graph.retry_of(&node.id) {
+            let index = retry.attempts.iter().position(|a| a.id == node.id)?;
+            if index > 0 {
+                let previous = retry.attempts[index - 1];
+                return Some((previous, times(previous)?.1));
+            }
+        }
+
+        let parents = self.graph.parents(&node.id);
+        if parents.is_empty() {
+            let hooked = node.node_flag.as_ref().and_then(|f| f.hooked) == Some(true);
+            let root = self.graph.root().filter(|r| hooked && r.id != node.id)?;
+            let last = self.last_leaf(root);
+            return Some((last, times(last)?.1));
+        }
+        parents
+            .into_iter()
+            .filter_map(|parent| {
+                let (start, end) = times(parent)?;
+                let ready = if is_container(parent) { start } else { end };
+                Some((parent, ready))
+            })
+            .max_by_key(|(parent, ready)| (*ready, std::cmp::Reverse(&parent.name)))
+    }
+
+    /// Returns the node which finished last within the node `node`.
+    fn last_leaf(&self, node: &'a NodeStatus) -> &'a NodeStatus {
+        if !is_container(node) {
+            return node;
+        }
+        let inner = match node.outbound_nodes.as_ref() {
+            Some(outbound) if !outbound.is_empty() => self.graph.outbound(&node.id),
+            _ => self.graph.children(&node.id),
+        };
+        inner
+            .into_iter()
+            .filter(|n| n.id != node.id)
+            .max_by_key(|n| times(n).map(|(_, end)| end))
+            .map_or(node, |n| self.last_leaf(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Start of the workflows of the fixtures.
+    const START: i64 = 1_767_225_600;
+
+    fn node(r#type: &str, (start, end): (i64, i64), children: &[&str]) -> Value {
+        json!({
+            "type": r#type,
+            "phase": "Succeeded",
+            "startedAt": format_timestamp(START + start),
+            "finishedAt": format_timestamp(START + end),
+            "children": children,
+        })
+    }
+
+    fn status(nodes: Vec<(&str, Value)>, end: i64, estimated: i64) -> WorkflowStatus {
+        let nodes: serde_json::Map<String, Value> = nodes
+            .into_iter()
+            .map(|(id, mut node)| {
+                // As in the controller, only the root node is named by its ID.
+                let name = match id {
+                    "wf" => String::from(id),
+                    _ => format!("wf.{}", id),
+                };
+                node["id"] = json!(id);
+                node["name"] = json!(name);
+                (String::from(id), node)
+            })
+            .collect();
+        serde_json::from_value(json!({
+            "startedAt": format_timestamp(START),
+            "finishedAt": format_timestamp(START + end),
+            "estimatedDuration": estimated,
+            "nodes": nodes,
+        }))
+        .unwrap()
+    }
+
+    /// A DAG where `c` waits for `a` and for `b`, retried once, followed by
+    /// the exit handler of the workflow.
+    fn fan_in() -> WorkflowStatus {
+        let mut wf = node("DAG", (0, 100), &["a", "b"]);
+        wf["outboundNodes"] = json!(["c"]);
+        let mut b0 = node("Pod", (1, 10), &[]);
+        b0["phase"] = json!("Failed");
+        let mut exit = node("Pod", (95, 100), &[]);
+        exit["nodeFlag"] = json!({"hooked": true});
+        status(
+            vec![
+                ("wf", wf),
+                ("a", node("Pod", (1, 20), &["c"])),
+                ("b", node("Retry", (1, 40), &["b0", "b1"])),
+                ("b0", b0),
+                ("b1", node("Pod", (15, 40), &["c"])),
+                ("c", node("Pod", (40, 90), &[])),
+                ("onExit", exit),
+            ],
+            100,
+            90,
+        )
+    }
+
+    fn ids(nodes: &[NodeTiming]) -> Vec<&str> {
+        nodes.iter().map(|n| n.id.as_str()).collect()
+    }
+
+    #[test]
+    fn critical_path() {
+        let status = fan_in();
+        let graph = status.node_graph();
+        let timing = Timing { graph: &graph };
+        let cause = |id| {
+            timing
+                .cause(graph.get(id).unwrap())
+                .map(|(n, t)| (n.id.as_str(), t - START))
+        };
+        assert_eq!(cause("a"), Some(("wf", 0)));
+        assert_eq!(cause("b0"), Some(("b", 1)));
+        assert_eq!(cause("b1"), Some(("b0", 10)));
+        assert_eq!(cause("c"), Some(("b1", 40)));
+        assert_eq!(cause("onExit"), Some(("c", 90)));
+        assert_eq!(cause("wf"), None);
+        assert_eq!(timing.last_leaf(graph.get("wf").unwrap()).id, "c");
+        assert_eq!(timing.last_leaf(graph.get("b").unwrap()).id, "b1");
+
+        let report = status.timing_report().unwrap();
+        assert_eq!(ids(&report.critical_path), ["b0", "b1", "c", "onExit"]);
+        assert_eq!(ids(&report.nodes), ["a", "b0", "b1", "c", "onExit"]);
+    }
+
+    #[test]
+    fn queue_and_run() {
+        let report = fan_in().timing_report().unwrap();
+        let times: Vec<(&str, i64, i64)> = report
+            .nodes
+            .iter()
+            .map(|n| (n.id.as_str(), n.queue, n.run))
+            .collect();
+        assert_eq!(
+            times,
+            [
+                ("a", 1, 19),
+                ("b0", 0, 9),
+                ("b1", 5, 25),
+                ("c", 0, 50),
+                ("onExit", 5, 5),
+            ]
+        );
+        assert_eq!(report.nodes[1].phase.as_deref(), Some("Failed"));
+    }
+
+    #[test]
+    fn parallelism() {
+        let report = fan_in().timing_report().unwrap();
+        let samples: Vec<(i64, usize)> = report
+            .parallelism
+            .iter()
+            .map(|s| (s.offset, s.running))
+            .collect();
+        // `b1` finishing as `c` starts leaves one pod running, as before.
+        assert_eq!(
+            samples,
+            [
+                (1, 2),
+                (10, 1),
+                (15, 2),
+                (20, 1),
+                (90, 0),
+                (95, 1),
+                (100, 0)
+            ]
+        );
+        assert_eq!(report.parallelism[0].time, format_timestamp(START + 1));
+        assert_eq!(report.max_parallelism, 2);
+        assert_eq!(report.average_parallelism, 1.08);
+    }
+
+    #[test]
+    fn estimate_gap() {
+        let report = fan_in().timing_report().unwrap();
+        assert_eq!(report.duration, 100);
+        assert_eq!(report.estimated_duration, Some(90));
+        assert_eq!(report.estimate_gap, Some(10));
+        assert!(report
+            .to_string()
+            .starts_with("Duration: 1m40s, estimated 1m30s (10s over)"));
+
+        let status = status(vec![("wf", node("Pod", (0, 60), &[]))], 60, 80);
+        let report = status.timing_report().unwrap();
+        assert_eq!(report.estimate_gap, Some(-20));
+        assert!(report.to_string().contains("(20s under)"));
+
+        let mut unfinished = fan_in();
+        unfinished.finished_at = None;
+        assert_eq!(unfinished.timing_report(), None);
+    }
+}
@@ -0,0 +1,336 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{LifecycleHook, Workflow, WorkflowSpec};
+use crate::error::{ExpressionError, ValidationError, ValidationErrorKind};
+use crate::expr::Expression;
+use crate::types::template::{depends_tasks, find_cycle, mask_tags, DAGTemplate, Template};
+use crate::types::workflow_template::WorkflowTemplate;
+
+impl WorkflowSpec {
+    /// Checks the spec offline, without an argo-server, and returns every
+    /// problem found. Each problem is located by a JSON pointer relative to
+    /// the spec.
+    ///
+    /// The checks are:
+    /// - `entrypoint`, `on_exit`, hooks, steps and DAG tasks name declared
+    ///   templates. They are skipped when the spec uses `workflow_template_ref`,
+    ///   as the templates are then declared by the referenced template.
+    /// - every template sets exactly one of container, script, resource,
+    ///   dag, steps, suspend, data, http, containerSet or plugin.
+    /// - template names, step names of a steps template and task names of
+    ///   a DAG are unique.
+    /// - DAG tasks only depend on declared tasks, and their `dependencies`
+    ///   and `depends` expressions do not form a cycle.
+    /// - resource templates are valid. See `ResourceTemplate::validate()`.
+    /// - `when` conditions, retry strategy and hook expressions, HTTP
+    ///   success conditions, data transformations and `ValueFrom`
+    ///   expressions parse as expr-language expressions. Template tags
+    ///   (`{{...}}`) they hold are taken as plain variables.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let templates = self.templates.as_deref().unwrap_or_default();
+        let mut validator = Validator {
+            templates: templates.iter().filter_map(|t| t.name.as_deref()).collect(),
+            resolve: self.workflow_template_ref.is_none(),
+            errors: Vec::new(),
+        };
+
+        match self.entrypoint.as_deref() {
+            Some(name) => validator.template_ref("/entrypoint", name),
+            None if validator.resolve => validator.error(
+                String::from("/entrypoint"),
+                ValidationErrorKind::MissingField("entrypoint"),
+            ),
+            None => {}
+        }
+        if let Some(name) = self.on_exit.as_deref() {
+            validator.template_ref("/onExit", name);
+        }
+        validator.hooks("", self.hooks.as_ref());
+        if let Some(expression) = self
+            .retry_strategy
+            .as_ref()
+            .and_then(|r| r.expression.as_deref())
+        {
+            validator.expression(String::from("/retryStrategy/expression"), expression);
+        }
+
+        let mut names = HashSet::new();
+        for (i, template) in templates.iter().enumerate() {
+            let pointer = format!("/templates/{}", i);
+            match template.name.as_deref() {
+                Some(name) if !names.insert(name) => validator.error(
+                    format!("{}/name", pointer),
+                    ValidationErrorKind::DuplicateName {
+                        kind: "template",
+                        name: String::from(name),
+                    },
+                ),
+                Some(_) => {}
+                None => validator.error(
+                    format!("{}/name", pointer),
+                    ValidationErrorKind::MissingField("name"),
+                ),
+            }
+            validator.template(&pointer, template);
+        }
+
+        if validator.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(validator.errors)
+        }
+    }
+}
+
+impl Workflow {
+    /// Checks the spec of the workflow offline. See `WorkflowSpec::validate()`.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        self.spec
+            .validate()
+            .map_err(|errors| prefix("/spec", errors))
+    }
+}
+
+impl WorkflowTemplate {
+    /// Checks the spec of the workflow template offline. See
+    /// `WorkflowSpec::validate()`.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        self.spec
+            .validate()
+            .map_err(|errors| prefix("/spec", errors))
+    }
+}
+
+pub(super) fn prefix(pointer: &str, mut errors: Vec<ValidationError>) -> Vec<ValidationError> {
+    for error in errors.iter_mut() {
+        error.pointer.insert_str(0, pointer);
+    }
+    errors
+}
+
+/// Escapes a map key to be used as a JSON pointer segment.
+fn escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+struct Validator<'a> {
+    templates: HashSet<&'a str>,
+    resolve: bool,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&mut self, pointer: String, kind: ValidationErrorKind) {
+        self.errors.push(ValidationError::new(pointer, kind));
+    }
+
+    fn template_ref(&mut self, pointer: &str, name: &str) {
+        if self.resolve && !self.templates.contains(name) {
+            self.error(
+                String::from(pointer),
+                ValidationErrorKind::UnknownTemplate(String::from(name)),
+            );
+        }
+    }
+
+    fn expression(&mut self, pointer: String, source: &str) {
+        if let Err(ExpressionError::Syntax { position, message }) =
+            Expression::parse(&mask_tags(source))
+        {
+            self.error(
+                pointer,
+                ValidationErrorKind::InvalidExpression { position, message },
+            );
+        }
+    }
+
+    fn hooks(&mut self, pointer: &str, hooks: Option<&HashMap<String, LifecycleHook>>) {
+        let mut hooks: Vec<_> = hooks.into_iter().flatten().collect();
+        hooks.sort_by_key(|(name, _)| name.as_str());
+        for (name, hook) in hooks {
+            let pointer = format!("{}/hooks/{}", pointer, escape(name));
+            if let Some(template) = hook.template.as_deref() {
+                self.template_ref(&format!("{}/template", pointer), template);
+            }
+            if let Some(expression) = hook.expression.as_deref() {
+                self.expression(format!("{}/expression", pointer), expression);
+            }
+        }
+    }
+
+    fn template(&mut self, pointer: &str, template: &'a Template) {
+        let types: Vec<&'static str> = [
+            ("container", template.container.is_some()),
+            ("script", template.script.is_some()),
+            ("resource", template.resource.is_some()),
+            ("dag", template.dag.is_some()),
+            ("steps", template.steps.is_some()),
+            ("suspend", template.suspend.is_some()),
+            ("data", template.data.is_some()),
+            ("http", template.http.is_some()),
+            ("containerSet", template.container_set.is_some()),
+            ("plugin", template.plugin.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, set)| set.then_some(name))
+        .collect();
+        if types.len() != 1 {
+            self.error(
+                String::from(pointer),
+                ValidationErrorKind::TemplateType(types),
+            );
+        }
+
+        if let Some(expression) = template
+            .retry_strategy
+            .as_ref()
+            .and_then(|r| r.expression.as_deref())
+        {
+            self.expression(format!("{}/retryStrategy/expression", pointer), expression);
+        }
+        if let Some(condition) = template
+            .http
+            .as_ref()
+            .and_then(|h| h.success_condition.as_deref())
+        {
+            self.expression(format!("{}/http/successCondition", pointer), condition);
+        }
+        for (i, step) in template
+            .data
+            .iter()
+            .flat_map(|d| d.transformation.iter())
+            .enumerate()
+        {
+            let pointer = format!("{}/data/transformation/{}/expression", pointer, i);
+            self.expression(pointer, &step.expression);
+        }
+        let outputs = template
+            .outputs
+            .as_ref()
+            .and_then(|o| o.parameters.as_ref());
+        for (i, parameter) in outputs.into_iter().flatten().enumerate() {
+            if let Some(expression) = parameter
+                .value_from
+                .as_ref()
+                .and_then(|v| v.expression.as_deref())
+            {
+                let pointer = format!("{}/outputs/parameters/{}/valueFrom/expression", pointer, i);
+                self.expression(pointer, expression);
+            }
+        }
+        if let Some(resource) = template.resource.as_deref() {
+            if let Err(errors) = resource.validate() {
+                let pointer = format!("{}/resource", pointer);
+                self.errors.extend(prefix(&pointer, errors));
+            }
+        }
+        if let Some(groups) = template.steps.as_deref() {
+            self.steps(pointer, groups);
+        }
+        if let Some(dag) = template.dag.as_deref() {
+            self.dag(&format!("{}/dag", pointer), dag);
+        }
+    }
+
+    fn steps(&mut self, pointer: &str, groups: &'a [Vec<super::WorkflowStep>]) {
+        let mut names = HashSet::new();
+        for (i, group) in groups.iter().enumerate() {
+            for (j, step) in group.iter().enumerate() {
+                let pointer = format!("{}/steps/{}/{}", pointer, i, j);
+                match step.name.as_deref() {
+                    Some(name) if !names.insert(name) => self.error(
+                        format!("{}/name", pointer),
+                        ValidationErrorKind::DuplicateName {
+                            kind: "step",
+                            name: String::from(name),
+                        },
+                    ),
+                    Some(_) => {}
+                    None => self.error(
+                        format!("{}/name", pointer),
+                        ValidationErrorKind::MissingField("name"),
+                    ),
+                }
+
+                if let Some(name) = step.template.as_deref() {
+                    self.template_ref(&format!("{}/template", pointer), name);
+                }
+                if let Some(name) = step.on_exit.as_deref() {
+                    self.template_ref(&format!("{}/onExit", pointer), name);
+                }
+                if let Some(when) = step.when.as_deref() {
+                    self.expression(format!("{}/when", pointer), when);
+                }
+                self.hooks(&pointer, step.hooks.as_ref());
+                if let Some(inline) = step.inline.as_deref() {
+                    self.template(&format!("{}/inline", pointer), inline);
+                }
+            }
+        }
+    }
+
+    fn dag(&mut self, pointer: &str, dag: &'a DAGTemplate) {
+        let mut names = HashSet::new();
+        for (i, task) in dag.tasks.iter().enumerate() {
+            if !names.insert(task.name.as_str()) {
+                self.error(
+                    format!("{}/tasks/{}/name", pointer, i),
+                    ValidationErrorKind::DuplicateName {
+                        kind: "task",
+                        name: task.name.clone(),
+                    },
+                );
+            }
+        }
+
+        let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (i, task) in dag.tasks.iter().enumerate() {
+            let task_pointer = format!("{}/tasks/{}", pointer, i);
+            let mut deps: Vec<(String, &str)> = Vec::new();
+            for (j, dep) in task.dependencies.iter().flatten().enumerate() {
+                deps.push((format!("{}/dependencies/{}", task_pointer, j), dep));
+            }
+            if let Some(depends) = task.depends.as_deref() {
+                for dep in depends_tasks(depends) {
+                    deps.push((format!("{}/depends", task_pointer), dep));
+                }
+            }
+
+            for (dep_pointer, dep) in deps {
+                if names.contains(dep) {
+                    graph.entry(task.name.as_str()).or_default().push(dep);
+                } else {
+                    self.error(
+                        dep_pointer,
+                        ValidationErrorKind::UnknownTask {
+                            task: task.name.clone(),
+                            dependency: String::from(dep),
+                        },
+                    );
+                }
+            }
+
+            if let Some(name) = task.template.as_deref() {
+                self.template_ref(&format!("{}/template", task_pointer), name);
+            }
+            if let Some(name) = task.on_exit.as_deref() {
+                self.template_ref(&format!("{}/onExit", task_pointer), name);
+            }
+            if let Some(when) = task.when.as_deref() {
+                self.expression(format!("{}/when", task_pointer), when);
+            }
+            self.hooks(&task_pointer, task.hooks.as_ref());
+            if let Some(inline) = task.inline.as_deref() {
+                self.template(&format!("{}/inline", task_pointer), inline);
+            }
+        }
+
+        let order: Vec<&str> = dag.tasks.iter().map(|t| t.name.as_str()).collect();
+        if let Some(cycle) = find_cycle(&order, &graph) {
+            self.error(
+                format!("{}/tasks", pointer),
+                ValidationErrorKind::Cycle(cycle),
+            );
+        }
+    }
+}
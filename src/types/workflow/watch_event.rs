@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// `WatchEvent` is a change to a workflow, streamed by `watch_workflows`.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WatchEvent {
+    /// `Type` of the change: `ADDED`, `MODIFIED` or `DELETED`, or
+    /// `BOOKMARK` for an event only marking the resource version reached.
+    #[serde(rename = "type")]
+    pub r#type: String,
+
+    /// `Object` is the workflow as of the change. The workflow of a
+    /// `BOOKMARK` only has the `resource_version` of its metadata set.
+    #[serde(rename = "object")]
+    pub object: Box<super::Workflow>,
+}
@@ -10,6 +10,9 @@ pub use self::list::WorkflowTemplateList;
 mod r#ref;
 pub use self::r#ref::WorkflowTemplateRef;
 
+mod resolver;
+pub use self::resolver::TemplateResolver;
+
 mod template;
 pub use self::template::WorkflowTemplate;
 
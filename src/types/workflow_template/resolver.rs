@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::WorkflowTemplate;
+use crate::api::workflow_template::get_workflow_template;
+use crate::config::Config;
+use crate::error::{workflow_template::GetWorkflowTemplateError, Error, ResolveError};
+use crate::types::merge::merge;
+use crate::types::template::Template;
+use crate::types::workflow::WorkflowSpec;
+
+/// Lists of objects merged by name when joining a workflow spec with the
+/// spec of its workflow template, rather than replaced.
+const MERGED_BY_NAME: &[&str] = &["parameters", "artifacts"];
+
+/// A `TemplateResolver` holds a catalogue of workflow templates and cluster
+/// workflow templates, and resolves the references made to them.
+#[derive(Clone, Debug, Default)]
+pub struct TemplateResolver {
+    templates: HashMap<String, WorkflowTemplate>,
+    cluster_templates: HashMap<String, WorkflowTemplate>,
+}
+
+impl TemplateResolver {
+    /// Constructs a new `TemplateResolver` with an empty catalogue.
+    pub fn new() -> Self {
+        TemplateResolver {
+            ..Default::default()
+        }
+    }
+
+    /// Adds a workflow template to the catalogue. Templates of kind
+    /// `ClusterWorkflowTemplate` are added as cluster templates.
+    pub fn insert(&mut self, template: WorkflowTemplate) {
+        if template.kind.as_deref() == Some("ClusterWorkflowTemplate") {
+            self.insert_cluster(template);
+        } else {
+            let name = template.metadata.name.clone().unwrap_or_default();
+            self.templates.insert(name, template);
+        }
+    }
+
+    /// Adds a cluster workflow template to the catalogue.
+    pub fn insert_cluster(&mut self, template: WorkflowTemplate) {
+        let name = template.metadata.name.clone().unwrap_or_default();
+        self.cluster_templates.insert(name, template);
+    }
+
+    /// Adds the workflow template stored as YAML, or JSON, in the file at
+    /// `path`.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ResolveError> {
+        let content = fs::read_to_string(path)?;
+        self.insert(serde_yaml::from_str(&content)?);
+        Ok(())
+    }
+
+    /// Fetches the workflow template `name` from the server and adds it to
+    /// the catalogue.
+    pub fn fetch(
+        &mut self,
+        config: &Config,
+        namespace: &str,
+        name: &str,
+    ) -> Result<(), Error<GetWorkflowTemplateError>> {
+        let template = get_workflow_template(config, namespace, name, None)?;
+        self.templates.insert(String::from(name), template);
+        Ok(())
+    }
+
+    /// Fetches from the server the workflow templates referenced by `spec`,
+    /// directly or through other workflow templates, which are missing from
+    /// the catalogue. Cluster workflow templates are not fetched.
+    pub fn fetch_missing(
+        &mut self,
+        config: &Config,
+        namespace: &str,
+        spec: &WorkflowSpec,
+    ) -> Result<(), Error<GetWorkflowTemplateError>> {
+        let mut pending = references(spec);
+        while let Some(name) = pending.pop() {
+            if self.templates.contains_key(&name) {
+                continue;
+            }
+            self.fetch(config, namespace, &name)?;
+            pending.extend(references(&self.templates[&name].spec));
+        }
+        Ok(())
+    }
+
+    /// Returns the spec the controller would execute for `spec`.
+    ///
+    /// When `spec` sets `workflow_template_ref`, it is joined with the spec
+    /// of the referenced template the way the controller does: fields set by
+    /// `spec` take precedence, argument parameters and artifacts are merged
+    /// by name, `template_defaults` and `hooks` are merged field by field,
+    /// and templates declared by `spec` replace the ones of the same name.
+    ///
+    /// Steps and DAG tasks using a `template_ref` are then given the
+    /// referenced template as `inline` template, along with the templates it
+    /// calls in turn. Referenced templates calling each other can not be
+    /// inlined and are reported as `ResolveError::Recursion`.
+    pub fn resolve(&self, spec: &WorkflowSpec) -> Result<WorkflowSpec, ResolveError> {
+        let mut spec = match spec.workflow_template_ref.as_deref() {
+            Some(reference) => {
+                let cluster_scope = reference.cluster_scope.unwrap_or(false);
+                let name = reference.name.as_deref().unwrap_or_default();
+                let template = self.lookup(cluster_scope, name)?;
+                join(&template.spec, spec)?
+            }
+            None => spec.clone(),
+        };
+
+        let mut stack = Vec::new();
+        for template in spec.templates.iter_mut().flatten() {
+            self.walk(template, None, &mut stack)?;
+        }
+        Ok(spec)
+    }
+
+    fn lookup(&self, cluster_scope: bool, name: &str) -> Result<&WorkflowTemplate, ResolveError> {
+        let templates = if cluster_scope {
+            &self.cluster_templates
+        } else {
+            &self.templates
+        };
+        templates
+            .get(name)
+            .ok_or_else(|| ResolveError::UnknownWorkflowTemplate {
+                name: String::from(name),
+                cluster_scope,
+            })
+    }
+
+    /// Returns the template `name` of a workflow template, with its own
+    /// references inlined.
+    fn inline(
+        &self,
+        scope: Scope<'_>,
+        name: &str,
+        stack: &mut Vec<String>,
+    ) -> Result<Template, ResolveError> {
+        let (cluster_scope, workflow_template) = scope;
+        let key = format!("{}/{}", workflow_template, name);
+        if let Some(start) = stack.iter().position(|k| *k == key) {
+            let mut path = stack[start..].to_vec();
+            path.push(key);
+            return Err(ResolveError::Recursion(path));
+        }
+
+        let mut template = self
+            .lookup(cluster_scope, workflow_template)?
+            .spec
+            .templates
+            .iter()
+            .flatten()
+            .find(|t| t.name.as_deref() == Some(name))
+            .cloned()
+            .ok_or_else(|| ResolveError::UnknownTemplate {
+                workflow_template: String::from(workflow_template),
+                template: String::from(name),
+            })?;
+
+        stack.push(key);
+        self.walk(&mut template, Some(scope), stack)?;
+        stack.pop();
+        Ok(template)
+    }
+
+    /// Inlines the templates referenced by the steps and tasks of
+    /// `template`. Within a workflow template (`scope`), templates called by
+    /// name are declared by that workflow template, and are inlined too.
+    fn walk(
+        &self,
+        template: &mut Template,
+        scope: Option<Scope<'_>>,
+        stack: &mut Vec<String>,
+    ) -> Result<(), ResolveError> {
+        for step in template.steps.iter_mut().flatten().flatten() {
+            let reference = step.template_ref.take().map(|r| {
+                let cluster_scope = r.cluster_scope.unwrap_or(false);
+                (cluster_scope, r.name, r.template)
+            });
+            self.call(
+                &mut step.inline,
+                &mut step.template,
+                reference,
+                scope,
+                stack,
+            )?;
+        }
+        for task in template.dag.iter_mut().flat_map(|d| d.tasks.iter_mut()) {
+            let reference = task.template_ref.take().map(|r| {
+                let cluster_scope = r.cluster_scope.unwrap_or(false);
+                (cluster_scope, r.name, r.template)
+            });
+            self.call(
+                &mut task.inline,
+                &mut task.template,
+                reference,
+                scope,
+                stack,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn call(
+        &self,
+        inline: &mut Option<Box<Template>>,
+        template: &mut Option<String>,
+        reference: Option<(bool, Option<String>, Option<String>)>,
+        scope: Option<Scope<'_>>,
+        stack: &mut Vec<String>,
+    ) -> Result<(), ResolveError> {
+        if let Some(inline) = inline.as_deref_mut() {
+            return self.walk(inline, scope, stack);
+        }
+
+        let resolved = match (reference, scope) {
+            (Some((cluster_scope, name, template)), _) => {
+                let name = name.unwrap_or_default();
+                let template = template.unwrap_or_default();
+                self.inline((cluster_scope, &name), &template, stack)?
+            }
+            (None, Some(scope)) => match template.take() {
+                Some(name) => self.inline(scope, &name, stack)?,
+                None => return Ok(()),
+            },
+            (None, None) => return Ok(()),
+        };
+        *inline = Some(Box::new(resolved));
+        Ok(())
+    }
+}
+
+/// A workflow template: whether it is cluster scoped, and its name.
+type Scope<'a> = (bool, &'a str);
+
+/// Returns the names of the namespaced workflow templates referenced by `spec`.
+fn references(spec: &WorkflowSpec) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(reference) = spec.workflow_template_ref.as_deref() {
+        if !reference.cluster_scope.unwrap_or(false) {
+            names.extend(reference.name.clone());
+        }
+    }
+    for template in spec.templates.iter().flatten() {
+        for step in template.steps.iter().flatten().flatten() {
+            if let Some(reference) = step.template_ref.as_deref() {
+                if !reference.cluster_scope.unwrap_or(false) {
+                    names.extend(reference.name.clone());
+                }
+            }
+        }
+        for task in template.dag.iter().flat_map(|d| d.tasks.iter()) {
+            if let Some(reference) = task.template_ref.as_deref() {
+                if !reference.cluster_scope.unwrap_or(false) {
+                    names.extend(reference.name.clone());
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Joins `spec` with the spec of its workflow template `base`.
+fn join(base: &WorkflowSpec, spec: &WorkflowSpec) -> Result<WorkflowSpec, ResolveError> {
+    let mut joined = base.clone();
+    let mut templates = joined.templates.take().unwrap_or_default();
+    for template in spec.templates.iter().flatten() {
+        match templates.iter_mut().find(|t| t.name == template.name) {
+            Some(existing) => *existing = template.clone(),
+            None => templates.push(template.clone()),
+        }
+    }
+
+    let mut overrides = spec.clone();
+    overrides.templates = None;
+    overrides.workflow_template_ref = None;
+
+    let mut value = serde_json::to_value(&joined)?;
+    merge(&mut value, serde_json::to_value(&overrides)?, &|field| {
+        MERGED_BY_NAME.contains(&field).then_some("name")
+    });
+    joined = serde_json::from_value(value)?;
+    joined.templates = Some(templates);
+    Ok(joined)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn load_file() {
+        let dir = env::temp_dir().join(format!("argoflows-resolver-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let yaml = dir.join("template.yaml");
+        fs::write(
+            &yaml,
+            "apiVersion: argoproj.io/v1alpha1\n\
+             kind: ClusterWorkflowTemplate\n\
+             metadata:\n  name: shared\n\
+             spec:\n  templates:\n    - name: hello\n      container:\n        image: alpine\n",
+        )
+        .unwrap();
+        let json = dir.join("template.json");
+        fs::write(
+            &json,
+            r#"{"metadata": {"name": "local"}, "spec": {"templates": [{"name": "hi"}]}}"#,
+        )
+        .unwrap();
+        let invalid = dir.join("invalid.yaml");
+        fs::write(&invalid, "spec: [").unwrap();
+
+        let mut resolver = TemplateResolver::new();
+        resolver.load_file(&yaml).unwrap();
+        resolver.load_file(&json).unwrap();
+        let result = resolver.load_file(&invalid);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(resolver.cluster_templates.contains_key("shared"));
+        assert!(resolver.templates.contains_key("local"));
+        assert!(matches!(result, Err(ResolveError::Yaml(_))));
+    }
+}
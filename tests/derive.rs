@@ -0,0 +1,111 @@
+#![cfg(feature = "derive")]
+
+use argoflows::error::ParamsError;
+use argoflows::types::workflow::{Parameter, WorkflowParams};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, WorkflowParams)]
+struct Etl {
+    /// Day to process.
+    #[param(default = "2026-01-01")]
+    date: String,
+    #[param(rename = "log-level", enum = ["info", "debug"])]
+    log_level: String,
+    retries: Option<u32>,
+    #[param(description = "Tables to load.", json)]
+    tables: Vec<Table>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Table {
+    name: String,
+}
+
+fn param(name: &str, value: &str) -> Parameter {
+    let mut param = Parameter::new(name);
+    param.value = Some(String::from(value));
+    param
+}
+
+#[test]
+fn parameters() {
+    let params = Etl::parameters();
+    let names: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["date", "log-level", "retries", "tables"]);
+
+    assert_eq!(params[0].default.as_deref(), Some("2026-01-01"));
+    assert_eq!(params[0].description.as_deref(), Some("Day to process."));
+    assert_eq!(
+        params[1].r#enum.as_deref(),
+        Some(&[String::from("info"), String::from("debug")][..])
+    );
+    assert_eq!(params[2].default, None);
+    assert_eq!(params[3].description.as_deref(), Some("Tables to load."));
+    assert!(params.iter().all(|p| p.value.is_none()));
+}
+
+#[test]
+fn round_trip() {
+    let etl = Etl {
+        date: String::from("2026-03-04"),
+        log_level: String::from("debug"),
+        retries: Some(3),
+        tables: vec![Table {
+            name: String::from("users"),
+        }],
+    };
+    let params = etl.to_parameters();
+    let values: Vec<(&str, Option<&str>)> = params
+        .iter()
+        .map(|p| (p.name.as_str(), p.value.as_deref()))
+        .collect();
+    assert_eq!(
+        values,
+        [
+            ("date", Some("2026-03-04")),
+            ("log-level", Some("debug")),
+            ("retries", Some("3")),
+            ("tables", Some(r#"[{"name":"users"}]"#)),
+        ]
+    );
+    assert_eq!(Etl::from_parameters(&params), Ok(etl));
+}
+
+#[test]
+fn defaults_and_options() {
+    let params = [param("log-level", "info"), param("tables", "[]")];
+    let etl = Etl::from_parameters(&params).unwrap();
+    assert_eq!(etl.date, "2026-01-01");
+    assert_eq!(etl.retries, None);
+
+    let etl = Etl {
+        retries: None,
+        ..etl
+    };
+    let names: Vec<String> = etl.to_parameters().into_iter().map(|p| p.name).collect();
+    assert_eq!(names, ["date", "log-level", "tables"]);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        Etl::from_parameters(&[param("tables", "[]")]),
+        Err(ParamsError::Missing(String::from("log-level")))
+    );
+    assert_eq!(
+        Etl::from_parameters(&[param("log-level", "trace"), param("tables", "[]")]),
+        Err(ParamsError::NotAllowed {
+            name: String::from("log-level"),
+            value: String::from("trace"),
+        })
+    );
+    let params = [
+        param("log-level", "info"),
+        param("retries", "many"),
+        param("tables", "[]"),
+    ];
+    assert!(matches!(
+        Etl::from_parameters(&params),
+        Err(ParamsError::Invalid { name, .. }) if name == "retries"
+    ));
+}